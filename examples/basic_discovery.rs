@@ -41,6 +41,7 @@ async fn main() -> eyre::Result<()> {
     scanner.start().await?;
     info!("Scanner running. Press Ctrl+C to stop.");
     tokio::signal::ctrl_c().await?;
+    scanner.shutdown().await;
 
     Ok(())
 }
@@ -0,0 +1,36 @@
+//! Stream price updates as JSON lines: `cargo run --example json_lines [-- <file>]`.
+//! Writes to stdout unless a file is given; logs go to stderr so stdout can be piped into jq.
+use dex_pool_scanner_rust::sinks::JsonLinesSink;
+use dex_pool_scanner_rust::{Scanner, ScannerOptions};
+use dotenvy::dotenv;
+use std::sync::Arc;
+use tracing::{info, Level};
+use tracing_subscriber::FmtSubscriber;
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    dotenv().ok();
+    tracing::subscriber::set_global_default(
+        FmtSubscriber::builder()
+            .with_max_level(Level::INFO)
+            .with_writer(std::io::stderr)
+            .finish(),
+    )?;
+
+    let sink = match std::env::args().nth(1) {
+        Some(path) => JsonLinesSink::file(path)?,
+        None => JsonLinesSink::stdout(),
+    };
+    let options = ScannerOptions {
+        sinks: vec![Arc::new(sink)],
+        ..Default::default()
+    };
+
+    let mut scanner = Scanner::with_options(Arc::new(|_, _, _| {}), options).await?;
+    scanner.start().await?;
+    info!("Scanner running. Press Ctrl+C to stop.");
+    tokio::signal::ctrl_c().await?;
+    scanner.shutdown().await;
+
+    Ok(())
+}
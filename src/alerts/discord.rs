@@ -0,0 +1,153 @@
+//! Alerts posted to a Discord channel through an incoming webhook, as embeds.
+
+use super::template::format_price;
+use super::{Alert, AlertKind, Notifier};
+use async_trait::async_trait;
+use eyre::{bail, Result, WrapErr};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+/// Embed colors: green for upward moves, red for downward ones, blue otherwise.
+const COLOR_UP: u32 = 0x2e_cc_71;
+const COLOR_DOWN: u32 = 0xe7_4c_3c;
+const COLOR_NEUTRAL: u32 = 0x34_98_db;
+
+/// Webhook and presentation of a `DiscordNotifier`.
+#[derive(Debug, Clone)]
+pub struct DiscordConfig {
+    /// Channel webhook (Channel settings → Integrations → Webhooks), `https://discord.com/api/webhooks/…`.
+    pub webhook_url: String,
+    /// Overrides the webhook's display name.
+    pub username: Option<String>,
+    /// Block explorer page of an address, linked from the embed title; the pool address is appended.
+    pub explorer_url: String,
+}
+
+impl Default for DiscordConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: String::new(),
+            username: None,
+            explorer_url: "https://etherscan.io/address/".to_string(),
+        }
+    }
+}
+
+/// Posts each alert as one embed: the pair and alert kind as a title linking to the pool, the alert message,
+/// and price / change / swap value / pool fields.
+#[derive(Debug)]
+pub struct DiscordNotifier {
+    config: DiscordConfig,
+    client: Client,
+}
+
+impl DiscordNotifier {
+    pub fn new(config: DiscordConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+}
+
+fn embed(alert: &Alert, explorer_url: &str) -> Value {
+    let title = match alert.kind {
+        AlertKind::PriceCross => "price level crossed",
+        AlertKind::PriceMove => "price move",
+        AlertKind::Spread => "spread",
+        AlertKind::LargeSwap => "large swap",
+        AlertKind::Expression => "rule matched",
+        AlertKind::Disconnected => "scanner disconnected",
+        AlertKind::Reconnected => "scanner reconnected",
+        AlertKind::SubgraphLag => "subgraph lagging",
+        AlertKind::ParseErrors => "parse errors",
+        AlertKind::FeedStale => "no price updates",
+        AlertKind::FeedResumed => "price updates resumed",
+    };
+    let color = match (alert.kind, alert.change_percent) {
+        (AlertKind::PriceMove, Some(change)) if change < 0.0 => COLOR_DOWN,
+        (AlertKind::PriceMove, Some(_)) | (AlertKind::Reconnected | AlertKind::FeedResumed, _) => COLOR_UP,
+        (AlertKind::Disconnected | AlertKind::SubgraphLag | AlertKind::ParseErrors | AlertKind::FeedStale, _) => {
+            COLOR_DOWN
+        }
+        _ => COLOR_NEUTRAL,
+    };
+    let mut fields = Vec::new();
+    if let Some(price) = alert.price {
+        fields.push(json!({ "name": "Price", "value": format_price(price), "inline": true }));
+    }
+    if let Some(change) = alert.change_percent {
+        fields.push(json!({ "name": "Change", "value": format!("{change:+.2}%"), "inline": true }));
+    }
+    if let Some(volume) = alert.volume_usd {
+        fields.push(json!({ "name": "Value", "value": format!("${volume:.0}"), "inline": true }));
+    }
+    if let Some(pool) = alert.pool {
+        fields.push(json!({ "name": "Pool", "value": format!("{} `{pool}`", alert.protocol), "inline": false }));
+    }
+    let timestamp = chrono::DateTime::from_timestamp(alert.timestamp as i64, 0).map(|time| time.to_rfc3339());
+    json!({
+        "title": format!("{} {}", alert.pair, title).trim(),
+        "url": alert.pool.map(|pool| format!("{explorer_url}{pool}")),
+        "description": alert.message,
+        "color": color,
+        "fields": fields,
+        "footer": { "text": alert.rule },
+        "timestamp": timestamp,
+    })
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let mut body = json!({ "embeds": [embed(alert, &self.config.explorer_url)] });
+        if let Some(username) = &self.config.username {
+            body["username"] = json!(username);
+        }
+        let response = self
+            .client
+            .post(&self.config.webhook_url)
+            .json(&body)
+            .send()
+            .await
+            .wrap_err("Discord webhook")?;
+        if !response.status().is_success() {
+            // Rate limits (429) come back here too and are retried like any failure.
+            bail!("Discord webhook failed ({}): {}", response.status(), response.text().await.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_embed() {
+        let alert = Alert {
+            rule: "eth-move".to_string(),
+            kind: AlertKind::PriceMove,
+            pool: Some(Address::repeat_byte(0xab)),
+            protocol: "uniswap-v3".to_string(),
+            pair: "WETH/USDC".to_string(),
+            price: Some(2950.5),
+            change_percent: Some(-2.5),
+            volume_usd: None,
+            message: "[eth-move] WETH/USDC moved -2.50% in 300s (3026.15 -> 2950.5)".to_string(),
+            timestamp: 1_714_521_600,
+        };
+        let embed = embed(&alert, "https://etherscan.io/address/");
+        assert_eq!(embed["title"], "WETH/USDC price move");
+        assert_eq!(embed["url"], format!("https://etherscan.io/address/{}", Address::repeat_byte(0xab)));
+        assert_eq!(embed["color"], COLOR_DOWN);
+        assert_eq!(embed["fields"][1]["value"], "-2.50%");
+        assert_eq!(embed["fields"].as_array().unwrap().len(), 3);
+        assert_eq!(embed["timestamp"], "2024-05-01T00:00:00+00:00");
+    }
+}
@@ -0,0 +1,425 @@
+//! Conditions of declarative alert rules, e.g. `pair == WETH/USDC && abs(change_5m) > 2%`.
+//!
+//! Operators, loosest first: `||`, `&&`, `!`, comparisons (`== != < <= > >=`), `+ -`, `* /`, unary `-`.
+//! Numbers may end in `%` (`2%` is `0.02`) and use `_` separators. `abs`, `min` and `max` are the
+//! functions. Any word that isn't a variable is a string, so `pair == WETH/USDC` needs no quotes (a `/`
+//! between two words without spaces belongs to the word; write division with spaces); quote strings with
+//! other characters (`protocol == "uniswap-v3"`). Strings compare case-insensitively, and two `A/B`
+//! pairs are equal in either order. A variable the current event doesn't have makes its comparison false.
+
+use eyre::{bail, eyre, Result};
+use std::time::Duration;
+
+/// Variables every event may provide. `change_<n><s|m|h>` (e.g. `change_5m`) is also a variable.
+pub(crate) const VARIABLES: &[&str] = &[
+    "event",
+    "pair",
+    "pool",
+    "protocol",
+    "token0",
+    "token1",
+    "price",
+    "price_usd",
+    "previous_price",
+    "change",
+    "liquidity_usd",
+    "volume_24h_usd",
+    "swap_usd",
+    "amount0",
+    "amount1",
+    "spread_bps",
+    "net_spread_bps",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Text(String),
+    Variable(String),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+    Call(String, Vec<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Word(String),
+    Text(String),
+    Symbol(&'static str),
+}
+
+/// A parsed rule condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let root = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            bail!("unexpected {:?} in {:?}", token, source);
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The `change_<window>` windows the expression reads.
+    pub(crate) fn change_windows(&self) -> Vec<Duration> {
+        let mut windows = Vec::new();
+        visit(&self.root, &mut |node| {
+            if let Node::Variable(name) = node
+                && let Some(window) = change_window(name)
+            {
+                windows.push(window);
+            }
+        });
+        windows
+    }
+
+    /// Whether the expression is true with `variable` resolving variable names.
+    pub(crate) fn matches(&self, variable: &dyn Fn(&str) -> Option<Value>) -> bool {
+        eval(&self.root, variable) == Some(Value::Bool(true))
+    }
+}
+
+/// `change_5m` → 5 minutes.
+pub(crate) fn change_window(name: &str) -> Option<Duration> {
+    let spec = name.strip_prefix("change_")?;
+    let unit = match spec.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        _ => return None,
+    };
+    let count: u64 = spec[..spec.len() - 1].parse().ok()?;
+    Some(Duration::from_secs(count * unit))
+}
+
+fn is_variable(name: &str) -> bool {
+    VARIABLES.contains(&name) || change_window(name).is_some()
+}
+
+fn visit(node: &Node, f: &mut dyn FnMut(&Node)) {
+    f(node);
+    match node {
+        Node::Not(inner) | Node::Neg(inner) => visit(inner, f),
+        Node::Binary(_, left, right) => {
+            visit(left, f);
+            visit(right, f);
+        }
+        Node::Call(_, args) => args.iter().for_each(|arg| visit(arg, f)),
+        Node::Number(_) | Node::Text(_) | Node::Variable(_) => {}
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$'
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() && chars.get(i + 1) != Some(&'x') {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            let mut number: f64 = text.parse().map_err(|_| eyre!("invalid number {:?}", text))?;
+            if chars.get(i) == Some(&'%') {
+                number /= 100.0;
+                i += 1;
+            }
+            tokens.push(Token::Number(number));
+        } else if is_word_char(c) {
+            let start = i;
+            while i < chars.len()
+                && (is_word_char(chars[i])
+                    || (chars[i] == '/' && chars.get(i + 1).is_some_and(|c| is_word_char(*c)) && i > start))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else if c == '"' || c == '\'' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|other| *other == c)
+                .ok_or_else(|| eyre!("unterminated string in {:?}", source))?;
+            tokens.push(Token::Text(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let symbol = ["&&", "||", "==", "!=", "<=", ">="]
+                .into_iter()
+                .find(|symbol| *symbol == two)
+                .or_else(|| ["!", "<", ">", "+", "-", "*", "/", "(", ")", ","].into_iter().find(|s| s.starts_with(c)))
+                .ok_or_else(|| eyre!("unexpected {:?} in {:?}", c, source))?;
+            i += symbol.len();
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, symbol: &str) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Symbol(symbol_str(symbol))) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<()> {
+        if !self.eat(symbol) {
+            bail!("expected {:?}", symbol);
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, ops: &[(&str, Op)], next: fn(&mut Self) -> Result<Node>) -> Result<Node> {
+        let mut left = next(self)?;
+        'outer: loop {
+            for (symbol, op) in ops {
+                if self.eat(symbol) {
+                    let right = next(self)?;
+                    left = Node::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn or(&mut self) -> Result<Node> {
+        self.binary(&[("||", Op::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Node> {
+        self.binary(&[("&&", Op::And)], Self::not)
+    }
+
+    fn not(&mut self) -> Result<Node> {
+        if self.eat("!") {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Node> {
+        let left = self.sum()?;
+        let ops = [("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)];
+        for (symbol, op) in ops {
+            if self.eat(symbol) {
+                let right = self.sum()?;
+                if !matches!(op, Op::Eq | Op::Ne) {
+                    numeric(&left)?;
+                    numeric(&right)?;
+                }
+                return Ok(Node::Binary(op, Box::new(left), Box::new(right)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Node> {
+        self.binary(&[("+", Op::Add), ("-", Op::Sub)], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Node> {
+        self.binary(&[("*", Op::Mul), ("/", Op::Div)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        if self.eat("-") {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node> {
+        let token = self.tokens.get(self.position).cloned().ok_or_else(|| eyre!("unexpected end of expression"))?;
+        self.position += 1;
+        match token {
+            Token::Number(number) => Ok(Node::Number(number)),
+            Token::Text(text) => Ok(Node::Text(text)),
+            Token::Symbol("(") => {
+                let inner = self.or()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Token::Word(word) if self.eat("(") => {
+                let arity = match word.as_str() {
+                    "abs" => 1,
+                    "min" | "max" => 2,
+                    _ => bail!("unknown function {:?}", word),
+                };
+                let mut args = vec![self.or()?];
+                while self.eat(",") {
+                    args.push(self.or()?);
+                }
+                self.expect(")")?;
+                if args.len() != arity {
+                    bail!("{} takes {} argument(s)", word, arity);
+                }
+                args.iter().try_for_each(numeric)?;
+                Ok(Node::Call(word, args))
+            }
+            Token::Word(word) if is_variable(&word) => Ok(Node::Variable(word)),
+            Token::Word(word) => Ok(Node::Text(word)),
+            Token::Symbol(symbol) => bail!("unexpected {:?}", symbol),
+        }
+    }
+}
+
+fn symbol_str(symbol: &str) -> &'static str {
+    [
+        "&&", "||", "==", "!=", "<=", ">=", "!", "<", ">", "+", "-", "*", "/", "(", ")", ",",
+    ]
+    .into_iter()
+    .find(|s| *s == symbol)
+    .unwrap_or("")
+}
+
+/// Reject string literals where a number is expected; that's usually a misspelled variable.
+fn numeric(node: &Node) -> Result<()> {
+    match node {
+        Node::Text(text) => Err(eyre!("{:?} is not a number or a known variable", text)),
+        _ => Ok(()),
+    }
+}
+
+fn eval(node: &Node, variable: &dyn Fn(&str) -> Option<Value>) -> Option<Value> {
+    let number = |node: &Node| match eval(node, variable)? {
+        Value::Number(number) => Some(number),
+        _ => None,
+    };
+    let truth = |node: &Node| eval(node, variable) == Some(Value::Bool(true));
+    Some(match node {
+        Node::Number(number) => Value::Number(*number),
+        Node::Text(text) => Value::Text(text.clone()),
+        Node::Variable(name) => variable(name)?,
+        Node::Not(inner) => Value::Bool(!truth(inner)),
+        Node::Neg(inner) => Value::Number(-number(inner)?),
+        Node::Binary(Op::Or, left, right) => Value::Bool(truth(left) || truth(right)),
+        Node::Binary(Op::And, left, right) => Value::Bool(truth(left) && truth(right)),
+        Node::Binary(op @ (Op::Eq | Op::Ne), left, right) => {
+            let equal = equals(&eval(left, variable)?, &eval(right, variable)?);
+            Value::Bool(equal == (*op == Op::Eq))
+        }
+        Node::Binary(op, left, right) => {
+            let (left, right) = (number(left)?, number(right)?);
+            match op {
+                Op::Lt => Value::Bool(left < right),
+                Op::Le => Value::Bool(left <= right),
+                Op::Gt => Value::Bool(left > right),
+                Op::Ge => Value::Bool(left >= right),
+                Op::Add => Value::Number(left + right),
+                Op::Sub => Value::Number(left - right),
+                Op::Mul => Value::Number(left * right),
+                Op::Div => Value::Number(left / right),
+                Op::Or | Op::And | Op::Eq | Op::Ne => unreachable!("handled above"),
+            }
+        }
+        Node::Call(function, args) => {
+            let args: Vec<f64> = args.iter().map(number).collect::<Option<_>>()?;
+            Value::Number(match function.as_str() {
+                "abs" => args[0].abs(),
+                "min" => args[0].min(args[1]),
+                _ => args[0].max(args[1]),
+            })
+        }
+    })
+}
+
+fn equals(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Text(left), Value::Text(right)) => {
+            let pair = |text: &str| text.split_once('/').map(|(a, b)| (a.to_lowercase(), b.to_lowercase()));
+            match (pair(left), pair(right)) {
+                (Some((a, b)), Some((c, d))) => (a == c && b == d) || (a == d && b == c),
+                _ => left.eq_ignore_ascii_case(right),
+            }
+        }
+        _ => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_eval() {
+        let vars = |name: &str| match name {
+            "pair" => Some(Value::Text("WETH/USDC".to_string())),
+            "protocol" => Some(Value::Text("uniswap-v3".to_string())),
+            "change_5m" => Some(Value::Number(-0.025)),
+            "price" => Some(Value::Number(3000.0)),
+            _ => None,
+        };
+        let check = |source: &str| Expression::parse(source).unwrap().matches(&vars);
+
+        assert!(check("pair == WETH/USDC && abs(change_5m) > 2%"));
+        assert!(check("pair == usdc/weth"));
+        assert!(!check("pair != WETH/USDC"));
+        assert!(check("protocol == 'Uniswap-V3' || swap_usd > 1"));
+        assert!(check("price / 2 - 500 >= 1_000 && !(change_5m > 0)"));
+        assert!(check("max(price, 10) * -1 < -2999"));
+        // `swap_usd` is missing on this event.
+        assert!(!check("swap_usd > 500000"));
+        assert!(check("!(swap_usd > 500000)"));
+
+        let expression = Expression::parse("change_5m > 1% || change_1h < -5%").unwrap();
+        assert_eq!(expression.change_windows(), vec![Duration::from_secs(300), Duration::from_secs(3600)]);
+        assert!(Expression::parse("swap_us > 5").is_err());
+        assert!(Expression::parse("abs(price, 1) > 5").is_err());
+        assert!(Expression::parse("(price > 5").is_err());
+        assert!(Expression::parse("price > 5 5").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! What the incident notifiers (PagerDuty, Opsgenie) do with an alert: open an incident for an escalated
+//! kind, close it on the matching recovery, and ignore the rest.
+
+use super::{Alert, AlertKind};
+
+/// Kinds that open an incident by default: the feed is down, or connected but silent.
+pub(crate) const DEFAULT_ESCALATE: &[AlertKind] = &[AlertKind::Disconnected, AlertKind::FeedStale];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Incident {
+    /// Open (or, if still open, update) the incident with this key.
+    Open(String),
+    Close(String),
+}
+
+/// The incident action for `alert`, if its kind is in `escalate` or resolves one that is. The key is unique
+/// per kind and, for market alerts, per rule and pool, so repeats update the open incident.
+pub(crate) fn incident(alert: &Alert, escalate: &[AlertKind]) -> Option<Incident> {
+    let resolves = match alert.kind {
+        AlertKind::Reconnected => Some(AlertKind::Disconnected),
+        AlertKind::FeedResumed => Some(AlertKind::FeedStale),
+        _ => None,
+    };
+    if let Some(kind) = resolves {
+        return escalate.contains(&kind).then(|| Incident::Close(kind_name(kind)));
+    }
+    if !escalate.contains(&alert.kind) {
+        return None;
+    }
+    let mut key = kind_name(alert.kind);
+    if !alert.protocol.is_empty() && alert.pool.is_none() {
+        key = format!("{key}/{}", alert.protocol);
+    }
+    if let Some(pool) = alert.pool {
+        key = format!("{key}/{}/{pool}", alert.rule);
+    }
+    Some(Incident::Open(key))
+}
+
+/// `price_move` for `AlertKind::PriceMove`.
+fn kind_name(kind: AlertKind) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|kind| kind.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_incident() {
+        let alert = |kind, protocol: &str, pool| Alert {
+            rule: "scanner".to_string(),
+            kind,
+            pool,
+            protocol: protocol.to_string(),
+            pair: String::new(),
+            price: None,
+            change_percent: None,
+            volume_usd: None,
+            message: String::new(),
+            timestamp: 0,
+        };
+        let disconnected = alert(AlertKind::Disconnected, "", None);
+        assert_eq!(incident(&disconnected, DEFAULT_ESCALATE), Some(Incident::Open("disconnected".to_string())));
+        let reconnected = alert(AlertKind::Reconnected, "", None);
+        assert_eq!(incident(&reconnected, DEFAULT_ESCALATE), Some(Incident::Close("disconnected".to_string())));
+        assert_eq!(incident(&reconnected, &[AlertKind::FeedStale]), None);
+        assert_eq!(incident(&alert(AlertKind::ParseErrors, "", None), DEFAULT_ESCALATE), None);
+
+        let lag = alert(AlertKind::SubgraphLag, "uniswap-v3", None);
+        assert_eq!(
+            incident(&lag, &[AlertKind::SubgraphLag]),
+            Some(Incident::Open("subgraph_lag/uniswap-v3".to_string()))
+        );
+        let spread = alert(AlertKind::Spread, "uniswap-v3", Some(Address::ZERO));
+        assert_eq!(
+            incident(&spread, &[AlertKind::Spread]),
+            Some(Incident::Open(format!("spread/scanner/{}", Address::ZERO)))
+        );
+    }
+}
@@ -0,0 +1,808 @@
+//! Alerts: market rules evaluated on the event stream (price crossing a level, a move over a window, a
+//! cross-pool spread, a large swap, or an `Expression` loaded from a `RulesFile`) and operational checks on
+//! the scanner itself, delivered to notifiers such as webhooks, Telegram, Discord and Slack, or incident
+//! tools (PagerDuty, Opsgenie). Add an `AlertSink` (market) or `OpsAlertSink` (operational) to
+//! `ScannerOptions::sinks`; both inherit the sinks' retry policy and isolation from price delivery.
+
+use crate::events::ScannerEvent;
+use crate::rpc::Swap;
+use crate::rpc::pair_filter::PairFilter;
+use crate::sinks::{Pending, Sink};
+use crate::types::{CachedPool, PoolPrice};
+use expr::{change_window, Value};
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub mod discord;
+pub mod expr;
+mod incident;
+pub mod ops;
+pub mod opsgenie;
+pub mod pagerduty;
+pub mod rules;
+pub mod slack;
+pub mod telegram;
+pub mod template;
+pub mod webhook;
+
+pub use discord::{DiscordConfig, DiscordNotifier};
+pub use expr::Expression;
+pub use ops::{OpsAlertConfig, OpsAlertSink, ParseErrorThreshold};
+pub use opsgenie::{OpsgenieConfig, OpsgenieNotifier};
+pub use pagerduty::{PagerDutyConfig, PagerDutyNotifier};
+pub use rules::RulesFile;
+pub use slack::{SlackConfig, SlackNotifier};
+pub use telegram::{TelegramConfig, TelegramNotifier};
+pub use template::MessageTemplate;
+pub use webhook::{WebhookConfig, WebhookNotifier};
+
+/// What an `AlertRule` fires on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertCondition {
+    /// The token0 price (in token1) crosses `level`, in either direction.
+    PriceCross { level: f64 },
+    /// The token0 price moved at least `percent`, either way, from the oldest price seen within `window`.
+    PriceMove { percent: f64, window: Duration },
+    /// Two pools of the pair are at least `bps` apart. Needs `ScannerOptions::spread_threshold_bps` at or
+    /// below `bps`, since it is evaluated on `ScannerEvent::SpreadUpdate`.
+    Spread { bps: f64 },
+    /// A single swap worth at least `min_usd` (needs USD prices: `ScannerOptions::pricing` or a stablecoin
+    /// leg). Every such swap fires; the cool-down doesn't apply.
+    LargeSwap { min_usd: f64 },
+    /// The expression holds for a price update, swap or spread update (see `expr`). Like `LargeSwap`,
+    /// matches on swaps aren't subject to the cool-down.
+    Expression(Expression),
+}
+
+/// A named condition over a set of pools.
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    /// Identifies the rule in its alerts.
+    pub name: String,
+    /// Pools the rule watches; every pool when empty.
+    pub pools: Vec<Address>,
+    /// `A/B` pairs of token symbols or addresses, in either order; every pair when empty.
+    pub pairs: Vec<String>,
+    pub condition: AlertCondition,
+    /// Notifiers the rule's alerts go to, by `Notifier::name` (or the names given to `AlertSink::named`);
+    /// every notifier when empty.
+    pub notify: Vec<String>,
+}
+
+/// Rules and repeat suppression of an `AlertSink`.
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    pub rules: Vec<AlertRule>,
+    /// A rule that fired for a pool stays quiet for that pool this long, so a price hovering around a
+    /// level doesn't flood the notifiers.
+    pub cooldown: Duration,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            cooldown: Duration::from_secs(300),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    PriceCross,
+    PriceMove,
+    Spread,
+    LargeSwap,
+    /// An `AlertCondition::Expression` matched.
+    Expression,
+    /// Operational (`OpsAlertSink`): the log subscription has been down for `OpsAlertConfig::disconnected_after`.
+    Disconnected,
+    /// Operational: the subscription is back after a `Disconnected` alert.
+    Reconnected,
+    /// Operational: a subgraph trails the chain head (`ScannerError::SubgraphLag`).
+    SubgraphLag,
+    /// Operational: undecodable logs above `OpsAlertConfig::parse_errors`.
+    ParseErrors,
+    /// Operational: no pool has had a price update for `OpsAlertConfig::stale_feed_after`.
+    FeedStale,
+    /// Operational: price updates are back after a `FeedStale` alert.
+    FeedResumed,
+}
+
+/// A fired rule, as delivered to notifiers (and POSTed as JSON by `WebhookNotifier`). Operational alerts
+/// have no pool or price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule: String,
+    pub kind: AlertKind,
+    /// The pool that triggered the rule; for spreads, the pool with the higher price.
+    pub pool: Option<Address>,
+    pub protocol: String,
+    /// `token0/token1` symbols.
+    pub pair: String,
+    /// token0 price in token1.
+    pub price: Option<f64>,
+    /// The move behind a `PriceMove` (or over the first `change_<window>` of an expression), or the spread
+    /// behind a `Spread`, in percent.
+    pub change_percent: Option<f64>,
+    /// USD value of the swap behind a `LargeSwap` or an expression matching a swap.
+    #[serde(default)]
+    pub volume_usd: Option<f64>,
+    /// One-line human-readable summary.
+    pub message: String,
+    pub timestamp: u64,
+}
+
+/// Delivers alerts somewhere: a webhook, a chat, an incident tool.
+#[async_trait]
+pub trait Notifier: std::fmt::Debug + Send + Sync {
+    /// Label for logs and `ScannerError::Sink`.
+    fn name(&self) -> &str;
+
+    async fn notify(&self, alert: &Alert) -> Result<()>;
+}
+
+/// Evaluates the rules on the event stream and applies the cool-down.
+#[derive(Debug)]
+pub(crate) struct AlertEngine {
+    rules: Vec<(AlertRule, PairFilter)>,
+    cooldown: u64,
+    /// Longest `PriceMove` or `change_<window>` window, in seconds; price history older than this is dropped.
+    history_window: u64,
+    /// Pools seen in price updates, to describe spread alerts.
+    pools: HashMap<Address, CachedPool>,
+    /// `(timestamp, token0 price)` per pool within `history_window`, oldest first.
+    history: HashMap<Address, VecDeque<(u64, f64)>>,
+    /// When each `(rule, pool)` last fired.
+    fired: HashMap<(usize, Option<Address>), u64>,
+}
+
+impl AlertEngine {
+    pub(crate) fn new(config: AlertConfig) -> Self {
+        let history_window = config
+            .rules
+            .iter()
+            .flat_map(|rule| match &rule.condition {
+                AlertCondition::PriceMove { window, .. } => vec![window.as_secs()],
+                AlertCondition::Expression(expression) => {
+                    expression.change_windows().iter().map(Duration::as_secs).collect()
+                }
+                _ => Vec::new(),
+            })
+            .max()
+            .unwrap_or_default();
+        Self {
+            rules: config
+                .rules
+                .into_iter()
+                .map(|rule| {
+                    let pairs = PairFilter::from_pairs(rule.pairs.iter().map(String::as_str));
+                    (rule, pairs)
+                })
+                .collect(),
+            cooldown: config.cooldown.as_secs(),
+            history_window,
+            pools: HashMap::new(),
+            history: HashMap::new(),
+            fired: HashMap::new(),
+        }
+    }
+
+    /// Alerts fired by `event`, after the cool-down.
+    pub(crate) fn evaluate(&mut self, event: &ScannerEvent) -> Vec<Alert> {
+        let candidates = match event {
+            ScannerEvent::PriceUpdate { pool, price, previous } => self.on_price(pool, price, previous.as_ref()),
+            ScannerEvent::SpreadUpdate(spread) => {
+                let Some(pool) = self.pools.get(&spread.bid_pool) else {
+                    return Vec::new();
+                };
+                let pool = pool.clone();
+                let variables = Variables {
+                    event: "spread",
+                    price: Some(spread.bid_price),
+                    spread_bps: Some(spread.spread_bps),
+                    net_spread_bps: Some(spread.net_spread_bps),
+                    ..Variables::new(&pool, spread.timestamp)
+                };
+                self.matching(&pool)
+                    .filter_map(|(index, rule)| match &rule.condition {
+                        AlertCondition::Spread { bps } if spread.spread_bps >= *bps => Some((
+                            index,
+                            alert(
+                                rule,
+                                AlertKind::Spread,
+                                &pool,
+                                spread.bid_price,
+                                Some(spread.spread_bps / 100.0),
+                                spread.timestamp,
+                                format!(
+                                    "{} spread {:.1} bps: {} at {} vs {} at {}",
+                                    pair(&pool),
+                                    spread.spread_bps,
+                                    spread.bid_pool,
+                                    spread.bid_price,
+                                    spread.ask_pool,
+                                    spread.ask_price
+                                ),
+                            ),
+                        )),
+                        AlertCondition::Expression(expression) if variables.matches(expression) => Some((
+                            index,
+                            alert(
+                                rule,
+                                AlertKind::Expression,
+                                &pool,
+                                spread.bid_price,
+                                Some(spread.spread_bps / 100.0),
+                                spread.timestamp,
+                                format!("{}: {} (spread {:.1} bps)", pair(&pool), expression.source(), spread.spread_bps),
+                            ),
+                        )),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            // Each swap is a separate occurrence, so the cool-down doesn't apply.
+            ScannerEvent::Swap(swap) => return self.on_swap(swap),
+            _ => Vec::new(),
+        };
+        candidates
+            .into_iter()
+            .filter(|(index, alert)| {
+                let key = (*index, alert.pool);
+                if self.fired.get(&key).is_some_and(|last| alert.timestamp < last + self.cooldown) {
+                    return false;
+                }
+                self.fired.insert(key, alert.timestamp);
+                true
+            })
+            .map(|(_, alert)| alert)
+            .collect()
+    }
+
+    fn on_price(&mut self, pool: &CachedPool, price: &PoolPrice, previous: Option<&PoolPrice>) -> Vec<(usize, Alert)> {
+        let now = price.timestamp;
+        let current = price.token0_price;
+        self.pools.insert(pool.address, pool.clone());
+        if self.history_window > 0 && current.is_finite() {
+            let history = self.history.entry(pool.address).or_default();
+            history.push_back((now, current));
+            while history.front().is_some_and(|(t, _)| *t + self.history_window < now) {
+                history.pop_front();
+            }
+        }
+        let history = self.history.get(&pool.address);
+        let variables = Variables {
+            event: "price",
+            price: Some(current),
+            price_usd: price.token0_price_usd,
+            previous_price: previous.map(|previous| previous.token0_price),
+            history,
+            ..Variables::new(pool, now)
+        };
+        self.matching(pool)
+            .filter_map(|(index, rule)| {
+                let alert = match &rule.condition {
+                    AlertCondition::PriceCross { level } => {
+                        let level = *level;
+                        let before = previous?.token0_price;
+                        if (before < level) == (current < level) {
+                            return None;
+                        }
+                        let direction = if current >= level { "above" } else { "below" };
+                        alert(
+                            rule,
+                            AlertKind::PriceCross,
+                            pool,
+                            current,
+                            None,
+                            now,
+                            format!("{} crossed {} {} ({})", pair(pool), direction, level, current),
+                        )
+                    }
+                    AlertCondition::PriceMove { percent, window } => {
+                        let (since, start) = window_start(history?, *window, now)?;
+                        let change = (current / start - 1.0) * 100.0;
+                        if start <= 0.0 || !change.is_finite() || change.abs() < *percent {
+                            return None;
+                        }
+                        alert(
+                            rule,
+                            AlertKind::PriceMove,
+                            pool,
+                            current,
+                            Some(change),
+                            now,
+                            format!(
+                                "{} moved {:+.2}% in {}s ({} -> {})",
+                                pair(pool),
+                                change,
+                                now - since,
+                                start,
+                                current
+                            ),
+                        )
+                    }
+                    AlertCondition::Expression(expression) if variables.matches(expression) => {
+                        let change = expression
+                            .change_windows()
+                            .first()
+                            .and_then(|window| variables.change(*window))
+                            .map(|change| change * 100.0);
+                        alert(
+                            rule,
+                            AlertKind::Expression,
+                            pool,
+                            current,
+                            change,
+                            now,
+                            format!("{}: {} (price {})", pair(pool), expression.source(), current),
+                        )
+                    }
+                    AlertCondition::Spread { .. }
+                    | AlertCondition::LargeSwap { .. }
+                    | AlertCondition::Expression(_) => return None,
+                };
+                Some((index, alert))
+            })
+            .collect()
+    }
+
+    fn on_swap(&self, swap: &Swap) -> Vec<Alert> {
+        // A pool's first swap is published before its first price update.
+        let pool = self.pools.get(&swap.pool_address).cloned().unwrap_or_else(|| CachedPool {
+            address: swap.pool_address,
+            token0: swap.token0,
+            token1: swap.token1,
+            ..Default::default()
+        });
+        let variables = Variables {
+            event: "swap",
+            price: Some(swap.price),
+            swap_usd: swap.volume_usd,
+            amount0: Some(swap.amount0),
+            amount1: Some(swap.amount1),
+            ..Variables::new(&pool, swap.timestamp)
+        };
+        self.matching(&pool)
+            .filter_map(|(_, rule)| match &rule.condition {
+                AlertCondition::LargeSwap { min_usd } if swap.volume_usd.is_some_and(|volume| volume >= *min_usd) => {
+                    let volume_usd = swap.volume_usd.unwrap_or_default();
+                    let (sold, bought) = if swap.zero_for_one {
+                        (&pool.token0_symbol, &pool.token1_symbol)
+                    } else {
+                        (&pool.token1_symbol, &pool.token0_symbol)
+                    };
+                    let message = format!("${:.0} swap on {}: {} sold for {}", volume_usd, pair(&pool), sold, bought);
+                    let alert = alert(rule, AlertKind::LargeSwap, &pool, swap.price, None, swap.timestamp, message);
+                    Some(Alert { volume_usd: Some(volume_usd), ..alert })
+                }
+                AlertCondition::Expression(expression) if variables.matches(expression) => {
+                    let value = swap.volume_usd.map(|volume| format!(", ${volume:.0}")).unwrap_or_default();
+                    let message = format!("{}: {} (swap at {}{})", pair(&pool), expression.source(), swap.price, value);
+                    let alert = alert(rule, AlertKind::Expression, &pool, swap.price, None, swap.timestamp, message);
+                    Some(Alert { volume_usd: swap.volume_usd, ..alert })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Rules watching `pool`, with their index.
+    fn matching<'a>(&'a self, pool: &'a CachedPool) -> impl Iterator<Item = (usize, &'a AlertRule)> + 'a {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, (rule, pairs))| {
+                (rule.pools.is_empty() || rule.pools.contains(&pool.address)) && pairs.matches(pool)
+            })
+            .map(|(index, (rule, _))| (index, rule))
+    }
+}
+
+fn pair(pool: &CachedPool) -> String {
+    format!("{}/{}", pool.token0_symbol, pool.token1_symbol)
+}
+
+/// The oldest `(timestamp, price)` of `history` within `window` of `now`.
+fn window_start(history: &VecDeque<(u64, f64)>, window: Duration, now: u64) -> Option<(u64, f64)> {
+    history.iter().find(|(t, _)| *t + window.as_secs() >= now).copied()
+}
+
+/// The variables of `expr` for one event; those the event doesn't have are `None`.
+#[derive(Debug)]
+struct Variables<'a> {
+    event: &'static str,
+    pool: &'a CachedPool,
+    now: u64,
+    history: Option<&'a VecDeque<(u64, f64)>>,
+    price: Option<f64>,
+    price_usd: Option<f64>,
+    previous_price: Option<f64>,
+    swap_usd: Option<f64>,
+    amount0: Option<f64>,
+    amount1: Option<f64>,
+    spread_bps: Option<f64>,
+    net_spread_bps: Option<f64>,
+}
+
+impl<'a> Variables<'a> {
+    fn new(pool: &'a CachedPool, now: u64) -> Self {
+        Self {
+            event: "",
+            pool,
+            now,
+            history: None,
+            price: None,
+            price_usd: None,
+            previous_price: None,
+            swap_usd: None,
+            amount0: None,
+            amount1: None,
+            spread_bps: None,
+            net_spread_bps: None,
+        }
+    }
+
+    fn matches(&self, expression: &Expression) -> bool {
+        expression.matches(&|name| self.get(name))
+    }
+
+    /// Relative change of the price from the oldest one within `window`, e.g. `0.02` for +2%.
+    fn change(&self, window: Duration) -> Option<f64> {
+        let (_, start) = window_start(self.history?, window, self.now)?;
+        Some(self.price? / start - 1.0).filter(|change| start > 0.0 && change.is_finite())
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        let text = |text: &str| Some(Value::Text(text.to_string()));
+        let number = match name {
+            "event" => return text(self.event),
+            "pair" => return text(&pair(self.pool)),
+            "pool" => return text(&self.pool.address.to_string()),
+            "protocol" => return text(&self.pool.protocol),
+            "token0" => return text(&self.pool.token0_symbol),
+            "token1" => return text(&self.pool.token1_symbol),
+            "price" => self.price,
+            "price_usd" => self.price_usd,
+            "previous_price" => self.previous_price,
+            "change" => Some(self.price? / self.previous_price? - 1.0).filter(|change| change.is_finite()),
+            "liquidity_usd" => Some(self.pool.liquidity_usd),
+            "volume_24h_usd" => Some(self.pool.volume_24h_usd),
+            "swap_usd" => self.swap_usd,
+            "amount0" => self.amount0,
+            "amount1" => self.amount1,
+            "spread_bps" => self.spread_bps,
+            "net_spread_bps" => self.net_spread_bps,
+            name => self.change(change_window(name)?),
+        };
+        number.map(Value::Number)
+    }
+}
+
+fn alert(
+    rule: &AlertRule,
+    kind: AlertKind,
+    pool: &CachedPool,
+    price: f64,
+    change_percent: Option<f64>,
+    timestamp: u64,
+    message: String,
+) -> Alert {
+    Alert {
+        rule: rule.name.clone(),
+        kind,
+        pool: Some(pool.address),
+        protocol: pool.protocol.clone(),
+        pair: pair(pool),
+        price: Some(price),
+        change_percent,
+        volume_usd: None,
+        message: format!("[{}] {}", rule.name, message),
+        timestamp,
+    }
+}
+
+/// Alerts waiting for one notifier.
+#[derive(Debug)]
+struct Outbox {
+    /// What `AlertRule::notify` calls the notifier.
+    name: String,
+    notifier: Arc<dyn Notifier>,
+    pending: Pending<Alert>,
+}
+
+/// A queue of alerts per notifier, so one that is down gets its backlog on a later flush without repeating
+/// deliveries to the others.
+#[derive(Debug)]
+pub(crate) struct Outboxes(Vec<Outbox>);
+
+impl Outboxes {
+    pub(crate) fn new(notifiers: Vec<Arc<dyn Notifier>>) -> Self {
+        Self::named(notifiers.into_iter().map(|notifier| (notifier.name().to_string(), notifier)).collect())
+    }
+
+    pub(crate) fn named(notifiers: Vec<(String, Arc<dyn Notifier>)>) -> Self {
+        Self(
+            notifiers
+                .into_iter()
+                .map(|(name, notifier)| Outbox {
+                    name,
+                    notifier,
+                    pending: Pending::new(1, Duration::ZERO),
+                })
+                .collect(),
+        )
+    }
+
+    pub(crate) fn push(&self, alerts: Vec<Alert>) {
+        self.route(alerts, |_| &[]);
+    }
+
+    /// Queue each alert for the notifiers `notify` names for it, or for all of them when it names none.
+    pub(crate) fn route<'a>(&self, alerts: Vec<Alert>, notify: impl Fn(&Alert) -> &'a [String]) {
+        for alert in alerts {
+            let names = notify(&alert);
+            for outbox in &self.0 {
+                if names.is_empty() || names.contains(&outbox.name) {
+                    outbox.pending.push(alert.clone());
+                }
+            }
+        }
+    }
+
+    /// Deliver every queued alert in order; a notifier that fails keeps the rest of its queue.
+    pub(crate) async fn deliver(&self) -> Result<()> {
+        let mut result = Ok(());
+        for outbox in &self.0 {
+            let alerts = outbox.pending.take(true);
+            for (i, alert) in alerts.iter().enumerate() {
+                if let Err(e) = outbox.notifier.notify(alert).await {
+                    outbox.pending.restore(alerts[i..].to_vec());
+                    result = result.and(Err(e.wrap_err(format!("notifier {}", outbox.name))));
+                    break;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Evaluates `AlertConfig::rules` on every event and sends the alerts to each notifier (or those in the
+/// rule's `notify`). Alerts are queued per notifier, so one that is down gets its backlog on a later flush
+/// without repeating deliveries to the others.
+#[derive(Debug)]
+pub struct AlertSink {
+    engine: Mutex<AlertEngine>,
+    outboxes: Outboxes,
+    /// `AlertRule::notify` by rule name.
+    routes: HashMap<String, Vec<String>>,
+}
+
+impl AlertSink {
+    pub fn new(config: AlertConfig, notifiers: Vec<Arc<dyn Notifier>>) -> Self {
+        Self::with_outboxes(config, Outboxes::new(notifiers))
+    }
+
+    /// Like `new`, with the notifiers known to `AlertRule::notify` by the given names rather than their
+    /// `Notifier::name`, e.g. to tell two Slack channels apart.
+    pub fn named(config: AlertConfig, notifiers: Vec<(String, Arc<dyn Notifier>)>) -> Self {
+        Self::with_outboxes(config, Outboxes::named(notifiers))
+    }
+
+    fn with_outboxes(config: AlertConfig, outboxes: Outboxes) -> Self {
+        Self {
+            routes: config.rules.iter().map(|rule| (rule.name.clone(), rule.notify.clone())).collect(),
+            engine: Mutex::new(AlertEngine::new(config)),
+            outboxes,
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for AlertSink {
+    fn name(&self) -> &str {
+        "alerts"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        let alerts = self.engine.lock().unwrap().evaluate(event);
+        self.outboxes.route(alerts, |alert| self.routes.get(&alert.rule).map_or(&[], Vec::as_slice));
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.outboxes.deliver().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::SpreadUpdate;
+
+    fn update(pool: &CachedPool, token0_price: f64, timestamp: u64, previous: Option<f64>) -> ScannerEvent {
+        let price = |token0_price| PoolPrice {
+            pool_address: pool.address,
+            token0_price,
+            timestamp,
+            ..Default::default()
+        };
+        ScannerEvent::PriceUpdate {
+            pool: pool.clone(),
+            price: price(token0_price),
+            previous: previous.map(price),
+        }
+    }
+
+    #[test]
+    fn test_rules_and_cooldown() {
+        let weth_usdc = CachedPool {
+            address: Address::repeat_byte(1),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            ..Default::default()
+        };
+        let wbtc_usdc = CachedPool {
+            address: Address::repeat_byte(2),
+            token0_symbol: "WBTC".to_string(),
+            ..weth_usdc.clone()
+        };
+        let rule = |name: &str, condition| AlertRule {
+            name: name.to_string(),
+            pools: Vec::new(),
+            pairs: vec!["USDC/WETH".to_string()],
+            condition,
+            notify: Vec::new(),
+        };
+        let mut engine = AlertEngine::new(AlertConfig {
+            rules: vec![
+                rule("level", AlertCondition::PriceCross { level: 3000.0 }),
+                rule(
+                    "move",
+                    AlertCondition::PriceMove {
+                        percent: 2.0,
+                        window: Duration::from_secs(300),
+                    },
+                ),
+                rule("spread", AlertCondition::Spread { bps: 50.0 }),
+                rule("whale", AlertCondition::LargeSwap { min_usd: 100_000.0 }),
+            ],
+            cooldown: Duration::from_secs(60),
+        });
+        let names = |alerts: Vec<Alert>| alerts.into_iter().map(|alert| alert.rule).collect::<Vec<_>>();
+
+        assert!(engine.evaluate(&update(&weth_usdc, 2990.0, 1_000, None)).is_empty());
+        // Crosses 3000, but has only moved 0.5% since t=1000.
+        let alerts = engine.evaluate(&update(&weth_usdc, 3005.0, 1_010, Some(2990.0)));
+        assert_eq!(alerts[0].message, "[level] WETH/USDC crossed above 3000 (3005)");
+        assert_eq!(names(alerts), vec!["level"]);
+        // Crosses back down within the cool-down.
+        assert!(
+            engine
+                .evaluate(&update(&weth_usdc, 2995.0, 1_020, Some(3005.0)))
+                .is_empty()
+        );
+        // +2.34% from the t=1000 price, and the level cool-down is over.
+        let alerts = engine.evaluate(&update(&weth_usdc, 3060.0, 1_100, Some(2995.0)));
+        assert_eq!(
+            alerts[1].change_percent.map(|c| (c * 100.0).round() / 100.0),
+            Some(2.34)
+        );
+        assert_eq!(names(alerts), vec!["level", "move"]);
+        // Other pairs are out of scope.
+        assert!(
+            engine
+                .evaluate(&update(&wbtc_usdc, 1.0, 1_100, Some(5000.0)))
+                .is_empty()
+        );
+        // t=1000 and t=1010 left the window; +2.17% from the t=1020 price, but the move rule is cooling down.
+        assert!(
+            engine
+                .evaluate(&update(&weth_usdc, 3060.0, 1_150, Some(3060.0)))
+                .is_empty()
+        );
+
+        let spread = |spread_bps| {
+            ScannerEvent::SpreadUpdate(SpreadUpdate {
+                token0: Address::ZERO,
+                token1: Address::ZERO,
+                bid_pool: weth_usdc.address,
+                bid_price: 3060.0,
+                ask_pool: Address::repeat_byte(3),
+                ask_price: 3040.0,
+                spread_bps,
+                net_spread_bps: 0.0,
+                gas_cost_usd: None,
+                timestamp: 1_200,
+            })
+        };
+        assert!(engine.evaluate(&spread(40.0)).is_empty());
+        assert_eq!(names(engine.evaluate(&spread(65.0))), vec!["spread"]);
+
+        // Every large swap fires, cool-down or not.
+        let swap = Swap {
+            pool_address: weth_usdc.address,
+            amount0: -100.0,
+            amount1: 306_000.0,
+            zero_for_one: false,
+            price: 3060.0,
+            volume_usd: Some(306_000.0),
+            token0: Address::ZERO,
+            token1: Address::ZERO,
+            sender: Address::ZERO,
+            recipient: Address::ZERO,
+            transaction_hash: None,
+            log_index: None,
+            block_number: None,
+            timestamp: 1_300,
+        };
+        for _ in 0..2 {
+            let alerts = engine.evaluate(&ScannerEvent::Swap(swap.clone()));
+            assert_eq!(alerts[0].message, "[whale] $306000 swap on WETH/USDC: USDC sold for WETH");
+            assert_eq!(alerts[0].volume_usd, Some(306_000.0));
+        }
+        assert!(engine.evaluate(&ScannerEvent::Swap(Swap { volume_usd: Some(1_000.0), ..swap })).is_empty());
+    }
+
+    #[test]
+    fn test_expression_rules() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            protocol: "uniswap-v3".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            ..Default::default()
+        };
+        let rule = |name: &str, when: &str| AlertRule {
+            name: name.to_string(),
+            pools: Vec::new(),
+            pairs: Vec::new(),
+            condition: AlertCondition::Expression(Expression::parse(when).unwrap()),
+            notify: Vec::new(),
+        };
+        let mut engine = AlertEngine::new(AlertConfig {
+            rules: vec![
+                rule("move", "pair == USDC/WETH && abs(change_5m) > 2%"),
+                rule("whale", "event == swap && swap_usd > 500_000"),
+            ],
+            cooldown: Duration::from_secs(60),
+        });
+
+        assert!(engine.evaluate(&update(&pool, 3000.0, 1_000, None)).is_empty());
+        let alerts = engine.evaluate(&update(&pool, 2900.0, 1_100, Some(3000.0)));
+        assert_eq!(alerts[0].kind, AlertKind::Expression);
+        assert_eq!(alerts[0].message, "[move] WETH/USDC: pair == USDC/WETH && abs(change_5m) > 2% (price 2900)");
+        assert_eq!(alerts[0].change_percent.map(|c| (c * 100.0).round() / 100.0), Some(-3.33));
+        assert!(engine.evaluate(&update(&pool, 2850.0, 1_120, Some(2900.0))).is_empty());
+
+        let swap = Swap {
+            pool_address: pool.address,
+            amount0: 200.0,
+            amount1: -580_000.0,
+            zero_for_one: true,
+            price: 2900.0,
+            volume_usd: Some(580_000.0),
+            token0: Address::ZERO,
+            token1: Address::ZERO,
+            sender: Address::ZERO,
+            recipient: Address::ZERO,
+            transaction_hash: None,
+            log_index: None,
+            block_number: None,
+            timestamp: 1_130,
+        };
+        for _ in 0..2 {
+            let alerts = engine.evaluate(&ScannerEvent::Swap(swap.clone()));
+            assert_eq!(alerts.len(), 1);
+            assert_eq!(alerts[0].volume_usd, Some(580_000.0));
+        }
+        assert!(engine.evaluate(&ScannerEvent::Swap(Swap { volume_usd: None, ..swap })).is_empty());
+    }
+}
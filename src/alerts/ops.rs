@@ -0,0 +1,299 @@
+//! Operational alerts about the scanner itself (feed down or silent, stale subgraph, decode failures), kept
+//! apart from market alerts so they can go to an on-call channel or an incident tool.
+
+use super::{Alert, AlertKind, Notifier, Outboxes};
+use crate::events::{ScannerError, ScannerEvent, ScannerStatus};
+use crate::sinks::Sink;
+use async_trait::async_trait;
+use eyre::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Rule name of operational alerts.
+const OPS_RULE: &str = "scanner";
+
+/// Undecodable logs that make an alert: at least `count` within `window`.
+#[derive(Debug, Clone)]
+pub struct ParseErrorThreshold {
+    pub count: usize,
+    pub window: Duration,
+}
+
+/// Which operational conditions an `OpsAlertSink` reports.
+#[derive(Debug, Clone)]
+pub struct OpsAlertConfig {
+    /// Alert once the log subscription has been down (dropped, or failing to subscribe) this long, and again
+    /// when it is back. `None` turns both off.
+    pub disconnected_after: Option<Duration>,
+    /// Alert once no pool has had a price update for this long, counted from the first subscription, and
+    /// again when updates resume. Catches a feed that is connected but silent. `None` turns both off.
+    pub stale_feed_after: Option<Duration>,
+    pub parse_errors: Option<ParseErrorThreshold>,
+    /// Alert on `ScannerError::SubgraphLag` (needs `DiscoveryConfig::max_subgraph_lag_blocks` and periodic
+    /// rediscovery).
+    pub subgraph_lag: bool,
+    /// Parse-error and per-protocol subgraph alerts stay quiet this long after firing.
+    pub cooldown: Duration,
+}
+
+impl Default for OpsAlertConfig {
+    fn default() -> Self {
+        Self {
+            disconnected_after: Some(Duration::from_secs(60)),
+            stale_feed_after: Some(Duration::from_secs(600)),
+            parse_errors: Some(ParseErrorThreshold {
+                count: 20,
+                window: Duration::from_secs(300),
+            }),
+            subgraph_lag: true,
+            cooldown: Duration::from_secs(900),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OpsEngine {
+    config: OpsAlertConfig,
+    /// When the subscription went down, while it is.
+    down_since: Option<Instant>,
+    /// A `Disconnected` alert went out for the current outage.
+    disconnect_alerted: bool,
+    /// The latest price update, or the first subscription before any.
+    last_update: Option<Instant>,
+    /// A `FeedStale` alert went out since the latest price update.
+    stale_alerted: bool,
+    parse_errors: VecDeque<Instant>,
+    /// When each `(kind, protocol)` last fired, for the cool-down.
+    fired: HashMap<(AlertKind, String), Instant>,
+}
+
+impl OpsEngine {
+    fn new(config: OpsAlertConfig) -> Self {
+        Self {
+            config,
+            down_since: None,
+            disconnect_alerted: false,
+            last_update: None,
+            stale_alerted: false,
+            parse_errors: VecDeque::new(),
+            fired: HashMap::new(),
+        }
+    }
+
+    fn on_event(&mut self, event: &ScannerEvent, now: Instant) -> Vec<Alert> {
+        match event {
+            ScannerEvent::PriceUpdate { .. } => {
+                let silent = self.last_update.replace(now);
+                if !std::mem::take(&mut self.stale_alerted) {
+                    return Vec::new();
+                }
+                let silent = silent.map(|since| now.duration_since(since).as_secs()).unwrap_or_default();
+                vec![ops_alert(AlertKind::FeedResumed, "", format!("Price updates resumed after {silent}s"))]
+            }
+            ScannerEvent::Status(ScannerStatus::SubscriptionDropped)
+            | ScannerEvent::Error(ScannerError::Subscription(_)) => {
+                self.down_since.get_or_insert(now);
+                Vec::new()
+            }
+            ScannerEvent::Status(ScannerStatus::Subscribed { .. } | ScannerStatus::Reconnected) => {
+                self.last_update.get_or_insert(now);
+                let down_since = self.down_since.take();
+                if !std::mem::take(&mut self.disconnect_alerted) {
+                    return Vec::new();
+                }
+                let down = down_since.map(|since| now.duration_since(since).as_secs()).unwrap_or_default();
+                vec![ops_alert(AlertKind::Reconnected, "", format!("Log subscription restored after {down}s"))]
+            }
+            ScannerEvent::Error(ScannerError::Parse { pool, message }) => {
+                let Some(threshold) = self.config.parse_errors.clone() else { return Vec::new() };
+                self.parse_errors.push_back(now);
+                while self.parse_errors.front().is_some_and(|t| now.duration_since(*t) > threshold.window) {
+                    self.parse_errors.pop_front();
+                }
+                if self.parse_errors.len() < threshold.count || !self.cool(AlertKind::ParseErrors, "", now) {
+                    return Vec::new();
+                }
+                let message = format!(
+                    "{} logs failed to decode in the last {}s (latest: pool {pool}: {message})",
+                    self.parse_errors.len(),
+                    threshold.window.as_secs()
+                );
+                vec![ops_alert(AlertKind::ParseErrors, "", message)]
+            }
+            ScannerEvent::Error(ScannerError::SubgraphLag { protocol, lag_blocks })
+                if self.config.subgraph_lag && self.cool(AlertKind::SubgraphLag, protocol, now) =>
+            {
+                let message = format!("{protocol} subgraph is {lag_blocks} blocks behind the chain head");
+                vec![ops_alert(AlertKind::SubgraphLag, protocol, message)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The `Disconnected` alert once the current outage has lasted `disconnected_after`, and the `FeedStale`
+    /// alert once price updates have stopped for `stale_feed_after`.
+    fn check(&mut self, now: Instant) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        if let (Some(after), Some(since)) = (self.config.disconnected_after, self.down_since) {
+            let down = now.duration_since(since);
+            if !self.disconnect_alerted && down >= after {
+                self.disconnect_alerted = true;
+                let message = format!("Log subscription down for {}s; no price updates are coming in", down.as_secs());
+                alerts.push(ops_alert(AlertKind::Disconnected, "", message));
+            }
+        }
+        if let (Some(after), Some(since)) = (self.config.stale_feed_after, self.last_update) {
+            let silent = now.duration_since(since);
+            if !self.stale_alerted && silent >= after {
+                self.stale_alerted = true;
+                let message = format!("No price updates from any pool for {}s", silent.as_secs());
+                alerts.push(ops_alert(AlertKind::FeedStale, "", message));
+            }
+        }
+        alerts
+    }
+
+    /// Whether `(kind, protocol)` is out of its cool-down; records the firing if so.
+    fn cool(&mut self, kind: AlertKind, protocol: &str, now: Instant) -> bool {
+        let key = (kind, protocol.to_string());
+        if self.fired.get(&key).is_some_and(|last| now.duration_since(*last) < self.config.cooldown) {
+            return false;
+        }
+        self.fired.insert(key, now);
+        true
+    }
+}
+
+fn ops_alert(kind: AlertKind, protocol: &str, message: String) -> Alert {
+    Alert {
+        rule: OPS_RULE.to_string(),
+        kind,
+        pool: None,
+        protocol: protocol.to_string(),
+        pair: String::new(),
+        price: None,
+        change_percent: None,
+        volume_usd: None,
+        message: format!("[{OPS_RULE}] {message}"),
+        timestamp: chrono::Utc::now().timestamp() as u64,
+    }
+}
+
+/// Watches the scanner's price, status and error events and alerts each notifier when the feed is down or
+/// silent, a subgraph lags or decoding starts failing. Add it to `ScannerOptions::sinks`, typically with a
+/// `SlackNotifier` for an operations channel and a `PagerDutyNotifier` or `OpsgenieNotifier` to page.
+#[derive(Debug)]
+pub struct OpsAlertSink {
+    engine: Mutex<OpsEngine>,
+    outboxes: Outboxes,
+}
+
+impl OpsAlertSink {
+    pub fn new(config: OpsAlertConfig, notifiers: Vec<Arc<dyn Notifier>>) -> Self {
+        Self {
+            engine: Mutex::new(OpsEngine::new(config)),
+            outboxes: Outboxes::new(notifiers),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for OpsAlertSink {
+    fn name(&self) -> &str {
+        "ops-alerts"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        self.outboxes.push(self.engine.lock().unwrap().on_event(event, Instant::now()));
+        Ok(())
+    }
+
+    /// Runs at least once per second, which is what times the disconnect and stale-feed alerts.
+    async fn flush(&self) -> Result<()> {
+        self.outboxes.push(self.engine.lock().unwrap().check(Instant::now()));
+        self.outboxes.deliver().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_ops_alerts() {
+        let mut engine = OpsEngine::new(OpsAlertConfig {
+            parse_errors: Some(ParseErrorThreshold {
+                count: 3,
+                window: Duration::from_secs(60),
+            }),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let kinds = |alerts: Vec<Alert>| alerts.into_iter().map(|alert| alert.kind).collect::<Vec<_>>();
+
+        // A reconnect within a minute stays quiet.
+        assert!(engine.on_event(&ScannerEvent::Status(ScannerStatus::SubscriptionDropped), at(0)).is_empty());
+        assert!(engine.check(at(30)).is_empty());
+        assert!(engine.on_event(&ScannerEvent::Status(ScannerStatus::Reconnected), at(31)).is_empty());
+        assert!(engine.check(at(100)).is_empty());
+
+        // A longer outage alerts once, then reports the recovery.
+        engine.on_event(&ScannerEvent::Error(ScannerError::Subscription("refused".to_string())), at(100));
+        assert_eq!(kinds(engine.check(at(160))), vec![AlertKind::Disconnected]);
+        assert!(engine.check(at(170)).is_empty());
+        let alerts = engine.on_event(&ScannerEvent::Status(ScannerStatus::Reconnected), at(190));
+        assert_eq!(alerts[0].message, "[scanner] Log subscription restored after 90s");
+
+        let parse_error = ScannerEvent::Error(ScannerError::Parse {
+            pool: Address::ZERO,
+            message: "bad data".to_string(),
+        });
+        assert!(engine.on_event(&parse_error, at(200)).is_empty());
+        assert!(engine.on_event(&parse_error, at(270)).is_empty());
+        assert!(engine.on_event(&parse_error, at(280)).is_empty());
+        assert_eq!(kinds(engine.on_event(&parse_error, at(290))), vec![AlertKind::ParseErrors]);
+        assert!(engine.on_event(&parse_error, at(295)).is_empty());
+
+        let lag = |protocol: &str| {
+            ScannerEvent::Error(ScannerError::SubgraphLag {
+                protocol: protocol.to_string(),
+                lag_blocks: 500,
+            })
+        };
+        assert_eq!(kinds(engine.on_event(&lag("uniswap-v3"), at(300))), vec![AlertKind::SubgraphLag]);
+        assert!(engine.on_event(&lag("uniswap-v3"), at(400)).is_empty());
+        assert_eq!(engine.on_event(&lag("uniswap-v2"), at(400))[0].protocol, "uniswap-v2");
+    }
+
+    #[test]
+    fn test_stale_feed() {
+        let mut engine = OpsEngine::new(OpsAlertConfig {
+            disconnected_after: None,
+            stale_feed_after: Some(Duration::from_secs(300)),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let update = ScannerEvent::PriceUpdate {
+            pool: Default::default(),
+            price: Default::default(),
+            previous: None,
+        };
+
+        // The clock starts at the first subscription, not during discovery.
+        assert!(engine.check(at(1_000)).is_empty());
+        engine.on_event(&ScannerEvent::Status(ScannerStatus::Subscribed { pools: 3 }), at(1_000));
+        assert!(engine.on_event(&update, at(1_100)).is_empty());
+        assert!(engine.check(at(1_399)).is_empty());
+        let alerts = engine.check(at(1_400));
+        assert_eq!(alerts[0].kind, AlertKind::FeedStale);
+        assert_eq!(alerts[0].message, "[scanner] No price updates from any pool for 300s");
+        assert!(engine.check(at(1_500)).is_empty());
+        let alerts = engine.on_event(&update, at(1_600));
+        assert_eq!(alerts[0].message, "[scanner] Price updates resumed after 500s");
+        assert!(engine.on_event(&update, at(1_601)).is_empty());
+    }
+}
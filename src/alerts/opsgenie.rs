@@ -0,0 +1,104 @@
+//! Incidents opened and closed as Opsgenie alerts through its Alert API.
+
+use super::incident::{incident, Incident, DEFAULT_ESCALATE};
+use super::{Alert, AlertKind, Notifier};
+use async_trait::async_trait;
+use eyre::{bail, eyre, Result, WrapErr};
+use reqwest::{Client, Url};
+use serde_json::json;
+
+/// API integration and escalation policy of an `OpsgenieNotifier`.
+#[derive(Debug, Clone)]
+pub struct OpsgenieConfig {
+    /// Key of an "API" integration of the team.
+    pub api_key: String,
+    /// `P1` (critical) to `P5` (informational).
+    pub priority: String,
+    /// The alerts' `source` and the prefix of their aliases, so several scanners can share a team.
+    pub source: String,
+    /// Alert kinds that open an Opsgenie alert; other alerts are ignored. The feed being down or silent by
+    /// default.
+    pub escalate: Vec<AlertKind>,
+    /// `https://api.eu.opsgenie.com` for EU accounts.
+    pub api_url: String,
+}
+
+impl Default for OpsgenieConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            priority: "P1".to_string(),
+            source: "dex-pool-scanner".to_string(),
+            escalate: DEFAULT_ESCALATE.to_vec(),
+            api_url: "https://api.opsgenie.com".to_string(),
+        }
+    }
+}
+
+/// Creates an Opsgenie alert for each escalated alert and closes it when the matching recovery alert
+/// (`Reconnected`, `FeedResumed`) arrives. Repeats of an open alert are deduplicated by its alias.
+#[derive(Debug)]
+pub struct OpsgenieNotifier {
+    config: OpsgenieConfig,
+    client: Client,
+}
+
+impl OpsgenieNotifier {
+    pub fn new(config: OpsgenieConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    /// `{api_url}/v2/alerts/{segments…}`, with the segments escaped.
+    fn url(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = Url::parse(&self.config.api_url).wrap_err("Opsgenie api_url")?;
+        url.path_segments_mut()
+            .map_err(|_| eyre!("Opsgenie api_url {} cannot be a base", self.config.api_url))?
+            .pop_if_empty()
+            .extend(["v2", "alerts"])
+            .extend(segments);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Notifier for OpsgenieNotifier {
+    fn name(&self) -> &str {
+        "opsgenie"
+    }
+
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let Some(incident) = incident(alert, &self.config.escalate) else { return Ok(()) };
+        let (request, what) = match incident {
+            Incident::Open(key) => {
+                let body = json!({
+                    "message": alert.message.chars().take(130).collect::<String>(),
+                    "alias": format!("{}:{key}", self.config.source),
+                    "description": alert.message,
+                    "priority": self.config.priority,
+                    "source": self.config.source,
+                    "details": { "rule": alert.rule, "protocol": alert.protocol, "pair": alert.pair },
+                });
+                (self.client.post(self.url(&[])?).json(&body), "create")
+            }
+            Incident::Close(key) => {
+                let alias = format!("{}:{key}", self.config.source);
+                let body = json!({ "source": self.config.source, "note": alert.message });
+                let url = self.url(&[&alias, "close"])?;
+                (self.client.post(url).query(&[("identifierType", "alias")]).json(&body), "close")
+            }
+        };
+        let response = request
+            .header("Authorization", format!("GenieKey {}", self.config.api_key))
+            .send()
+            .await
+            .wrap_err_with(|| format!("Opsgenie {what}"))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            bail!("Opsgenie {what}: {} {}", status, response.text().await.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! Incidents opened and resolved through the PagerDuty Events API v2.
+
+use super::incident::{incident, Incident, DEFAULT_ESCALATE};
+use super::{Alert, AlertKind, Notifier};
+use async_trait::async_trait;
+use eyre::{bail, Result, WrapErr};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+/// Service integration and escalation policy of a `PagerDutyNotifier`.
+#[derive(Debug, Clone)]
+pub struct PagerDutyConfig {
+    /// Integration key of an "Events API v2" integration on the service.
+    pub routing_key: String,
+    /// `payload.source` of the events and the prefix of their dedup keys, so several scanners can share a
+    /// service.
+    pub source: String,
+    /// `critical`, `error`, `warning` or `info`.
+    pub severity: String,
+    /// Alert kinds that open an incident; other alerts are ignored. The feed being down or silent by default.
+    pub escalate: Vec<AlertKind>,
+    pub api_url: String,
+}
+
+impl Default for PagerDutyConfig {
+    fn default() -> Self {
+        Self {
+            routing_key: String::new(),
+            source: "dex-pool-scanner".to_string(),
+            severity: "critical".to_string(),
+            escalate: DEFAULT_ESCALATE.to_vec(),
+            api_url: "https://events.pagerduty.com/v2/enqueue".to_string(),
+        }
+    }
+}
+
+/// Triggers an incident for each escalated alert and resolves it when the matching recovery alert
+/// (`Reconnected`, `FeedResumed`) arrives. Repeats of an open incident are deduplicated by PagerDuty.
+#[derive(Debug)]
+pub struct PagerDutyNotifier {
+    config: PagerDutyConfig,
+    client: Client,
+}
+
+impl PagerDutyNotifier {
+    pub fn new(config: PagerDutyConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+}
+
+/// The Events API body for `alert`, if it opens or resolves an incident.
+fn event(alert: &Alert, config: &PagerDutyConfig) -> Option<Value> {
+    Some(match incident(alert, &config.escalate)? {
+        Incident::Open(key) => json!({
+            "routing_key": config.routing_key,
+            "event_action": "trigger",
+            "dedup_key": format!("{}/{key}", config.source),
+            "payload": {
+                "summary": alert.message,
+                "source": config.source,
+                "severity": config.severity,
+                "timestamp": chrono::DateTime::from_timestamp(alert.timestamp as i64, 0).map(|t| t.to_rfc3339()),
+                "component": alert.protocol,
+                "custom_details": alert,
+            },
+        }),
+        Incident::Close(key) => json!({
+            "routing_key": config.routing_key,
+            "event_action": "resolve",
+            "dedup_key": format!("{}/{key}", config.source),
+        }),
+    })
+}
+
+#[async_trait]
+impl Notifier for PagerDutyNotifier {
+    fn name(&self) -> &str {
+        "pagerduty"
+    }
+
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let Some(event) = event(alert, &self.config) else { return Ok(()) };
+        let response = self
+            .client
+            .post(&self.config.api_url)
+            .json(&event)
+            .send()
+            .await
+            .wrap_err("PagerDuty enqueue")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            bail!("PagerDuty enqueue: {} {}", status, response.text().await.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event() {
+        let config = PagerDutyConfig {
+            routing_key: "R0UT1NG".to_string(),
+            ..Default::default()
+        };
+        let alert = |kind, message: &str| Alert {
+            rule: "scanner".to_string(),
+            kind,
+            pool: None,
+            protocol: String::new(),
+            pair: String::new(),
+            price: None,
+            change_percent: None,
+            volume_usd: None,
+            message: message.to_string(),
+            timestamp: 1_700_000_000,
+        };
+
+        let trigger = event(&alert(AlertKind::FeedStale, "[scanner] No price updates"), &config).unwrap();
+        assert_eq!(trigger["event_action"], "trigger");
+        assert_eq!(trigger["dedup_key"], "dex-pool-scanner/feed_stale");
+        assert_eq!(trigger["payload"]["summary"], "[scanner] No price updates");
+        assert_eq!(trigger["payload"]["severity"], "critical");
+        assert_eq!(trigger["payload"]["timestamp"], "2023-11-14T22:13:20+00:00");
+        let resolve = event(&alert(AlertKind::FeedResumed, ""), &config).unwrap();
+        assert_eq!(resolve["event_action"], "resolve");
+        assert_eq!(resolve["dedup_key"], trigger["dedup_key"]);
+        assert!(event(&alert(AlertKind::ParseErrors, ""), &config).is_none());
+    }
+}
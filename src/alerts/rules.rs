@@ -0,0 +1,220 @@
+//! Declarative alert rules: named notifiers and `when` expressions in a YAML or JSON file, e.g.
+//!
+//! ```yaml
+//! cooldownSecs: 300
+//! notifiers:
+//!   traders: { type: telegram, botToken: "123456:ABC", chatId: "-1001234" }
+//!   ops: { type: slack, webhookUrl: "https://hooks.slack.com/services/T0/B0/X" }
+//! rules:
+//!   - name: eth-move
+//!     when: "pair == WETH/USDC && abs(change_5m) > 2%"
+//!     notify: [traders]
+//!   - name: whales
+//!     when: "swap_usd > 500000"
+//! ```
+
+use super::{
+    AlertCondition, AlertConfig, AlertRule, AlertSink, DiscordConfig, DiscordNotifier, Expression,
+    MessageTemplate, Notifier, SlackConfig, SlackNotifier, TelegramConfig, TelegramNotifier, WebhookConfig,
+    WebhookNotifier,
+};
+use alloy::primitives::Address;
+use eyre::{bail, Result, WrapErr};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Root of a rules file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RulesFile {
+    /// `AlertConfig::cooldown`, 300 by default.
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// Notifiers by the name rules refer to them with.
+    #[serde(default)]
+    pub notifiers: BTreeMap<String, NotifierEntry>,
+    pub rules: Vec<RuleEntry>,
+}
+
+fn default_cooldown_secs() -> u64 {
+    300
+}
+
+/// A notifier of a rules file, by `type`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase", deny_unknown_fields)]
+pub enum NotifierEntry {
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        timeout_secs: Option<u64>,
+    },
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+        template: Option<String>,
+        explorer_url: Option<String>,
+    },
+    Discord {
+        webhook_url: String,
+        username: Option<String>,
+        explorer_url: Option<String>,
+    },
+    Slack {
+        webhook_url: String,
+        template: Option<String>,
+        explorer_url: Option<String>,
+    },
+}
+
+/// A rule of a rules file; `pools` and `pairs` scope it like `AlertRule`'s.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RuleEntry {
+    pub name: String,
+    /// The condition, an `Expression`.
+    pub when: String,
+    #[serde(default)]
+    pub pools: Vec<Address>,
+    #[serde(default)]
+    pub pairs: Vec<String>,
+    /// Names from `notifiers`; every notifier when empty.
+    #[serde(default)]
+    pub notify: Vec<String>,
+}
+
+impl RulesFile {
+    /// Read a rules file: YAML when it ends in `.yaml` or `.yml`, JSON otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).wrap_err_with(|| format!("reading {}", path.display()))?;
+        let yaml = path.extension().is_some_and(|extension| extension == "yaml" || extension == "yml");
+        if yaml { Self::from_yaml(&content) } else { Self::from_json(&content) }
+            .wrap_err_with(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    /// Parse the rules' expressions; an invalid one, or a `notify` name not in `notifiers`, is an error.
+    pub fn alert_config(&self) -> Result<AlertConfig> {
+        let mut rules = Vec::new();
+        for rule in &self.rules {
+            if let Some(name) = rule.notify.iter().find(|name| !self.notifiers.contains_key(*name)) {
+                bail!("rule {}: unknown notifier {:?}", rule.name, name);
+            }
+            let expression = Expression::parse(&rule.when).wrap_err_with(|| format!("rule {}", rule.name))?;
+            rules.push(AlertRule {
+                name: rule.name.clone(),
+                pools: rule.pools.clone(),
+                pairs: rule.pairs.clone(),
+                condition: AlertCondition::Expression(expression),
+                notify: rule.notify.clone(),
+            });
+        }
+        Ok(AlertConfig {
+            rules,
+            cooldown: Duration::from_secs(self.cooldown_secs),
+        })
+    }
+
+    /// An `AlertSink` with the file's rules and notifiers; add it to `ScannerOptions::sinks`.
+    pub fn into_sink(self) -> Result<AlertSink> {
+        let config = self.alert_config()?;
+        let notifiers = self
+            .notifiers
+            .into_iter()
+            .map(|(name, entry)| (name, entry.into_notifier()))
+            .collect();
+        Ok(AlertSink::named(config, notifiers))
+    }
+}
+
+impl NotifierEntry {
+    fn into_notifier(self) -> Arc<dyn Notifier> {
+        let template = |template: Option<String>, explorer_url: Option<String>, default: MessageTemplate| {
+            MessageTemplate {
+                template: template.unwrap_or(default.template),
+                explorer_url: explorer_url.unwrap_or(default.explorer_url),
+            }
+        };
+        match self {
+            NotifierEntry::Webhook { url, headers, timeout_secs } => {
+                let defaults = WebhookConfig::default();
+                Arc::new(WebhookNotifier::new(WebhookConfig {
+                    url,
+                    headers: headers.into_iter().collect(),
+                    timeout: timeout_secs.map_or(defaults.timeout, Duration::from_secs),
+                }))
+            }
+            NotifierEntry::Telegram { bot_token, chat_id, template: text, explorer_url } => {
+                let defaults = TelegramConfig::default();
+                Arc::new(TelegramNotifier::new(TelegramConfig {
+                    bot_token,
+                    chat_id,
+                    template: template(text, explorer_url, defaults.template.clone()),
+                    ..defaults
+                }))
+            }
+            NotifierEntry::Discord { webhook_url, username, explorer_url } => {
+                let defaults = DiscordConfig::default();
+                Arc::new(DiscordNotifier::new(DiscordConfig {
+                    webhook_url,
+                    username,
+                    explorer_url: explorer_url.unwrap_or(defaults.explorer_url),
+                }))
+            }
+            NotifierEntry::Slack { webhook_url, template: text, explorer_url } => {
+                let defaults = SlackConfig::default();
+                Arc::new(SlackNotifier::new(SlackConfig {
+                    webhook_url,
+                    template: template(text, explorer_url, defaults.template),
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_file() {
+        let yaml = r#"
+cooldownSecs: 60
+notifiers:
+  traders: { type: telegram, botToken: "123:ABC", chatId: "-100" }
+  ops: { type: slack, webhookUrl: "https://hooks.slack.com/services/T0/B0/X", template: "{message}" }
+rules:
+  - name: eth-move
+    when: "pair == WETH/USDC && abs(change_5m) > 2%"
+    notify: [traders]
+  - name: whales
+    when: swap_usd > 500000
+"#;
+        let file = RulesFile::from_yaml(yaml).unwrap();
+        let config = file.alert_config().unwrap();
+        assert_eq!(config.cooldown, Duration::from_secs(60));
+        assert_eq!(config.rules[0].notify, vec!["traders"]);
+        assert!(matches!(&config.rules[1].condition, AlertCondition::Expression(e) if e.source() == "swap_usd > 500000"));
+        file.into_sink().unwrap();
+
+        let json = r#"{ "rules": [{ "name": "typo", "when": "swap_us > 5" }] }"#;
+        let error = RulesFile::from_json(json).unwrap().alert_config().unwrap_err();
+        assert!(format!("{error:#}").starts_with("rule typo: \"swap_us\""), "{error:#}");
+        let json = r#"{ "rules": [{ "name": "lost", "when": "price > 5", "notify": ["pager"] }] }"#;
+        assert!(RulesFile::from_json(json).unwrap().alert_config().is_err());
+        assert!(RulesFile::from_json(r#"{ "rules": [], "cooldown": 5 }"#).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Alerts posted to a Slack channel through an incoming webhook.
+
+use super::template::MessageTemplate;
+use super::{Alert, Notifier};
+use async_trait::async_trait;
+use eyre::{bail, Result, WrapErr};
+use reqwest::Client;
+use serde_json::json;
+
+/// Webhook and message layout of a `SlackNotifier`.
+#[derive(Debug, Clone)]
+pub struct SlackConfig {
+    /// Incoming webhook of the channel, `https://hooks.slack.com/services/…`.
+    pub webhook_url: String,
+    pub template: MessageTemplate,
+}
+
+impl Default for SlackConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: String::new(),
+            template: MessageTemplate {
+                template: ":rotating_light: {message}\n{pool_link}".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Posts each alert as a text message rendered from `SlackConfig::template`.
+#[derive(Debug)]
+pub struct SlackNotifier {
+    config: SlackConfig,
+    client: Client,
+}
+
+impl SlackNotifier {
+    pub fn new(config: SlackConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.config.webhook_url)
+            .json(&json!({ "text": self.config.template.render(alert) }))
+            .send()
+            .await
+            .wrap_err("Slack webhook")?;
+        if !response.status().is_success() {
+            // Slack answers with a short reason such as `invalid_token` or `channel_is_archived`.
+            bail!("Slack webhook failed ({}): {}", response.status(), response.text().await.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
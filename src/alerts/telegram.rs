@@ -0,0 +1,74 @@
+//! Alerts sent as Telegram messages by a bot, to a user, group or channel.
+
+use super::template::MessageTemplate;
+use super::{Alert, Notifier};
+use async_trait::async_trait;
+use eyre::{bail, Result, WrapErr};
+use reqwest::Client;
+use serde_json::json;
+
+/// Bot, chat and message layout of a `TelegramNotifier`.
+#[derive(Debug, Clone)]
+pub struct TelegramConfig {
+    /// Token from @BotFather, `123456:ABC-…`.
+    pub bot_token: String,
+    /// Numeric chat id (negative for groups) or `@channelname`. The bot must be a member.
+    pub chat_id: String,
+    pub template: MessageTemplate,
+    /// Bot API server; the public one by default.
+    pub api_url: String,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            bot_token: String::new(),
+            chat_id: String::new(),
+            template: MessageTemplate::default(),
+            api_url: "https://api.telegram.org".to_string(),
+        }
+    }
+}
+
+/// Sends each alert with the Bot API's `sendMessage`, as plain text rendered from `TelegramConfig::template`.
+#[derive(Debug)]
+pub struct TelegramNotifier {
+    config: TelegramConfig,
+    client: Client,
+}
+
+impl TelegramNotifier {
+    pub fn new(config: TelegramConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let url = format!("{}/bot{}/sendMessage", self.config.api_url, self.config.bot_token);
+        let response = self
+            .client
+            .post(url)
+            .json(&json!({
+                "chat_id": self.config.chat_id,
+                "text": self.config.template.render(alert),
+                "disable_web_page_preview": true,
+            }))
+            .send()
+            .await
+            .wrap_err("Telegram sendMessage")?;
+        if !response.status().is_success() {
+            // The body's `description` says why, e.g. "Bad Request: chat not found"; the URL holds the token.
+            bail!("Telegram sendMessage failed ({}): {}", response.status(), response.text().await.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
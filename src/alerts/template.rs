@@ -0,0 +1,100 @@
+//! Plain-text alert messages for chat notifiers, from a template with `{placeholder}`s.
+
+use super::Alert;
+
+/// Message layout of a chat notifier. Placeholders: `{rule}`, `{kind}`, `{pair}`, `{protocol}`, `{pool}`,
+/// `{pool_link}`, `{price}` (the last three empty for operational alerts), `{change}` (e.g. `+2.34%`, empty when the alert has none), `{volume_usd}`
+/// (e.g. `$1250000`, empty likewise), `{message}` and `{timestamp}`; unknown ones are left as they are.
+#[derive(Debug, Clone)]
+pub struct MessageTemplate {
+    pub template: String,
+    /// Block explorer page of an address, for `{pool_link}`: the pool address is appended.
+    pub explorer_url: String,
+}
+
+impl Default for MessageTemplate {
+    fn default() -> Self {
+        Self {
+            template: "🔔 {pair} {price} {change}\n{message}\n{pool_link}".to_string(),
+            explorer_url: "https://etherscan.io/address/".to_string(),
+        }
+    }
+}
+
+impl MessageTemplate {
+    pub fn render(&self, alert: &Alert) -> String {
+        let kind = serde_json::to_value(alert.kind)
+            .ok()
+            .and_then(|kind| kind.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let change = alert.change_percent.map(|change| format!("{change:+.2}%")).unwrap_or_default();
+        [
+            ("{rule}", alert.rule.clone()),
+            ("{kind}", kind),
+            ("{pair}", alert.pair.clone()),
+            ("{protocol}", alert.protocol.clone()),
+            ("{pool_link}", alert.pool.map(|pool| format!("{}{pool}", self.explorer_url)).unwrap_or_default()),
+            ("{pool}", alert.pool.map(|pool| pool.to_string()).unwrap_or_default()),
+            ("{price}", alert.price.map(format_price).unwrap_or_default()),
+            ("{change}", change),
+            ("{volume_usd}", alert.volume_usd.map(|volume| format!("${volume:.0}")).unwrap_or_default()),
+            ("{message}", alert.message.clone()),
+            ("{timestamp}", alert.timestamp.to_string()),
+        ]
+        .iter()
+        .fold(self.template.clone(), |text, (placeholder, value)| text.replace(placeholder, value))
+        .trim()
+        .to_string()
+    }
+}
+
+/// A price with six significant digits (at most 12 decimals), without trailing zeros.
+pub(crate) fn format_price(price: f64) -> String {
+    if !price.is_finite() || price == 0.0 {
+        return price.to_string();
+    }
+    let decimals = (5 - price.abs().log10().floor() as i32).clamp(0, 12) as usize;
+    let text = format!("{price:.decimals$}");
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::AlertKind;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_render() {
+        let alert = Alert {
+            rule: "eth-move".to_string(),
+            kind: AlertKind::PriceMove,
+            pool: Some(Address::repeat_byte(0xab)),
+            protocol: "uniswap-v3".to_string(),
+            pair: "WETH/USDC".to_string(),
+            price: Some(3061.234567),
+            change_percent: Some(2.3456),
+            volume_usd: None,
+            message: "moved".to_string(),
+            timestamp: 1_714_521_600,
+        };
+        let template = MessageTemplate {
+            template: "{kind} {pair} @ {price} ({change}) {pool_link} {unknown}".to_string(),
+            explorer_url: "https://basescan.org/address/".to_string(),
+        };
+        assert_eq!(
+            template.render(&alert),
+            format!("price_move WETH/USDC @ 3061.23 (+2.35%) https://basescan.org/address/{} {{unknown}}", Address::repeat_byte(0xab))
+        );
+        let default = MessageTemplate::default().render(&Alert { change_percent: None, ..alert });
+        assert!(default.starts_with("🔔 WETH/USDC 3061.23 \nmoved\nhttps://etherscan.io/address/"));
+
+        assert_eq!(format_price(0.000123456789), "0.000123457");
+        assert_eq!(format_price(1_234_567.8), "1234568");
+        assert_eq!(format_price(1.5), "1.5");
+    }
+}
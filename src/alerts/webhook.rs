@@ -0,0 +1,67 @@
+//! Alerts POSTed as JSON to an HTTP endpoint, for anything that accepts webhooks (n8n, Zapier, a custom
+//! service).
+
+use super::{Alert, Notifier};
+use async_trait::async_trait;
+use eyre::{Result, WrapErr};
+use reqwest::Client;
+use std::time::Duration;
+
+/// Endpoint of a `WebhookNotifier`.
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Extra request headers, e.g. `("Authorization", "Bearer …")`.
+    pub headers: Vec<(String, String)>,
+    pub timeout: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            headers: Vec::new(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// POSTs each alert as a JSON `Alert` object; any non-2xx response is a failure (and retried).
+#[derive(Debug)]
+pub struct WebhookNotifier {
+    config: WebhookConfig,
+    client: Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.config.url)
+            .timeout(self.config.timeout)
+            .json(alert);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .wrap_err_with(|| format!("POST {}", self.config.url))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use crate::types::CachedPool;
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Fee assumed for pools whose fee discovery didn't report (V2 pairs): 0.3%.
+const DEFAULT_FEE: u32 = 3000;
+/// Total log-rate a cycle must beat, so float noise on break-even cycles isn't reported.
+const EPSILON: f64 = 1e-9;
+/// Longest cycle searched, in tokens; each extra token multiplies the paths walked by the tokens' pool count.
+const MAX_CYCLE_TOKENS: usize = 4;
+
+/// A profitable cycle of swaps through three or four tokens (A → B → C → A).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleOpportunity {
+    /// Tokens in trade order; the cycle starts and ends at `tokens[0]`.
+    pub tokens: Vec<Address>,
+    /// `pools[i]` swaps `tokens[i]` into the next token.
+    pub pools: Vec<Address>,
+    /// Return of an infinitesimal trade around the cycle at current prices after fees, in basis points.
+    pub profit_bps: f64,
+    /// `tokens[0]` put in, in raw units; the most profitable size found.
+    pub size: U256,
+    /// `tokens[0]` received minus `size` and the gas cost, in raw units.
+    pub expected_profit: U256,
+    /// `expected_profit` in USD, when `tokens[0]` has a USD price.
+    pub expected_profit_usd: Option<f64>,
+    /// Estimated execution cost deducted from `expected_profit`, in USD.
+    pub gas_cost_usd: f64,
+    pub timestamp: u64,
+}
+
+/// Cycle found in the token graph, before sizing.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Cycle {
+    pub tokens: Vec<Address>,
+    pub pools: Vec<Address>,
+    pub profit_bps: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PoolEdge {
+    token0: Address,
+    token1: Address,
+    /// token0 price in token1 units.
+    price: f64,
+    fee: u32,
+}
+
+/// Latest price of every tracked pool, as a graph of tokens connected by pools.
+#[derive(Debug, Default)]
+pub(crate) struct TokenGraph {
+    pools: Mutex<HashMap<Address, PoolEdge>>,
+}
+
+impl TokenGraph {
+    /// Record a pool's token0 price. Pools for which `is_tracked` is false are dropped.
+    pub(crate) fn update(&self, pool: &CachedPool, token0_price: f64, is_tracked: impl Fn(&Address) -> bool) {
+        let mut pools = self.pools.lock().unwrap();
+        if token0_price.is_finite() && token0_price > 0.0 {
+            pools.insert(
+                pool.address,
+                PoolEdge {
+                    token0: pool.token0,
+                    token1: pool.token1,
+                    price: token0_price,
+                    fee: if pool.fee == 0 { DEFAULT_FEE } else { pool.fee },
+                },
+            );
+        } else {
+            pools.remove(&pool.address);
+        }
+        pools.retain(|address, _| is_tracked(address));
+    }
+
+    /// The most profitable cycle of three to `MAX_CYCLE_TOKENS` tokens that trades through the pool `through`.
+    /// Each direction of a pool is an edge weighted `-ln(rate after fee)`, keeping the best pool per token
+    /// pair and direction, so a cycle whose weights sum below zero is a sequence of swaps that returns more
+    /// than it started with. The search starts from both directions of `through` and walks simple paths back
+    /// to its first token without using `through` again, so round trips over two pools of one pair, and
+    /// mispriced pools elsewhere in the graph, don't hide the cycles through it.
+    pub(crate) fn find_cycle(&self, through: Address) -> Option<Cycle> {
+        let pools = self.pools.lock().unwrap();
+        let updated = *pools.get(&through)?;
+        let mut best: HashMap<(Address, Address), (f64, Address)> = HashMap::new();
+        for (address, pool) in pools.iter().filter(|(address, _)| **address != through) {
+            for (from, to, weight) in pool.directions() {
+                let edge = best.entry((from, to)).or_insert((weight, *address));
+                if weight < edge.0 {
+                    *edge = (weight, *address);
+                }
+            }
+        }
+        drop(pools);
+        let mut edges: HashMap<Address, Vec<(Address, f64, Address)>> = HashMap::new();
+        for ((from, to), (weight, pool)) in best {
+            edges.entry(from).or_default().push((to, weight, pool));
+        }
+
+        let mut found: Option<(f64, Vec<Address>, Vec<Address>)> = None;
+        for (from, to, weight) in updated.directions() {
+            let mut search = CycleSearch {
+                edges: &edges,
+                tokens: vec![from, to],
+                pools: vec![through],
+                best: found.take(),
+            };
+            search.extend(weight);
+            found = search.best;
+        }
+        let (total, tokens, pools) = found?;
+        Some(Cycle {
+            tokens,
+            pools,
+            profit_bps: ((-total).exp() - 1.0) * 10_000.0,
+        })
+    }
+}
+
+impl PoolEdge {
+    /// Both directions of the pool as `(from, to, -ln(rate after fee))`.
+    fn directions(&self) -> [(Address, Address, f64); 2] {
+        let after_fee = 1.0 - f64::from(self.fee) / 1_000_000.0;
+        [
+            (self.token0, self.token1, -(self.price * after_fee).ln()),
+            (self.token1, self.token0, -(after_fee / self.price).ln()),
+        ]
+    }
+}
+
+/// Depth-first walk of the simple paths that close a cycle back to `tokens[0]`.
+struct CycleSearch<'a> {
+    edges: &'a HashMap<Address, Vec<(Address, f64, Address)>>,
+    /// The path so far, starting with the updated pool's input token.
+    tokens: Vec<Address>,
+    pools: Vec<Address>,
+    /// Lowest total weight found, with its tokens and pools.
+    best: Option<(f64, Vec<Address>, Vec<Address>)>,
+}
+
+impl CycleSearch<'_> {
+    fn extend(&mut self, weight: f64) {
+        let last = *self.tokens.last().expect("the path starts with the updated pool");
+        for &(next, edge_weight, pool) in self.edges.get(&last).map(Vec::as_slice).unwrap_or_default() {
+            let total = weight + edge_weight;
+            if next == self.tokens[0] {
+                let closes = self.tokens.len() >= 3 && total < -EPSILON;
+                if closes && self.best.as_ref().is_none_or(|(best, ..)| total < *best) {
+                    let mut pools = self.pools.clone();
+                    pools.push(pool);
+                    self.best = Some((total, self.tokens.clone(), pools));
+                }
+            } else if self.tokens.len() < MAX_CYCLE_TOKENS && !self.tokens.contains(&next) {
+                self.tokens.push(next);
+                self.pools.push(pool);
+                self.extend(total);
+                self.tokens.pop();
+                self.pools.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    fn pool(address: Address, token0: Address, token1: Address) -> CachedPool {
+        CachedPool {
+            address,
+            token0,
+            token1,
+            fee: 500,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_finds_triangle_through_updated_pool() {
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let wbtc = address!("2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599");
+        let (eth_usd, btc_usd, btc_eth) = (
+            address!("0000000000000000000000000000000000000001"),
+            address!("0000000000000000000000000000000000000002"),
+            address!("0000000000000000000000000000000000000003"),
+        );
+        let graph = TokenGraph::default();
+        graph.update(&pool(eth_usd, weth, usdc), 2_000.0, |_| true);
+        graph.update(&pool(btc_usd, wbtc, usdc), 60_000.0, |_| true);
+        // Consistent cross rate: no cycle.
+        graph.update(&pool(btc_eth, wbtc, weth), 30.0, |_| true);
+        assert!(graph.find_cycle(btc_eth).is_none());
+
+        // BTC is 1% rich against ETH: sell BTC for ETH, ETH for USDC, USDC for BTC.
+        graph.update(&pool(btc_eth, wbtc, weth), 30.3, |_| true);
+        let cycle = graph.find_cycle(btc_eth).unwrap();
+        assert_eq!(cycle.pools.len(), 3);
+        let start = cycle.tokens.iter().position(|t| *t == wbtc).unwrap();
+        assert_eq!(cycle.pools[start], btc_eth);
+        assert_eq!(cycle.tokens[(start + 1) % 3], weth);
+        let expected = (1.01 * 0.9995f64.powi(3) - 1.0) * 10_000.0;
+        assert!((cycle.profit_bps - expected).abs() < 1e-6, "{}", cycle.profit_bps);
+    }
+
+    #[test]
+    fn test_two_pool_round_trips_do_not_hide_triangles() {
+        let (weth, usdc, wbtc, dai, usdt) = (
+            Address::repeat_byte(0xee),
+            Address::repeat_byte(0xcc),
+            Address::repeat_byte(0xbb),
+            Address::repeat_byte(0xda),
+            Address::repeat_byte(0xdd),
+        );
+        let graph = TokenGraph::default();
+        graph.update(&pool(Address::repeat_byte(1), weth, usdc), 2_000.0, |_| true);
+        graph.update(&pool(Address::repeat_byte(2), wbtc, usdc), 60_000.0, |_| true);
+        // A stale second WBTC/WETH pool: a 2-pool round trip with the updated one.
+        graph.update(&pool(Address::repeat_byte(3), wbtc, weth), 29.0, |_| true);
+        // A mispriced DAI/USDT pair elsewhere in the graph.
+        graph.update(&pool(Address::repeat_byte(4), dai, usdt), 1.0, |_| true);
+        graph.update(&pool(Address::repeat_byte(5), dai, usdt), 1.05, |_| true);
+
+        let btc_eth = Address::repeat_byte(6);
+        graph.update(&pool(btc_eth, wbtc, weth), 30.3, |_| true);
+        let cycle = graph.find_cycle(btc_eth).unwrap();
+        assert_eq!(cycle.tokens, vec![wbtc, weth, usdc]);
+        assert_eq!(cycle.pools, vec![btc_eth, Address::repeat_byte(1), Address::repeat_byte(2)]);
+
+        // Only the round trip is left: no cycle of three tokens.
+        graph.update(&pool(Address::repeat_byte(2), wbtc, usdc), 60_600.0, |_| true);
+        assert!(graph.find_cycle(btc_eth).is_none());
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// Fee market of the latest block, in wei per gas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasPrice {
+    pub block_number: u64,
+    pub base_fee: u128,
+    /// `eth_maxPriorityFeePerGas` when the head arrived.
+    pub priority_fee: u128,
+}
+
+impl GasPrice {
+    /// USD cost of `gas` units paying the base fee plus the priority fee, with the native token at
+    /// `native_usd`.
+    pub fn cost_usd(&self, gas: u64, native_usd: f64) -> f64 {
+        let wei = self.base_fee.saturating_add(self.priority_fee) as f64 * gas as f64;
+        wei / 1e18 * native_usd
+    }
+}
+
+/// Latest gas price, written by the new-heads subscription.
+#[derive(Debug, Default)]
+pub(crate) struct GasOracle {
+    latest: RwLock<Option<GasPrice>>,
+}
+
+impl GasOracle {
+    pub(crate) fn update(&self, price: GasPrice) {
+        *self.latest.write().unwrap() = Some(price);
+    }
+
+    pub(crate) fn latest(&self) -> Option<GasPrice> {
+        *self.latest.read().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_usd() {
+        let price = GasPrice {
+            block_number: 1,
+            base_fee: 20_000_000_000,
+            priority_fee: 1_000_000_000,
+        };
+        // 300k gas at 21 gwei = 0.0063 ETH.
+        assert!((price.cost_usd(300_000, 2_000.0) - 12.6).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,142 @@
+//! Arbitrage detection: two pools of the same pair, and cycles through three or more tokens.
+
+mod cycles;
+mod gas;
+
+pub use cycles::CycleOpportunity;
+pub(crate) use cycles::TokenGraph;
+pub(crate) use gas::GasOracle;
+pub use gas::GasPrice;
+use alloy::primitives::{address, Address, U256};
+use serde::{Deserialize, Serialize};
+
+/// Enables the detector through `ScannerOptions::arbitrage`.
+#[derive(Debug, Clone)]
+pub struct ArbitrageConfig {
+    /// Execution cost of one arbitrage transaction, in USD, used until the first block header arrives
+    /// or while `native_token` has no USD price. Costs are converted to token1 with its USD price
+    /// (`ScannerOptions::pricing` or a stablecoin token1), and ignored when token1 has none.
+    pub gas_cost_usd: f64,
+    /// Gas used per swap of an opportunity; a two-pool trade is two swaps, a cycle one per pool.
+    /// Priced at the latest block's base fee plus priority fee.
+    pub gas_per_swap: u64,
+    /// Token gas is paid in (wrapped), priced through `ScannerOptions::pricing`. Mainnet WETH by default.
+    pub native_token: Address,
+    /// Smallest profit after gas worth reporting, in USD (same conversion as `gas_cost_usd`).
+    pub min_profit_usd: f64,
+    /// Also search the graph of all tracked pools for profitable token cycles on every update and
+    /// publish them as `ScannerEvent::CycleOpportunity`.
+    pub cycles: bool,
+}
+
+impl Default for ArbitrageConfig {
+    fn default() -> Self {
+        Self {
+            gas_cost_usd: 5.0,
+            min_profit_usd: 0.0,
+            gas_per_swap: 150_000,
+            native_token: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            cycles: false,
+        }
+    }
+}
+
+impl ArbitrageConfig {
+    /// Estimated USD cost of a transaction making `swaps` swaps: at the latest gas price when it and
+    /// the native token's USD price are known, `gas_cost_usd` otherwise.
+    pub fn execution_cost_usd(&self, swaps: usize, gas: Option<GasPrice>, native_usd: Option<f64>) -> f64 {
+        match (gas, native_usd) {
+            (Some(gas), Some(native_usd)) => gas.cost_usd(self.gas_per_swap.saturating_mul(swaps as u64), native_usd),
+            _ => self.gas_cost_usd,
+        }
+    }
+}
+
+/// A profitable round trip between two pools of the same pair: pay `size` token1 into `buy_pool`
+/// for token0, sell that token0 into `sell_pool` for more token1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArbOpportunity {
+    pub token0: Address,
+    pub token1: Address,
+    pub buy_pool: Address,
+    pub sell_pool: Address,
+    /// token1 paid into `buy_pool`, in raw units; the most profitable size found.
+    pub size: U256,
+    /// token1 received minus `size` and the gas cost, in raw units.
+    pub expected_profit: U256,
+    /// `expected_profit` in USD, when token1 has a USD price.
+    pub expected_profit_usd: Option<f64>,
+    /// Estimated execution cost deducted from `expected_profit`, in USD.
+    pub gas_cost_usd: f64,
+    pub timestamp: u64,
+}
+
+/// Decades of whole start-token units tried before refining: 0.001 up to 10^9 tokens.
+const SIZE_DECADES: std::ops::RangeInclusive<i32> = -3..=9;
+/// Ternary search steps between the neighbours of the best decade.
+const REFINE_STEPS: usize = 48;
+
+/// Most profitable trade size for `round_trip` (start token in → start token out, e.g. token1 of a
+/// two-pool trade; `None` when a leg can't be filled), net of `gas` (raw units of the start token).
+/// `unit` is one whole start token. Returns (size, profit) when some size beats the gas cost. Profit
+/// is concave in size (every leg has diminishing returns), so a coarse scan over decades followed by
+/// a ternary search finds the optimum.
+pub fn best_size(round_trip: impl Fn(U256) -> Option<U256>, unit: f64, gas: U256) -> Option<(U256, U256)> {
+    let profit = |size: U256| {
+        round_trip(size)
+            .and_then(|out| out.checked_sub(size))
+            .and_then(|gross| gross.checked_sub(gas))
+    };
+    let amount = |tokens: f64| U256::from((tokens * unit) as u128);
+
+    let (best_decade, _) = SIZE_DECADES
+        .filter_map(|decade| profit(amount(10f64.powi(decade))).map(|p| (decade, p)))
+        .max_by_key(|(_, p)| *p)?;
+    let (mut low, mut high) = (10f64.powi(best_decade - 1), 10f64.powi(best_decade + 1));
+    let value = |tokens: f64| profit(amount(tokens)).unwrap_or_default();
+    for _ in 0..REFINE_STEPS {
+        let third = (high - low) / 3.0;
+        if value(low + third) < value(high - third) {
+            low += third;
+        } else {
+            high -= third;
+        }
+    }
+    let size = amount((low + high) / 2.0);
+    let candidates = [size, amount(10f64.powi(best_decade))];
+    candidates
+        .into_iter()
+        .filter_map(|size| profit(size).map(|p| (size, p)))
+        .max_by_key(|(_, p)| *p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liquidity_pools::v3_math::constant_product_amount_out;
+
+    #[test]
+    fn test_best_size_between_two_constant_product_pools() {
+        let unit = 1e6;
+        let e18 = U256::from(10u64).pow(U256::from(18));
+        // Cheap pool: 1000 WETH / 2,000,000 USDC. Rich pool: 1000 WETH / 2,100,000 USDC.
+        let (cheap_weth, cheap_usdc) = (U256::from(1_000u64) * e18, U256::from(2_000_000_000_000u64));
+        let (rich_weth, rich_usdc) = (U256::from(1_000u64) * e18, U256::from(2_100_000_000_000u64));
+        let round_trip = |usdc: U256| {
+            let weth = constant_product_amount_out(usdc, cheap_usdc, cheap_weth, 3000);
+            Some(constant_product_amount_out(weth, rich_weth, rich_usdc, 3000))
+        };
+
+        let gas = U256::from(5_000_000u64);
+        let (size, profit) = best_size(round_trip, unit, gas).unwrap();
+        // A brute-force scan puts the optimum at ~$21.7k in for ~$470 gross profit.
+        let size_usd = f64::from(size) / unit;
+        assert!((21_000.0..22_500.0).contains(&size_usd), "size {}", size_usd);
+        let expected = round_trip(size).unwrap() - size - gas;
+        assert_eq!(profit, expected);
+        assert!(profit > U256::from(460_000_000u64));
+
+        // A gas cost above any possible profit leaves nothing.
+        assert!(best_size(round_trip, unit, U256::from(10_000_000_000_000u64)).is_none());
+    }
+}
@@ -0,0 +1,117 @@
+//! `backfill`: replay the discovered pools' historical swaps over a block range into price/candle files.
+
+use clap::{Args, ValueEnum};
+use dex_pool_scanner_rust::sinks::{CsvConfig, CsvRows, CsvSink, JsonLinesSink, Sink};
+use dex_pool_scanner_rust::{MetricsConfig, Scanner, ScannerOptions};
+use eyre::{bail, eyre};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One price update per line.
+    Jsonl,
+    /// Daily `prices-<date>.csv` (and `candles-<date>.csv`) files in the output directory.
+    Csv,
+}
+
+#[derive(Debug, Args)]
+pub struct BackfillArgs {
+    /// First block to replay.
+    #[arg(long)]
+    from_block: u64,
+    /// Last block to replay; the chain head by default.
+    #[arg(long)]
+    to_block: Option<u64>,
+    #[arg(long, value_enum, default_value_t = Format::Jsonl)]
+    format: Format,
+    /// File to append JSON lines to (stdout by default), or the directory of the CSV files (`backfill` by
+    /// default).
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Also write OHLCV candles of these intervals, e.g. `1m,1h` (CSV only).
+    #[arg(long, value_delimiter = ',', value_parser = parse_interval)]
+    candles: Vec<Duration>,
+}
+
+pub async fn run(args: BackfillArgs) -> eyre::Result<()> {
+    let mut sinks: Vec<Arc<dyn Sink>> = Vec::new();
+    match args.format {
+        Format::Jsonl => {
+            if !args.candles.is_empty() {
+                bail!("--candles needs --format csv");
+            }
+            sinks.push(Arc::new(match &args.output {
+                Some(path) => JsonLinesSink::file(path)?,
+                None => JsonLinesSink::stdout(),
+            }));
+        }
+        Format::Csv => {
+            let dir = args.output.clone().unwrap_or_else(|| PathBuf::from("backfill"));
+            sinks.push(Arc::new(CsvSink::new(CsvConfig {
+                dir: dir.clone(),
+                ..Default::default()
+            })?));
+            if !args.candles.is_empty() {
+                sinks.push(Arc::new(CsvSink::new(CsvConfig {
+                    dir,
+                    rows: CsvRows::Candles,
+                    ..Default::default()
+                })?));
+            }
+        }
+    }
+    let options = ScannerOptions {
+        metrics: MetricsConfig {
+            candle_intervals: args.candles,
+            ..Default::default()
+        },
+        sinks,
+        ..Default::default()
+    };
+
+    let scanner = Scanner::with_options(Arc::new(|_, _, _| {}), options).await?;
+    let summary = scanner.backfill(args.from_block, args.to_block).await?;
+    info!(
+        "Replayed {} logs from blocks {} to {}: {} price updates, {} candles",
+        summary.logs, summary.from_block, summary.to_block, summary.price_updates, summary.candles
+    );
+    Ok(())
+}
+
+/// `30s`, `5m`, `1h` or `1d`.
+pub(crate) fn parse_interval(value: &str) -> eyre::Result<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (count, unit) = value.split_at(split);
+    let count: u64 = count.parse().map_err(|_| eyre!("invalid interval {value:?}"))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("invalid interval {value:?}, expected e.g. 30s, 5m, 1h or 1d"),
+    };
+    if count == 0 {
+        bail!("interval {value:?} is empty");
+    }
+    Ok(Duration::from_secs(count * unit_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_interval("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_interval("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86_400));
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("m").is_err());
+        assert!(parse_interval("5").is_err());
+        assert!(parse_interval("5w").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! `scan --daemon`: a PID file and systemd readiness notification (`Type=notify`).
+
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// The process id, written on creation and removed on drop.
+pub struct PidFile(PathBuf);
+
+impl PidFile {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        std::fs::write(path, format!("{}\n", std::process::id()))?;
+        Ok(Self(path.to_path_buf()))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            warn!("Failed to remove PID file {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Send `state` (e.g. `READY=1`) to the service manager at `$NOTIFY_SOCKET`. Does nothing when the
+/// variable is unset, i.e. when not started by systemd with `Type=notify`.
+pub fn notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else { return };
+    if let Err(e) = notify_socket(&socket, state) {
+        warn!("sd_notify {} failed: {}", state, e);
+    }
+}
+
+/// Send `state` as one datagram to `socket`: a path, or an abstract socket name after `@`.
+fn notify_socket(socket: &OsStr, state: &str) -> io::Result<()> {
+    let sender = UnixDatagram::unbound()?;
+    match socket.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            sender.send_to_addr(state.as_bytes(), &address)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return Err(io::Error::new(io::ErrorKind::Unsupported, "abstract sockets need Linux")),
+        None => {
+            sender.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_socket() {
+        let path = std::env::temp_dir().join(format!("notify-{}.sock", std::process::id()));
+        let receiver = UnixDatagram::bind(&path).unwrap();
+        notify_socket(path.as_os_str(), "READY=1").unwrap();
+        let mut buffer = [0; 64];
+        let received = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..received], b"READY=1");
+        std::fs::remove_file(&path).unwrap();
+
+        let pid_path = std::env::temp_dir().join(format!("scanner-{}.pid", std::process::id()));
+        let pid_file = PidFile::create(&pid_path).unwrap();
+        assert_eq!(std::fs::read_to_string(&pid_path).unwrap(), format!("{}\n", std::process::id()));
+        drop(pid_file);
+        assert!(!pid_path.exists());
+    }
+}
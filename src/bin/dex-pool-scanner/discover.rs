@@ -0,0 +1,55 @@
+//! `discover`: the pool list `scan` would track, for review and hand-curation.
+
+use clap::{Args, ValueEnum};
+use dex_pool_scanner_rust::sinks::csv::write_pools;
+use dex_pool_scanner_rust::{CachedPool, Scanner};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Args)]
+pub struct DiscoverArgs {
+    /// Output format; by default CSV for a `.csv` output file and JSON otherwise.
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+    /// Write to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub async fn run(args: DiscoverArgs) -> eyre::Result<()> {
+    let scanner = Scanner::new(Arc::new(|_, _, _| {})).await?;
+    let mut pools = scanner.discover().await?;
+    // Highest liquidity first, the order worth reviewing in.
+    pools.sort_by(|a, b| b.liquidity_usd.total_cmp(&a.liquidity_usd));
+    info!("Discovered {} pools", pools.len());
+
+    let format = args.format.unwrap_or_else(|| match &args.output {
+        Some(path) if path.extension().is_some_and(|extension| extension == "csv") => Format::Csv,
+        _ => Format::Json,
+    });
+    match &args.output {
+        Some(path) => write(BufWriter::new(File::create(path)?), &pools, format),
+        None => write(io::stdout().lock(), &pools, format),
+    }
+}
+
+fn write(mut writer: impl Write, pools: &[CachedPool], format: Format) -> eyre::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, pools)?;
+            writeln!(writer)?;
+            writer.flush()?;
+            Ok(())
+        }
+        Format::Csv => write_pools(writer, pools),
+    }
+}
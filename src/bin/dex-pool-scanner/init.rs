@@ -0,0 +1,240 @@
+//! `init`: starter `protocols.json`, `tokens.json` (or `scanner.toml`) and `.env` for a chain.
+
+use clap::{Args, ValueEnum};
+use dex_pool_scanner_rust::config::CONFIG_VERSION;
+use eyre::bail;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Chain {
+    /// Ethereum mainnet: Uniswap V3 and Uniswap V2.
+    Mainnet,
+    /// Base: Uniswap V3, Aerodrome CL and SushiSwap V3.
+    Base,
+    /// Arbitrum One: Uniswap V3.
+    Arbitrum,
+}
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    #[arg(long, value_enum)]
+    chain: Chain,
+    /// Directory to write the files to.
+    #[arg(long, default_value = ".")]
+    dir: PathBuf,
+    /// Overwrite existing files.
+    #[arg(long)]
+    force: bool,
+    /// Write one `scanner.toml` instead of `protocols.json` and `tokens.json`.
+    #[arg(long)]
+    toml: bool,
+}
+
+/// A protocol entry: id, name, factory, subgraph id and pool type.
+type Protocol = (&'static str, &'static str, &'static str, &'static str, &'static str);
+
+impl Chain {
+    fn protocols(self) -> &'static [Protocol] {
+        match self {
+            Chain::Mainnet => &[
+                (
+                    "uniswap-v3",
+                    "Uniswap V3",
+                    "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                    "5zvR82QoaXYFyDEKLZ9t6v9adgnptxYpKpSbxtgVENFV",
+                    "UniswapV3",
+                ),
+                (
+                    "uniswap-v2",
+                    "Uniswap V2",
+                    "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
+                    "A3Np3RQbaBA6oKJgiwDJeo5T3zrYfGHPWFYayMwtNDum",
+                    "UniswapV2",
+                ),
+            ],
+            Chain::Base => &[
+                (
+                    "uniswap-v3",
+                    "Uniswap V3",
+                    "0x33128a8fC17869897dcE68Ed026d694621f6FDfD",
+                    "43Hwfi3dJSoGpyas9VwNoDAv55yjgGrPpNSmbQZArzMG",
+                    "UniswapV3",
+                ),
+                (
+                    "aerodrome-cl",
+                    "Aerodrome CL",
+                    "0x5e7BB104d84c7CB9B682AaC2F3d509f5F406809A",
+                    "GENunSHWLBXm59mBSgPzQ8metBEp9YDfdqwFr91Av1UM",
+                    "UniswapV3",
+                ),
+                (
+                    "sushiswap-v3",
+                    "SushiSwap V3",
+                    "0xc35DADB65012eC5796536bD9864eD8773aBc74C4",
+                    "Cz4Snpih41NNNPZcbj1gd3fYXPwFr5q92iWMoZjCarEb",
+                    "UniswapV3",
+                ),
+            ],
+            Chain::Arbitrum => &[(
+                "uniswap-v3",
+                "Uniswap V3",
+                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                "FbCGRftH4a3yZugY7TnbYgPJVEv2LvMT6oF1fxPe9aJM",
+                "UniswapV3",
+            )],
+        }
+    }
+
+    /// The chain's most liquid tokens, symbol and address.
+    fn tokens(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Chain::Mainnet => &[
+                ("WETH", "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+                ("USDC", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+                ("USDT", "0xdAC17F958D2ee523a2206206994597C13D831ec7"),
+                ("DAI", "0x6B175474E89094C44Da98b954EedeAC495271d0F"),
+                ("WBTC", "0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599"),
+            ],
+            Chain::Base => &[
+                ("WETH", "0x4200000000000000000000000000000000000006"),
+                ("USDC", "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"),
+                ("USDbC", "0xd9aAEc86B65D86f6A7B5B1b0c42FFA531710b6CA"),
+                ("cbETH", "0x2Ae3F1Ec7F1F5012CFEab0185bfc7aa3cf0DEc22"),
+                ("cbBTC", "0xcbB7C0000aB88B473b1f5aFd9ef808440eed33Bf"),
+                ("DAI", "0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb"),
+                ("AERO", "0x940181a94A35A4569E4529A3CDfB74e38FD98631"),
+            ],
+            Chain::Arbitrum => &[
+                ("WETH", "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"),
+                ("USDC", "0xaf88d065e77c8cC2239327C5EDb3A432268e5831"),
+                ("USDT", "0xFd086bC7CD5C481DCC9C85ebE478A1C0b69FCbb9"),
+                ("WBTC", "0x2f2a2543B76A4166549F7aaB2e75Bef0aefC5B0f"),
+                ("ARB", "0x912CE59144191C1204E64559FE8253a0e49E6548"),
+            ],
+        }
+    }
+
+    /// Example WebSocket endpoint for the `.env` template.
+    fn rpc_example(self) -> &'static str {
+        match self {
+            Chain::Mainnet => "wss://eth-mainnet.g.alchemy.com/v2/<key>",
+            Chain::Base => "wss://base-mainnet.g.alchemy.com/v2/<key>",
+            Chain::Arbitrum => "wss://arb-mainnet.g.alchemy.com/v2/<key>",
+        }
+    }
+}
+
+fn protocols_json(chain: Chain) -> Value {
+    let protocols: serde_json::Map<String, Value> = chain
+        .protocols()
+        .iter()
+        .map(|(id, name, factory, subgraph_id, pool_type)| {
+            let entry = json!({
+                "name": name,
+                "factory": factory,
+                "subgraphId": subgraph_id,
+                "enabled": true,
+                "poolType": pool_type,
+            });
+            (id.to_string(), entry)
+        })
+        .collect();
+    json!({
+        "version": CONFIG_VERSION,
+        "protocols": protocols,
+        "discovery": {
+            "minLiquidityUSD": 10000,
+            "cacheRefreshMinutes": 60,
+            "maxPoolsPerProtocol": 100,
+        },
+    })
+}
+
+fn tokens_json(chain: Chain) -> Value {
+    let tokens: serde_json::Map<String, Value> =
+        chain.tokens().iter().map(|(symbol, address)| (symbol.to_string(), json!(address))).collect();
+    json!({ "version": CONFIG_VERSION, "tokens": tokens })
+}
+
+fn env_template(chain: Chain) -> String {
+    format!(
+        "# The Graph API key for the subgraphs in protocols.json: https://thegraph.com/studio/apikeys/\n\
+         THE_GRAPH_API_KEY=\n\
+         # WebSocket RPC endpoint, e.g. {}\n\
+         RPC_URL=\n\
+         ENABLE_LOG=true\n",
+        chain.rpc_example()
+    )
+}
+
+/// `protocols.json` and `tokens.json` in one table.
+fn scanner_toml(chain: Chain) -> eyre::Result<String> {
+    let mut root = protocols_json(chain);
+    root["tokens"] = tokens_json(chain)["tokens"].take();
+    Ok(format!(
+        "# RPC_URL and THE_GRAPH_API_KEY are read from .env; `[rpc] url` and `theGraphApiKey` here work too.\n\n{}",
+        toml::to_string_pretty(&root)?
+    ))
+}
+
+/// The files for `chain`: name and content.
+fn files(chain: Chain, toml: bool) -> eyre::Result<Vec<(&'static str, String)>> {
+    let mut files = if toml {
+        vec![("scanner.toml", scanner_toml(chain)?)]
+    } else {
+        vec![
+            ("protocols.json", serde_json::to_string_pretty(&protocols_json(chain))? + "\n"),
+            ("tokens.json", serde_json::to_string_pretty(&tokens_json(chain))? + "\n"),
+        ]
+    };
+    files.push((".env", env_template(chain)));
+    Ok(files)
+}
+
+pub async fn run(args: InitArgs) -> eyre::Result<()> {
+    write(&args.dir, args.chain, args.force, args.toml)?;
+    println!("Next: set THE_GRAPH_API_KEY and RPC_URL in .env, then run `dex-pool-scanner validate-config`.");
+    Ok(())
+}
+
+fn write(dir: &Path, chain: Chain, force: bool, toml: bool) -> eyre::Result<()> {
+    let files = files(chain, toml)?;
+    if !force {
+        let existing: Vec<_> = files.iter().map(|(name, _)| dir.join(name)).filter(|path| path.exists()).collect();
+        if !existing.is_empty() {
+            let names: Vec<_> = existing.iter().map(|path| path.display().to_string()).collect();
+            bail!("{} already exists; pass --force to overwrite", names.join(", "));
+        }
+    }
+    std::fs::create_dir_all(dir)?;
+    for (name, content) in files {
+        let path = dir.join(name);
+        std::fs::write(&path, content)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_pool_scanner_rust::config::{CheckStatus, ValidationReport};
+
+    #[test]
+    fn test_files_validate() {
+        let dir = std::env::temp_dir().join(format!("init-{}", std::process::id()));
+        for chain in [Chain::Mainnet, Chain::Base, Chain::Arbitrum] {
+            write(&dir, chain, true, false).unwrap();
+            write(&dir, chain, true, true).unwrap();
+            let split = ValidationReport::check_files(&dir.join("protocols.json"), &dir.join("tokens.json"));
+            let unified = ValidationReport::check_scanner_file(&dir.join("scanner.toml"));
+            // Everything but the unset API key.
+            for check in split.checks.iter().chain(&unified.checks).filter(|check| check.subject != "THE_GRAPH_API_KEY") {
+                assert_eq!(check.status, CheckStatus::Pass, "{chain:?} {}: {}", check.subject, check.detail);
+            }
+        }
+        assert!(write(&dir, Chain::Base, false, false).unwrap_err().to_string().contains("--force"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
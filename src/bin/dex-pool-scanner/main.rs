@@ -0,0 +1,81 @@
+//! `dex-pool-scanner`: the scanner from the command line. Reads `.env`, then `RPC_URL`, `PROTOCOLS_JSON` and
+//! `TOKENS_JSON` like the library. Logs go to stderr, so command output on stdout can be piped.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use dotenvy::dotenv;
+use tracing::Level;
+use tracing_subscriber::FmtSubscriber;
+
+mod backfill;
+#[cfg(unix)]
+mod daemon;
+mod discover;
+mod init;
+mod replay;
+mod scan;
+#[cfg(feature = "tui")]
+mod tui;
+mod validate_config;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Multi-protocol DEX pool discovery and real-time price tracking")]
+struct Cli {
+    /// Format of the log lines on stderr.
+    #[arg(long, global = true, value_enum, env = "LOG_FORMAT", default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    Text,
+    /// One JSON object per line, with the event's fields (`pool`, `pair`, `block`, `price`, ...) at the top
+    /// level, for Loki, Elasticsearch and the like.
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run discovery only and write the pools that would be scanned, as JSON or CSV.
+    Discover(discover::DiscoverArgs),
+    /// Discover pools and track their prices until Ctrl+C.
+    Scan(scan::ScanArgs),
+    /// Replay the discovered pools' swaps over a block range and write their prices and candles.
+    Backfill(backfill::BackfillArgs),
+    /// Play recorded price updates (JSON lines or a Parquet archive) back through the callback and sinks.
+    Replay(replay::ReplayArgs),
+    /// Check protocols.json and tokens.json (or scanner.toml), RPC_URL and THE_GRAPH_API_KEY, and print a pass/fail report.
+    ValidateConfig(validate_config::ValidateConfigArgs),
+    /// Write starter protocols.json, tokens.json and .env files for a chain.
+    Init(init::InitArgs),
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    dotenv().ok();
+    let cli = Cli::parse();
+    // The dashboard owns the terminal; log lines would tear it.
+    #[cfg(feature = "tui")]
+    let logs = !matches!(&cli.command, Command::Scan(args) if args.tui);
+    #[cfg(not(feature = "tui"))]
+    let logs = true;
+    if logs {
+        let builder = FmtSubscriber::builder().with_max_level(Level::INFO).with_writer(std::io::stderr);
+        match cli.log_format {
+            LogFormat::Text => tracing::subscriber::set_global_default(builder.finish())?,
+            LogFormat::Json => tracing::subscriber::set_global_default(
+                builder.json().flatten_event(true).with_current_span(false).finish(),
+            )?,
+        }
+    }
+
+    match cli.command {
+        Command::Discover(args) => discover::run(args).await,
+        Command::Scan(args) => scan::run(args).await,
+        Command::Backfill(args) => backfill::run(args).await,
+        Command::Replay(args) => replay::run(args).await,
+        Command::ValidateConfig(args) => validate_config::run(args).await,
+        Command::Init(args) => init::run(args).await,
+    }
+}
@@ -0,0 +1,97 @@
+//! `replay`: play recorded price updates back through the callback and sinks, without an RPC.
+
+use crate::scan::log_update;
+use clap::Args;
+use dex_pool_scanner_rust::sinks::{json_lines, JsonLinesSink, Sink};
+use dex_pool_scanner_rust::{CachedPool, PoolPrice, Replay, ScannerOptions};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// A JSON lines file written by `JsonLinesSink` (or `backfill`), or the root of a `ParquetSink` archive.
+    input: PathBuf,
+    /// Pace the updates by their timestamps: 1 is the recorded pace, 60 plays an hour a minute. As fast as
+    /// possible by default.
+    #[arg(long)]
+    speed: Option<f64>,
+    /// Pool list from `discover` (JSON), to name the pools of a Parquet archive, which records addresses only.
+    #[arg(long)]
+    pools: Option<PathBuf>,
+    /// Also write the replayed updates as JSON lines to this file.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Don't log each update.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+pub async fn run(args: ReplayArgs) -> eyre::Result<()> {
+    let mut updates = read_updates(&args.input)?;
+    if let Some(path) = &args.pools {
+        let pools: Vec<CachedPool> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        let pools: HashMap<_, _> = pools.into_iter().map(|pool| (pool.address, pool)).collect();
+        for (pool, _) in &mut updates {
+            if let Some(known) = pools.get(&pool.address) {
+                *pool = known.clone();
+            }
+        }
+    }
+    info!("Replaying {} price updates from {}", updates.len(), args.input.display());
+
+    let mut sinks: Vec<Arc<dyn Sink>> = Vec::new();
+    if let Some(path) = &args.output {
+        sinks.push(Arc::new(JsonLinesSink::file(path)?));
+    }
+    let options = ScannerOptions {
+        sinks,
+        ..Default::default()
+    };
+    let on_price_change = if args.quiet { Arc::new(|_, _, _| {}) as _ } else { Arc::new(log_update) as _ };
+    let mut replay = Replay::new(on_price_change, options);
+    if let Some(speed) = args.speed {
+        replay = replay.with_speed(speed);
+    }
+    let cancel = replay.cancellation_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+
+    let summary = replay.run(updates).await?;
+    info!(
+        "Replayed {} price updates of {} pools{}",
+        summary.price_updates,
+        summary.pools,
+        if summary.cancelled { " (interrupted)" } else { "" }
+    );
+    Ok(())
+}
+
+/// Updates of a JSON lines file, or of a Parquet archive directory.
+fn read_updates(input: &Path) -> eyre::Result<Vec<(CachedPool, PoolPrice)>> {
+    if !input.is_dir() {
+        return json_lines::read_prices(BufReader::new(File::open(input)?));
+    }
+    #[cfg(feature = "parquet")]
+    {
+        let prices = dex_pool_scanner_rust::sinks::parquet::read_prices(input)?;
+        Ok(prices
+            .into_iter()
+            .map(|price| {
+                let pool = CachedPool {
+                    address: price.pool_address,
+                    ..Default::default()
+                };
+                (pool, price)
+            })
+            .collect())
+    }
+    #[cfg(not(feature = "parquet"))]
+    eyre::bail!("{} is a directory; replaying a Parquet archive needs the `parquet` feature", input.display())
+}
@@ -0,0 +1,106 @@
+//! `scan`: discover pools and track their prices until Ctrl+C.
+
+use clap::Args;
+use dex_pool_scanner_rust::{CachedPool, MetricsConfig, PoolPrice, Scanner, ScannerOptions};
+#[cfg(unix)]
+use dex_pool_scanner_rust::ScannerStatus;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+#[derive(Debug, Args)]
+pub struct ScanArgs {
+    /// Show a live dashboard of the tracked pools instead of logging each price update.
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub tui: bool,
+    /// Run as a service: write a PID file, tell systemd (`Type=notify`) READY once the first subscription
+    /// and catch-up are done, and stop on SIGTERM as well as Ctrl+C.
+    #[cfg(unix)]
+    #[arg(long)]
+    #[cfg_attr(feature = "tui", arg(conflicts_with = "tui"))]
+    pub daemon: bool,
+    /// Where `--daemon` writes the process id.
+    #[cfg(unix)]
+    #[arg(long, requires = "daemon", default_value = "dex-pool-scanner.pid")]
+    pub pid_file: PathBuf,
+    /// Reload protocols.json / tokens.json (or scanner.toml) when they change, without a restart.
+    #[cfg(feature = "hot-reload")]
+    #[arg(long)]
+    pub watch_config: bool,
+    /// Replay this much history (e.g. `6h`) into the TWAPs, candles and volatility before going live.
+    #[arg(long, value_parser = crate::backfill::parse_interval)]
+    pub warmup: Option<Duration>,
+}
+
+pub async fn run(args: ScanArgs) -> eyre::Result<()> {
+    let options = ScannerOptions {
+        metrics: MetricsConfig {
+            warmup: args.warmup,
+            ..Default::default()
+        },
+        #[cfg(feature = "hot-reload")]
+        watch_config: args.watch_config,
+        ..Default::default()
+    };
+    #[cfg(feature = "tui")]
+    if args.tui {
+        return crate::tui::run(options).await;
+    }
+    #[cfg(unix)]
+    if args.daemon {
+        return daemon(&args.pid_file, options).await;
+    }
+    let _ = args;
+
+    let mut scanner = Scanner::with_options(Arc::new(log_update), options).await?;
+    scanner.start().await?;
+    info!("Scanner running. Press Ctrl+C to stop.");
+    tokio::signal::ctrl_c().await?;
+    scanner.shutdown().await;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn daemon(pid_file: &Path, options: ScannerOptions) -> eyre::Result<()> {
+    use crate::daemon::{notify, PidFile};
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let _pid_file = PidFile::create(pid_file)?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut scanner = Scanner::with_options(Arc::new(log_update), options).await?;
+    scanner.on_status(Arc::new(|status| {
+        if let ScannerStatus::Live { pools } = status {
+            info!("Live with {} pools", pools);
+            notify("READY=1");
+        }
+    }));
+    scanner.start().await?;
+    info!("Scanner running as a daemon (PID {}).", std::process::id());
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {}
+    }
+    notify("STOPPING=1");
+    scanner.shutdown().await;
+    Ok(())
+}
+
+/// Log a price update with `pool`, `pair`, `protocol`, `block`, `price` (token0 in token1) and `change_pct`
+/// (since the previous update) fields.
+pub fn log_update(pool: CachedPool, price: PoolPrice, previous: Option<PoolPrice>) {
+    let change_pct = previous
+        .filter(|previous| previous.token0_price != 0.0)
+        .map(|previous| (price.token0_price / previous.token0_price - 1.0) * 100.0);
+    info!(
+        pool = %pool.address,
+        pair = format_args!("{}/{}", pool.token0_symbol, pool.token1_symbol),
+        protocol = %pool.protocol,
+        block = price.block_number,
+        price = price.token0_price,
+        change_pct,
+        "price update"
+    );
+}
@@ -0,0 +1,355 @@
+//! `scan --tui`: a live table of the tracked pools with their prices, change since the dashboard started,
+//! volume and liquidity, under a status line for the connection.
+
+use dex_pool_scanner_rust::{PoolSnapshot, Scanner, ScannerEvent, ScannerOptions, ScannerStats, ScannerStatus};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Cell, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+/// How often the table is refreshed and keys are read.
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Pair,
+    Protocol,
+    Price,
+    Change,
+    Volume,
+    Liquidity,
+    Updates,
+    Age,
+}
+
+impl Column {
+    const ALL: [Column; 8] = [
+        Column::Pair,
+        Column::Protocol,
+        Column::Price,
+        Column::Change,
+        Column::Volume,
+        Column::Liquidity,
+        Column::Updates,
+        Column::Age,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Pair => "Pair",
+            Column::Protocol => "Protocol",
+            Column::Price => "Price",
+            Column::Change => "Change",
+            Column::Volume => "Volume 24h",
+            Column::Liquidity => "Liquidity",
+            Column::Updates => "Updates",
+            Column::Age => "Last update",
+        }
+    }
+
+    fn width(self) -> Constraint {
+        match self {
+            Column::Pair => Constraint::Min(14),
+            Column::Protocol => Constraint::Length(14),
+            Column::Age => Constraint::Length(11),
+            _ => Constraint::Length(13),
+        }
+    }
+}
+
+/// One pool of the table.
+#[derive(Debug, Clone, Default)]
+struct PoolRow {
+    pair: String,
+    protocol: String,
+    price: Option<f64>,
+    /// Since the pool's first price on the dashboard, in percent.
+    change: Option<f64>,
+    volume_usd: f64,
+    liquidity_usd: f64,
+    updates: u64,
+    /// Seconds since the pool's last log.
+    age: u64,
+    stale: bool,
+}
+
+impl PoolRow {
+    fn compare(&self, other: &Self, column: Column) -> std::cmp::Ordering {
+        // Missing prices sort as the lowest values.
+        let number = |value: Option<f64>| value.unwrap_or(f64::NEG_INFINITY);
+        match column {
+            Column::Pair => self.pair.to_lowercase().cmp(&other.pair.to_lowercase()),
+            Column::Protocol => self.protocol.cmp(&other.protocol),
+            Column::Price => number(self.price).total_cmp(&number(other.price)),
+            Column::Change => number(self.change).total_cmp(&number(other.change)),
+            Column::Volume => self.volume_usd.total_cmp(&other.volume_usd),
+            Column::Liquidity => self.liquidity_usd.total_cmp(&other.liquidity_usd),
+            Column::Updates => self.updates.cmp(&other.updates),
+            Column::Age => self.age.cmp(&other.age),
+        }
+    }
+}
+
+fn sort_rows(rows: &mut [PoolRow], column: Column, descending: bool) {
+    rows.sort_by(|a, b| {
+        let order = a.compare(b, column);
+        if descending { order.reverse() } else { order }
+    });
+}
+
+struct App {
+    rows: Vec<PoolRow>,
+    /// First price seen per pool, the reference of `PoolRow::change`.
+    first_prices: HashMap<String, f64>,
+    stats: ScannerStats,
+    connection: String,
+    last_error: Option<String>,
+    sort: Column,
+    descending: bool,
+    table: TableState,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            first_prices: HashMap::new(),
+            stats: ScannerStats::default(),
+            connection: "connecting".to_string(),
+            last_error: None,
+            sort: Column::Liquidity,
+            descending: true,
+            table: TableState::default().with_selected(0),
+        }
+    }
+
+    fn refresh(&mut self, snapshot: Vec<PoolSnapshot>, stats: ScannerStats) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.rows = snapshot
+            .into_iter()
+            .map(|snapshot| {
+                let pool = snapshot.pool;
+                let price = snapshot.price.map(|price| price.token0_price);
+                let first = price.map(|price| *self.first_prices.entry(pool.address.to_string()).or_insert(price));
+                PoolRow {
+                    pair: format!("{}/{}", pool.token0_symbol, pool.token1_symbol),
+                    protocol: pool.protocol,
+                    change: price
+                        .zip(first)
+                        .filter(|(_, first)| *first != 0.0)
+                        .map(|(price, first)| (price / first - 1.0) * 100.0),
+                    price,
+                    volume_usd: pool.volume_24h_usd,
+                    liquidity_usd: pool.liquidity_usd,
+                    updates: stats.pool_updates.get(&pool.address).copied().unwrap_or_default(),
+                    age: now.saturating_sub(snapshot.last_activity),
+                    stale: snapshot.stale,
+                }
+            })
+            .collect();
+        sort_rows(&mut self.rows, self.sort, self.descending);
+        self.stats = stats;
+    }
+
+    fn on_event(&mut self, event: ScannerEvent) {
+        match event {
+            ScannerEvent::Status(status) => {
+                self.connection = match status {
+                    ScannerStatus::Subscribed { pools } => format!("connected, {pools} pools"),
+                    ScannerStatus::SubscriptionDropped => "disconnected".to_string(),
+                    ScannerStatus::Reconnecting { attempt, delay_ms } => {
+                        format!("reconnecting (attempt {attempt}, in {:.1}s)", delay_ms as f64 / 1000.0)
+                    }
+                    ScannerStatus::Reconnected => "connected".to_string(),
+                    ScannerStatus::Live { pools } => format!("live, {pools} pools"),
+                }
+            }
+            ScannerEvent::Error(error) => self.last_error = Some(error.to_string()),
+            _ => {}
+        }
+    }
+
+    /// Handle a key; `false` to quit.
+    fn on_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        let index = Column::ALL.iter().position(|column| *column == self.sort).unwrap_or_default();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::PageDown => self.table.scroll_down_by(20),
+            KeyCode::PageUp => self.table.scroll_up_by(20),
+            KeyCode::Right | KeyCode::Char('s') => self.sort = Column::ALL[(index + 1) % Column::ALL.len()],
+            KeyCode::Left => self.sort = Column::ALL[(index + Column::ALL.len() - 1) % Column::ALL.len()],
+            KeyCode::Char('r') => self.descending = !self.descending,
+            KeyCode::Char(digit @ '1'..='8') => {
+                let column = Column::ALL[digit as usize - '1' as usize];
+                if column == self.sort {
+                    self.descending = !self.descending;
+                }
+                self.sort = column;
+            }
+            _ => {}
+        }
+        sort_rows(&mut self.rows, self.sort, self.descending);
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [status, table, help] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+
+        let stats = &self.stats;
+        let connected = stats.subscription_uptime.is_some();
+        let mut line = vec![
+            Span::styled("● ", Style::new().fg(if connected { Color::Green } else { Color::Red })),
+            Span::raw(self.connection.clone()).bold(),
+            Span::raw(format!(
+                "  ·  {} pools  ·  block {}  ·  {} logs  ·  {} updates  ·  {} parse errors",
+                self.rows.len(),
+                stats.last_block.map(|block| block.to_string()).unwrap_or_else(|| "-".to_string()),
+                stats.logs_received,
+                stats.price_updates,
+                stats.parse_errors
+            )),
+        ];
+        if let Some(since) = stats.since_last_log {
+            line.push(Span::raw(format!("  ·  last log {}s ago", since.as_secs())));
+        }
+        let error = self.last_error.as_deref().map(|error| Line::from(error.to_string()).red()).unwrap_or_default();
+        frame.render_widget(Paragraph::new(vec![Line::from(line), error]), status);
+
+        let header = Row::new(Column::ALL.iter().map(|column| {
+            let arrow = match (*column == self.sort, self.descending) {
+                (false, _) => "",
+                (true, true) => " ▼",
+                (true, false) => " ▲",
+            };
+            Cell::from(format!("{}{arrow}", column.title()))
+        }))
+        .style(Style::new().add_modifier(Modifier::BOLD).fg(Color::Cyan));
+        let rows = self.rows.iter().map(|row| {
+            let change = match row.change {
+                Some(change) if change > 0.0 => Cell::from(format!("{change:+.2}%")).green(),
+                Some(change) if change < 0.0 => Cell::from(format!("{change:+.2}%")).red(),
+                Some(change) => Cell::from(format!("{change:+.2}%")),
+                None => Cell::from("-"),
+            };
+            let row_style = if row.stale { Style::new().dim() } else { Style::new() };
+            Row::new(vec![
+                Cell::from(row.pair.clone()),
+                Cell::from(row.protocol.clone()),
+                Cell::from(row.price.map(format_price).unwrap_or_else(|| "-".to_string())),
+                change,
+                Cell::from(format_usd(row.volume_usd)),
+                Cell::from(format_usd(row.liquidity_usd)),
+                Cell::from(row.updates.to_string()),
+                Cell::from(format!("{}s", row.age)),
+            ])
+            .style(row_style)
+        });
+        let widths: Vec<_> = Column::ALL.iter().map(|column| column.width()).collect();
+        let table_widget = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table_widget, table, &mut self.table);
+
+        frame.render_widget(
+            Paragraph::new("↑↓ scroll  ·  ←→ / s / 1-8 sort column  ·  r reverse  ·  q quit").dim(),
+            help,
+        );
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        scanner: &Scanner,
+        events: &mut tokio::sync::broadcast::Receiver<ScannerEvent>,
+    ) -> eyre::Result<()> {
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    while event::poll(Duration::ZERO)? {
+                        if let Event::Key(key) = event::read()?
+                            && key.kind == KeyEventKind::Press
+                            && !self.on_key(key.code, key.modifiers)
+                        {
+                            return Ok(());
+                        }
+                    }
+                    self.refresh(scanner.snapshot(), scanner.stats());
+                    terminal.draw(|frame| self.draw(frame))?;
+                }
+                event = events.recv() => match event {
+                    Ok(event) => self.on_event(event),
+                    // Price updates are read from the snapshot; missing some of the bus is harmless.
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+            }
+        }
+    }
+}
+
+/// Six significant digits.
+fn format_price(price: f64) -> String {
+    if !price.is_finite() || price == 0.0 {
+        return price.to_string();
+    }
+    let decimals = (5 - price.abs().log10().floor() as i32).clamp(0, 12) as usize;
+    format!("{price:.decimals$}")
+}
+
+/// `$1.25M`, `$830.1K`, `$12`.
+fn format_usd(value: f64) -> String {
+    match value {
+        v if v >= 1e9 => format!("${:.2}B", v / 1e9),
+        v if v >= 1e6 => format!("${:.2}M", v / 1e6),
+        v if v >= 1e3 => format!("${:.1}K", v / 1e3),
+        v => format!("${v:.0}"),
+    }
+}
+
+pub async fn run(options: ScannerOptions) -> eyre::Result<()> {
+    let mut scanner = Scanner::with_options(Arc::new(|_, _, _| {}), options).await?;
+    // Subscribe first so the dashboard sees the initial `Subscribed`.
+    let mut events = scanner.subscribe();
+    eprintln!("Discovering pools…");
+    scanner.start().await?;
+
+    let mut terminal = ratatui::init();
+    let result = App::new().run(&mut terminal, &scanner, &mut events).await;
+    ratatui::restore();
+    scanner.shutdown().await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_rows() {
+        let row = |pair: &str, change: Option<f64>| PoolRow {
+            pair: pair.to_string(),
+            change,
+            ..Default::default()
+        };
+        let mut rows = vec![row("weth/usdc", Some(-1.0)), row("WBTC/USDC", None), row("ARB/WETH", Some(2.5))];
+        let pairs = |rows: &[PoolRow]| rows.iter().map(|row| row.pair.clone()).collect::<Vec<_>>();
+
+        sort_rows(&mut rows, Column::Change, true);
+        assert_eq!(pairs(&rows), vec!["ARB/WETH", "weth/usdc", "WBTC/USDC"]);
+        sort_rows(&mut rows, Column::Pair, false);
+        assert_eq!(pairs(&rows), vec!["ARB/WETH", "WBTC/USDC", "weth/usdc"]);
+        assert_eq!(format_usd(1_250_000.0), "$1.25M");
+        assert_eq!(format_price(0.000123456789), "0.000123457");
+    }
+}
@@ -0,0 +1,21 @@
+//! `validate-config`: check the configuration files and services before a run, with a pass/fail report.
+
+use clap::Args;
+use dex_pool_scanner_rust::config;
+
+#[derive(Debug, Args)]
+pub struct ValidateConfigArgs {
+    /// Only check the files; don't connect to the RPC endpoint or query subgraphs.
+    #[arg(long)]
+    offline: bool,
+}
+
+pub async fn run(args: ValidateConfigArgs) -> eyre::Result<()> {
+    let report = config::validate(!args.offline).await;
+    println!("{report}");
+    if !report.passed() {
+        // The report says what failed; exit non-zero for scripts and CI.
+        std::process::exit(1);
+    }
+    Ok(())
+}
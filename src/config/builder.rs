@@ -0,0 +1,281 @@
+//! Builders for `ProtocolConfig` and `DiscoveryConfig`, for configuration that doesn't come from files (e.g.
+//! generated from a database). The results go to `PoolDiscovery::discover_pools` or, as a `ScanConfig`, to
+//! `ScannerOptions::config`.
+
+use super::checks::{self, Problem};
+use crate::discovery::PoolRanking;
+use crate::types::{DiscoveryConfig, DiscoveryMode, Protocol, ProtocolConfig, QueryOverrides};
+use alloy::primitives::Address;
+use eyre::Result;
+
+/// Builds a `ProtocolConfig`; see `ProtocolConfig::builder`.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolConfigBuilder {
+    id: String,
+    name: Option<String>,
+    pool_type: Option<Protocol>,
+    subgraph_url: String,
+    factory: Address,
+    start_block: Option<u64>,
+    fee_tiers: Vec<u32>,
+    min_liquidity_usd: Option<f64>,
+    max_pools: Option<u32>,
+    dex_id: Option<String>,
+    query_overrides: Option<QueryOverrides>,
+}
+
+impl ProtocolConfig {
+    /// An enabled protocol; `id` and `pool_type` are required, `name` defaults to the id.
+    pub fn builder() -> ProtocolConfigBuilder {
+        ProtocolConfigBuilder::default()
+    }
+}
+
+impl ProtocolConfigBuilder {
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn pool_type(mut self, pool_type: Protocol) -> Self {
+        self.pool_type = Some(pool_type);
+        self
+    }
+
+    /// GraphQL endpoint of the protocol's subgraph, e.g. a gateway URL with the API key in it.
+    pub fn subgraph_url(mut self, url: impl Into<String>) -> Self {
+        self.subgraph_url = url.into();
+        self
+    }
+
+    pub fn factory(mut self, factory: Address) -> Self {
+        self.factory = factory;
+        self
+    }
+
+    pub fn start_block(mut self, block: u64) -> Self {
+        self.start_block = Some(block);
+        self
+    }
+
+    pub fn fee_tiers(mut self, fee_tiers: impl IntoIterator<Item = u32>) -> Self {
+        self.fee_tiers = fee_tiers.into_iter().collect();
+        self
+    }
+
+    pub fn min_liquidity_usd(mut self, min_liquidity_usd: f64) -> Self {
+        self.min_liquidity_usd = Some(min_liquidity_usd);
+        self
+    }
+
+    pub fn max_pools(mut self, max_pools: u32) -> Self {
+        self.max_pools = Some(max_pools);
+        self
+    }
+
+    pub fn dex_id(mut self, dex_id: impl Into<String>) -> Self {
+        self.dex_id = Some(dex_id.into());
+        self
+    }
+
+    pub fn query_overrides(mut self, overrides: QueryOverrides) -> Self {
+        self.query_overrides = Some(overrides);
+        self
+    }
+
+    /// Fails listing every problem: a missing id or pool type, `max_pools` 0, a negative `min_liquidity_usd`.
+    pub fn build(self) -> Result<ProtocolConfig> {
+        let mut problems = Vec::new();
+        if self.id.is_empty() {
+            problems.push(Problem::new("id", "required"));
+        }
+        if self.pool_type.is_none() {
+            problems.push(Problem::new("pool_type", "required"));
+        }
+        if self.max_pools == Some(0) {
+            problems.push(Problem::new("max_pools", "must be at least 1"));
+        }
+        if self.min_liquidity_usd.is_some_and(|min| min < 0.0) {
+            problems.push(Problem::new("min_liquidity_usd", "must not be negative"));
+        }
+        checks::ensure_valid(&format!("protocol {:?}", self.id), &problems)?;
+        Ok(ProtocolConfig {
+            name: self.name.unwrap_or_else(|| self.id.clone()),
+            id: self.id,
+            subgraph_url: self.subgraph_url,
+            pool_type: self.pool_type.unwrap_or(Protocol::UniswapV2),
+            enabled: true,
+            factory: self.factory,
+            start_block: self.start_block,
+            fee_tiers: self.fee_tiers,
+            min_liquidity_usd: self.min_liquidity_usd,
+            max_pools: self.max_pools,
+            dex_id: self.dex_id,
+            query_overrides: self.query_overrides,
+        })
+    }
+}
+
+/// Builds a `DiscoveryConfig`; see `DiscoveryConfig::builder`.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryConfigBuilder {
+    config: DiscoveryConfig,
+}
+
+impl DiscoveryConfig {
+    /// Starts from `DiscoveryConfig::default()`, the protocols file's defaults.
+    pub fn builder() -> DiscoveryConfigBuilder {
+        DiscoveryConfigBuilder::default()
+    }
+}
+
+impl DiscoveryConfigBuilder {
+    pub fn min_liquidity_usd(mut self, min_liquidity_usd: f64) -> Self {
+        self.config.min_liquidity_usd = min_liquidity_usd;
+        self
+    }
+
+    pub fn max_pools_per_protocol(mut self, max_pools: u32) -> Self {
+        self.config.max_pools_per_protocol = max_pools;
+        self
+    }
+
+    pub fn mode(mut self, mode: DiscoveryMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    /// Read and write the pool cache at `file`.
+    pub fn cache_file(mut self, file: impl Into<String>) -> Self {
+        self.config.cache_enabled = true;
+        self.config.cache_file = file.into();
+        self
+    }
+
+    pub fn cache_refresh_minutes(mut self, minutes: u32) -> Self {
+        self.config.cache_refresh_minutes = minutes;
+        self
+    }
+
+    pub fn log_chunk_size(mut self, blocks: u64) -> Self {
+        self.config.log_chunk_size = blocks;
+        self
+    }
+
+    pub fn retries(mut self, max_retries: u32, base_delay_ms: u64) -> Self {
+        self.config.max_retries = max_retries;
+        self.config.retry_base_delay_ms = base_delay_ms;
+        self
+    }
+
+    pub fn verify_token_metadata(mut self, verify: bool) -> Self {
+        self.config.verify_token_metadata = verify;
+        self
+    }
+
+    pub fn risk_filter(mut self, enabled: bool) -> Self {
+        self.config.risk_filter = enabled;
+        self
+    }
+
+    pub fn detect_token_quirks(mut self, detect: bool) -> Self {
+        self.config.detect_token_quirks = detect;
+        self
+    }
+
+    pub fn fee_on_transfer_tokens(mut self, tokens: impl IntoIterator<Item = Address>) -> Self {
+        self.config.fee_on_transfer_tokens = tokens.into_iter().collect();
+        self
+    }
+
+    pub fn rebasing_tokens(mut self, tokens: impl IntoIterator<Item = Address>) -> Self {
+        self.config.rebasing_tokens = tokens.into_iter().collect();
+        self
+    }
+
+    pub fn ranking(mut self, ranking: PoolRanking) -> Self {
+        self.config.ranking = Some(ranking);
+        self
+    }
+
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.config.network = network.into();
+        self
+    }
+
+    pub fn seed_tokens(mut self, tokens: impl IntoIterator<Item = Address>) -> Self {
+        self.config.seed_tokens = tokens.into_iter().collect();
+        self
+    }
+
+    pub fn max_subgraph_lag_blocks(mut self, blocks: u64, fail: bool) -> Self {
+        self.config.max_subgraph_lag_blocks = Some(blocks);
+        self.config.fail_on_subgraph_lag = fail;
+        self
+    }
+
+    pub fn discovery_concurrency(mut self, protocols: usize) -> Self {
+        self.config.discovery_concurrency = protocols;
+        self
+    }
+
+    /// Rediscover incrementally, with a full discovery every `full_refresh_every` runs.
+    pub fn incremental_discovery(mut self, full_refresh_every: u32) -> Self {
+        self.config.incremental_discovery = true;
+        self.config.full_refresh_every = full_refresh_every;
+        self
+    }
+
+    /// Fails listing every problem: `max_pools_per_protocol` 0, a negative `min_liquidity_usd`, no `network` in
+    /// the aggregator modes.
+    pub fn build(self) -> Result<DiscoveryConfig> {
+        checks::ensure_valid("discovery settings", &checks::discovery_problems(&self.config))?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::ScanConfig;
+
+    #[test]
+    fn test_builders() {
+        let protocol = ProtocolConfig::builder()
+            .id("uniswap-v3")
+            .pool_type(Protocol::UniswapV3)
+            .subgraph_url("http://localhost/v3")
+            .fee_tiers([500, 3000])
+            .max_pools(20)
+            .build()
+            .unwrap();
+        assert_eq!((protocol.name.as_str(), protocol.enabled), ("uniswap-v3", true));
+        assert!(!protocol.allows_fee(100));
+
+        let error = ProtocolConfig::builder().max_pools(0).build().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "invalid configuration in protocol \"\":\n  id: required\n  pool_type: required\n  max_pools: must be at least 1"
+        );
+
+        let discovery = DiscoveryConfig::builder().min_liquidity_usd(50_000.0).incremental_discovery(10).build().unwrap();
+        assert_eq!(discovery.max_pools_per_protocol, 100);
+        assert_eq!(protocol.discovery_settings(&discovery).max_pools_per_protocol, 20);
+        assert!(DiscoveryConfig::builder().mode(DiscoveryMode::GeckoTerminal).build().is_err());
+
+        let config = ScanConfig::new(vec![protocol.clone()], discovery).unwrap();
+        assert_eq!(config.protocols, vec![protocol.clone()]);
+        let onchain = DiscoveryConfig::builder().mode(DiscoveryMode::FactoryLogs).build().unwrap();
+        let error = ScanConfig::new(vec![protocol.clone(), protocol], onchain).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "invalid configuration in scan configuration:\n  protocols.uniswap-v3.factory: required for FactoryLogs discovery\n  \
+             protocols.uniswap-v3.id: used by more than one protocol\n  protocols.uniswap-v3.factory: required for FactoryLogs discovery"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Checks of a loaded protocols or tokens file, shared by the loaders (which refuse a file with problems) and
+//! `ValidationReport` (which lists them).
+
+use super::{ProtocolsFile, TokenEntries, TokensFile};
+use crate::types::{DiscoveryConfig, DiscoveryMode, Protocol, ProtocolConfig};
+use alloy::primitives::Address;
+use std::collections::HashMap;
+use std::fmt;
+
+/// One invalid setting: where it is (`protocols.uniswap-v3.poolType`) and what's wrong with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Problem {
+    pub field: String,
+    pub message: String,
+}
+
+impl Problem {
+    pub(crate) fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// An error listing every problem of `source`, or `Ok` when there are none.
+pub(crate) fn ensure_valid(source: &str, problems: &[Problem]) -> eyre::Result<()> {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let lines: Vec<String> = problems.iter().map(|problem| format!("  {}", problem)).collect();
+    eyre::bail!("invalid configuration in {}:\n{}", source, lines.join("\n"))
+}
+
+/// The `poolType` names.
+pub(crate) fn pool_type(name: &str) -> Option<Protocol> {
+    match name {
+        "UniswapV2" => Some(Protocol::UniswapV2),
+        "UniswapV3" => Some(Protocol::UniswapV3),
+        _ => None,
+    }
+}
+
+/// Problems of the enabled protocols (by id) and the discovery section, given the Graph API key in use.
+pub(crate) fn protocol_problems(file: &ProtocolsFile, api_key: &str) -> Vec<Problem> {
+    let mode = file.discovery.mode;
+    let mut problems = Vec::new();
+    let mut needs_key = false;
+    let mut enabled: Vec<_> = file.protocols.iter().filter(|(_, entry)| entry.enabled).collect();
+    enabled.sort_by_key(|(id, _)| id.as_str());
+    for (id, entry) in enabled {
+        let field = |key: &str| format!("protocols.{}.{}", id, key);
+        match entry.factory.parse::<Address>() {
+            Ok(factory) if factory.is_zero() && mode.is_onchain() => {
+                problems.push(Problem::new(field("factory"), format!("required for {:?} discovery", mode)))
+            }
+            Ok(_) => {}
+            Err(_) if entry.factory.is_empty() && !mode.is_onchain() => {}
+            Err(_) if entry.factory.is_empty() => {
+                problems.push(Problem::new(field("factory"), format!("required for {:?} discovery", mode)))
+            }
+            Err(_) => problems.push(Problem::new(field("factory"), format!("{:?} is not an address", entry.factory))),
+        }
+        if pool_type(&entry.pool_type).is_none() {
+            let message = format!("{:?} is not UniswapV2 or UniswapV3", entry.pool_type);
+            problems.push(Problem::new(field("poolType"), message));
+        }
+        if entry.max_pools == Some(0) {
+            problems.push(Problem::new(field("maxPools"), "must be at least 1"));
+        }
+        if entry.min_liquidity_usd.is_some_and(|min| min < 0.0) {
+            problems.push(Problem::new(field("minLiquidityUSD"), "must not be negative"));
+        }
+        match mode {
+            DiscoveryMode::Subgraph if entry.subgraph_url.is_none() && entry.subgraph_id.is_empty() => {
+                problems.push(Problem::new(field("subgraphId"), "required for Subgraph discovery, unless subgraphUrl is set"))
+            }
+            DiscoveryMode::Subgraph if entry.subgraph_url.is_none() => needs_key = true,
+            DiscoveryMode::GeckoTerminal | DiscoveryMode::Dexscreener if entry.dex_id.is_none() => {
+                problems.push(Problem::new(field("dexId"), format!("required for {:?} discovery", mode)))
+            }
+            _ => {}
+        }
+    }
+    if needs_key && api_key.is_empty() {
+        problems.push(Problem::new("THE_GRAPH_API_KEY", "not set; protocols with a subgraphId need it"));
+    }
+    let discovery = &file.discovery;
+    if matches!(mode, DiscoveryMode::GeckoTerminal | DiscoveryMode::Dexscreener) && discovery.network.is_empty() {
+        problems.push(Problem::new("discovery.network", format!("required for {:?} discovery", mode)));
+    }
+    if discovery.max_pools_per_protocol == 0 {
+        problems.push(Problem::new("discovery.maxPoolsPerProtocol", "must be at least 1"));
+    }
+    if discovery.min_liquidity_usd < 0.0 {
+        problems.push(Problem::new("discovery.minLiquidityUSD", "must not be negative"));
+    }
+    problems
+}
+
+/// Problems of programmatic discovery settings, by field name.
+pub(crate) fn discovery_problems(config: &DiscoveryConfig) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mode = config.mode;
+    if matches!(mode, DiscoveryMode::GeckoTerminal | DiscoveryMode::Dexscreener) && config.network.is_empty() {
+        problems.push(Problem::new("network", format!("required for {:?} discovery", mode)));
+    }
+    if config.max_pools_per_protocol == 0 {
+        problems.push(Problem::new("max_pools_per_protocol", "must be at least 1"));
+    }
+    if config.min_liquidity_usd < 0.0 {
+        problems.push(Problem::new("min_liquidity_usd", "must not be negative"));
+    }
+    problems
+}
+
+/// What programmatic protocols lack for `discovery`'s mode (a subgraph URL, a factory, a DEX id), plus duplicate
+/// ids and the problems of `discovery` itself.
+pub(crate) fn scan_problems(protocols: &[ProtocolConfig], discovery: &DiscoveryConfig) -> Vec<Problem> {
+    let mode = discovery.mode;
+    let mut problems = Vec::new();
+    let mut ids = std::collections::HashSet::new();
+    for protocol in protocols {
+        let field = |key: &str| format!("protocols.{}.{}", protocol.id, key);
+        if !ids.insert(protocol.id.as_str()) {
+            problems.push(Problem::new(field("id"), "used by more than one protocol"));
+        }
+        let missing = match mode {
+            DiscoveryMode::Subgraph if protocol.subgraph_url.is_empty() => Some("subgraph_url"),
+            DiscoveryMode::FactoryLogs | DiscoveryMode::FactoryEnumeration if protocol.factory.is_zero() => {
+                Some("factory")
+            }
+            DiscoveryMode::GeckoTerminal | DiscoveryMode::Dexscreener if protocol.dex_id.is_none() => Some("dex_id"),
+            _ => None,
+        };
+        if let Some(key) = missing {
+            problems.push(Problem::new(field(key), format!("required for {:?} discovery", mode)));
+        }
+    }
+    problems.extend(discovery_problems(discovery).into_iter().map(|problem| Problem {
+        field: format!("discovery.{}", problem.field),
+        ..problem
+    }));
+    problems
+}
+
+/// Token addresses that don't parse, pairs that don't resolve to two tokens, invalid blacklist addresses, of
+/// the top-level sections and of each chain section (`8453.tokens.WETH`).
+pub(crate) fn token_problems(file: &TokensFile) -> Vec<Problem> {
+    let shared = file.tokens.resolve(None);
+    // The top-level pairs may name tokens of the chain sections.
+    let mut all = shared.clone();
+    all.extend(file.chains.values().flat_map(|section| section.tokens.resolve(None)));
+    let mut problems = section_problems(file, &all);
+    for (chain_id, section) in &file.chains {
+        // A chain's pairs may name top-level tokens.
+        let mut symbols = shared.clone();
+        symbols.extend(section.tokens.resolve(None));
+        problems.extend(section_problems(section, &symbols).into_iter().map(|problem| Problem {
+            field: format!("{}.{}", chain_id, problem.field),
+            ..problem
+        }));
+    }
+    problems
+}
+
+/// `token_problems` of one section, with `symbols` for its pairs.
+fn section_problems(file: &TokensFile, symbols: &HashMap<String, Address>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let not_an_address = |address: &str| format!("{:?} is not an address", address);
+    match &file.tokens {
+        TokenEntries::Map(tokens) => {
+            let mut tokens: Vec<_> = tokens.iter().collect();
+            tokens.sort();
+            for (symbol, address) in tokens {
+                if address.parse::<Address>().is_err() {
+                    problems.push(Problem::new(format!("tokens.{}", symbol), not_an_address(address)));
+                }
+            }
+        }
+        TokenEntries::List(tokens) => {
+            for (i, token) in tokens.iter().enumerate() {
+                if token.address.parse::<Address>().is_err() {
+                    problems.push(Problem::new(format!("tokens[{}].address", i), not_an_address(&token.address)));
+                }
+            }
+        }
+    }
+    let resolves = |side: &str| side.trim().parse::<Address>().is_ok() || symbols.contains_key(side.trim());
+    for (i, pair) in file.pairs.iter().enumerate() {
+        if !pair.split_once('/').is_some_and(|(a, b)| resolves(a) && resolves(b)) {
+            let message = format!("{:?} is not two known tokens, as \"SYMBOL/SYMBOL\" or addresses", pair);
+            problems.push(Problem::new(format!("pairs[{}]", i), message));
+        }
+    }
+    for (section, entries) in [("tokens", &file.blacklist.tokens), ("pools", &file.blacklist.pools)] {
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.parse::<Address>().is_err() {
+                problems.push(Problem::new(format!("blacklist.{}[{}]", section, i), not_an_address(entry)));
+            }
+        }
+    }
+    problems
+}
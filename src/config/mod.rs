@@ -1,35 +1,181 @@
-use crate::types::{DiscoveryConfig, Protocol, ProtocolConfig};
-use std::collections::HashMap;
+use crate::discovery::{canonical_pair, Blacklist, PoolRanking};
+use crate::types::{DiscoveryConfig, DiscoveryMode, ProtocolConfig, QueryOverrides};
+use alloy::primitives::Address;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
 use eyre::Result;
 
+mod builder;
+mod checks;
+mod overrides;
+mod scanner_file;
+mod secrets;
+mod validate;
+mod version;
+
+pub use builder::{DiscoveryConfigBuilder, ProtocolConfigBuilder};
+pub use scanner_file::{scanner_file_path, RpcSection, ScannerFile, SinkEntry};
+pub use secrets::secret;
+pub(crate) use secrets::Secrets;
+pub use validate::{validate, Check, CheckStatus, ValidationReport};
+pub use version::CONFIG_VERSION;
+pub(crate) use version::FileKind;
+
+/// Fails listing every problem of programmatic protocols and discovery settings (see `ScanConfig::new`).
+pub(crate) fn ensure_valid_scan(protocols: &[ProtocolConfig], discovery: &DiscoveryConfig) -> Result<()> {
+    checks::ensure_valid("scan configuration", &checks::scan_problems(protocols, discovery))
+}
+
+/// Default names of the protocols file, in the order they are looked for.
+pub(crate) const PROTOCOLS_FILES: &[&str] = &["protocols.json", "protocols.yaml", "protocols.yml"];
+/// Default names of the tokens file, in the order they are looked for.
+pub(crate) const TOKENS_FILES: &[&str] = &["tokens.json", "tokens.yaml", "tokens.yml"];
+/// Default names of the secrets file (see `secrets`), in the order they are looked for.
+pub(crate) const SECRETS_FILES: &[&str] = &["secrets.toml", "secrets.yaml", "secrets.yml", "secrets.json"];
+
+/// The file named by `env_key`, or the first of `defaults` that exists in the working directory (the first
+/// one when none does).
+pub(crate) fn config_path(env_key: &str, defaults: &[&str]) -> PathBuf {
+    if let Ok(path) = std::env::var(env_key) {
+        return PathBuf::from(path);
+    }
+    let dir = std::env::current_dir().unwrap();
+    defaults
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| dir.join(defaults[0]))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// YAML for `.yaml` / `.yml`, TOML for `.toml`, JSON for `.json`, `default` otherwise.
+    pub(crate) fn of(path: &Path, default: Format) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            _ => default,
+        }
+    }
+
+    pub(crate) fn parse<T: DeserializeOwned>(self, content: &str) -> Result<T> {
+        match self {
+            Format::Json => Ok(serde_json::from_str(content)?),
+            Format::Yaml => Ok(serde_yaml::from_str(content)?),
+            Format::Toml => Ok(toml::from_str(content)?),
+        }
+    }
+
+    /// `parse` of a `kind` file named `source`, migrated to the current `version` (see `version::migrate`), with
+    /// the `SCANNER_*` environment overrides (see `overrides`) of its sections applied and its secret references
+    /// resolved (see `secrets`).
+    pub(crate) fn parse_config<T: DeserializeOwned>(self, content: &str, kind: FileKind, source: &str) -> Result<T> {
+        let mut value: serde_json::Value = self.parse(content)?;
+        version::migrate(&mut value, kind, source)?;
+        let vars = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+        overrides::apply(&mut value, &overrides::overrides(vars, kind.sections()))?;
+        Secrets::load()?.resolve(&mut value, source)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Parse a `kind` config file by its extension: YAML for `.yaml` / `.yml`, TOML for `.toml`, JSON otherwise.
+/// Migrated and with its `SCANNER_*` overrides (see `Format::parse_config`).
+pub(crate) fn parse_file<T: DeserializeOwned>(path: &Path, content: &str, kind: FileKind) -> Result<T> {
+    Format::of(path, Format::Json).parse_config(content, kind, &path.display().to_string())
+}
+
 /// Format of each protocol entry in protocols.json (camelCase).
-#[derive(serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 struct ProtocolEntry {
     name: String,
-    #[allow(dead_code)]
     factory: String,
-    #[serde(rename = "subgraphId")]
+    #[serde(rename = "subgraphId", default)]
     subgraph_id: String,
+    #[serde(rename = "subgraphUrl", default)]
+    subgraph_url: Option<String>,
     enabled: bool,
     #[serde(rename = "poolType")]
     pool_type: String,
+    #[serde(rename = "startBlock", default)]
+    start_block: Option<u64>,
+    /// `None` uses `discovery.feeTiers`; `[]` keeps every tier.
+    #[serde(rename = "feeTiers", default)]
+    fee_tiers: Option<Vec<u32>>,
+    #[serde(rename = "minLiquidityUSD", default)]
+    min_liquidity_usd: Option<f64>,
+    #[serde(rename = "maxPools", default)]
+    max_pools: Option<u32>,
+    #[serde(rename = "dexId", default)]
+    dex_id: Option<String>,
+    #[serde(rename = "queryOverrides", default)]
+    query_overrides: Option<QueryOverrides>,
 }
 
 /// Format of discovery section in protocols.json (camelCase).
-#[derive(serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 struct DiscoveryEntry {
     #[serde(rename = "minLiquidityUSD")]
     min_liquidity_usd: f64,
     #[serde(rename = "cacheRefreshMinutes")]
-    #[allow(dead_code)]
     cache_refresh_minutes: u32,
     #[serde(rename = "maxPoolsPerProtocol")]
     max_pools_per_protocol: u32,
+    /// Fee tiers of the V3 protocols without their own `feeTiers`.
+    #[serde(rename = "feeTiers", default)]
+    fee_tiers: Vec<u32>,
+    #[serde(default)]
+    mode: DiscoveryMode,
+    #[serde(rename = "logChunkSize", default)]
+    log_chunk_size: Option<u64>,
+    #[serde(rename = "maxRetries", default)]
+    max_retries: Option<u32>,
+    #[serde(rename = "retryBaseDelayMs", default)]
+    retry_base_delay_ms: Option<u64>,
+    #[serde(rename = "cacheEnabled", default)]
+    cache_enabled: bool,
+    #[serde(rename = "cacheFile", default)]
+    cache_file: Option<String>,
+    #[serde(rename = "verifyTokenMetadata", default)]
+    verify_token_metadata: Option<bool>,
+    #[serde(rename = "riskFilter", default)]
+    risk_filter: bool,
+    #[serde(rename = "detectTokenQuirks", default)]
+    detect_token_quirks: Option<bool>,
+    #[serde(rename = "feeOnTransferTokens", default)]
+    fee_on_transfer_tokens: Vec<Address>,
+    #[serde(rename = "rebasingTokens", default)]
+    rebasing_tokens: Vec<Address>,
+    #[serde(default)]
+    ranking: Option<PoolRanking>,
+    #[serde(default)]
+    network: String,
+    #[serde(rename = "seedTokens", default)]
+    seed_tokens: Vec<Address>,
+    #[serde(rename = "maxSubgraphLagBlocks", default)]
+    max_subgraph_lag_blocks: Option<u64>,
+    #[serde(rename = "failOnSubgraphLag", default)]
+    fail_on_subgraph_lag: bool,
+    #[serde(rename = "discoveryConcurrency", default)]
+    discovery_concurrency: Option<usize>,
+    #[serde(rename = "incrementalDiscovery", default)]
+    incremental_discovery: bool,
+    #[serde(rename = "fullRefreshEvery", default)]
+    full_refresh_every: Option<u32>,
 }
 
 /// Root format of protocols.json: { "protocols": { "id": {...} }, "discovery": {...} }
-#[derive(serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 struct ProtocolsFile {
     protocols: HashMap<String, ProtocolEntry>,
     discovery: DiscoveryEntry,
@@ -43,86 +189,540 @@ fn subgraph_url_from_id(subgraph_id: &str, api_key: &str) -> String {
     )
 }
 
-/// Load protocols.json and discovery config from the same file.
-/// Expects format: { "protocols": { "id": { name, factory, subgraphId, enabled, poolType } }, "discovery": { minLiquidityUSD, cacheRefreshMinutes, maxPoolsPerProtocol } }.
-/// Subgraph URL is built using THE_GRAPH_API_KEY. Returns only enabled protocols.
+/// Load protocols.json (or the same structure in YAML, see `parse`) and discovery config from the same file.
+/// Expects format: { "version"?, "protocols": { "id": { name, factory, subgraphId | subgraphUrl, enabled, poolType, startBlock?, feeTiers?, minLiquidityUSD?, maxPools?, dexId?, queryOverrides? } }, "discovery": { minLiquidityUSD, cacheRefreshMinutes, maxPoolsPerProtocol, feeTiers?, mode?, logChunkSize?, maxRetries?, retryBaseDelayMs?, cacheEnabled?, cacheFile?, verifyTokenMetadata?, riskFilter?, detectTokenQuirks?, feeOnTransferTokens?, rebasingTokens?, ranking?, network?, seedTokens?, maxSubgraphLagBlocks?, failOnSubgraphLag?, discoveryConcurrency?, incrementalDiscovery?, fullRefreshEvery? } }.
+/// Older layouts are migrated and a `version` newer than `CONFIG_VERSION` is an error (see `version`).
+/// Subgraph URL is `subgraphUrl` when set, otherwise built from `subgraphId` using THE_GRAPH_API_KEY (from the
+/// environment or the secrets file, see `secret`). Returns only
+/// enabled protocols. A protocol's `feeTiers`, `minLiquidityUSD` and `maxPools` replace the discovery section's
+/// `feeTiers`, `minLiquidityUSD` and `maxPoolsPerProtocol` for that protocol. Fails listing every problem with its location (`protocols.uniswap-v3.poolType`) when an
+/// enabled protocol has an unknown `poolType`, a malformed factory, lacks what the discovery mode needs (a subgraph
+/// URL or THE_GRAPH_API_KEY, a factory, a `dexId`), or a pool limit is 0 or a liquidity threshold negative.
 pub fn load_protocols_file(path: &str) -> Result<(Vec<ProtocolConfig>, DiscoveryConfig)> {
     let content = fs::read_to_string(path)?;
-    let file: ProtocolsFile = serde_json::from_str(&content)?;
+    let file: ProtocolsFile = parse_file(Path::new(path), &content, FileKind::Protocols)?;
+    resolve_protocols(file, &secret("THE_GRAPH_API_KEY").unwrap_or_default(), path)
+}
 
-    let api_key = std::env::var("THE_GRAPH_API_KEY").unwrap_or_else(|_| {
-        tracing::warn!("THE_GRAPH_API_KEY not set; subgraph URLs will be empty");
-        String::new()
-    });
+/// The enabled protocols and discovery settings of `file`, with gateway URLs built from `api_key`; `source` names
+/// the file in the error when it has problems.
+fn resolve_protocols(
+    file: ProtocolsFile,
+    api_key: &str,
+    source: &str,
+) -> Result<(Vec<ProtocolConfig>, DiscoveryConfig)> {
+    checks::ensure_valid(source, &checks::protocol_problems(&file, api_key))?;
+    let mode = file.discovery.mode;
 
     let mut protocols = Vec::new();
     for (id, entry) in file.protocols {
-        if !entry.enabled {
+        // Only enabled entries are checked.
+        let (true, Some(pool_type)) = (entry.enabled, checks::pool_type(&entry.pool_type)) else {
             continue;
-        }
-        let subgraph_url = if api_key.is_empty() {
-            String::new()
-        } else {
-            subgraph_url_from_id(&entry.subgraph_id, &api_key)
         };
-        // Skip protocols we can't query (no subgraph URL)
-        if subgraph_url.is_empty() {
-            continue;
-        }
-        let pool_type = match entry.pool_type.as_str() {
-            "UniswapV2" => Protocol::UniswapV2,
-            _ => Protocol::UniswapV3,
+        // A direct URL (self-hosted graph-node, Goldsky, ...) bypasses the gateway.
+        let subgraph_url = match entry.subgraph_url {
+            Some(url) => url,
+            None if api_key.is_empty() || entry.subgraph_id.is_empty() => String::new(),
+            None => subgraph_url_from_id(&entry.subgraph_id, api_key),
         };
+        // Only the on-chain modes need a factory.
+        let factory: Address = entry.factory.parse().unwrap_or_default();
         protocols.push(ProtocolConfig {
             id,
             name: entry.name,
             subgraph_url,
             pool_type,
             enabled: entry.enabled,
+            factory,
+            start_block: entry.start_block,
+            fee_tiers: entry.fee_tiers.unwrap_or_else(|| file.discovery.fee_tiers.clone()),
+            min_liquidity_usd: entry.min_liquidity_usd,
+            max_pools: entry.max_pools,
+            dex_id: entry.dex_id,
+            query_overrides: entry.query_overrides,
         });
     }
 
     let discovery = DiscoveryConfig {
         min_liquidity_usd: file.discovery.min_liquidity_usd,
         max_pools_per_protocol: file.discovery.max_pools_per_protocol,
-        cache_enabled: false,
-        cache_file: String::new(),
+        cache_enabled: file.discovery.cache_enabled,
+        cache_file: file.discovery.cache_file.unwrap_or_else(|| "pools_cache.json".to_string()),
+        mode,
+        log_chunk_size: file.discovery.log_chunk_size.unwrap_or(10_000),
+        max_retries: file.discovery.max_retries.unwrap_or(3),
+        retry_base_delay_ms: file.discovery.retry_base_delay_ms.unwrap_or(500),
+        cache_refresh_minutes: file.discovery.cache_refresh_minutes,
+        verify_token_metadata: file.discovery.verify_token_metadata.unwrap_or(true),
+        risk_filter: file.discovery.risk_filter,
+        detect_token_quirks: file.discovery.detect_token_quirks.unwrap_or(true),
+        fee_on_transfer_tokens: file.discovery.fee_on_transfer_tokens,
+        rebasing_tokens: file.discovery.rebasing_tokens,
+        ranking: file.discovery.ranking,
+        network: file.discovery.network,
+        seed_tokens: file.discovery.seed_tokens,
+        max_subgraph_lag_blocks: file.discovery.max_subgraph_lag_blocks,
+        fail_on_subgraph_lag: file.discovery.fail_on_subgraph_lag,
+        discovery_concurrency: file.discovery.discovery_concurrency.unwrap_or(4),
+        incremental_discovery: file.discovery.incremental_discovery,
+        full_refresh_every: file.discovery.full_refresh_every.unwrap_or(60),
     };
 
     Ok((protocols, discovery))
 }
 
+/// The enabled protocols of a protocols file in any layout `load_protocols_file` reads, including the version 0
+/// protocol list (snake_case `ProtocolConfig`s).
 pub fn load_protocol_config(path: &str) -> Result<Vec<ProtocolConfig>> {
-    let content = fs::read_to_string(path)?;
-    let config: Vec<ProtocolConfig> = serde_json::from_str(&content)?;
-    Ok(config)
+    Ok(load_protocols_file(path)?.0)
 }
 
+/// The discovery settings of a protocols file in any layout `load_protocols_file` reads, including version 0
+/// discovery settings (a snake_case `DiscoveryConfig`).
 pub fn load_discovery_config(path: &str) -> Result<DiscoveryConfig> {
-    let content = fs::read_to_string(path)?;
-    let config: DiscoveryConfig = serde_json::from_str(&content)?;
-    Ok(config)
+    Ok(load_protocols_file(path)?.1)
 }
 
-/// Root format of tokens.json: { "tokens": { "SYMBOL": "0x..." } }
-#[derive(serde::Deserialize)]
+/// Root format of tokens.json: { "tokens": { "SYMBOL": "0x..." }, "pairs"?: ["SYMBOL/SYMBOL", ...], "blacklist"?: {...} }.
+/// A Uniswap token list (`{ "name", "tokens": [{ chainId, address, symbol, decimals, logoURI }] }`) is accepted too.
+/// Keys that are chain ids (`{ "1": {...}, "8453": {...} }`) hold a section of the same format for that chain, used
+/// on top of the top-level sections, which may then be left out.
+#[derive(Debug, Clone)]
 struct TokensFile {
-    tokens: HashMap<String, String>,
+    tokens: TokenEntries,
+    pairs: Vec<String>,
+    blacklist: BlacklistEntry,
+    chains: BTreeMap<u64, TokensFile>,
 }
 
-/// Load token whitelist from tokens.json. Returns symbol -> address map.
-/// Optional: if file is missing or invalid, returns empty map.
+/// The sections of one chain, or the top-level ones.
+#[derive(Debug, Clone, serde::Deserialize)]
+struct TokensSection {
+    tokens: Option<TokenEntries>,
+    #[serde(default)]
+    pairs: Vec<String>,
+    #[serde(default)]
+    blacklist: BlacklistEntry,
+}
+
+impl<'de> serde::Deserialize<'de> for TokensFile {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use serde::de::Error;
+        let mut root = serde_json::Map::deserialize(deserializer)?;
+        let chain_keys: Vec<String> = root
+            .keys()
+            .filter(|key| !key.is_empty() && key.bytes().all(|byte| byte.is_ascii_digit()))
+            .cloned()
+            .collect();
+        let mut chains = BTreeMap::new();
+        for key in chain_keys {
+            let section = root.remove(&key).unwrap_or_default();
+            let chain_id: u64 = key.parse().map_err(|_| D::Error::custom(format!("{}: not a chain id", key)))?;
+            let section: TokensSection =
+                serde_json::from_value(section).map_err(|e| D::Error::custom(format!("{}: {}", key, e)))?;
+            let tokens = section.tokens.ok_or_else(|| D::Error::custom(format!("{}: missing field `tokens`", key)))?;
+            chains.insert(chain_id, TokensFile::from_section(tokens, section.pairs, section.blacklist));
+        }
+        let shared: TokensSection = serde_json::from_value(serde_json::Value::Object(root)).map_err(D::Error::custom)?;
+        let tokens = match shared.tokens {
+            Some(tokens) => tokens,
+            None if !chains.is_empty() => TokenEntries::Map(HashMap::new()),
+            None => return Err(D::Error::missing_field("tokens")),
+        };
+        Ok(TokensFile {
+            chains,
+            ..TokensFile::from_section(tokens, shared.pairs, shared.blacklist)
+        })
+    }
+}
+
+/// The `tokens` section: the bespoke symbol -> address map or a token list's array.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(untagged)]
+enum TokenEntries {
+    Map(HashMap<String, String>),
+    List(Vec<TokenListEntry>),
+}
+
+/// One token of a Uniswap token list (https://tokenlists.org). Other fields (`name`, `decimals`, `logoURI`, ...) are ignored.
+#[derive(Debug, Clone, serde::Deserialize)]
+struct TokenListEntry {
+    #[serde(rename = "chainId")]
+    chain_id: u64,
+    address: String,
+    symbol: String,
+}
+
+impl TokenEntries {
+    fn is_empty(&self) -> bool {
+        match self {
+            TokenEntries::Map(tokens) => tokens.is_empty(),
+            TokenEntries::List(tokens) => tokens.is_empty(),
+        }
+    }
+
+    /// Symbol -> address. Token list entries are restricted to `chain_id` when given; a symbol listed
+    /// with several addresses keeps the first under its symbol and the others under their address.
+    fn resolve(&self, chain_id: Option<u64>) -> HashMap<String, Address> {
+        match self {
+            TokenEntries::Map(tokens) => tokens
+                .iter()
+                .filter_map(|(symbol, addr)| Some((symbol.clone(), addr.parse().ok()?)))
+                .collect(),
+            TokenEntries::List(tokens) => {
+                let mut out = HashMap::new();
+                for token in tokens.iter().filter(|t| chain_id.is_none_or(|id| t.chain_id == id)) {
+                    let Ok(addr) = token.address.parse::<Address>() else {
+                        continue;
+                    };
+                    match out.get(&token.symbol) {
+                        None => {
+                            out.insert(token.symbol.clone(), addr);
+                        }
+                        Some(existing) if *existing != addr => {
+                            out.insert(format!("{:#x}", addr), addr);
+                        }
+                        Some(_) => {}
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    /// Symbols a token list has with several addresses among the tokens of `chain_id` (all of them without one),
+    /// which `resolve` keeps under one of them only.
+    fn ambiguous_symbols(&self, chain_id: Option<u64>) -> HashSet<&str> {
+        let TokenEntries::List(tokens) = self else {
+            return HashSet::new();
+        };
+        let mut addresses: HashMap<&str, HashSet<Address>> = HashMap::new();
+        for token in tokens.iter().filter(|t| chain_id.is_none_or(|id| t.chain_id == id)) {
+            if let Ok(addr) = token.address.parse() {
+                addresses.entry(&token.symbol).or_default().insert(addr);
+            }
+        }
+        addresses.into_iter().filter(|(_, addresses)| addresses.len() > 1).map(|(symbol, _)| symbol).collect()
+    }
+}
+
+/// Format of the blacklist section in tokens.json.
+#[derive(Debug, Clone, serde::Deserialize, Default)]
+struct BlacklistEntry {
+    #[serde(default)]
+    tokens: Vec<String>,
+    #[serde(default)]
+    pools: Vec<String>,
+    #[serde(default)]
+    symbols: Vec<String>,
+}
+
+/// Load token whitelist from tokens.json (or tokens.yaml, see `parse`). Returns symbol -> address map.
+/// Optional: if the file is missing, returns an empty map. Fails listing every malformed address and unresolvable
+/// pair of the file (`tokens.USDC`, `pairs[1]`, `blacklist.pools[0]`).
 pub fn load_tokens_file(path: &str) -> Result<HashMap<String, alloy::primitives::Address>> {
+    load_tokens_file_for_chain(path, None)
+}
+
+/// Like `load_tokens_file`, but when the file is a Uniswap token list only tokens of `chain_id` are kept, and
+/// when it has chain sections only that chain's (see `TokensFile::for_chain`).
+pub fn load_tokens_file_for_chain(path: &str, chain_id: Option<u64>) -> Result<HashMap<String, Address>> {
     let content = match fs::read_to_string(path) {
         Ok(c) => c,
         Err(_) => return Ok(HashMap::new()),
     };
-    let file: TokensFile = serde_json::from_str(&content)?;
-    let mut out = HashMap::new();
-    for (symbol, addr_str) in file.tokens {
-        if let Ok(addr) = addr_str.parse() {
-            out.insert(symbol, addr);
+    let file = read_tokens_file(path, &content)?.for_chain(path, chain_id)?;
+    Ok(file.tokens.resolve(chain_id))
+}
+
+/// Load the pair whitelist (`pairs` in tokens.json, e.g. `"WETH/USDC"`). Each side is a symbol from
+/// `tokens` or a token address. Pairs are returned in `discovery::canonical_pair` order.
+/// Optional: if the file or section is missing, returns an empty set. Fails like `load_tokens_file`.
+pub fn load_token_pairs(path: &str) -> Result<HashSet<(Address, Address)>> {
+    load_token_pairs_for_chain(path, None)
+}
+
+/// Like `load_token_pairs`, with the shared pairs and those of the `chain_id` section. Symbols of a token list
+/// are looked up among the tokens of `chain_id`; without one, a symbol the list has on several chains fails.
+pub fn load_token_pairs_for_chain(path: &str, chain_id: Option<u64>) -> Result<HashSet<(Address, Address)>> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(_) => return Ok(HashSet::new()),
+    };
+    read_tokens_file(path, &content)?.for_chain(path, chain_id)?.pairs(path, chain_id)
+}
+
+/// Load the `blacklist` section of tokens.json: `{ "tokens": [...], "pools": [...], "symbols": [...] }`.
+/// Token and pool entries are addresses; symbols are case-insensitive patterns with `*` wildcards.
+/// Optional: if the file or section is missing, returns an empty blacklist. Fails like `load_tokens_file`.
+pub fn load_blacklist(path: &str) -> Result<Blacklist> {
+    load_blacklist_for_chain(path, None)
+}
+
+/// Like `load_blacklist`, with the shared blacklist and that of the `chain_id` section.
+pub fn load_blacklist_for_chain(path: &str, chain_id: Option<u64>) -> Result<Blacklist> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(_) => return Ok(Blacklist::default()),
+    };
+    Ok(read_tokens_file(path, &content)?.for_chain(path, chain_id)?.blacklist())
+}
+
+/// Parse the tokens file at `path` and check its entries.
+fn read_tokens_file(path: &str, content: &str) -> Result<TokensFile> {
+    let file: TokensFile = parse_file(Path::new(path), content, FileKind::Tokens)?;
+    checks::ensure_valid(path, &checks::token_problems(&file))?;
+    Ok(file)
+}
+
+impl TokensFile {
+    fn from_section(tokens: TokenEntries, pairs: Vec<String>, blacklist: BlacklistEntry) -> Self {
+        TokensFile {
+            tokens,
+            pairs,
+            blacklist,
+            chains: BTreeMap::new(),
+        }
+    }
+
+    /// The file as one section for `chain_id`: the top-level sections with the chain's on top, its tokens
+    /// replacing top-level ones of the same symbol; pairs are resolved with the symbols of their chain. Without a chain id every chain section is used, and a symbol
+    /// with several addresses keeps the first under its symbol and the others under their address, like in a
+    /// token list. Fails when the file has chain sections but none for `chain_id` and no top-level tokens, rather
+    /// than tracking every token of that chain; `source` names the file in the error.
+    fn for_chain(self, source: &str, chain_id: Option<u64>) -> Result<TokensFile> {
+        if self.chains.is_empty() {
+            return Ok(self);
+        }
+        let sections: Vec<&TokensFile> = match chain_id {
+            Some(id) => match self.chains.get(&id) {
+                Some(section) => vec![section],
+                None if self.tokens.is_empty() => {
+                    let ids: Vec<String> = self.chains.keys().map(ToString::to_string).collect();
+                    eyre::bail!("{} has no section for chain {} (sections: {})", source, id, ids.join(", "));
+                }
+                None => Vec::new(),
+            },
+            None => self.chains.values().collect(),
+        };
+        let shared = self.tokens.resolve(chain_id);
+        let mut tokens = shared.clone();
+        let resolved = |pairs: &mut Vec<String>, entries: &[String], symbols: &HashMap<String, Address>| {
+            let entries = entries.iter().filter_map(|pair| resolve_pair(pair, symbols));
+            pairs.extend(entries.map(|(a, b)| format!("{:#x}/{:#x}", a, b)));
+        };
+        let mut pairs = Vec::new();
+        if sections.is_empty() {
+            resolved(&mut pairs, &self.pairs, &shared);
+        }
+        let mut blacklist = self.blacklist.clone();
+        for section in sections {
+            let section_tokens = section.tokens.resolve(chain_id);
+            // The top-level pairs apply to every chain, with its symbols.
+            let mut symbols = shared.clone();
+            symbols.extend(section_tokens.clone());
+            resolved(&mut pairs, &self.pairs, &symbols);
+            resolved(&mut pairs, &section.pairs, &symbols);
+            for (symbol, addr) in section_tokens {
+                match tokens.get(&symbol) {
+                    Some(existing) if chain_id.is_none() && *existing != addr => {
+                        tokens.insert(format!("{:#x}", addr), addr);
+                    }
+                    Some(_) if chain_id.is_none() => {}
+                    _ => {
+                        tokens.insert(symbol, addr);
+                    }
+                }
+            }
+            blacklist.tokens.extend(section.blacklist.tokens.iter().cloned());
+            blacklist.pools.extend(section.blacklist.pools.iter().cloned());
+            blacklist.symbols.extend(section.blacklist.symbols.iter().cloned());
+        }
+        let tokens = tokens.into_iter().map(|(symbol, addr)| (symbol, format!("{:#x}", addr))).collect();
+        Ok(TokensFile::from_section(TokenEntries::Map(tokens), pairs, blacklist))
+    }
+
+    /// The `pairs` section, with the symbols of `chain_id`'s tokens. Unresolvable entries are left out; the
+    /// loaders refuse files with any. Fails when a pair names a symbol that has several addresses (a token list
+    /// spanning chains, without `chain_id`), rather than matching one chain's pools only; `source` names the file
+    /// in the error.
+    fn pairs(&self, source: &str, chain_id: Option<u64>) -> Result<HashSet<(Address, Address)>> {
+        let ambiguous = self.tokens.ambiguous_symbols(chain_id);
+        for pair in &self.pairs {
+            if let Some(symbol) = pair.split('/').map(str::trim).find(|side| ambiguous.contains(side)) {
+                eyre::bail!(
+                    "{}: pair {} is ambiguous: {} has several addresses in the token list; connect to a chain \
+                     or name the token by address",
+                    source,
+                    pair,
+                    symbol
+                );
+            }
         }
+        let symbols = self.tokens.resolve(chain_id);
+        Ok(self
+            .pairs
+            .iter()
+            .filter_map(|pair| resolve_pair(pair, &symbols))
+            .map(|(a, b)| canonical_pair(a, b))
+            .collect())
+    }
+
+    /// The `blacklist` section. Invalid addresses are left out, like in `pairs`.
+    fn blacklist(&self) -> Blacklist {
+        let parse = |entries: &[String]| -> HashSet<Address> {
+            entries.iter().filter_map(|entry| entry.parse().ok()).collect()
+        };
+        Blacklist {
+            tokens: parse(&self.blacklist.tokens),
+            pools: parse(&self.blacklist.pools),
+            symbols: self.blacklist.symbols.clone(),
+        }
+    }
+}
+
+/// The two tokens of a `"SYMBOL/SYMBOL"` pair, each a symbol of `symbols` or an address.
+fn resolve_pair(pair: &str, symbols: &HashMap<String, Address>) -> Option<(Address, Address)> {
+    let resolve = |side: &str| -> Option<Address> {
+        let side = side.trim();
+        side.parse().ok().or_else(|| symbols.get(side).copied())
+    };
+    let (a, b) = pair.split_once('/')?;
+    Some((resolve(a)?, resolve(b)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_tokens_file_accepts_token_lists() {
+        let list = r#"{
+            "name": "Example List",
+            "tokens": [
+                { "chainId": 1, "address": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "symbol": "WETH", "decimals": 18, "logoURI": "ipfs://x" },
+                { "chainId": 8453, "address": "0x4200000000000000000000000000000000000006", "symbol": "WETH", "decimals": 18 }
+            ]
+        }"#;
+        let file: TokensFile = serde_json::from_str(list).unwrap();
+        let mainnet = file.tokens.resolve(Some(1));
+        assert_eq!(mainnet.len(), 1);
+        assert_eq!(mainnet["WETH"], "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".parse::<Address>().unwrap());
+        assert_eq!(file.tokens.resolve(None).len(), 2);
+
+        // Pairs resolve against the chain's tokens; without a chain, a symbol on both chains is an error.
+        let with_pairs = TokensFile {
+            pairs: vec!["WETH/0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string()],
+            ..file.clone()
+        };
+        let base_weth = "0x4200000000000000000000000000000000000006".parse::<Address>().unwrap();
+        let pairs = with_pairs.pairs("tokens.json", Some(8453)).unwrap();
+        assert!(pairs.iter().any(|(a, b)| *a == base_weth || *b == base_weth));
+        assert!(with_pairs.pairs("tokens.json", None).is_err());
+
+        let bespoke: TokensFile = serde_json::from_str(r#"{ "tokens": { "WETH": "0x4200000000000000000000000000000000000006" } }"#).unwrap();
+        assert_eq!(bespoke.tokens.resolve(Some(1)).len(), 1);
+    }
+
+    #[test]
+    fn test_tokens_file_chain_sections() {
+        let tokens = r#"{
+            "tokens": { "DAI": "0x6B175474E89094C44Da98b954EedeAC495271d0F" },
+            "pairs": ["WETH/USDC"],
+            "1": {
+                "tokens": { "WETH": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "USDC": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48" }
+            },
+            "8453": {
+                "tokens": { "WETH": "0x4200000000000000000000000000000000000006", "USDC": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913" },
+                "pairs": ["WETH/DAI"],
+                "blacklist": { "symbols": ["*SCAM*"] }
+            }
+        }"#;
+        let file: TokensFile = parse_file(Path::new("tokens.json"), tokens, FileKind::Tokens).unwrap();
+        assert!(checks::token_problems(&file).is_empty());
+        let weth = |address: &str| address.parse::<Address>().unwrap();
+
+        let base = file.clone().for_chain("tokens.json", Some(8453)).unwrap();
+        let symbols = base.tokens.resolve(None);
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols["WETH"], weth("0x4200000000000000000000000000000000000006"));
+        assert_eq!(base.pairs("tokens.json", Some(8453)).unwrap().len(), 2);
+        assert_eq!(base.blacklist().symbols, vec!["*SCAM*"]);
+
+        let mainnet = file.clone().for_chain("tokens.json", Some(1)).unwrap();
+        assert_eq!(mainnet.tokens.resolve(None)["WETH"], weth("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"));
+        assert!(mainnet.blacklist().symbols.is_empty());
+
+        // Every chain: both WETHs and USDCs are kept.
+        assert_eq!(file.clone().for_chain("tokens.json", None).unwrap().tokens.resolve(None).len(), 5);
+        // A chain without a section gets the top-level tokens, unless there are none.
+        assert_eq!(file.clone().for_chain("tokens.json", Some(10)).unwrap().tokens.resolve(None).len(), 1);
+        let chains_only = "'1':\n  tokens:\n    WETH: 0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2\n8453:\n  tokens: {}\n";
+        let file: TokensFile = parse_file(Path::new("tokens.yaml"), chains_only, FileKind::Tokens).unwrap();
+        let error = file.for_chain("tokens.yaml", Some(10)).unwrap_err();
+        assert_eq!(error.to_string(), "tokens.yaml has no section for chain 10 (sections: 1, 8453)");
+
+        let invalid = tokens.replace("[\"WETH/DAI\"]", "[\"WETH/USDT\"]");
+        let file: TokensFile = parse_file(Path::new("tokens.json"), &invalid, FileKind::Tokens).unwrap();
+        assert_eq!(checks::token_problems(&file)[0].field, "8453.pairs[0]");
+    }
+
+    #[test]
+    fn test_yaml_files() {
+        // Unquoted addresses stay strings rather than hex integers.
+        let protocols = "
+protocols:
+  uniswap-v2:
+    name: Uniswap V2
+    factory: 0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f
+    subgraphUrl: http://localhost/v2
+    enabled: true
+    poolType: UniswapV2
+discovery:
+  minLiquidityUSD: 10000
+  cacheRefreshMinutes: 60
+  maxPoolsPerProtocol: 100
+  seedTokens: [0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2]
+";
+        let file: ProtocolsFile = parse_file(Path::new("protocols.yaml"), protocols, FileKind::Protocols).unwrap();
+        let (configs, discovery) = resolve_protocols(file, "", "protocols.yaml").unwrap();
+        assert_eq!(configs[0].factory, "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f".parse::<Address>().unwrap());
+        assert_eq!(discovery.seed_tokens.len(), 1);
+
+        let tokens = "
+tokens:
+  WETH: 0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2
+  USDC: '0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48'
+pairs: [WETH/USDC]
+";
+        let file: TokensFile = parse_file(Path::new("tokens.yml"), tokens, FileKind::Tokens).unwrap();
+        assert_eq!(file.tokens.resolve(None).len(), 2);
+        assert_eq!(file.pairs("tokens.yml", None).unwrap().len(), 1);
+        assert!(parse_file::<TokensFile>(Path::new("tokens.json"), tokens, FileKind::Tokens).is_err());
+    }
+
+    #[test]
+    fn test_protocol_discovery_overrides() {
+        let file: ProtocolsFile = serde_json::from_str(r#"{
+            "protocols": {
+                "mainnet": { "name": "Uniswap V3", "factory": "", "subgraphUrl": "http://localhost/v3", "enabled": true, "poolType": "UniswapV3" },
+                "fork": {
+                    "name": "Fork", "factory": "", "subgraphUrl": "http://localhost/fork", "enabled": true, "poolType": "UniswapV3",
+                    "minLiquidityUSD": 500, "maxPools": 20, "feeTiers": []
+                }
+            },
+            "discovery": { "minLiquidityUSD": 1000000, "cacheRefreshMinutes": 60, "maxPoolsPerProtocol": 100, "feeTiers": [500, 3000] }
+        }"#).unwrap();
+        let (mut configs, discovery) = resolve_protocols(file, "", "protocols.json").unwrap();
+        configs.sort_by(|a, b| a.id.cmp(&b.id));
+        let (fork, mainnet) = (&configs[0], &configs[1]);
+
+        assert_eq!(mainnet.fee_tiers, vec![500, 3000]);
+        assert!(matches!(mainnet.discovery_settings(&discovery), Cow::Borrowed(_)));
+        assert!(fork.fee_tiers.is_empty());
+        let settings = fork.discovery_settings(&discovery);
+        assert_eq!((settings.min_liquidity_usd, settings.max_pools_per_protocol), (500.0, 20));
     }
-    Ok(out)
 }
@@ -0,0 +1,304 @@
+//! `SCANNER_*` environment variables layered over the config files, e.g.
+//! `SCANNER_DISCOVERY__MIN_LIQUIDITY_USD=500000` for `discovery.minLiquidityUSD`.
+//!
+//! `__` separates the keys of the path. Each key matches an existing key case-insensitively, ignoring `_` and
+//! `-` (`UNISWAP_V3` is `uniswap-v3`, `MIN_LIQUIDITY_USD` is `minLiquidityUSD`); a key the file doesn't have yet
+//! matches the serde names of its section the same way (see `FIELDS`), and an override that matches none is an
+//! error. Keys of maps named by the user (protocol ids, token symbols, notifiers) are added as written. Array
+//! elements are addressed by index (`SCANNER_SINKS__0__PATH`). Values are parsed as JSON when they are
+//! (`500000`, `true`, `["WETH/USDC"]`), except where the file has a string, and taken as strings otherwise.
+
+use eyre::Result;
+use serde_json::{Map, Value};
+
+pub(crate) const PREFIX: &str = "SCANNER_";
+
+/// Variables with the prefix that name a file, not a setting.
+const RESERVED: &[&str] = &["SCANNER_CONFIG", "SCANNER_SECRETS"];
+
+/// Keys of a map named by the user, taken as written.
+const ANY: &[&str] = &["*"];
+
+/// The serde names of each object of the config files, by path; `*` stands for any map key or array index. The
+/// sinks and notifiers list the keys of every `type`.
+const FIELDS: &[(&str, &[&str])] = &[
+    (
+        "",
+        &["version", "rpc", "theGraphApiKey", "discovery", "protocols", "tokens", "pairs", "blacklist", "sinks", "alerts"],
+    ),
+    ("rpc", &["url"]),
+    (
+        "discovery",
+        &[
+            "minLiquidityUSD",
+            "cacheRefreshMinutes",
+            "maxPoolsPerProtocol",
+            "feeTiers",
+            "mode",
+            "logChunkSize",
+            "maxRetries",
+            "retryBaseDelayMs",
+            "cacheEnabled",
+            "cacheFile",
+            "verifyTokenMetadata",
+            "riskFilter",
+            "detectTokenQuirks",
+            "feeOnTransferTokens",
+            "rebasingTokens",
+            "ranking",
+            "network",
+            "seedTokens",
+            "maxSubgraphLagBlocks",
+            "failOnSubgraphLag",
+            "discoveryConcurrency",
+            "incrementalDiscovery",
+            "fullRefreshEvery",
+        ],
+    ),
+    ("discovery.ranking", &["maxPoolsPerPair", "liquidityWeight", "volumeWeight"]),
+    ("protocols", ANY),
+    (
+        "protocols.*",
+        &[
+            "name",
+            "factory",
+            "subgraphId",
+            "subgraphUrl",
+            "enabled",
+            "poolType",
+            "startBlock",
+            "feeTiers",
+            "minLiquidityUSD",
+            "maxPools",
+            "dexId",
+            "queryOverrides",
+        ],
+    ),
+    ("protocols.*.queryOverrides", &["orderBy", "where"]),
+    ("protocols.*.queryOverrides.where", ANY),
+    ("tokens", ANY),
+    ("blacklist", &["tokens", "pools", "symbols"]),
+    (
+        "sinks.*",
+        &[
+            "type",
+            "path",
+            "dir",
+            "candles",
+            "rotateEverySecs",
+            "maxBytes",
+            "url",
+            "database",
+            "table",
+            "user",
+            "password",
+            "org",
+            "bucket",
+            "token",
+            "chain",
+            "batchSize",
+            "flushIntervalSecs",
+            "retentionSecs",
+            "timescale",
+            "prefix",
+            "ttlSecs",
+        ],
+    ),
+    ("alerts", &["cooldownSecs", "notifiers", "rules"]),
+    ("alerts.notifiers", ANY),
+    (
+        "alerts.notifiers.*",
+        &["type", "url", "headers", "timeoutSecs", "botToken", "chatId", "template", "explorerUrl", "webhookUrl", "username"],
+    ),
+    ("alerts.notifiers.*.headers", ANY),
+    ("alerts.rules.*", &["name", "when", "pools", "pairs", "notify"]),
+];
+
+/// The override variables among `vars` whose first key is one of `sections` (every one with `None`), as the
+/// path of keys and the value.
+pub(crate) fn overrides(
+    vars: impl IntoIterator<Item = (String, String)>,
+    sections: Option<&[&str]>,
+) -> Vec<(Vec<String>, String)> {
+    let mut overrides: Vec<_> = vars
+        .into_iter()
+        .filter(|(name, _)| !RESERVED.contains(&name.as_str()))
+        .filter_map(|(name, value)| {
+            let path: Vec<String> = name.strip_prefix(PREFIX)?.split("__").map(str::to_string).collect();
+            if path.iter().any(String::is_empty) {
+                return None;
+            }
+            let in_section = sections.is_none_or(|sections| sections.iter().any(|section| same_key(section, &path[0])));
+            in_section.then_some((path, value))
+        })
+        .collect();
+    // Deterministic when two variables name the same setting.
+    overrides.sort();
+    overrides
+}
+
+/// Set every override in `root`, creating the objects on its path. Fails for an override that names no setting.
+pub(crate) fn apply(root: &mut Value, overrides: &[(Vec<String>, String)]) -> Result<()> {
+    for (path, value) in overrides {
+        let mut names = Vec::new();
+        if let Err(key) = set(root, path, value, &mut names) {
+            let object = if names.is_empty() { "the config file".to_string() } else { names.join(".") };
+            eyre::bail!("{}{}: {} has no setting {}", PREFIX, path.join("__"), object, key);
+        }
+    }
+    Ok(())
+}
+
+/// Set `path` below `node`, whose path from the root is `names`. Fails with the key that matches no setting.
+fn set<'a>(node: &mut Value, path: &'a [String], raw: &str, names: &mut Vec<String>) -> Result<(), &'a str> {
+    let Some((key, rest)) = path.split_first() else {
+        *node = match node {
+            Value::String(_) => Value::String(raw.to_string()),
+            _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+        };
+        return Ok(());
+    };
+    if let Value::Array(items) = node {
+        if let Some(item) = key.parse::<usize>().ok().and_then(|index| items.get_mut(index)) {
+            names.push(key.clone());
+            return set(item, rest, raw, names);
+        }
+        return Ok(());
+    }
+    if !node.is_object() {
+        *node = Value::Object(Map::new());
+    }
+    let Value::Object(map) = node else { unreachable!() };
+    let name = match map.keys().find(|existing| same_key(existing, key)) {
+        Some(existing) => existing.clone(),
+        None => field(names, key).ok_or(key.as_str())?,
+    };
+    names.push(name.clone());
+    set(map.entry(name).or_insert(Value::Null), rest, raw, names)
+}
+
+/// The serde name `key` stands for in the object at `names`, from `FIELDS`.
+fn field(names: &[String], key: &str) -> Option<String> {
+    let (_, fields) = FIELDS.iter().find(|(pattern, _)| {
+        let pattern: Vec<&str> = pattern.split('.').filter(|segment| !segment.is_empty()).collect();
+        pattern.len() == names.len() && pattern.iter().zip(names).all(|(segment, name)| *segment == "*" || segment == name)
+    })?;
+    if *fields == ANY {
+        return Some(key.to_string());
+    }
+    fields.iter().find(|field| same_key(field, key)).map(|field| field.to_string())
+}
+
+/// `minLiquidityUSD`, `MIN_LIQUIDITY_USD` and `min-liquidity-usd` are the same key.
+fn same_key(a: &str, b: &str) -> bool {
+    let normalize = |key: &str| -> String {
+        key.chars().filter(|c| !matches!(c, '_' | '-')).flat_map(char::to_lowercase).collect()
+    };
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_overrides() {
+        let mut file = json!({
+            "protocols": { "uniswap-v3": { "enabled": true, "subgraphId": "abc" } },
+            "discovery": { "minLiquidityUSD": 10000 },
+            "sinks": [{ "type": "json_lines" }],
+        });
+        let vars = [
+            ("SCANNER_DISCOVERY__MIN_LIQUIDITY_USD", "500000"),
+            ("SCANNER_DISCOVERY__LOG_CHUNK_SIZE", "2000"),
+            ("SCANNER_DISCOVERY__MAX_SUBGRAPH_LAG_BLOCKS", "100"),
+            ("SCANNER_DISCOVERY__RANKING__MAX_POOLS_PER_PAIR", "2"),
+            ("SCANNER_PROTOCOLS__UNISWAP_V3__MIN_LIQUIDITY_USD", "1000"),
+            ("SCANNER_TOKENS__ARB", "0x912CE59144191C1204E64559FE8253a0e49E6548"),
+            ("SCANNER_PROTOCOLS__UNISWAP_V3__ENABLED", "false"),
+            ("SCANNER_PROTOCOLS__UNISWAP_V3__SUBGRAPH_ID", "123"),
+            ("SCANNER_SINKS__0__PATH", "prices.jsonl"),
+            ("SCANNER_PAIRS", r#"["WETH/USDC"]"#),
+            ("SCANNER_CONFIG", "scanner.toml"),
+            ("SCANNER_", "ignored"),
+            ("RPC_URL", "ignored"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let protocols_only = overrides(vars.clone(), Some(&["protocols", "discovery"]));
+        assert_eq!(protocols_only.len(), 7);
+
+        apply(&mut file, &overrides(vars, None)).unwrap();
+        assert_eq!(
+            file,
+            json!({
+                "protocols": { "uniswap-v3": { "enabled": false, "subgraphId": "123", "minLiquidityUSD": 1000 } },
+                "discovery": {
+                    "minLiquidityUSD": 500000,
+                    "logChunkSize": 2000,
+                    "maxSubgraphLagBlocks": 100,
+                    "ranking": { "maxPoolsPerPair": 2 },
+                },
+                "tokens": { "ARB": "0x912CE59144191C1204E64559FE8253a0e49E6548" },
+                "sinks": [{ "type": "json_lines", "path": "prices.jsonl" }],
+                "pairs": ["WETH/USDC"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let mut file = json!({ "discovery": { "minLiquidityUSD": 10000 } });
+        let vars = [("SCANNER_DISCOVERY__MIN_LIQUIDITY".to_string(), "1".to_string())];
+        let error = apply(&mut file, &overrides(vars, None)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "SCANNER_DISCOVERY__MIN_LIQUIDITY: discovery has no setting MIN_LIQUIDITY"
+        );
+    }
+
+    /// Field names the serde derive of `T` expects, to check `FIELDS` against the config structs.
+    fn serde_fields<'de, T: serde::Deserialize<'de>>() -> Vec<&'static str> {
+        use serde::de::{value::Error, Error as _, Visitor};
+        struct Fields(&'static [&'static str]);
+        impl<'de> serde::Deserializer<'de> for &mut Fields {
+            type Error = Error;
+            fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+                Err(Error::custom("not a struct"))
+            }
+            fn deserialize_struct<V: Visitor<'de>>(
+                self,
+                _: &'static str,
+                fields: &'static [&'static str],
+                _: V,
+            ) -> Result<V::Value, Error> {
+                self.0 = fields;
+                Err(Error::custom("fields read"))
+            }
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option unit unit_struct
+                newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+            }
+        }
+        let mut fields = Fields(&[]);
+        let _ = T::deserialize(&mut fields);
+        fields.0.to_vec()
+    }
+
+    #[test]
+    fn test_fields_match_config_structs() {
+        let table = |path: &str| FIELDS.iter().find(|(pattern, _)| *pattern == path).unwrap().1.to_vec();
+        let mut root = table("");
+        root.retain(|field| *field != "version");
+        assert_eq!(root, serde_fields::<crate::config::ScannerFile>());
+        assert_eq!(table("rpc"), serde_fields::<crate::config::RpcSection>());
+        assert_eq!(table("discovery"), serde_fields::<super::super::DiscoveryEntry>());
+        assert_eq!(table("discovery.ranking"), serde_fields::<crate::discovery::PoolRanking>());
+        assert_eq!(table("protocols.*"), serde_fields::<super::super::ProtocolEntry>());
+        assert_eq!(table("protocols.*.queryOverrides"), serde_fields::<crate::types::QueryOverrides>());
+        assert_eq!(table("blacklist"), serde_fields::<super::super::BlacklistEntry>());
+        assert_eq!(table("alerts"), serde_fields::<crate::alerts::RulesFile>());
+        assert_eq!(table("alerts.rules.*"), serde_fields::<crate::alerts::rules::RuleEntry>());
+    }
+}
@@ -0,0 +1,428 @@
+//! One `scanner.toml` in place of `protocols.json`, `tokens.json` and the environment, e.g.
+//!
+//! ```toml
+//! pairs = ["WETH/USDC"]
+//!
+//! [rpc]
+//! url = "wss://eth-mainnet.g.alchemy.com/v2/<key>"
+//!
+//! [discovery]
+//! minLiquidityUSD = 10000
+//! cacheRefreshMinutes = 60
+//! maxPoolsPerProtocol = 100
+//!
+//! [protocols.uniswap-v3]
+//! name = "Uniswap V3"
+//! factory = "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+//! subgraphId = "5zvR82QoaXYFyDEKLZ9t6v9adgnptxYpKpSbxtgVENFV"
+//! enabled = true
+//! poolType = "UniswapV3"
+//!
+//! [tokens]
+//! WETH = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+//! USDC = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+//!
+//! [[sinks]]
+//! type = "json_lines"
+//! path = "prices.jsonl"
+//!
+//! [[alerts.rules]]
+//! name = "whales"
+//! when = "swap_usd > 500000"
+//! ```
+//!
+//! The sections take the same keys as the JSON files; `alerts` is a rules file (`alerts::RulesFile`). The same
+//! structure is accepted as YAML (`scanner.yaml`).
+
+use super::checks::{ensure_valid, protocol_problems, token_problems, Problem};
+use super::secrets::{secret, Secrets};
+use super::version::migrate;
+use super::{
+    config_path, resolve_protocols, FileKind, Format, BlacklistEntry, DiscoveryEntry, ProtocolEntry, ProtocolsFile, TokenEntries,
+    TokensFile,
+};
+use crate::alerts::RulesFile;
+use crate::discovery::Blacklist;
+use crate::sinks::{
+    ClickHouseConfig, ClickHouseSink, CsvConfig, CsvRows, CsvSink, InfluxConfig, InfluxSink, JsonLinesSink, Sink,
+};
+use crate::types::{DiscoveryConfig, ProtocolConfig};
+use alloy::primitives::Address;
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Label of a scanner file not read from a path, in errors.
+const SOURCE: &str = "the scanner file";
+
+/// `SCANNER_CONFIG` when set, otherwise `scanner.toml` (or `scanner.yaml` / `scanner.yml`) in the working
+/// directory if there is one. `None` means the split `protocols.json` / `tokens.json` setup.
+pub fn scanner_file_path() -> Option<PathBuf> {
+    let explicit = std::env::var_os("SCANNER_CONFIG").is_some();
+    let path = config_path("SCANNER_CONFIG", &["scanner.toml", "scanner.yaml", "scanner.yml"]);
+    (explicit || path.exists()).then_some(path)
+}
+
+/// Root of a `scanner.toml`: RPC endpoint, discovery, protocols, token filters, sinks and alert rules.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannerFile {
+    #[serde(default)]
+    pub rpc: RpcSection,
+    /// Used when `THE_GRAPH_API_KEY` is not set.
+    #[serde(default)]
+    the_graph_api_key: Option<String>,
+    discovery: DiscoveryEntry,
+    #[serde(default)]
+    protocols: HashMap<String, ProtocolEntry>,
+    /// The token whitelist; every token's pools are tracked without it.
+    #[serde(default)]
+    tokens: Option<TokenEntries>,
+    #[serde(default)]
+    pairs: Vec<String>,
+    #[serde(default)]
+    blacklist: BlacklistEntry,
+    #[serde(default)]
+    pub sinks: Vec<SinkEntry>,
+    #[serde(default)]
+    pub alerts: Option<RulesFile>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RpcSection {
+    /// WebSocket endpoint; `RPC_URL` takes precedence.
+    pub url: Option<String>,
+}
+
+/// A sink of `scanner.toml`, by `type`. Sinks that need a feature are only accepted with it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase", deny_unknown_fields)]
+pub enum SinkEntry {
+    /// `JsonLinesSink` appending to `path`, or writing to stdout without one.
+    JsonLines { path: Option<PathBuf> },
+    /// `CsvSink`; price updates, or completed candles with `candles = true`.
+    Csv {
+        dir: PathBuf,
+        #[serde(default)]
+        candles: bool,
+        rotate_every_secs: Option<u64>,
+        max_bytes: Option<u64>,
+    },
+    #[serde(rename = "clickhouse")]
+    ClickHouse {
+        url: String,
+        database: Option<String>,
+        table: Option<String>,
+        user: Option<String>,
+        password: Option<String>,
+        batch_size: Option<usize>,
+        flush_interval_secs: Option<u64>,
+    },
+    Influx {
+        url: String,
+        org: String,
+        bucket: Option<String>,
+        token: Option<String>,
+        chain: Option<String>,
+        batch_size: Option<usize>,
+        flush_interval_secs: Option<u64>,
+    },
+    #[cfg(feature = "sqlite")]
+    Sqlite { path: PathBuf, retention_secs: Option<u64> },
+    #[cfg(feature = "parquet")]
+    Parquet { dir: PathBuf, flush_interval_secs: Option<u64> },
+    #[cfg(feature = "postgres")]
+    Postgres {
+        url: String,
+        #[serde(default)]
+        timescale: bool,
+        batch_size: Option<usize>,
+        flush_interval_secs: Option<u64>,
+    },
+    #[cfg(feature = "redis")]
+    Redis {
+        url: String,
+        chain: Option<String>,
+        prefix: Option<String>,
+        ttl_secs: Option<u64>,
+    },
+}
+
+impl SinkEntry {
+    /// Open the sink; the database sinks connect here.
+    pub async fn open(&self) -> Result<Arc<dyn Sink>> {
+        Ok(match self.clone() {
+            SinkEntry::JsonLines { path } => Arc::new(match path {
+                Some(path) => JsonLinesSink::file(path)?,
+                None => JsonLinesSink::stdout(),
+            }),
+            SinkEntry::Csv { dir, candles, rotate_every_secs, max_bytes } => {
+                let defaults = CsvConfig::default();
+                Arc::new(CsvSink::new(CsvConfig {
+                    dir,
+                    rows: if candles { CsvRows::Candles } else { CsvRows::PriceUpdates },
+                    rotate_every: rotate_every_secs.map_or(defaults.rotate_every, Duration::from_secs),
+                    max_bytes,
+                })?)
+            }
+            SinkEntry::ClickHouse { url, database, table, user, password, batch_size, flush_interval_secs } => {
+                let defaults = ClickHouseConfig::default();
+                Arc::new(ClickHouseSink::new(ClickHouseConfig {
+                    url,
+                    database: database.unwrap_or(defaults.database),
+                    table: table.unwrap_or(defaults.table),
+                    user,
+                    password,
+                    batch_size: batch_size.unwrap_or(defaults.batch_size),
+                    flush_interval: flush_interval_secs.map_or(defaults.flush_interval, Duration::from_secs),
+                }))
+            }
+            SinkEntry::Influx { url, org, bucket, token, chain, batch_size, flush_interval_secs } => {
+                let defaults = InfluxConfig::default();
+                Arc::new(InfluxSink::new(InfluxConfig {
+                    url,
+                    org,
+                    bucket: bucket.unwrap_or(defaults.bucket),
+                    token,
+                    chain: chain.unwrap_or(defaults.chain),
+                    batch_size: batch_size.unwrap_or(defaults.batch_size),
+                    flush_interval: flush_interval_secs.map_or(defaults.flush_interval, Duration::from_secs),
+                    tags: defaults.tags,
+                }))
+            }
+            #[cfg(feature = "sqlite")]
+            SinkEntry::Sqlite { path, retention_secs } => {
+                let sink = crate::sinks::SqliteSink::open(path)?;
+                Arc::new(match retention_secs {
+                    Some(secs) => sink.with_retention(Duration::from_secs(secs)),
+                    None => sink,
+                })
+            }
+            #[cfg(feature = "parquet")]
+            SinkEntry::Parquet { dir, flush_interval_secs } => {
+                let defaults = crate::sinks::ParquetConfig::default();
+                Arc::new(crate::sinks::ParquetSink::new(crate::sinks::ParquetConfig {
+                    dir,
+                    flush_interval: flush_interval_secs.map_or(defaults.flush_interval, Duration::from_secs),
+                    ..defaults
+                })?)
+            }
+            #[cfg(feature = "postgres")]
+            SinkEntry::Postgres { url, timescale, batch_size, flush_interval_secs } => {
+                let defaults = crate::sinks::PostgresConfig::default();
+                let config = crate::sinks::PostgresConfig {
+                    url,
+                    timescale,
+                    batch_size: batch_size.unwrap_or(defaults.batch_size),
+                    flush_interval: flush_interval_secs.map_or(defaults.flush_interval, Duration::from_secs),
+                };
+                Arc::new(crate::sinks::PostgresSink::connect(config).await?)
+            }
+            #[cfg(feature = "redis")]
+            SinkEntry::Redis { url, chain, prefix, ttl_secs } => {
+                let defaults = crate::sinks::RedisConfig::default();
+                let config = crate::sinks::RedisConfig {
+                    url,
+                    chain: chain.unwrap_or(defaults.chain),
+                    prefix: prefix.unwrap_or(defaults.prefix),
+                    ttl: ttl_secs.map(Duration::from_secs).or(defaults.ttl),
+                };
+                Arc::new(crate::sinks::RedisSink::connect(config).await?)
+            }
+        })
+    }
+}
+
+impl ScannerFile {
+    /// Read a scanner file: YAML when it ends in `.yaml` or `.yml`, JSON for `.json`, TOML otherwise. `SCANNER_*`
+    /// environment variables override its settings (`SCANNER_DISCOVERY__MIN_LIQUIDITY_USD=500000`). Fails listing
+    /// every problem of its protocols and tokens, like `load_protocols_file` and `load_tokens_file`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = Self::read(path)?;
+        ensure_valid(&path.display().to_string(), &file.problems())?;
+        Ok(file)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Self::from_content(Format::Toml, content)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        Self::from_content(Format::Yaml, content)
+    }
+
+    /// Parse and check `content` without the `SCANNER_*` overrides.
+    fn from_content(format: Format, content: &str) -> Result<Self> {
+        let mut value: serde_json::Value = format.parse(content)?;
+        migrate(&mut value, FileKind::Scanner, SOURCE)?;
+        Secrets::load()?.resolve(&mut value, SOURCE)?;
+        let file: Self = serde_json::from_value(value)?;
+        ensure_valid(SOURCE, &file.problems())?;
+        Ok(file)
+    }
+
+    /// `load` without the checks, for `ValidationReport`.
+    pub(super) fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).wrap_err_with(|| format!("reading {}", path.display()))?;
+        Format::of(path, Format::Toml)
+            .parse_config(&content, FileKind::Scanner, &path.display().to_string())
+            .wrap_err_with(|| format!("parsing {}", path.display()))
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        let mut problems = protocol_problems(&self.protocols_file(), &self.api_key());
+        problems.extend(token_problems(&self.tokens_file()));
+        problems
+    }
+
+    /// `RPC_URL` when set (in the environment or the secrets file), otherwise `rpc.url`.
+    pub fn rpc_url(&self) -> Option<String> {
+        secret("RPC_URL").or_else(|| self.rpc.url.clone())
+    }
+
+    /// `THE_GRAPH_API_KEY` when set (in the environment or the secrets file), otherwise `theGraphApiKey`.
+    pub(super) fn api_key(&self) -> String {
+        secret("THE_GRAPH_API_KEY")
+            .or_else(|| self.the_graph_api_key.clone())
+            .unwrap_or_default()
+    }
+
+    /// The enabled protocols and discovery settings, like `load_protocols_file`. Fails only when
+    /// `THE_GRAPH_API_KEY` was unset since loading and the protocols need it.
+    pub fn protocols(&self) -> Result<(Vec<ProtocolConfig>, DiscoveryConfig)> {
+        resolve_protocols(self.protocols_file(), &self.api_key(), SOURCE)
+    }
+
+    /// The token whitelist, like `load_tokens_file_for_chain`; empty without a `tokens` section.
+    pub fn tokens(&self, chain_id: Option<u64>) -> HashMap<String, Address> {
+        self.tokens.as_ref().map(|tokens| tokens.resolve(chain_id)).unwrap_or_default()
+    }
+
+    /// The pair whitelist, like `load_token_pairs_for_chain`.
+    pub fn token_pairs(&self, chain_id: Option<u64>) -> Result<HashSet<(Address, Address)>> {
+        self.tokens_file().pairs(SOURCE, chain_id)
+    }
+
+    /// The blacklist, like `load_blacklist`.
+    pub fn blacklist(&self) -> Blacklist {
+        self.tokens_file().blacklist()
+    }
+
+    /// Open every sink of `sinks`, followed by an `AlertSink` when there are `alerts`.
+    pub async fn open_sinks(&self) -> Result<Vec<Arc<dyn Sink>>> {
+        let mut sinks = Vec::new();
+        for entry in &self.sinks {
+            sinks.push(entry.open().await?);
+        }
+        if let Some(alerts) = &self.alerts {
+            sinks.push(Arc::new(alerts.clone().into_sink()?) as Arc<dyn Sink>);
+        }
+        Ok(sinks)
+    }
+
+    pub(super) fn tokens_file(&self) -> TokensFile {
+        TokensFile::from_section(
+            self.tokens.clone().unwrap_or_else(|| TokenEntries::Map(HashMap::new())),
+            self.pairs.clone(),
+            self.blacklist.clone(),
+        )
+    }
+
+    pub(super) fn protocols_file(&self) -> ProtocolsFile {
+        ProtocolsFile {
+            protocols: self.protocols.clone(),
+            discovery: self.discovery.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scanner_file() {
+        let content = r#"
+            theGraphApiKey = "key"
+            pairs = ["WETH/USDC"]
+
+            [rpc]
+            url = "wss://localhost:8546"
+
+            [discovery]
+            minLiquidityUSD = 10000
+            cacheRefreshMinutes = 60
+            maxPoolsPerProtocol = 100
+
+            [protocols.uniswap-v3]
+            name = "Uniswap V3"
+            factory = "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+            subgraphUrl = "http://localhost/v3"
+            enabled = true
+            poolType = "UniswapV3"
+
+            [protocols.uniswap-v2]
+            name = "Uniswap V2"
+            factory = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+            subgraphId = "A3Np3RQbaBA6oKJgiwDJeo5T3zrYfGHPWFYayMwtNDum"
+            enabled = false
+            poolType = "UniswapV2"
+
+            [tokens]
+            WETH = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+            USDC = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+
+            [blacklist]
+            symbols = ["*SCAM*"]
+
+            [[sinks]]
+            type = "json_lines"
+
+            [[sinks]]
+            type = "influx"
+            url = "http://localhost:8086"
+            org = "dex"
+
+            [[alerts.rules]]
+            name = "whales"
+            when = "swap_usd > 500000"
+        "#;
+        let file = ScannerFile::from_toml(content).unwrap();
+        assert_eq!(file.rpc.url.as_deref(), Some("wss://localhost:8546"));
+
+        let (protocols, discovery) = file.protocols().unwrap();
+        assert_eq!(protocols.len(), 1);
+        assert_eq!(protocols[0].subgraph_url, "http://localhost/v3");
+        assert_eq!(discovery.max_pools_per_protocol, 100);
+        assert_eq!(file.tokens(None).len(), 2);
+        assert_eq!(file.token_pairs(None).unwrap().len(), 1);
+        assert_eq!(file.blacklist().symbols, vec!["*SCAM*"]);
+
+        // JSON lines, InfluxDB and the alert rules.
+        assert_eq!(file.open_sinks().await.unwrap().len(), 3);
+
+        let unknown_sink = "[discovery]\nminLiquidityUSD = 1\ncacheRefreshMinutes = 1\nmaxPoolsPerProtocol = 1\n\
+                            [[sinks]]\ntype = \"ftp\"";
+        assert!(ScannerFile::from_toml(unknown_sink).is_err());
+
+        // Every problem is reported, with its location.
+        let invalid = content
+            .replace("poolType = \"UniswapV3\"", "poolType = \"V3\"")
+            .replace("maxPoolsPerProtocol = 100", "maxPoolsPerProtocol = 0")
+            .replace("USDC = \"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48\"", "USDC = \"0xA0b8\"");
+        let error = ScannerFile::from_toml(&invalid).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "invalid configuration in the scanner file:\n  \
+             protocols.uniswap-v3.poolType: \"V3\" is not UniswapV2 or UniswapV3\n  \
+             discovery.maxPoolsPerProtocol: must be at least 1\n  \
+             tokens.USDC: \"0xA0b8\" is not an address\n  \
+             pairs[0]: \"WETH/USDC\" is not two known tokens, as \"SYMBOL/SYMBOL\" or addresses"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Secrets kept out of the config files: `THE_GRAPH_API_KEY`, `RPC_URL` and `${...}` references in any string of
+//! a config file, so the files can be committed without the keys embedded in subgraph URLs or sink credentials.
+//!
+//! `${NAME}` is replaced by the environment variable `NAME`, or else `NAME` in the secrets file (`secrets.toml`,
+//! or the file named by `SCANNER_SECRETS`; a flat table of names to strings, in any config format).
+//! `${keyring:SERVICE/ACCOUNT}` is read from the OS keyring (feature `keyring`). `$${` is a literal `${`.
+
+use super::{config_path, Format, SECRETS_FILES};
+use eyre::{Result, WrapErr};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The secrets file's table; empty when there is no file.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Secrets {
+    values: HashMap<String, String>,
+}
+
+impl Secrets {
+    /// Read `SCANNER_SECRETS`, or the first `secrets.*` in the working directory. A missing default file is
+    /// empty; a missing `SCANNER_SECRETS` file is an error.
+    pub(crate) fn load() -> Result<Self> {
+        let path = config_path("SCANNER_SECRETS", SECRETS_FILES);
+        if !path.exists() && std::env::var_os("SCANNER_SECRETS").is_none() {
+            return Ok(Self::default());
+        }
+        Self::read(&path)
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).wrap_err_with(|| format!("reading {}", path.display()))?;
+        let values = Format::of(path, Format::Toml)
+            .parse(&content)
+            .wrap_err_with(|| format!("parsing {}: expected names with string values", path.display()))?;
+        Ok(Self { values })
+    }
+
+    /// The environment variable `name`, or else `name` in the file.
+    pub(crate) fn get(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok().filter(|value| !value.is_empty()).or_else(|| self.values.get(name).cloned())
+    }
+
+    /// The file's values, to keep them out of error messages.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &str> {
+        self.values.values().map(String::as_str)
+    }
+
+    /// Replace the references in every string of `document` (a file named `source`). Fails naming every
+    /// reference that can't be resolved.
+    pub(crate) fn resolve(&self, document: &mut Value, source: &str) -> Result<()> {
+        let mut missing = Vec::new();
+        self.resolve_value(document, &mut missing);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        eyre::bail!("{}: cannot resolve {}", source, missing.join(", "))
+    }
+
+    fn resolve_value(&self, value: &mut Value, missing: &mut Vec<String>) {
+        match value {
+            Value::String(text) if text.contains("${") => *text = self.interpolate(text, missing),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.resolve_value(item, missing)),
+            Value::Object(map) => map.values_mut().for_each(|item| self.resolve_value(item, missing)),
+            _ => {}
+        }
+    }
+
+    fn interpolate(&self, text: &str, missing: &mut Vec<String>) -> String {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find('}') else {
+                missing.push(format!("{:?} (no closing brace)", &rest[start..]));
+                return out;
+            };
+            let reference = &rest[start + 2..start + end];
+            match self.lookup(reference) {
+                Ok(value) => out.push_str(&value),
+                Err(reason) => missing.push(format!("${{{}}} ({})", reference, reason)),
+            }
+            rest = &rest[start + end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn lookup(&self, reference: &str) -> std::result::Result<String, String> {
+        match reference.split_once(':') {
+            Some(("keyring", entry)) => keyring_secret(entry),
+            Some((scheme, _)) => Err(format!("unknown source {:?}", scheme)),
+            None => self.get(reference).ok_or_else(|| "not in the environment or the secrets file".to_string()),
+        }
+    }
+}
+
+/// `SERVICE/ACCOUNT` from the OS keyring (Keychain, Credential Manager, the kernel keyring).
+#[cfg(feature = "keyring")]
+fn keyring_secret(entry: &str) -> std::result::Result<String, String> {
+    let (service, account) = entry.rsplit_once('/').ok_or("expected keyring:SERVICE/ACCOUNT")?;
+    keyring::Entry::new(service, account)
+        .and_then(|entry| entry.get_password())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_secret(_entry: &str) -> std::result::Result<String, String> {
+    Err("keyring references need the `keyring` feature".to_string())
+}
+
+/// `name` from the environment or the secrets file (see `Secrets::get`).
+pub fn secret(name: &str) -> Option<String> {
+    Secrets::load().unwrap_or_default().get(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve() {
+        let secrets = Secrets {
+            values: [("GRAPH_KEY".to_string(), "k3y".to_string()), ("DB_PASSWORD".to_string(), "pw".to_string())].into(),
+        };
+        let mut document = json!({
+            "protocols": { "uniswap-v3": { "subgraphUrl": "https://gateway.thegraph.com/api/${GRAPH_KEY}/subgraphs/id/x" } },
+            "sinks": [{ "type": "clickhouse", "password": "${DB_PASSWORD}", "table": "cost: $${5}" }]
+        });
+        secrets.resolve(&mut document, "scanner.toml").unwrap();
+        assert_eq!(
+            document["protocols"]["uniswap-v3"]["subgraphUrl"],
+            "https://gateway.thegraph.com/api/k3y/subgraphs/id/x"
+        );
+        assert_eq!(document["sinks"][0]["password"], "pw");
+        assert_eq!(document["sinks"][0]["table"], "cost: ${5}");
+
+        let error = secrets.resolve(&mut json!(["${NO_SUCH_SECRET_SET}", "${vault:x}"]), "tokens.json").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "tokens.json: cannot resolve ${NO_SUCH_SECRET_SET} (not in the environment or the secrets file), \
+             ${vault:x} (unknown source \"vault\")"
+        );
+    }
+}
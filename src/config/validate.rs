@@ -0,0 +1,352 @@
+//! Pre-flight checks of `protocols.json`, `tokens.json` (or `scanner.toml`), the RPC endpoint and The Graph,
+//! reported as a list of pass/warn/fail lines instead of failing (or silently skipping entries) at runtime.
+
+use super::checks::{protocol_problems, token_problems};
+use super::{
+    config_path, load_protocols_file, parse_file, scanner_file_path, secret, Secrets, subgraph_url_from_id, FileKind, ProtocolsFile,
+    ScannerFile, TokensFile, PROTOCOLS_FILES, TOKENS_FILES,
+};
+use crate::discovery::SubgraphClient;
+use crate::types::{DiscoveryConfig, DiscoveryMode, ProtocolConfig};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long connecting to the RPC endpoint may take.
+const RPC_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but probably not as intended.
+    Warn,
+    Fail,
+}
+
+/// One line of a `ValidationReport`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub status: CheckStatus,
+    /// What was checked, e.g. `protocols.json` or `protocol uniswap-v3`.
+    pub subject: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub checks: Vec<Check>,
+}
+
+impl ValidationReport {
+    /// Parse both files and check their entries: addresses, pool types, what the discovery mode needs.
+    pub fn check_files(protocols: &Path, tokens: &Path) -> Self {
+        let mut report = Self::default();
+        report.check_protocols(protocols);
+        report.check_tokens(tokens);
+        report
+    }
+
+    /// Parse a `scanner.toml` and check its protocols and tokens like `check_files`, and its alert rules.
+    pub fn check_scanner_file(path: &Path) -> Self {
+        let mut report = Self::default();
+        let name = file_name(path);
+        let file = match ScannerFile::read(path) {
+            Ok(file) => file,
+            Err(e) => {
+                report.fail(name, format!("invalid: {:#}", e));
+                return report;
+            }
+        };
+        let rules = file.alerts.as_ref().map_or(0, |alerts| alerts.rules.len());
+        report.pass(&name, format!("{} sinks, {} alert rules", file.sinks.len(), rules));
+        report.check_protocols_file("protocols", &file.protocols_file(), &file.api_key());
+        report.check_tokens_file("tokens", &file.tokens_file());
+        if let Some(Err(e)) = file.alerts.as_ref().map(|alerts| alerts.alert_config()) {
+            report.fail("alerts", format!("{:#}", e));
+        }
+        report
+    }
+
+    /// Connect to `rpc_url` and, in subgraph mode, query every protocol's subgraph. The first query through the
+    /// gateway tests `THE_GRAPH_API_KEY`; the other gateway subgraphs are skipped when it fails.
+    pub async fn check_services(&mut self, protocols: &Path, rpc_url: Option<&str>) {
+        self.check_rpc(rpc_url).await;
+        let Ok((configs, discovery)) = load_protocols_file(&protocols.to_string_lossy()) else { return };
+        self.check_subgraphs(configs, discovery, &secret("THE_GRAPH_API_KEY").unwrap_or_default())
+            .await;
+    }
+
+    async fn check_subgraphs(&mut self, configs: Vec<ProtocolConfig>, mut discovery: DiscoveryConfig, api_key: &str) {
+        if discovery.mode != DiscoveryMode::Subgraph {
+            return;
+        }
+        // One attempt each: a pre-flight check should report a failure, not wait it out.
+        discovery.max_retries = 0;
+        let gateway = subgraph_url_from_id("", api_key);
+        let client = SubgraphClient::new();
+        let mut key_works = None;
+        for config in sorted(configs) {
+            let through_gateway = !api_key.is_empty() && config.subgraph_url.starts_with(&gateway);
+            if through_gateway && key_works == Some(false) {
+                self.warn(format!("subgraph {}", config.id), "skipped: the gateway test query failed");
+                continue;
+            }
+            let indexed = client.indexed_block(&config, &discovery).await;
+            if through_gateway && key_works.is_none() {
+                key_works = Some(indexed.is_ok());
+                if let Err(e) = &indexed {
+                    let detail = format!("test query on subgraph {} failed: {}", config.id, redact(e, api_key));
+                    self.fail("THE_GRAPH_API_KEY", detail);
+                    continue;
+                }
+                self.pass("THE_GRAPH_API_KEY", "accepted by the gateway");
+            }
+            match indexed {
+                Ok(block) => self.pass(format!("subgraph {}", config.id), format!("indexed up to block {}", block)),
+                Err(e) => self.fail(format!("subgraph {}", config.id), redact(&e, api_key)),
+            }
+        }
+    }
+
+    /// No check failed.
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|check| check.status == CheckStatus::Fail).count()
+    }
+
+    fn push(&mut self, status: CheckStatus, subject: impl Into<String>, detail: impl Into<String>) {
+        self.checks.push(Check {
+            status,
+            subject: subject.into(),
+            detail: detail.into(),
+        });
+    }
+
+    fn pass(&mut self, subject: impl Into<String>, detail: impl Into<String>) {
+        self.push(CheckStatus::Pass, subject, detail);
+    }
+
+    fn warn(&mut self, subject: impl Into<String>, detail: impl Into<String>) {
+        self.push(CheckStatus::Warn, subject, detail);
+    }
+
+    fn fail(&mut self, subject: impl Into<String>, detail: impl Into<String>) {
+        self.push(CheckStatus::Fail, subject, detail);
+    }
+
+    fn check_protocols(&mut self, path: &Path) {
+        let name = file_name(path);
+        let file: ProtocolsFile = match std::fs::read_to_string(path) {
+            Ok(content) => match parse_file(path, &content, FileKind::Protocols) {
+                Ok(file) => file,
+                Err(e) => return self.fail(name, format!("invalid: {}", e)),
+            },
+            Err(e) => return self.fail(name, format!("cannot read {}: {}", path.display(), e)),
+        };
+        self.check_protocols_file(&name, &file, &secret("THE_GRAPH_API_KEY").unwrap_or_default());
+    }
+
+    /// `name` is the subject of the summary line.
+    fn check_protocols_file(&mut self, name: &str, file: &ProtocolsFile, api_key: &str) {
+        let mode = file.discovery.mode;
+        let mut enabled: Vec<_> = file.protocols.iter().filter(|(_, entry)| entry.enabled).collect();
+        enabled.sort_by_key(|(id, _)| id.as_str());
+        if enabled.is_empty() {
+            return self.fail(name, "no enabled protocols");
+        }
+        self.pass(
+            name,
+            format!("{} protocols, {} enabled, {:?} discovery", file.protocols.len(), enabled.len(), mode),
+        );
+
+        let problems = protocol_problems(file, api_key);
+        for (id, entry) in enabled {
+            let prefix = format!("protocols.{}.", id);
+            let own: Vec<String> = problems
+                .iter()
+                .filter_map(|problem| Some(format!("{} {}", problem.field.strip_prefix(&prefix)?, problem.message)))
+                .collect();
+            if own.is_empty() {
+                self.pass(format!("protocol {}", id), format!("{} ({})", entry.name, entry.pool_type));
+            } else {
+                self.fail(format!("protocol {}", id), own.join("; "));
+            }
+        }
+        // `discovery.network` is reported as `discovery: network ...`.
+        for problem in problems.iter().filter(|problem| !problem.field.starts_with("protocols.")) {
+            match problem.field.split_once('.') {
+                Some((section, key)) => self.fail(section, format!("{} {}", key, problem.message)),
+                None => self.fail(&problem.field, &problem.message),
+            }
+        }
+    }
+
+    fn check_tokens(&mut self, path: &Path) {
+        let name = file_name(path);
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return self.warn(name, "not found; pools of every token are tracked"),
+        };
+        let file: TokensFile = match parse_file(path, &content, FileKind::Tokens) {
+            Ok(file) => file,
+            Err(e) => return self.fail(name, format!("invalid: {}", e)),
+        };
+        self.check_tokens_file(&name, &file);
+    }
+
+    /// `name` is the subject of the summary line.
+    fn check_tokens_file(&mut self, name: &str, file: &TokensFile) {
+        let problems: Vec<String> = token_problems(file).iter().map(ToString::to_string).collect();
+        if problems.is_empty() {
+            let count = |section: &TokensFile| format!("{} tokens, {} pairs", section.tokens.resolve(None).len(), section.pairs.len());
+            let mut summary = count(file);
+            for (chain_id, section) in &file.chains {
+                summary.push_str(&format!("; chain {}: {}", chain_id, count(section)));
+            }
+            self.pass(name, summary);
+        } else {
+            self.fail(name, problems.join("; "));
+        }
+    }
+
+    async fn check_rpc(&mut self, rpc_url: Option<&str>) {
+        let Some(rpc_url) = rpc_url.filter(|url| !url.is_empty()) else {
+            return self.fail("RPC_URL", "not set");
+        };
+        let connected = tokio::time::timeout(RPC_TIMEOUT, async {
+            let provider = crate::rpc::connect(rpc_url).await?;
+            let chain_id = provider.get_chain_id().await?;
+            let block = provider.get_block_number().await?;
+            eyre::Ok((chain_id, block))
+        })
+        .await;
+        match connected {
+            Ok(Ok((chain_id, block))) => self.pass("RPC_URL", format!("connected to chain {} at block {}", chain_id, block)),
+            Ok(Err(e)) => self.fail("RPC_URL", format!("cannot connect: {}", e)),
+            Err(_) => self.fail("RPC_URL", format!("no answer within {}s", RPC_TIMEOUT.as_secs())),
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+            };
+            writeln!(f, "{}  {}: {}", status, check.subject, check.detail)?;
+        }
+        match self.failures() {
+            0 => write!(f, "Configuration OK"),
+            1 => write!(f, "1 check failed"),
+            n => write!(f, "{} checks failed", n),
+        }
+    }
+}
+
+/// Check the files the scanner would load (`scanner.toml`, or `PROTOCOLS_JSON` and `TOKENS_JSON`) and, with
+/// `services`, the services it would use (`RPC_URL`, `THE_GRAPH_API_KEY`).
+pub async fn validate(services: bool) -> ValidationReport {
+    if let Some(path) = scanner_file_path() {
+        let mut report = ValidationReport::check_scanner_file(&path);
+        if let (true, Ok(file)) = (services, ScannerFile::load(&path)) {
+            report.check_rpc(file.rpc_url().as_deref()).await;
+            if let Ok((configs, discovery)) = file.protocols() {
+                report.check_subgraphs(configs, discovery, &file.api_key()).await;
+            }
+        }
+        return report;
+    }
+    let protocols = config_path("PROTOCOLS_JSON", PROTOCOLS_FILES);
+    let mut report = ValidationReport::check_files(&protocols, &config_path("TOKENS_JSON", TOKENS_FILES));
+    if services {
+        report.check_services(&protocols, secret("RPC_URL").as_deref()).await;
+    }
+    report
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+fn sorted(mut configs: Vec<ProtocolConfig>) -> Vec<ProtocolConfig> {
+    configs.sort_by(|a, b| a.id.cmp(&b.id));
+    configs
+}
+
+/// `error` without the API key, which request errors include as part of the gateway URL, or the secrets file's
+/// values (e.g. a key in a `subgraphUrl`).
+fn redact(error: &eyre::Report, api_key: &str) -> String {
+    let mut message = format!("{:#}", error);
+    if !api_key.is_empty() {
+        message = message.replace(api_key, "<THE_GRAPH_API_KEY>");
+    }
+    for value in Secrets::load().unwrap_or_default().values().filter(|value| !value.is_empty()) {
+        message = message.replace(value, "<secret>");
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_files() {
+        let dir = std::env::temp_dir().join(format!("validate-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let protocols = dir.join("protocols.json");
+        let tokens = dir.join("tokens.json");
+        std::fs::write(
+            &protocols,
+            r#"{
+                "protocols": {
+                    "uniswap-v2": { "name": "Uniswap V2", "factory": "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f", "subgraphUrl": "http://localhost/v2", "enabled": true, "poolType": "UniswapV2" },
+                    "uniswap-v3": { "name": "Uniswap V3", "factory": "0x1F98", "subgraphUrl": "http://localhost/v3", "enabled": true, "poolType": "V3" },
+                    "sushiswap": { "name": "SushiSwap", "factory": "", "enabled": false, "poolType": "UniswapV2" }
+                },
+                "discovery": { "minLiquidityUSD": 10000, "cacheRefreshMinutes": 60, "maxPoolsPerProtocol": 100 }
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            &tokens,
+            r#"{ "tokens": { "WETH": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "USDC": "0xA0b8" }, "pairs": ["WETH/USDC", "WETH/DAI"] }"#,
+        )
+        .unwrap();
+
+        let report = ValidationReport::check_files(&protocols, &tokens);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let lines: Vec<_> = report.checks.iter().map(|c| (c.status, c.subject.as_str())).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (CheckStatus::Pass, "protocols.json"),
+                (CheckStatus::Pass, "protocol uniswap-v2"),
+                (CheckStatus::Fail, "protocol uniswap-v3"),
+                (CheckStatus::Fail, "tokens.json"),
+            ]
+        );
+        assert_eq!(report.checks[0].detail, "3 protocols, 2 enabled, Subgraph discovery");
+        assert_eq!(
+            report.checks[2].detail,
+            "factory \"0x1F98\" is not an address; poolType \"V3\" is not UniswapV2 or UniswapV3"
+        );
+        // USDC doesn't resolve, so neither does the pair using it.
+        assert_eq!(
+            report.checks[3].detail,
+            "tokens.USDC: \"0xA0b8\" is not an address; \
+             pairs[0]: \"WETH/USDC\" is not two known tokens, as \"SYMBOL/SYMBOL\" or addresses; \
+             pairs[1]: \"WETH/DAI\" is not two known tokens, as \"SYMBOL/SYMBOL\" or addresses"
+        );
+        assert_eq!(report.failures(), 2);
+        assert!(report.to_string().ends_with("2 checks failed"));
+    }
+}
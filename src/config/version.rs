@@ -0,0 +1,210 @@
+//! The `version` of the config files, and migration of older protocols file layouts to the current one.
+//!
+//! Version 1 is the current layout. A file without `version` is read as version 1 unless it has one of the
+//! layouts from before versioning, read by the former `load_protocol_config` / `load_discovery_config`: a list
+//! of `ProtocolConfig`s, a `DiscoveryConfig`, or both as `{ "protocols": [...], "discovery": {...} }`, all with
+//! snake_case keys. Those are version 0 and are rewritten to version 1 before the file is parsed, so the
+//! `SCANNER_*` overrides and the checks see the current keys.
+
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+/// The layout this build reads and writes.
+pub const CONFIG_VERSION: u64 = 1;
+
+/// Which config file a document is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileKind {
+    Protocols,
+    Tokens,
+    Scanner,
+}
+
+impl FileKind {
+    /// Top-level keys of the file, for `SCANNER_*` overrides; every key for `scanner.toml`.
+    pub(crate) fn sections(self) -> Option<&'static [&'static str]> {
+        match self {
+            FileKind::Protocols => Some(&["protocols", "discovery"]),
+            FileKind::Tokens => Some(&["tokens", "pairs", "blacklist"]),
+            FileKind::Scanner => None,
+        }
+    }
+}
+
+/// snake_case keys of version 0 discovery settings and protocols, with their version 1 names. Keys not listed
+/// (`mode`, `network`, `ranking`, ...) are the same in both.
+const RENAMED_KEYS: &[(&str, &str)] = &[
+    ("min_liquidity_usd", "minLiquidityUSD"),
+    ("max_pools_per_protocol", "maxPoolsPerProtocol"),
+    ("cache_enabled", "cacheEnabled"),
+    ("cache_file", "cacheFile"),
+    ("log_chunk_size", "logChunkSize"),
+    ("max_retries", "maxRetries"),
+    ("retry_base_delay_ms", "retryBaseDelayMs"),
+    ("cache_refresh_minutes", "cacheRefreshMinutes"),
+    ("verify_token_metadata", "verifyTokenMetadata"),
+    ("risk_filter", "riskFilter"),
+    ("detect_token_quirks", "detectTokenQuirks"),
+    ("fee_on_transfer_tokens", "feeOnTransferTokens"),
+    ("rebasing_tokens", "rebasingTokens"),
+    ("seed_tokens", "seedTokens"),
+    ("max_subgraph_lag_blocks", "maxSubgraphLagBlocks"),
+    ("fail_on_subgraph_lag", "failOnSubgraphLag"),
+    ("discovery_concurrency", "discoveryConcurrency"),
+    ("incremental_discovery", "incrementalDiscovery"),
+    ("full_refresh_every", "fullRefreshEvery"),
+    ("subgraph_url", "subgraphUrl"),
+    ("pool_type", "poolType"),
+    ("start_block", "startBlock"),
+    ("fee_tiers", "feeTiers"),
+    ("max_pools", "maxPools"),
+    ("dex_id", "dexId"),
+    ("query_overrides", "queryOverrides"),
+];
+
+/// Check the `version` of `document` (a `kind` file named `source`) and migrate a version 0 protocols file to
+/// the current layout. Fails for a version newer than `CONFIG_VERSION`.
+pub(crate) fn migrate(document: &mut Value, kind: FileKind, source: &str) -> eyre::Result<()> {
+    let version = match document.get("version") {
+        None => None,
+        // A Uniswap token list's own `{ major, minor, patch }`.
+        Some(Value::Object(_)) if kind == FileKind::Tokens => None,
+        Some(Value::Number(number)) if number.as_u64().is_some() => number.as_u64(),
+        Some(other) => eyre::bail!("{}: version: {} is not a version number", source, other),
+    };
+    if let Some(version) = version.filter(|&version| version > CONFIG_VERSION) {
+        eyre::bail!(
+            "{}: version {} is newer than this build reads (up to {}); upgrade dex-pool-scanner",
+            source,
+            version,
+            CONFIG_VERSION
+        );
+    }
+    if kind != FileKind::Protocols || version == Some(CONFIG_VERSION) {
+        return Ok(());
+    }
+    let Some(layout) = legacy_layout(document, version) else {
+        return Ok(());
+    };
+    warn!(
+        "{} has the version 0 layout ({}); read as version {}. Rewrite it with the current keys and \"version\": {}.",
+        source, layout, CONFIG_VERSION, CONFIG_VERSION
+    );
+    *document = match std::mem::take(document) {
+        Value::Array(protocols) => json!({ "protocols": protocols, "discovery": {} }),
+        Value::Object(mut root) if !root.contains_key("protocols") => {
+            root.remove("version");
+            json!({ "protocols": {}, "discovery": root })
+        }
+        other => other,
+    };
+    let Value::Object(root) = document else { unreachable!() };
+    if let Some(Value::Array(protocols)) = root.remove("protocols") {
+        let protocols: Map<String, Value> = protocols.into_iter().filter_map(protocol_entry).collect();
+        root.insert("protocols".into(), Value::Object(protocols));
+    }
+    let discovery = root.entry("discovery").or_insert_with(|| json!({}));
+    if let Value::Object(discovery) = discovery {
+        rename_keys(discovery);
+        // Version 0 defaulted these; version 1 requires them.
+        for (key, default) in [("minLiquidityUSD", json!(0.0)), ("cacheRefreshMinutes", json!(0)), ("maxPoolsPerProtocol", json!(100))] {
+            discovery.entry(key).or_insert(default);
+        }
+    }
+    root.insert("version".into(), json!(CONFIG_VERSION));
+    Ok(())
+}
+
+/// Which version 0 layout `document` has, when it has one: a bare protocol list, bare discovery settings, or
+/// both with a protocol list. A file that says `"version": 0` with the version 1 layout is taken as is.
+fn legacy_layout(document: &Value, version: Option<u64>) -> Option<&'static str> {
+    match document {
+        Value::Array(_) => Some("a protocol list"),
+        Value::Object(root) if root.get("protocols").is_some_and(Value::is_array) => Some("a protocol list"),
+        Value::Object(root)
+            if !root.contains_key("protocols")
+                && (version == Some(0) || root.contains_key("min_liquidity_usd") || root.contains_key("max_pools_per_protocol")) =>
+        {
+            Some("discovery settings")
+        }
+        Value::Object(root) if version == Some(0) && root.get("discovery").is_some_and(has_snake_case_keys) => {
+            Some("snake_case discovery settings")
+        }
+        _ => None,
+    }
+}
+
+fn has_snake_case_keys(section: &Value) -> bool {
+    section.as_object().is_some_and(|section| section.keys().any(|key| key.contains('_')))
+}
+
+/// A version 0 `ProtocolConfig` as `(id, entry)`; entries without an id are dropped.
+fn protocol_entry(protocol: Value) -> Option<(String, Value)> {
+    let Value::Object(mut protocol) = protocol else { return None };
+    let Some(Value::String(id)) = protocol.remove("id") else { return None };
+    rename_keys(&mut protocol);
+    protocol.entry("name").or_insert_with(|| json!(id));
+    protocol.entry("factory").or_insert_with(|| json!(""));
+    protocol.entry("enabled").or_insert(json!(true));
+    // An empty URL meant "none".
+    if protocol.get("subgraphUrl").is_some_and(|url| url == "") {
+        protocol.remove("subgraphUrl");
+    }
+    Some((id, Value::Object(protocol)))
+}
+
+fn rename_keys(section: &mut Map<String, Value>) {
+    for (old, new) in RENAMED_KEYS {
+        if let Some(value) = section.remove(*old) {
+            section.insert(new.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let mut protocols = json!([
+            {
+                "id": "uniswap-v2", "name": "Uniswap V2", "subgraph_url": "http://localhost/v2",
+                "pool_type": "UniswapV2", "enabled": true, "factory": "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+            }
+        ]);
+        migrate(&mut protocols, FileKind::Protocols, "protocols.json").unwrap();
+        assert_eq!(
+            protocols,
+            json!({
+                "version": 1,
+                "protocols": {
+                    "uniswap-v2": {
+                        "name": "Uniswap V2", "subgraphUrl": "http://localhost/v2", "poolType": "UniswapV2",
+                        "enabled": true, "factory": "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+                    }
+                },
+                "discovery": { "minLiquidityUSD": 0.0, "cacheRefreshMinutes": 0, "maxPoolsPerProtocol": 100 }
+            })
+        );
+
+        let mut discovery = json!({ "min_liquidity_usd": 5000.0, "max_pools_per_protocol": 20, "mode": "factoryLogs" });
+        migrate(&mut discovery, FileKind::Protocols, "discovery.json").unwrap();
+        assert_eq!(
+            discovery["discovery"],
+            json!({ "minLiquidityUSD": 5000.0, "maxPoolsPerProtocol": 20, "mode": "factoryLogs", "cacheRefreshMinutes": 0 })
+        );
+
+        // The current layout is left alone, with or without `version`.
+        let current = json!({ "protocols": {}, "discovery": { "minLiquidityUSD": 1 } });
+        let mut unversioned = current.clone();
+        migrate(&mut unversioned, FileKind::Protocols, "protocols.json").unwrap();
+        assert_eq!(unversioned, current);
+
+        let error = migrate(&mut json!({ "version": 2, "tokens": {} }), FileKind::Tokens, "tokens.json").unwrap_err();
+        assert_eq!(error.to_string(), "tokens.json: version 2 is newer than this build reads (up to 1); upgrade dex-pool-scanner");
+        assert!(migrate(&mut json!({ "version": "1" }), FileKind::Scanner, "scanner.toml").is_err());
+        let list = json!({ "name": "Example List", "version": { "major": 3, "minor": 0, "patch": 1 }, "tokens": [] });
+        assert!(migrate(&mut list.clone(), FileKind::Tokens, "tokens.json").is_ok());
+    }
+}
@@ -0,0 +1,338 @@
+//! Discovery through aggregator APIs: GeckoTerminal and Dexscreener. DeFiLlama is not offered: its public
+//! API has no DEX pool contract addresses, only protocol-level TVL and yield pool ids.
+
+use super::{fetch_token_metadata_batch, retry_delay, DiscoverySource};
+use crate::rpc::RpcProvider;
+use crate::types::{CachedPool, DiscoveryConfig, Protocol, ProtocolConfig};
+use alloy::primitives::Address;
+use eyre::Result;
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+
+const GECKO_TERMINAL_API: &str = "https://api.geckoterminal.com/api/v2";
+/// GeckoTerminal serves 20 pools per page and at most 10 pages.
+const GECKO_TERMINAL_MAX_PAGES: u32 = 10;
+const DEXSCREENER_API: &str = "https://api.dexscreener.com";
+
+/// Pools of a DEX from GeckoTerminal's public API (`/networks/{network}/dexes/{dexId}/pools`).
+/// No API key; the free tier allows about 30 requests per minute.
+pub struct GeckoTerminalSource {
+    client: Client,
+}
+
+impl GeckoTerminalSource {
+    pub fn new() -> Self {
+        Self { client: Client::new() }
+    }
+
+    async fn fetch(&self, protocol: &ProtocolConfig, config: &DiscoveryConfig) -> Result<Vec<CachedPool>> {
+        let dex_id = dex_id(protocol)?;
+        info!("Fetching pools of {} from GeckoTerminal...", protocol.name);
+
+        let mut pools = Vec::new();
+        for page in 1..=GECKO_TERMINAL_MAX_PAGES {
+            let url = format!(
+                "{}/networks/{}/dexes/{}/pools?page={}&sort=h24_volume_usd_desc&include=base_token,quote_token",
+                GECKO_TERMINAL_API, config.network, dex_id, page
+            );
+            let data = get_with_retry(&self.client, &url, "GeckoTerminal", config).await?;
+            let page_pools = data.get("data").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+            if page_pools.is_empty() {
+                break;
+            }
+
+            let tokens: HashMap<&str, &Value> = data
+                .get("included")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|t| Some((t.get("id")?.as_str()?, t.get("attributes")?)))
+                .collect();
+            let token = |pool: &Value, side: &str| -> Option<AggregatorToken> {
+                let id = pool.pointer(&format!("/relationships/{}/data/id", side))?.as_str()?;
+                let attributes = tokens.get(id)?;
+                Some(AggregatorToken {
+                    address: attributes.get("address")?.as_str()?.parse().ok()?,
+                    symbol: attributes.get("symbol").and_then(Value::as_str).unwrap_or_default().to_string(),
+                    decimals: attributes.get("decimals").and_then(Value::as_u64).map(|d| d as u8),
+                })
+            };
+
+            for pool in page_pools {
+                let attributes = &pool["attributes"];
+                let (Some(address), Some(base), Some(quote)) = (
+                    attributes.get("address").and_then(Value::as_str).and_then(|a| a.parse().ok()),
+                    token(pool, "base_token"),
+                    token(pool, "quote_token"),
+                ) else {
+                    continue;
+                };
+                let fee = attributes
+                    .get("pool_fee_percentage")
+                    .and_then(number)
+                    .map(|pct| (pct * 10_000.0).round() as u32)
+                    .unwrap_or(0);
+                pools.extend(aggregator_pool(
+                    protocol,
+                    address,
+                    base,
+                    quote,
+                    fee,
+                    attributes.get("reserve_in_usd").and_then(number).unwrap_or(0.0),
+                    attributes.pointer("/volume_usd/h24").and_then(number).unwrap_or(0.0),
+                ));
+            }
+        }
+
+        let pools = select_pools(pools, protocol, config);
+        info!("Fetched {} pools of {} from GeckoTerminal", pools.len(), protocol.name);
+        Ok(pools)
+    }
+}
+
+impl Default for GeckoTerminalSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiscoverySource for GeckoTerminalSource {
+    fn name(&self) -> &str {
+        "geckoTerminal"
+    }
+
+    fn fetch_pools<'a>(
+        &'a self,
+        protocol: &'a ProtocolConfig,
+        config: &'a DiscoveryConfig,
+    ) -> BoxFuture<'a, Result<Vec<CachedPool>>> {
+        Box::pin(self.fetch(protocol, config))
+    }
+}
+
+/// Pools from Dexscreener's public API. Dexscreener can't list a DEX's pools, so the pairs of every
+/// `seedTokens` entry are fetched (`/token-pairs/v1/{chainId}/{token}`) and kept when their `dexId`
+/// (and `v2` / `v3` label, if any) match the protocol. Dexscreener reports neither token decimals nor
+/// fee tiers, so decimals are read from the tokens: the source needs an RPC provider (`with_provider`), and
+/// pools with a token that doesn't answer `decimals()` are dropped.
+pub struct DexscreenerSource {
+    client: Client,
+    provider: Option<RpcProvider>,
+}
+
+impl DexscreenerSource {
+    /// A source without a provider, which fails to fetch; see `with_provider`.
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            provider: None,
+        }
+    }
+
+    /// A source reading token decimals through `provider`.
+    pub fn with_provider(provider: RpcProvider) -> Self {
+        Self {
+            provider: Some(provider),
+            ..Self::new()
+        }
+    }
+
+    async fn fetch(&self, protocol: &ProtocolConfig, config: &DiscoveryConfig) -> Result<Vec<CachedPool>> {
+        let dex_id = dex_id(protocol)?;
+        if config.seed_tokens.is_empty() {
+            return Err(eyre::eyre!("Dexscreener discovery requires seedTokens"));
+        }
+        let Some(provider) = &self.provider else {
+            return Err(eyre::eyre!("Dexscreener discovery requires PoolDiscovery::with_provider to read token decimals"));
+        };
+        info!("Fetching pools of {} from Dexscreener...", protocol.name);
+
+        let label = match protocol.pool_type {
+            Protocol::UniswapV2 => "v2",
+            Protocol::UniswapV3 => "v3",
+        };
+        let mut seen = HashSet::new();
+        let mut pairs = Vec::new();
+        for seed in &config.seed_tokens {
+            let url = format!("{}/token-pairs/v1/{}/{:#x}", DEXSCREENER_API, config.network, seed);
+            let data = get_with_retry(&self.client, &url, "Dexscreener", config).await?;
+            for pair in data.as_array().map(Vec::as_slice).unwrap_or_default() {
+                if pair.get("dexId").and_then(Value::as_str) != Some(dex_id) {
+                    continue;
+                }
+                if let Some(labels) = pair.get("labels").and_then(Value::as_array)
+                    && !labels.is_empty()
+                    && !labels.iter().any(|l| l.as_str() == Some(label))
+                {
+                    continue;
+                }
+                let token = |key: &str| -> Option<AggregatorToken> {
+                    let token = pair.get(key)?;
+                    Some(AggregatorToken {
+                        address: token.get("address")?.as_str()?.parse().ok()?,
+                        symbol: token.get("symbol").and_then(Value::as_str).unwrap_or_default().to_string(),
+                        decimals: None,
+                    })
+                };
+                let (Some(address), Some(base), Some(quote)) = (
+                    pair.get("pairAddress").and_then(Value::as_str).and_then(|a| a.parse::<Address>().ok()),
+                    token("baseToken"),
+                    token("quoteToken"),
+                ) else {
+                    continue;
+                };
+                if !seen.insert(address) {
+                    continue;
+                }
+                let liquidity_usd = pair.pointer("/liquidity/usd").and_then(number).unwrap_or(0.0);
+                let volume_24h_usd = pair.pointer("/volume/h24").and_then(number).unwrap_or(0.0);
+                pairs.push((address, base, quote, liquidity_usd, volume_24h_usd));
+            }
+        }
+
+        let tokens: Vec<Address> = pairs.iter().flat_map(|(_, base, quote, ..)| [base.address, quote.address]).collect();
+        let metadata = fetch_token_metadata_batch(provider, &tokens).await?;
+        let with_decimals = |mut token: AggregatorToken| {
+            token.decimals = metadata.get(&token.address).map(|metadata| metadata.decimals);
+            token
+        };
+        let found = pairs.len();
+        let pools: Vec<CachedPool> = pairs
+            .into_iter()
+            .filter_map(|(address, base, quote, liquidity_usd, volume_24h_usd)| {
+                aggregator_pool(protocol, address, with_decimals(base), with_decimals(quote), 0, liquidity_usd, volume_24h_usd)
+            })
+            .collect();
+        if pools.len() < found {
+            warn!("Dropped {} Dexscreener pools of {} whose token decimals couldn't be read", found - pools.len(), protocol.name);
+        }
+
+        let pools = select_pools(pools, protocol, config);
+        info!("Fetched {} pools of {} from Dexscreener", pools.len(), protocol.name);
+        Ok(pools)
+    }
+}
+
+impl Default for DexscreenerSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiscoverySource for DexscreenerSource {
+    fn name(&self) -> &str {
+        "dexscreener"
+    }
+
+    fn fetch_pools<'a>(
+        &'a self,
+        protocol: &'a ProtocolConfig,
+        config: &'a DiscoveryConfig,
+    ) -> BoxFuture<'a, Result<Vec<CachedPool>>> {
+        Box::pin(self.fetch(protocol, config))
+    }
+}
+
+struct AggregatorToken {
+    address: Address,
+    symbol: String,
+    decimals: Option<u8>,
+}
+
+fn dex_id(protocol: &ProtocolConfig) -> Result<&str> {
+    protocol
+        .dex_id
+        .as_deref()
+        .ok_or_else(|| eyre::eyre!("{} has no dexId for aggregator discovery", protocol.name))
+}
+
+/// Aggregators report base/quote tokens; order them like Uniswap-style factories (lower address first).
+/// `None` when a token's decimals are unknown, since a guess would misprice every update of the pool.
+fn aggregator_pool(
+    protocol: &ProtocolConfig,
+    address: Address,
+    a: AggregatorToken,
+    b: AggregatorToken,
+    fee: u32,
+    liquidity_usd: f64,
+    volume_24h_usd: f64,
+) -> Option<CachedPool> {
+    let (token0, token1) = if a.address < b.address { (a, b) } else { (b, a) };
+    Some(CachedPool {
+        address,
+        protocol: protocol.id.clone(),
+        token0: token0.address,
+        token0_symbol: token0.symbol,
+        token0_decimals: token0.decimals?,
+        token1: token1.address,
+        token1_symbol: token1.symbol,
+        token1_decimals: token1.decimals?,
+        fee,
+        liquidity_usd,
+        volume_24h_usd,
+        last_seen: chrono::Utc::now().to_rfc3339(),
+        ..Default::default()
+    })
+}
+
+/// Apply `minLiquidityUSD`, fee tiers (when the fee is known) and `maxPoolsPerProtocol`, highest liquidity first.
+fn select_pools(pools: Vec<CachedPool>, protocol: &ProtocolConfig, config: &DiscoveryConfig) -> Vec<CachedPool> {
+    let mut pools: Vec<CachedPool> = pools
+        .into_iter()
+        .filter(|p| p.liquidity_usd >= config.min_liquidity_usd)
+        .filter(|p| p.fee == 0 || protocol.allows_fee(p.fee))
+        .collect();
+    pools.sort_by(|a, b| b.liquidity_usd.total_cmp(&a.liquidity_usd));
+    pools.truncate(config.max_pools_per_protocol as usize);
+    pools
+}
+
+/// Aggregator APIs return numbers as JSON numbers or decimal strings.
+fn number(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str()?.parse().ok())
+}
+
+/// GET a JSON document, retrying network errors and 429/5xx responses like subgraph requests.
+async fn get_with_retry(client: &Client, url: &str, source: &str, config: &DiscoveryConfig) -> Result<Value> {
+    let mut attempt = 0;
+    loop {
+        let err = match get(client, url).await {
+            Ok(data) => return Ok(data),
+            Err(e) => e,
+        };
+        if attempt >= config.max_retries {
+            return Err(err.wrap_err(format!("{} request failed after {} attempts", source, attempt + 1)));
+        }
+        let delay = retry_delay(config.retry_base_delay_ms, attempt);
+        warn!("{} request failed ({}); retrying in {}ms", source, err, delay.as_millis());
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn get(client: &Client, url: &str) -> Result<Value> {
+    Ok(client.get(url).send().await?.error_for_status()?.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregator_pools_need_decimals() {
+        let protocol = ProtocolConfig::builder().id("uniswap-v3").pool_type(Protocol::UniswapV3).build().unwrap();
+        let token = |byte: u8, symbol: &str, decimals| AggregatorToken {
+            address: Address::repeat_byte(byte),
+            symbol: symbol.to_string(),
+            decimals,
+        };
+        let pool = aggregator_pool(&protocol, Address::ZERO, token(2, "WETH", Some(18)), token(1, "USDC", Some(6)), 500, 1.0, 0.0)
+            .unwrap();
+        assert_eq!((pool.token0_symbol.as_str(), pool.token0_decimals, pool.token1_decimals), ("USDC", 6, 18));
+        assert!(aggregator_pool(&protocol, Address::ZERO, token(2, "WETH", Some(18)), token(1, "USDC", None), 500, 1.0, 0.0).is_none());
+    }
+}
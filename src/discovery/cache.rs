@@ -0,0 +1,125 @@
+use crate::types::{CachedPool, DiscoveryConfig, ProtocolConfig};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// On-disk format of the pool cache file.
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    /// Unix seconds when the pools were discovered.
+    saved_at: u64,
+    /// Discovery settings the pools were discovered with; a mismatch invalidates the cache.
+    fingerprint: String,
+    pools: Vec<CachedPool>,
+}
+
+/// JSON file cache of discovered pools, so restarts don't re-query subgraphs or factories.
+pub struct PoolCache {
+    path: PathBuf,
+}
+
+impl PoolCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load cached pools if the file exists, was written for the same protocols and discovery
+    /// settings, and is younger than `max_age` (`None` = never expires).
+    pub fn load(&self, fingerprint: &str, max_age: Option<Duration>) -> Option<Vec<CachedPool>> {
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        let file: CacheFile = match serde_json::from_str(&content) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Ignoring unreadable pool cache {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        if file.fingerprint != fingerprint {
+            debug!("Pool cache {} was written for different settings", self.path.display());
+            return None;
+        }
+        let age = Duration::from_secs(unix_now().saturating_sub(file.saved_at));
+        if max_age.is_some_and(|max_age| age > max_age) {
+            debug!("Pool cache {} is stale ({}s old)", self.path.display(), age.as_secs());
+            return None;
+        }
+        info!(
+            "Loaded {} pools from cache {} ({}s old)",
+            file.pools.len(),
+            self.path.display(),
+            age.as_secs()
+        );
+        Some(file.pools)
+    }
+
+    /// Write `pools` to the cache file atomically (temp file + rename).
+    pub fn save(&self, fingerprint: &str, pools: &[CachedPool]) -> Result<()> {
+        let file = CacheFile {
+            saved_at: unix_now(),
+            fingerprint: fingerprint.to_string(),
+            pools: pools.to_vec(),
+        };
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&file)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Identifies the discovery inputs a cache entry is valid for. Protocols with their own liquidity threshold or
+/// pool limit are listed as `id:min:max`.
+pub(crate) fn cache_fingerprint(protocols: &[ProtocolConfig], config: &DiscoveryConfig) -> String {
+    let mut ids: Vec<String> = protocols
+        .iter()
+        .map(|p| match (p.min_liquidity_usd, p.max_pools) {
+            (None, None) => p.id.clone(),
+            (min, max) => format!("{}:{:?}:{:?}", p.id, min, max),
+        })
+        .collect();
+    ids.sort_unstable();
+    format!(
+        "{:?}|{}|{}|{}|{}",
+        config.mode,
+        config.network,
+        ids.join(","),
+        config.min_liquidity_usd,
+        config.max_pools_per_protocol
+    )
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_round_trip_and_fingerprint_mismatch() {
+        let path = std::env::temp_dir().join(format!("pool-cache-test-{}.json", std::process::id()));
+        let cache = PoolCache::new(&path);
+        let pools = vec![CachedPool {
+            protocol: "uniswap_v3".to_string(),
+            ..Default::default()
+        }];
+        cache.save("a", &pools).unwrap();
+
+        assert_eq!(cache.load("a", Some(Duration::from_secs(60))).map(|p| p.len()), Some(1));
+        assert!(cache.load("b", None).is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
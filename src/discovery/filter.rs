@@ -0,0 +1,194 @@
+use super::ranking::PoolRanking;
+use crate::types::CachedPool;
+use alloy::primitives::Address;
+use std::collections::HashSet;
+
+/// A token pair in canonical (lower address first) order, so `WETH/USDC` and `USDC/WETH` match.
+pub fn canonical_pair(a: Address, b: Address) -> (Address, Address) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+/// Tokens and pools that are never tracked, e.g. known scams, rebasing tokens or broken pools.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Blacklist {
+    pub tokens: HashSet<Address>,
+    pub pools: HashSet<Address>,
+    /// Case-insensitive symbol patterns; `*` matches any run of characters (e.g. `"*INU"`).
+    pub symbols: Vec<String>,
+}
+
+impl Blacklist {
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty() && self.pools.is_empty() && self.symbols.is_empty()
+    }
+
+    pub fn blocks(&self, pool: &CachedPool) -> bool {
+        self.pools.contains(&pool.address)
+            || self.tokens.contains(&pool.token0)
+            || self.tokens.contains(&pool.token1)
+            || self.symbols.iter().any(|pattern| {
+                glob_match(pattern, &pool.token0_symbol) || glob_match(pattern, &pool.token1_symbol)
+            })
+    }
+}
+
+/// Case-insensitive match of `text` against `pattern`, where `*` matches any (possibly empty) run.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    let mut parts = pattern.split('*');
+    // The first part must be a prefix; without any `*` the whole pattern must match exactly.
+    let first = parts.next().unwrap_or_default();
+    if !pattern.contains('*') {
+        return first == text;
+    }
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Everything that decides which discovered pools the scanner tracks. Empty lists don't filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PoolFilter {
+    /// Both tokens of a pool must be in this set (`tokens` in tokens.json).
+    pub token_whitelist: HashSet<Address>,
+    /// The pool's token pair must be one of these (`pairs` in tokens.json), in `canonical_pair` order.
+    pub pair_whitelist: HashSet<(Address, Address)>,
+    /// Applied after the whitelists (`blacklist` in tokens.json).
+    pub blacklist: Blacklist,
+    /// Optional final stage keeping only the best pools per pair (`ranking` in protocols.json).
+    pub ranking: Option<PoolRanking>,
+}
+
+impl PoolFilter {
+    pub fn allows(&self, pool: &CachedPool) -> bool {
+        let tokens_ok = self.token_whitelist.is_empty()
+            || (self.token_whitelist.contains(&pool.token0) && self.token_whitelist.contains(&pool.token1));
+        let pair_ok =
+            self.pair_whitelist.is_empty() || self.pair_whitelist.contains(&canonical_pair(pool.token0, pool.token1));
+        tokens_ok && pair_ok && !self.blacklist.blocks(pool)
+    }
+
+    pub fn apply(&self, pools: Vec<CachedPool>) -> Vec<CachedPool> {
+        let pools: Vec<CachedPool> = pools.into_iter().filter(|p| self.allows(p)).collect();
+        match &self.ranking {
+            Some(ranking) => ranking.apply(pools),
+            None => pools,
+        }
+    }
+
+    /// Whether `pool`, found while `tracked` are tracked, should be tracked too: it must pass `allows` and, with
+    /// a ranking, rank among the best pools of its pair together with the tracked ones. Returns the tracked
+    /// pools of the pair it pushes out of the ranking, or `None` when it isn't tracked.
+    pub fn admit(&self, pool: &CachedPool, tracked: &[CachedPool]) -> Option<Vec<Address>> {
+        if !self.allows(pool) {
+            return None;
+        }
+        let Some(ranking) = &self.ranking else {
+            return Some(Vec::new());
+        };
+        let pair = canonical_pair(pool.token0, pool.token1);
+        // The new pool goes last, so it loses ties against the pools already tracked.
+        let candidates: Vec<CachedPool> = tracked
+            .iter()
+            .filter(|p| p.address != pool.address && canonical_pair(p.token0, p.token1) == pair)
+            .chain([pool])
+            .cloned()
+            .collect();
+        let addresses: Vec<Address> = candidates.iter().map(|p| p.address).collect();
+        let kept: HashSet<Address> = ranking.apply(candidates).iter().map(|p| p.address).collect();
+        kept.contains(&pool.address).then(|| addresses.into_iter().filter(|a| !kept.contains(a)).collect())
+    }
+}
+
+/// Keep only pools whose token pair is in `pairs` (in `canonical_pair` order).
+/// If `pairs` is empty, returns `pools` unchanged (no filtering).
+pub fn filter_pools_by_pair_whitelist(
+    pools: Vec<CachedPool>,
+    pairs: &HashSet<(Address, Address)>,
+) -> Vec<CachedPool> {
+    PoolFilter {
+        pair_whitelist: pairs.clone(),
+        ..Default::default()
+    }
+    .apply(pools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_whitelist_matches_either_token_order() {
+        let (weth, usdc, usdt) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let pool = |token0, token1| CachedPool {
+            token0,
+            token1,
+            ..Default::default()
+        };
+        let filter = PoolFilter {
+            token_whitelist: HashSet::from([weth, usdc, usdt]),
+            pair_whitelist: HashSet::from([canonical_pair(usdc, weth)]),
+            ..Default::default()
+        };
+        assert!(filter.allows(&pool(weth, usdc)));
+        assert!(filter.allows(&pool(usdc, weth)));
+        assert!(!filter.allows(&pool(weth, usdt)));
+    }
+
+    #[test]
+    fn test_symbol_blacklist_patterns() {
+        assert!(glob_match("*inu", "SHIBINU"));
+        assert!(glob_match("st*", "stETH"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("a*b*c", "aXXcYYb"));
+        assert!(glob_match("AMPL", "ampl"));
+        assert!(!glob_match("AMPL", "AMPLX"));
+
+        let blacklist = Blacklist {
+            symbols: vec!["*INU".to_string()],
+            ..Default::default()
+        };
+        let pool = CachedPool {
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "ShibaInu".to_string(),
+            ..Default::default()
+        };
+        assert!(blacklist.blocks(&pool));
+    }
+
+    #[test]
+    fn test_admit_ranks_against_tracked_pools_of_the_pair() {
+        let (weth, usdc, usdt) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let pool = |id: u8, token0, token1, liquidity_usd| CachedPool {
+            address: Address::repeat_byte(id),
+            token0,
+            token1,
+            liquidity_usd,
+            ..Default::default()
+        };
+        let filter = PoolFilter {
+            ranking: Some(PoolRanking {
+                max_pools_per_pair: 2,
+                liquidity_weight: 1.0,
+                volume_weight: 0.0,
+            }),
+            ..Default::default()
+        };
+        let tracked = [pool(10, weth, usdc, 5_000.0), pool(11, weth, usdc, 1_000.0), pool(12, weth, usdt, 0.0)];
+        // A new, empty pool doesn't displace a tracked one; a pair with room takes it.
+        assert_eq!(filter.admit(&pool(20, usdc, weth, 0.0), &tracked), None);
+        assert_eq!(filter.admit(&pool(21, weth, usdt, 0.0), &tracked), Some(Vec::new()));
+        // One that outranks the weakest pool of its pair evicts it.
+        assert_eq!(filter.admit(&pool(22, weth, usdc, 2_000.0), &tracked), Some(vec![Address::repeat_byte(11)]));
+    }
+}
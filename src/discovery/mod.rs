@@ -1,21 +1,59 @@
-use crate::types::{CachedPool, Protocol, ProtocolConfig, DiscoveryConfig};
+use crate::rpc::RpcProvider;
+use crate::types::{CachedPool, DiscoveryMode, Protocol, ProtocolConfig, DiscoveryConfig};
+use alloy::primitives::Address;
+use rand::Rng;
 use reqwest::Client;
 use serde_json::json;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use eyre::Result;
-use tracing::{info, error};
+use futures::future::join_all;
+use tracing::{info, error, warn};
+
+mod aggregators;
+mod cache;
+mod filter;
+mod onchain;
+mod pool_set;
+mod quirks;
+mod ranking;
+mod risk;
+mod source;
+
+pub use aggregators::{DexscreenerSource, GeckoTerminalSource};
+pub use cache::PoolCache;
+pub use filter::{canonical_pair, filter_pools_by_pair_whitelist, Blacklist, PoolFilter};
+pub use onchain::{
+    creation_event_signature, fetch_token_metadata, fetch_token_metadata_batch, verify_token_metadata, FactoryClient,
+    TokenMetadata,
+};
+pub use pool_set::PoolSet;
+pub use quirks::tag_token_quirks;
+pub use ranking::PoolRanking;
+pub use risk::{screen_pools, RejectedPool, RiskFlag};
+pub use source::DiscoverySource;
+
+/// Largest `first` The Graph accepts; bigger requests are paginated.
+const SUBGRAPH_PAGE_SIZE: u32 = 1000;
 
 pub struct SubgraphClient {
     client: Client,
+    /// Newest `createdAtTimestamp` seen per protocol id, the starting point of incremental discovery.
+    creation_cursors: Mutex<HashMap<String, u64>>,
 }
 
 impl SubgraphClient {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
+            creation_cursors: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Fetch up to `max_pools_per_protocol` pools above `min_liquidity_usd`, highest liquidity first.
+    /// Requests are paginated in pages of 1000 (The Graph's `first` limit) by pool id.
     pub async fn fetch_pools_from_protocol(&self, config: &ProtocolConfig, discovery_config: &DiscoveryConfig) -> Result<Vec<CachedPool>> {
         if !config.enabled {
             return Ok(vec![]);
@@ -23,14 +61,190 @@ impl SubgraphClient {
 
         info!("Fetching pools from {} subgraph...", config.name);
 
+        let (filter, order_by) = pool_query_filter(config, discovery_config);
+        let cached_pools = self
+            .query_pools(config, discovery_config, filter, order_by, false, discovery_config.max_pools_per_protocol)
+            .await?;
+
+        info!("Fetched {} pools from {} subgraph", cached_pools.len(), config.name);
+
+        Ok(cached_pools)
+    }
+
+    /// Fetch pools created after the newest `createdAtTimestamp` seen so far for `config` (see
+    /// `creation_cursor`), oldest first, so the pools beyond `max_pools_per_protocol` are fetched by the next
+    /// call. Returns `None` when no cursor has been recorded yet.
+    pub async fn fetch_new_pools(
+        &self,
+        config: &ProtocolConfig,
+        discovery_config: &DiscoveryConfig,
+    ) -> Result<Option<Vec<CachedPool>>> {
+        let Some(cursor) = self.creation_cursor(&config.id) else {
+            return Ok(None);
+        };
+        let (mut filter, _) = pool_query_filter(config, discovery_config);
+        filter["createdAtTimestamp_gt"] = json!(cursor.to_string());
+        let pools = self
+            .query_pools(config, discovery_config, filter, "createdAtTimestamp", true, discovery_config.max_pools_per_protocol)
+            .await?;
+        Ok(Some(pools))
+    }
+
+    /// Current liquidity and volume of the given pools, whatever their liquidity.
+    pub async fn fetch_pools_by_address(
+        &self,
+        config: &ProtocolConfig,
+        discovery_config: &DiscoveryConfig,
+        addresses: &[Address],
+    ) -> Result<Vec<CachedPool>> {
+        let mut pools = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(SUBGRAPH_PAGE_SIZE as usize) {
+            let ids: Vec<String> = chunk.iter().map(|a| format!("{:#x}", a)).collect();
+            let (_, order_by) = pool_query_filter(config, discovery_config);
+            let filter = json!({ "id_in": ids });
+            pools.extend(self.query_pools(config, discovery_config, filter, order_by, false, chunk.len() as u32).await?);
+        }
+        Ok(pools)
+    }
+
+    /// Newest pool creation timestamp seen in any response of `protocol_id`'s subgraph.
+    pub fn creation_cursor(&self, protocol_id: &str) -> Option<u64> {
+        self.creation_cursors.lock().unwrap().get(protocol_id).copied()
+    }
+
+    /// Run the pool list query with `filter` and return the first `limit` pools by `order_by` (highest first,
+    /// or lowest first when `ascending`). Pages are fetched by `id` (`id_gt` the last id received) rather than
+    /// with `skip`, which The Graph caps at 5000, so every pool matching `filter` is read and the ranking is
+    /// done here. Advances the protocol's creation cursor: to the newest pool read, or with `ascending` (the
+    /// oldest pools returned first) to the newest pool returned.
+    async fn query_pools(
+        &self,
+        config: &ProtocolConfig,
+        discovery_config: &DiscoveryConfig,
+        filter: serde_json::Value,
+        order_by: &str,
+        ascending: bool,
+        limit: u32,
+    ) -> Result<Vec<CachedPool>> {
         let query = if config.pool_type == Protocol::UniswapV2 {
             r#"
-            query GetV2Pairs($first: Int!, $minLiquidityUSD: BigDecimal!) {
+            query GetV2Pairs($first: Int!, $where: Pair_filter!) {
                 pairs(
                     first: $first
+                    orderBy: id
+                    orderDirection: asc
+                    where: $where
+                ) {
+                    id
+                    token0 { id symbol decimals }
+                    token1 { id symbol decimals }
+                    reserveUSD
+                    volumeUSD
+                    createdAtTimestamp
+                }
+            }
+            "#
+        } else {
+            r#"
+            query GetV3Pools($first: Int!, $where: Pool_filter!) {
+                pools(
+                    first: $first
+                    orderBy: id
+                    orderDirection: asc
+                    where: $where
+                ) {
+                    id
+                    token0 { id symbol decimals }
+                    token1 { id symbol decimals }
+                    feeTier
+                    totalValueLockedUSD
+                    volumeUSD
+                    createdAtTimestamp
+                }
+            }
+            "#
+        };
+
+        // Select a custom `queryOverrides.orderBy` field too, so the pools can be ranked by it.
+        let selected = ["id", "reserveUSD", "totalValueLockedUSD", "volumeUSD", "feeTier", "createdAtTimestamp"];
+        let query = if !selected.contains(&order_by) && order_by.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            query.replacen("createdAtTimestamp", &format!("createdAtTimestamp\n                    {}", order_by), 1)
+        } else {
+            query.to_string()
+        };
+        let mut variables = json!({ "first": SUBGRAPH_PAGE_SIZE, "where": filter });
+        let mut ranked = Vec::new();
+
+        loop {
+            let body = json!({ "query": query, "variables": variables });
+            let data = self.post_with_retry(config, &body, discovery_config).await?;
+
+            let pools_json = if config.pool_type == Protocol::UniswapV2 {
+                data.get("data").and_then(|d| d.get("pairs"))
+            } else {
+                data.get("data").and_then(|d| d.get("pools"))
+            };
+            let page = pools_json.and_then(|p| p.as_array()).map(Vec::as_slice).unwrap_or_default();
+
+            ranked.extend(page.iter().filter_map(|pool| {
+                let rank = pool.get(order_by).and_then(|v| v.as_str().and_then(|v| v.parse::<f64>().ok()).or(v.as_f64()));
+                let created_at = pool.get("createdAtTimestamp").and_then(|v| v.as_str()?.parse::<u64>().ok());
+                Some((rank.unwrap_or(f64::NEG_INFINITY), created_at, parse_subgraph_pool(config, pool)?))
+            }));
+
+            // A short page means the subgraph has no more pools matching the filter.
+            let last_id = page.last().and_then(|pool| pool.get("id")).cloned();
+            match last_id {
+                Some(last_id) if page.len() as u32 >= SUBGRAPH_PAGE_SIZE => variables["where"]["id_gt"] = last_id,
+                _ => break,
+            }
+        }
+
+        // Stable, so pools without an `order_by` value keep their id order.
+        if ascending {
+            ranked.sort_by(|(a, ..), (b, ..)| a.total_cmp(b));
+        } else {
+            ranked.sort_by(|(a, ..), (b, ..)| b.total_cmp(a));
+        }
+        let truncated = ranked.len() > limit as usize;
+        let newest_read = ranked.iter().filter_map(|(_, created_at, _)| *created_at).max();
+        ranked.truncate(limit as usize);
+        let newest_created_at = if ascending && truncated {
+            // Pools created in the same second as the last one returned may have been cut off; `_gt` the
+            // cursor must still match them (the caller drops those it already knows).
+            ranked.iter().filter_map(|(_, created_at, _)| *created_at).max().map(|t| t.saturating_sub(1))
+        } else {
+            newest_read
+        };
+
+        if let Some(created_at) = newest_created_at {
+            let mut cursors = self.creation_cursors.lock().unwrap();
+            let cursor = cursors.entry(config.id.clone()).or_default();
+            *cursor = (*cursor).max(created_at);
+        }
+        Ok(ranked.into_iter().map(|(_, _, pool)| pool).collect())
+    }
+
+    /// Fetch every pool of `config` whose tokens are exactly `token_a` / `token_b` (either order),
+    /// highest liquidity first. No liquidity threshold is applied.
+    pub async fn fetch_pair_pools(
+        &self,
+        config: &ProtocolConfig,
+        discovery_config: &DiscoveryConfig,
+        token_a: Address,
+        token_b: Address,
+    ) -> Result<Vec<CachedPool>> {
+        if !config.enabled {
+            return Ok(vec![]);
+        }
+
+        let query = if config.pool_type == Protocol::UniswapV2 {
+            r#"
+            query GetV2Pair($token0: String!, $token1: String!) {
+                pairs(
                     orderBy: reserveUSD
                     orderDirection: desc
-                    where: { reserveUSD_gte: $minLiquidityUSD }
+                    where: { token0: $token0, token1: $token1 }
                 ) {
                     id
                     token0 { id symbol decimals }
@@ -42,12 +256,11 @@ impl SubgraphClient {
             "#
         } else {
             r#"
-            query GetV3Pools($first: Int!, $minLiquidityUSD: BigDecimal!) {
+            query GetV3PairPools($token0: String!, $token1: String!) {
                 pools(
-                    first: $first
                     orderBy: totalValueLockedUSD
                     orderDirection: desc
-                    where: { totalValueLockedUSD_gte: $minLiquidityUSD }
+                    where: { token0: $token0, token1: $token1 }
                 ) {
                     id
                     token0 { id symbol decimals }
@@ -60,77 +273,491 @@ impl SubgraphClient {
             "#
         };
 
-        let response = self.client.post(&config.subgraph_url)
-            .json(&json!({
-                "query": query,
-                "variables": {
-                    "first": discovery_config.max_pools_per_protocol,
-                    "minLiquidityUSD": discovery_config.min_liquidity_usd.to_string()
-                }
-            }))
-            .send()
-            .await?;
+        // Uniswap-style factories always store the lower address as token0.
+        let (token0, token1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+        let body = json!({
+            "query": query,
+            "variables": {
+                "token0": format!("{:#x}", token0),
+                "token1": format!("{:#x}", token1),
+            }
+        });
+        let data = self.post_with_retry(config, &body, discovery_config).await?;
 
-        let data: serde_json::Value = response.json().await?;
+        let key = if config.pool_type == Protocol::UniswapV2 { "pairs" } else { "pools" };
+        Ok(data
+            .get("data")
+            .and_then(|d| d.get(key))
+            .and_then(|p| p.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|pool| parse_subgraph_pool(config, pool))
+            .filter(|pool| config.allows_fee(pool.fee))
+            .collect())
+    }
+
+    /// Latest block the subgraph has indexed (`_meta { block { number } }`).
+    pub async fn indexed_block(&self, config: &ProtocolConfig, discovery_config: &DiscoveryConfig) -> Result<u64> {
+        let body = json!({ "query": "{ _meta { block { number } } }" });
+        let data = self.post_with_retry(config, &body, discovery_config).await?;
+        data.pointer("/data/_meta/block/number")
+            .and_then(|n| n.as_u64())
+            .ok_or_else(|| eyre::eyre!("{} subgraph returned no _meta block", config.name))
+    }
 
+    /// POST a GraphQL request, retrying transient failures (network errors, 429/5xx, GraphQL `errors`)
+    /// up to `max_retries` times with exponential backoff and full jitter.
+    async fn post_with_retry(
+        &self,
+        config: &ProtocolConfig,
+        body: &serde_json::Value,
+        discovery_config: &DiscoveryConfig,
+    ) -> Result<serde_json::Value> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.post(&config.subgraph_url, body).await {
+                Ok(data) => return Ok(data),
+                Err(e) => e,
+            };
+            if attempt >= discovery_config.max_retries {
+                return Err(err.wrap_err(format!("{} subgraph failed after {} attempts", config.name, attempt + 1)));
+            }
+            let delay = retry_delay(discovery_config.retry_base_delay_ms, attempt);
+            warn!(
+                "{} subgraph request failed ({}); retrying in {}ms",
+                config.name,
+                err,
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn post(&self, url: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        let response = self.client.post(url).json(body).send().await?.error_for_status()?;
+        let data: serde_json::Value = response.json().await?;
         if let Some(errors) = data.get("errors") {
-            error!("GraphQL errors from {}: {:?}", config.name, errors);
-            return Ok(vec![]);
+            return Err(eyre::eyre!("GraphQL errors: {}", errors));
         }
+        Ok(data)
+    }
+}
 
-        let pools_json = if config.pool_type == Protocol::UniswapV2 {
-            data.get("data").and_then(|d| d.get("pairs"))
-        } else {
-            data.get("data").and_then(|d| d.get("pools"))
-        };
+/// Default `where` filter (liquidity threshold, fee tiers, `queryOverrides`) and ordering of the pool list query.
+fn pool_query_filter<'a>(config: &'a ProtocolConfig, discovery_config: &DiscoveryConfig) -> (serde_json::Value, &'a str) {
+    let min_liquidity = discovery_config.min_liquidity_usd.to_string();
+    let (mut filter, order_by) = if config.pool_type == Protocol::UniswapV2 {
+        (json!({ "reserveUSD_gte": min_liquidity }), "reserveUSD")
+    } else {
+        let mut filter = json!({ "totalValueLockedUSD_gte": min_liquidity });
+        if !config.fee_tiers.is_empty() {
+            filter["feeTier_in"] = json!(config.fee_tiers.iter().map(|fee| fee.to_string()).collect::<Vec<_>>());
+        }
+        (filter, "totalValueLockedUSD")
+    };
+    // `queryOverrides` constraints win over the generated ones.
+    let overrides = config.query_overrides.as_ref();
+    if let (Some(overrides), Some(filter)) = (overrides, filter.as_object_mut()) {
+        filter.extend(overrides.where_clause.clone());
+    }
+    let order_by = overrides.and_then(|o| o.order_by.as_deref()).unwrap_or(order_by);
+    (filter, order_by)
+}
 
-        let mut cached_pools = Vec::new();
-
-        if let Some(pools) = pools_json.and_then(|p| p.as_array()) {
-            for pool in pools {
-                let address: alloy::primitives::Address = pool.get("id").and_then(|v| v.as_str()).unwrap_or_default().parse().unwrap_or_default();
-                let token0 = pool.get("token0").unwrap();
-                let token1 = pool.get("token1").unwrap();
-                
-                cached_pools.push(CachedPool {
-                    address,
-                    protocol: config.id.clone(),
-                    token0: token0.get("id").and_then(|v| v.as_str()).unwrap_or_default().parse().unwrap_or_default(),
-                    token0_symbol: token0.get("symbol").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
-                    token0_decimals: token0.get("decimals").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(18),
-                    token1: token1.get("id").and_then(|v| v.as_str()).unwrap_or_default().parse().unwrap_or_default(),
-                    token1_symbol: token1.get("symbol").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
-                    token1_decimals: token1.get("decimals").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(18),
-                    fee: pool.get("feeTier").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0),
-                    liquidity_usd: pool.get("totalValueLockedUSD").or(pool.get("reserveUSD")).and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
-                    volume_24h_usd: pool.get("volumeUSD").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
-                    last_seen: chrono::Utc::now().to_rfc3339(),
-                });
-            }
-        }
-        
-        Ok(cached_pools)
+/// Exponential backoff with full jitter: a random delay in `[0, base * 2^attempt]`, capped at 30s.
+fn retry_delay(base_ms: u64, attempt: u32) -> Duration {
+    let cap = base_ms.saturating_mul(1u64 << attempt.min(16)).min(30_000);
+    Duration::from_millis(rand::thread_rng().gen_range(0..=cap))
+}
+
+/// Convert one `pairs` / `pools` entry of a subgraph response into a `CachedPool`.
+fn parse_subgraph_pool(config: &ProtocolConfig, pool: &serde_json::Value) -> Option<CachedPool> {
+    let address: alloy::primitives::Address = pool.get("id").and_then(|v| v.as_str()).unwrap_or_default().parse().unwrap_or_default();
+    let token0 = pool.get("token0")?;
+    let token1 = pool.get("token1")?;
+
+    Some(CachedPool {
+        address,
+        protocol: config.id.clone(),
+        token0: token0.get("id").and_then(|v| v.as_str()).unwrap_or_default().parse().unwrap_or_default(),
+        token0_symbol: token0.get("symbol").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        token0_decimals: token0.get("decimals").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(18),
+        token1: token1.get("id").and_then(|v| v.as_str()).unwrap_or_default().parse().unwrap_or_default(),
+        token1_symbol: token1.get("symbol").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        token1_decimals: token1.get("decimals").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(18),
+        fee: pool.get("feeTier").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0),
+        liquidity_usd: pool.get("totalValueLockedUSD").or(pool.get("reserveUSD")).and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
+        volume_24h_usd: pool.get("volumeUSD").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
+        last_seen: chrono::Utc::now().to_rfc3339(),
+        ..Default::default()
+    })
+}
+
+impl Default for SubgraphClient {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// How far a protocol's subgraph trailed the chain head at the last discovery.
+#[derive(Debug, Clone)]
+pub struct SubgraphHealth {
+    pub protocol: String,
+    pub indexed_block: u64,
+    pub chain_head: u64,
+    pub lag_blocks: u64,
+}
+
 pub struct PoolDiscovery {
     subgraph_client: SubgraphClient,
+    gecko_terminal: GeckoTerminalSource,
+    dexscreener: DexscreenerSource,
+    factory_client: Option<FactoryClient>,
+    source: Option<Arc<dyn DiscoverySource>>,
+    subgraph_health: Mutex<Vec<SubgraphHealth>>,
 }
 
 impl PoolDiscovery {
     pub fn new() -> Self {
         Self {
             subgraph_client: SubgraphClient::new(),
+            gecko_terminal: GeckoTerminalSource::new(),
+            dexscreener: DexscreenerSource::new(),
+            factory_client: None,
+            source: None,
+            subgraph_health: Mutex::new(Vec::new()),
         }
     }
 
-    pub async fn discover_pools(&self, protocols: &[ProtocolConfig], config: &DiscoveryConfig) -> Result<Vec<CachedPool>> {
-        let mut all_pools = Vec::new();
+    /// Discovery with an RPC provider, required for the on-chain modes
+    /// (`DiscoveryMode::FactoryLogs`, `DiscoveryMode::FactoryEnumeration`) and for `DiscoveryMode::Dexscreener`.
+    pub fn with_provider(provider: RpcProvider) -> Self {
+        Self {
+            dexscreener: DexscreenerSource::with_provider(provider.clone()),
+            factory_client: Some(FactoryClient::new(provider)),
+            ..Self::new()
+        }
+    }
+
+    /// Cheap refresh for subgraph mode: fetch only pools created since each protocol's creation cursor,
+    /// and the current liquidity/volume of the `known` pools. Known pools that fell below
+    /// `min_liquidity_usd` are dropped; new pools go through metadata verification, quirk tagging and
+    /// the risk filter. Falls back to `refresh_pools` outside subgraph mode or while a protocol has no
+    /// cursor yet (e.g. the first run after starting from the cache). The cache is not rewritten.
+    pub async fn refresh_incremental(
+        &self,
+        protocols: &[ProtocolConfig],
+        config: &DiscoveryConfig,
+        known: &[CachedPool],
+    ) -> Result<PoolSet> {
+        let incremental = config.mode == DiscoveryMode::Subgraph
+            && self.source.is_none()
+            && protocols.iter().all(|p| self.subgraph_client.creation_cursor(&p.id).is_some());
+        if !incremental {
+            return self.refresh_pools(protocols, config).await;
+        }
+
+        let known_addresses: HashSet<Address> = known.iter().map(|p| p.address).collect();
+        let mut kept = Vec::new();
+        let mut new_pools = Vec::new();
+        let mut failed = Vec::new();
         for protocol in protocols {
-            let pools = self.subgraph_client.fetch_pools_from_protocol(protocol, config).await?;
-            all_pools.extend(pools);
+            let config = &*protocol.discovery_settings(config);
+            let protocol_known: Vec<&CachedPool> = known.iter().filter(|p| p.protocol == protocol.id).collect();
+            let addresses: Vec<Address> = protocol_known.iter().map(|p| p.address).collect();
+            let updates = self
+                .subgraph_client
+                .fetch_pools_by_address(protocol, config, &addresses)
+                .await;
+            let created = self.subgraph_client.fetch_new_pools(protocol, config).await;
+            match (updates, created) {
+                (Ok(updates), Ok(created)) => {
+                    let updates: HashMap<Address, CachedPool> = updates.into_iter().map(|p| (p.address, p)).collect();
+                    // Keep verified metadata and quirk tags; only liquidity and volume change.
+                    kept.extend(protocol_known.into_iter().filter_map(|pool| {
+                        let update = updates.get(&pool.address)?;
+                        (update.liquidity_usd >= config.min_liquidity_usd).then(|| CachedPool {
+                            liquidity_usd: update.liquidity_usd,
+                            volume_24h_usd: update.volume_24h_usd,
+                            last_seen: update.last_seen.clone(),
+                            ..pool.clone()
+                        })
+                    }));
+                    new_pools.extend(
+                        created
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter(|p| !known_addresses.contains(&p.address)),
+                    );
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!("Incremental discovery of {} failed; keeping its pools: {:#}", protocol.name, e);
+                    kept.extend(protocol_known.into_iter().cloned());
+                    failed.push(protocol.id.clone());
+                }
+            }
+        }
+        if failed.len() == protocols.len() && !protocols.is_empty() {
+            return Err(eyre::eyre!("incremental discovery failed for every protocol"));
+        }
+
+        info!("Incremental discovery: {} new pools, {} known pools kept", new_pools.len(), kept.len());
+        kept.extend(self.post_process(new_pools, config).await?);
+        Ok(PoolSet::new(kept).with_failed(failed))
+    }
+
+    /// Fetch every protocol's pools from `source` instead of the backend selected by `DiscoveryConfig::mode`.
+    /// Metadata verification, quirk tagging, the risk filter and the cache still apply.
+    pub fn with_source(mut self, source: impl DiscoverySource + 'static) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    fn factory_client(&self) -> Result<&FactoryClient> {
+        self.factory_client
+            .as_ref()
+            .ok_or_else(|| eyre::eyre!("on-chain discovery requires PoolDiscovery::with_provider"))
+    }
+
+    /// Discover pools for every protocol. With `cache_enabled`, pools are served from `cache_file` while it
+    /// is fresh (younger than `cache_refresh_minutes`, or any age when that is 0) and written back after a
+    /// network discovery.
+    pub async fn discover_pools(&self, protocols: &[ProtocolConfig], config: &DiscoveryConfig) -> Result<PoolSet> {
+        if config.cache_enabled {
+            let max_age = (config.cache_refresh_minutes > 0)
+                .then(|| std::time::Duration::from_secs(u64::from(config.cache_refresh_minutes) * 60));
+            let fingerprint = cache::cache_fingerprint(protocols, config);
+            if let Some(pools) = PoolCache::new(&config.cache_file).load(&fingerprint, max_age) {
+                return Ok(pools.into());
+            }
+        }
+        self.refresh_pools(protocols, config).await
+    }
+
+    /// Subgraph sync status recorded by the last `refresh_pools` (empty unless `max_subgraph_lag_blocks` is set).
+    pub fn subgraph_health(&self) -> Vec<SubgraphHealth> {
+        self.subgraph_health.lock().unwrap().clone()
+    }
+
+    /// Discover pools from the network, bypassing (and, with `cache_enabled`, rewriting) the cache.
+    /// Protocols are fetched concurrently (`discovery_concurrency` at a time). A protocol that still fails after retries is skipped and reported in a summary at the end;
+    /// discovery only errors if every protocol failed. The skipped protocols are listed in `PoolSet::failed_protocols`.
+    pub async fn refresh_pools(&self, protocols: &[ProtocolConfig], config: &DiscoveryConfig) -> Result<PoolSet> {
+        let chain_head = self.chain_head_for_sync_check(config).await;
+        let mut health = Vec::new();
+        let mut all_pools = Vec::new();
+        let mut failures: Vec<(&ProtocolConfig, eyre::Report)> = Vec::new();
+        // Up to `discovery_concurrency` protocols are fetched at once; `join_all` keeps config order.
+        let permits = Semaphore::new(config.discovery_concurrency.max(1));
+        let results = join_all(protocols.iter().map(|protocol| {
+            let permits = &permits;
+            async move {
+                let _permit = permits.acquire().await.expect("semaphore is never closed");
+                let status = match chain_head {
+                    Some(chain_head) => match self.check_subgraph_sync(protocol, config, chain_head).await {
+                        Ok(status) => Some(status),
+                        Err(e) => return (protocol, None, Err(e)),
+                    },
+                    None => None,
+                };
+                (protocol, status, self.discover_protocol(protocol, config).await)
+            }
+        }))
+        .await;
+        for (protocol, status, result) in results {
+            health.extend(status);
+            match result {
+                Ok(pools) => all_pools.extend(pools),
+                Err(e) => failures.push((protocol, e)),
+            }
+        }
+
+        let all_pools = self.post_process(all_pools, config).await?;
+
+        if chain_head.is_some() {
+            *self.subgraph_health.lock().unwrap() = health;
+        }
+
+        if !failures.is_empty() {
+            error!("Discovery failed for {} of {} protocols:", failures.len(), protocols.len());
+            for (protocol, e) in &failures {
+                error!("  {}: {:#}", protocol.name, e);
+            }
+            if failures.len() == protocols.len() {
+                return Err(failures.remove(0).1);
+            }
+        }
+
+        // Don't persist partial results; the next start should retry the failed protocols.
+        if config.cache_enabled && failures.is_empty() {
+            let cache = PoolCache::new(&config.cache_file);
+            match cache.save(&cache::cache_fingerprint(protocols, config), &all_pools) {
+                Ok(()) => info!("Cached {} pools in {}", all_pools.len(), cache.path().display()),
+                Err(e) => warn!("Failed to write pool cache {}: {}", cache.path().display(), e),
+            }
+        }
+        let failed = failures.iter().map(|(protocol, _)| protocol.id.clone()).collect();
+        Ok(PoolSet::new(all_pools).with_failed(failed))
+    }
+
+    /// Metadata verification, quirk tagging and the risk filter, applied to freshly fetched pools.
+    async fn post_process(&self, mut pools: Vec<CachedPool>, config: &DiscoveryConfig) -> Result<Vec<CachedPool>> {
+        // The on-chain modes already read metadata from the tokens themselves.
+        if !config.mode.is_onchain()
+            && config.verify_token_metadata
+            && let Some(factory_client) = &self.factory_client
+        {
+            match verify_token_metadata(factory_client.provider(), &mut pools).await {
+                Ok(0) => {}
+                Ok(n) => info!("Corrected {} token symbol/decimals values from on-chain metadata", n),
+                Err(e) => warn!("On-chain token metadata verification failed: {}", e),
+            }
+        }
+
+        if config.detect_token_quirks {
+            let provider = self.factory_client.as_ref().map(FactoryClient::provider);
+            match tag_token_quirks(provider, &mut pools, config).await {
+                Ok(0) => {}
+                Ok(n) => info!("{} pools contain fee-on-transfer or rebasing tokens", n),
+                Err(e) => warn!("Fee-on-transfer/rebasing detection failed: {}", e),
+            }
         }
-        Ok(all_pools)
+
+        if config.risk_filter {
+            match &self.factory_client {
+                Some(factory_client) => match screen_pools(factory_client.provider(), std::mem::take(&mut pools)).await {
+                    Ok((kept, rejected)) => {
+                        for r in &rejected {
+                            let reasons: Vec<String> = r.flags.iter().map(ToString::to_string).collect();
+                            warn!(
+                                "Excluding pool {:?} ({}/{}): token {:?}: {}",
+                                r.pool.address,
+                                r.pool.token0_symbol,
+                                r.pool.token1_symbol,
+                                r.token,
+                                reasons.join(", ")
+                            );
+                        }
+                        pools = kept;
+                    }
+                    // Fail closed: the user asked for suspicious pools to be excluded.
+                    Err(e) => return Err(e.wrap_err("risk filter failed")),
+                },
+                None => warn!("riskFilter requires PoolDiscovery::with_provider; skipping"),
+            }
+        }
+        Ok(pools)
+    }
+
+    /// Find all pools trading exactly `token_a` / `token_b` across `protocols`. Protocols with a
+    /// `factory` are queried on-chain (`getPair` / `getPool` per V3 fee tier) when a provider is
+    /// available, the others through their subgraph. Failing protocols are logged and skipped.
+    pub async fn discover_pair(
+        &self,
+        protocols: &[ProtocolConfig],
+        config: &DiscoveryConfig,
+        token_a: Address,
+        token_b: Address,
+    ) -> Result<PoolSet> {
+        let mut pools = Vec::new();
+        for protocol in protocols.iter().filter(|p| p.enabled) {
+            let result = match &self.factory_client {
+                Some(factory_client) if !protocol.factory.is_zero() => {
+                    factory_client.fetch_pair_pools(protocol, token_a, token_b).await
+                }
+                _ if !protocol.subgraph_url.is_empty() => {
+                    self.subgraph_client
+                        .fetch_pair_pools(protocol, config, token_a, token_b)
+                        .await
+                }
+                _ => continue,
+            };
+            match result {
+                Ok(found) => pools.extend(found),
+                Err(e) => warn!("Pair lookup on {} failed: {:#}", protocol.name, e),
+            }
+        }
+        info!("Found {} pools for pair {:?}/{:?}", pools.len(), token_a, token_b);
+        Ok(pools.into())
+    }
+
+    /// Chain head to compare subgraphs against, when the sync check applies (subgraph mode,
+    /// `max_subgraph_lag_blocks` set, RPC provider available).
+    async fn chain_head_for_sync_check(&self, config: &DiscoveryConfig) -> Option<u64> {
+        config.max_subgraph_lag_blocks?;
+        if config.mode != DiscoveryMode::Subgraph || self.source.is_some() {
+            return None;
+        }
+        let Some(factory_client) = &self.factory_client else {
+            warn!("maxSubgraphLagBlocks requires PoolDiscovery::with_provider; skipping the sync check");
+            return None;
+        };
+        match factory_client.provider().get_block_number().await {
+            Ok(head) => Some(head),
+            Err(e) => {
+                warn!("Could not read the chain head for the subgraph sync check: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Compare the subgraph's indexed block with `chain_head`. A subgraph lagging more than
+    /// `max_subgraph_lag_blocks` is an error with `fail_on_subgraph_lag`, otherwise a warning.
+    async fn check_subgraph_sync(
+        &self,
+        protocol: &ProtocolConfig,
+        config: &DiscoveryConfig,
+        chain_head: u64,
+    ) -> Result<SubgraphHealth> {
+        let indexed_block = self.subgraph_client.indexed_block(protocol, config).await?;
+        let lag_blocks = chain_head.saturating_sub(indexed_block);
+        let max_lag = config.max_subgraph_lag_blocks.unwrap_or(u64::MAX);
+        if lag_blocks > max_lag {
+            if config.fail_on_subgraph_lag {
+                return Err(eyre::eyre!(
+                    "{} subgraph is {} blocks behind the chain head (max {})",
+                    protocol.name,
+                    lag_blocks,
+                    max_lag
+                ));
+            }
+            warn!(
+                "{} subgraph is {} blocks behind the chain head (max {}); its pools may be stale",
+                protocol.name, lag_blocks, max_lag
+            );
+        }
+        Ok(SubgraphHealth {
+            protocol: protocol.id.clone(),
+            indexed_block,
+            chain_head,
+            lag_blocks,
+        })
+    }
+
+    async fn discover_protocol(&self, protocol: &ProtocolConfig, config: &DiscoveryConfig) -> Result<Vec<CachedPool>> {
+        let config = &*protocol.discovery_settings(config);
+        if let Some(source) = &self.source {
+            return source.fetch_pools(protocol, config).await;
+        }
+        match config.mode {
+            DiscoveryMode::Subgraph => self.subgraph_client.fetch_pools_from_protocol(protocol, config).await,
+            DiscoveryMode::FactoryLogs => self.factory_client()?.fetch_pools_from_factory(protocol, config).await,
+            // V3 factories keep no pool array, so they fall back to creation logs.
+            DiscoveryMode::FactoryEnumeration if protocol.pool_type == Protocol::UniswapV2 => {
+                self.factory_client()?.fetch_pools_by_enumeration(protocol, config).await
+            }
+            DiscoveryMode::FactoryEnumeration => self.factory_client()?.fetch_pools_from_factory(protocol, config).await,
+            DiscoveryMode::GeckoTerminal => self.gecko_terminal.fetch_pools(protocol, config).await,
+            DiscoveryMode::Dexscreener => self.dexscreener.fetch_pools(protocol, config).await,
+        }
+    }
+}
+
+impl Default for PoolDiscovery {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -148,3 +775,16 @@ pub fn filter_pools_by_token_whitelist(
         .filter(|p| whitelist.contains(&p.token0) && whitelist.contains(&p.token1))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_is_bounded() {
+        for attempt in 0..5 {
+            assert!(retry_delay(500, attempt) <= Duration::from_millis(500 << attempt));
+        }
+        assert!(retry_delay(500, 40) <= Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,438 @@
+use crate::rpc::logs::LogChunks;
+use crate::rpc::{multicall, RpcProvider};
+use crate::types::{CachedPool, DiscoveryConfig, Protocol, ProtocolConfig};
+use alloy::primitives::{Address, Bytes, B256, U256};
+use alloy::rpc::types::eth::{Filter, TransactionRequest};
+use alloy::sol;
+use alloy::sol_types::{SolCall, SolEvent};
+use eyre::Result;
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+sol! {
+    event PairCreated(address indexed token0, address indexed token1, address pair, uint256 pairIndex);
+    event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool);
+
+    function decimals() external view returns (uint8);
+    function symbol() external view returns (string);
+
+    function allPairsLength() external view returns (uint256);
+    function allPairs(uint256 index) external view returns (address);
+    function token0() external view returns (address);
+    function token1() external view returns (address);
+
+    function getPair(address tokenA, address tokenB) external view returns (address pair);
+    function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool);
+}
+
+/// Standard Uniswap V3 fee tiers (in hundredths of a bip) probed by `getPool` lookups when the protocol
+/// lists no `fee_tiers`.
+const V3_FEE_TIERS: [u32; 4] = [100, 500, 3000, 10000];
+
+/// The fee tiers `getPool` is asked for: the protocol's `fee_tiers` (forks use others, e.g. 2500 or 80),
+/// or the standard ones.
+fn probed_fee_tiers(config: &ProtocolConfig) -> &[u32] {
+    if config.fee_tiers.is_empty() { &V3_FEE_TIERS } else { &config.fee_tiers }
+}
+
+/// ERC-20 metadata read from the token contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// A pool creation event emitted by a factory.
+struct CreatedPool {
+    address: Address,
+    token0: Address,
+    token1: Address,
+    fee: u32,
+}
+
+/// Discovers pools directly from factory contracts over RPC, without The Graph.
+pub struct FactoryClient {
+    provider: RpcProvider,
+}
+
+impl FactoryClient {
+    pub fn new(provider: RpcProvider) -> Self {
+        Self { provider }
+    }
+
+    pub fn provider(&self) -> &RpcProvider {
+        &self.provider
+    }
+
+    /// Scan `PairCreated` (V2) or `PoolCreated` (V3) logs of `config.factory` with chunked `eth_getLogs`,
+    /// walking back from the chain head to `config.start_block` until `max_pools_per_protocol` pools are found.
+    /// Token symbols/decimals are read on-chain. Liquidity and volume are unknown (reported as 0), so
+    /// `min_liquidity_usd` is not applied in this mode.
+    pub async fn fetch_pools_from_factory(
+        &self,
+        config: &ProtocolConfig,
+        discovery_config: &DiscoveryConfig,
+    ) -> Result<Vec<CachedPool>> {
+        if !config.enabled {
+            return Ok(vec![]);
+        }
+
+        info!("Scanning {} factory logs...", config.name);
+
+        let max_pools = discovery_config.max_pools_per_protocol as usize;
+        let start = config.start_block.unwrap_or(0);
+        let head = self.provider.get_block_number().await?;
+        if config.start_block.is_none() {
+            warn!(
+                "{} has no startBlock; scanning back towards genesis may take many requests",
+                config.name
+            );
+        }
+
+        let topic = creation_event_signature(&config.pool_type);
+
+        let filter = Filter::new().address(config.factory).event_signature(topic);
+        let mut created = Vec::new();
+
+        // Walk backwards from the head so the newest pools are found first.
+        let mut chunks = LogChunks::new(&self.provider, &filter, start, head, discovery_config.log_chunk_size).backwards();
+        while created.len() < max_pools
+            && let Some((_, _, logs)) = chunks.next().await?
+        {
+            for log in logs.iter().rev() {
+                match decode_created_pool(&config.pool_type, log) {
+                    Ok(pool) if config.allows_fee(pool.fee) => created.push(pool),
+                    Ok(_) => {}
+                    Err(e) => debug!("Skipping undecodable factory log: {}", e),
+                }
+            }
+        }
+        created.truncate(max_pools);
+
+        let cached_pools = self.build_cached_pools(config, created).await;
+        info!("Found {} pools from {} factory logs", cached_pools.len(), config.name);
+        Ok(cached_pools)
+    }
+
+    /// Enumerate a V2 factory with `allPairsLength()` and batched `allPairs(i)` / `token0()` / `token1()`
+    /// multicalls, newest pairs first, up to `max_pools_per_protocol`. An alternative to subgraphs when
+    /// they are stale or unavailable. Liquidity and volume are reported as 0.
+    pub async fn fetch_pools_by_enumeration(
+        &self,
+        config: &ProtocolConfig,
+        discovery_config: &DiscoveryConfig,
+    ) -> Result<Vec<CachedPool>> {
+        if !config.enabled {
+            return Ok(vec![]);
+        }
+        if config.pool_type != Protocol::UniswapV2 {
+            return Err(eyre::eyre!(
+                "{}: allPairs enumeration is only available for UniswapV2 factories",
+                config.name
+            ));
+        }
+
+        info!("Enumerating {} factory pairs...", config.name);
+
+        let length_raw = self
+            .provider
+            .call(&TransactionRequest::default().to(config.factory).input(allPairsLengthCall {}.abi_encode().into()))
+            .await?;
+        let length: u64 = allPairsLengthCall::abi_decode_returns(&length_raw, false)?._0.saturating_to();
+        let count = length.min(discovery_config.max_pools_per_protocol as u64);
+
+        let index_calls: Vec<(Address, Bytes)> = (0..count)
+            .map(|i| {
+                let index = U256::from(length - 1 - i);
+                (config.factory, allPairsCall { index }.abi_encode().into())
+            })
+            .collect();
+        let pairs: Vec<Address> = multicall::multicall(&self.provider, &index_calls)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter_map(|raw| allPairsCall::abi_decode_returns(&raw, false).ok().map(|r| r._0))
+            .collect();
+
+        let token_calls: Vec<(Address, Bytes)> = pairs
+            .iter()
+            .flat_map(|pair| {
+                [
+                    (*pair, token0Call {}.abi_encode().into()),
+                    (*pair, token1Call {}.abi_encode().into()),
+                ]
+            })
+            .collect();
+        let token_results = multicall::multicall(&self.provider, &token_calls).await?;
+
+        let created: Vec<CreatedPool> = pairs
+            .iter()
+            .zip(token_results.chunks(2))
+            .filter_map(|(pair, tokens)| {
+                let token0 = token0Call::abi_decode_returns(tokens[0].as_ref()?, false).ok()?._0;
+                let token1 = token1Call::abi_decode_returns(tokens[1].as_ref()?, false).ok()?._0;
+                Some(CreatedPool {
+                    address: *pair,
+                    token0,
+                    token1,
+                    fee: 0,
+                })
+            })
+            .collect();
+
+        let cached_pools = self.build_cached_pools(config, created).await;
+        info!("Found {} of {} pairs from {} factory", cached_pools.len(), length, config.name);
+        Ok(cached_pools)
+    }
+
+    /// Look up the pools for `token_a` / `token_b` directly on the factory: `getPair` for V2,
+    /// `getPool` for every configured (or standard) fee tier for V3, in one multicall.
+    pub async fn fetch_pair_pools(
+        &self,
+        config: &ProtocolConfig,
+        token_a: Address,
+        token_b: Address,
+    ) -> Result<Vec<CachedPool>> {
+        if !config.enabled {
+            return Ok(vec![]);
+        }
+        // Uniswap-style factories always store the lower address as token0.
+        let (token0, token1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+
+        let (calls, fees): (Vec<(Address, Bytes)>, Vec<u32>) = match config.pool_type {
+            Protocol::UniswapV2 => (
+                vec![(config.factory, getPairCall { tokenA: token0, tokenB: token1 }.abi_encode().into())],
+                vec![0],
+            ),
+            Protocol::UniswapV3 => probed_fee_tiers(config)
+                .iter()
+                .map(|fee| {
+                    let call = getPoolCall {
+                        tokenA: token0,
+                        tokenB: token1,
+                        fee: alloy::primitives::Uint::from(*fee),
+                    };
+                    ((config.factory, call.abi_encode().into()), *fee)
+                })
+                .unzip(),
+        };
+
+        let created: Vec<CreatedPool> = multicall::multicall(&self.provider, &calls)
+            .await?
+            .into_iter()
+            .zip(fees)
+            .filter_map(|(raw, fee)| {
+                // Both calls return a single address word.
+                let address = getPairCall::abi_decode_returns(raw.as_ref()?, false).ok()?.pair;
+                (!address.is_zero()).then_some(CreatedPool {
+                    address,
+                    token0,
+                    token1,
+                    fee,
+                })
+            })
+            .collect();
+
+        Ok(self.build_cached_pools(config, created).await)
+    }
+
+    /// Turn a single factory creation log (`PairCreated` / `PoolCreated`) into a `CachedPool`,
+    /// reading token metadata on-chain. Returns `None` if the tokens don't expose ERC-20 metadata.
+    pub async fn pool_from_creation_log(
+        &self,
+        config: &ProtocolConfig,
+        log: &alloy::rpc::types::eth::Log,
+    ) -> Result<Option<CachedPool>> {
+        let created = decode_created_pool(&config.pool_type, log)?;
+        if !config.allows_fee(created.fee) {
+            return Ok(None);
+        }
+        Ok(self.build_cached_pools(config, vec![created]).await.pop())
+    }
+
+    /// Resolve token metadata for created pools and turn them into `CachedPool`s.
+    async fn build_cached_pools(&self, config: &ProtocolConfig, created: Vec<CreatedPool>) -> Vec<CachedPool> {
+        let tokens: Vec<Address> = created.iter().flat_map(|p| [p.token0, p.token1]).collect();
+        let metadata = match fetch_token_metadata_batch(&self.provider, &tokens).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("Token metadata multicall failed for {}: {}", config.name, e);
+                return vec![];
+            }
+        };
+        let mut cached_pools = Vec::with_capacity(created.len());
+        for pool in created {
+            // Pools whose tokens don't expose ERC-20 metadata can't be priced.
+            let (Some(meta0), Some(meta1)) = (metadata.get(&pool.token0).cloned(), metadata.get(&pool.token1).cloned())
+            else {
+                continue;
+            };
+            cached_pools.push(CachedPool {
+                address: pool.address,
+                protocol: config.id.clone(),
+                token0: pool.token0,
+                token0_symbol: meta0.symbol,
+                token0_decimals: meta0.decimals,
+                token1: pool.token1,
+                token1_symbol: meta1.symbol,
+                token1_decimals: meta1.decimals,
+                fee: pool.fee,
+                liquidity_usd: 0.0,
+                volume_24h_usd: 0.0,
+                last_seen: chrono::Utc::now().to_rfc3339(),
+                ..Default::default()
+            });
+        }
+
+        cached_pools
+    }
+}
+
+/// topic0 of the factory event announcing a new pool of `pool_type`.
+pub fn creation_event_signature(pool_type: &Protocol) -> B256 {
+    match pool_type {
+        Protocol::UniswapV2 => PairCreated::SIGNATURE_HASH,
+        Protocol::UniswapV3 => PoolCreated::SIGNATURE_HASH,
+    }
+}
+
+fn decode_created_pool(pool_type: &Protocol, log: &alloy::rpc::types::eth::Log) -> Result<CreatedPool> {
+    Ok(match pool_type {
+        Protocol::UniswapV2 => {
+            let event = log.log_decode::<PairCreated>()?.inner.data;
+            CreatedPool {
+                address: event.pair,
+                token0: event.token0,
+                token1: event.token1,
+                fee: 0,
+            }
+        }
+        Protocol::UniswapV3 => {
+            let event = log.log_decode::<PoolCreated>()?.inner.data;
+            CreatedPool {
+                address: event.pool,
+                token0: event.token0,
+                token1: event.token1,
+                fee: event.fee.to::<u32>(),
+            }
+        }
+    })
+}
+
+/// Read `decimals()` and `symbol()` from an ERC-20 contract. Accepts legacy `bytes32` symbols (e.g. MKR).
+pub async fn fetch_token_metadata(provider: &RpcProvider, token: Address) -> Result<TokenMetadata> {
+    let decimals_raw = provider
+        .call(&TransactionRequest::default().to(token).input(decimalsCall {}.abi_encode().into()))
+        .await?;
+    let decimals = decimalsCall::abi_decode_returns(&decimals_raw, false)?._0;
+
+    let symbol_raw = provider
+        .call(&TransactionRequest::default().to(token).input(symbolCall {}.abi_encode().into()))
+        .await?;
+    let symbol = decode_symbol(&symbol_raw).unwrap_or_default();
+
+    Ok(TokenMetadata { symbol, decimals })
+}
+
+/// Read `decimals()` and `symbol()` for many tokens in batched Multicall3 calls. Tokens whose
+/// `decimals()` reverts are missing from the result; an unreadable symbol becomes an empty string.
+pub async fn fetch_token_metadata_batch(
+    provider: &RpcProvider,
+    tokens: &[Address],
+) -> Result<HashMap<Address, TokenMetadata>> {
+    let mut unique: Vec<Address> = tokens.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+
+    let calls: Vec<(Address, Bytes)> = unique
+        .iter()
+        .flat_map(|token| {
+            [
+                (*token, decimalsCall {}.abi_encode().into()),
+                (*token, symbolCall {}.abi_encode().into()),
+            ]
+        })
+        .collect();
+    let results = multicall::multicall(provider, &calls).await?;
+
+    Ok(unique
+        .into_iter()
+        .zip(results.chunks(2))
+        .filter_map(|(token, raw)| {
+            let decimals = decimalsCall::abi_decode_returns(raw[0].as_ref()?, false).ok()?._0;
+            let symbol = raw[1].as_ref().and_then(|raw| decode_symbol(raw)).unwrap_or_default();
+            Some((token, TokenMetadata { symbol, decimals }))
+        })
+        .collect())
+}
+
+/// Overwrite token symbols/decimals of `pools` with values read on-chain. Subgraphs occasionally
+/// report wrong or missing decimals, which silently corrupts every price of the pool. Tokens that
+/// don't answer `decimals()` keep their reported values. Returns the number of corrected fields.
+pub async fn verify_token_metadata(provider: &RpcProvider, pools: &mut [CachedPool]) -> Result<usize> {
+    let tokens: Vec<Address> = pools.iter().flat_map(|p| [p.token0, p.token1]).collect();
+    let metadata = fetch_token_metadata_batch(provider, &tokens).await?;
+
+    let mut corrected = 0;
+    for pool in pools.iter_mut() {
+        for (token, symbol, decimals) in [
+            (pool.token0, &mut pool.token0_symbol, &mut pool.token0_decimals),
+            (pool.token1, &mut pool.token1_symbol, &mut pool.token1_decimals),
+        ] {
+            let Some(onchain) = metadata.get(&token) else {
+                debug!("No on-chain metadata for token {:?}; keeping subgraph values", token);
+                continue;
+            };
+            if *decimals != onchain.decimals {
+                warn!(
+                    "Token {:?} ({}): subgraph reports {} decimals, on-chain {}",
+                    token, symbol, decimals, onchain.decimals
+                );
+                *decimals = onchain.decimals;
+                corrected += 1;
+            }
+            if !onchain.symbol.is_empty() && *symbol != onchain.symbol {
+                debug!("Token {:?}: symbol {:?} corrected to {:?}", token, symbol, onchain.symbol);
+                *symbol = onchain.symbol.clone();
+                corrected += 1;
+            }
+        }
+    }
+    Ok(corrected)
+}
+
+/// Decode a `symbol()` return value that is either an ABI string or a right-padded `bytes32`.
+pub(crate) fn decode_symbol(raw: &[u8]) -> Option<String> {
+    // An ABI-encoded string is at least 64 bytes (offset + length), so a single word is bytes32.
+    if raw.len() == 32 {
+        let end = raw.iter().position(|b| *b == 0).unwrap_or(32);
+        return String::from_utf8(raw[..end].to_vec()).ok();
+    }
+    symbolCall::abi_decode_returns(raw, false).ok().map(|ret| ret._0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_symbol_string_and_bytes32() {
+        let encoded = symbolCall::abi_encode_returns(&("WETH".to_string(),));
+        assert_eq!(decode_symbol(&encoded).as_deref(), Some("WETH"));
+
+        let mut bytes32 = [0u8; 32];
+        bytes32[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_symbol(&bytes32).as_deref(), Some("MKR"));
+    }
+
+    #[test]
+    fn test_probed_fee_tiers() {
+        let mut config: ProtocolConfig = serde_json::from_value(serde_json::json!({
+            "id": "pancakeswap-v3", "name": "PancakeSwap V3", "subgraph_url": "", "pool_type": "UniswapV3", "enabled": true
+        }))
+        .unwrap();
+        assert_eq!(probed_fee_tiers(&config), [100, 500, 3000, 10000]);
+        config.fee_tiers = vec![100, 500, 2500, 10000];
+        assert_eq!(probed_fee_tiers(&config), [100, 500, 2500, 10000]);
+    }
+}
@@ -0,0 +1,150 @@
+use super::filter::canonical_pair;
+use crate::types::CachedPool;
+use alloy::primitives::Address;
+use std::ops::Deref;
+
+/// Pools returned by discovery, with query helpers. Derefs to `[CachedPool]`, so slice methods
+/// (`len`, `iter`, ...) work directly; `into_vec` gives back the plain list.
+#[derive(Debug, Clone, Default)]
+pub struct PoolSet {
+    pools: Vec<CachedPool>,
+    failed: Vec<String>,
+}
+
+impl PoolSet {
+    pub fn new(pools: Vec<CachedPool>) -> Self {
+        Self { pools, failed: Vec::new() }
+    }
+
+    /// Record that the protocols with ids `protocol_ids` could not be fetched, so the set is partial.
+    pub fn with_failed(mut self, protocol_ids: Vec<String>) -> Self {
+        self.failed = protocol_ids;
+        self
+    }
+
+    /// Ids of the protocols whose discovery failed; the set holds none (or only previously known) of their
+    /// pools. Empty after a complete discovery.
+    pub fn failed_protocols(&self) -> &[String] {
+        &self.failed
+    }
+
+    pub fn into_vec(self) -> Vec<CachedPool> {
+        self.pools
+    }
+
+    /// Pools trading exactly `token_a` / `token_b`, in either order.
+    pub fn by_pair(&self, token_a: Address, token_b: Address) -> PoolSet {
+        let pair = canonical_pair(token_a, token_b);
+        self.matching(|p| canonical_pair(p.token0, p.token1) == pair)
+    }
+
+    /// Pools of the protocol with id `protocol_id` (the key in protocols.json).
+    pub fn by_protocol(&self, protocol_id: &str) -> PoolSet {
+        self.matching(|p| p.protocol == protocol_id)
+    }
+
+    /// Pools containing `token` on either side.
+    pub fn by_token(&self, token: Address) -> PoolSet {
+        self.matching(|p| p.token0 == token || p.token1 == token)
+    }
+
+    /// The `n` pools with the highest `liquidity_usd`, highest first.
+    pub fn top_by_liquidity(&self, n: usize) -> PoolSet {
+        let mut pools = self.pools.clone();
+        pools.sort_by(|a, b| b.liquidity_usd.total_cmp(&a.liquidity_usd));
+        pools.truncate(n);
+        PoolSet::new(pools)
+    }
+
+    /// The pool at `address`, if present.
+    pub fn find(&self, address: Address) -> Option<&CachedPool> {
+        self.pools.iter().find(|p| p.address == address)
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.pools.iter().map(|p| p.address).collect()
+    }
+
+    fn matching(&self, predicate: impl Fn(&CachedPool) -> bool) -> PoolSet {
+        self.pools.iter().filter(|p| predicate(p)).cloned().collect()
+    }
+}
+
+impl Deref for PoolSet {
+    type Target = [CachedPool];
+
+    fn deref(&self) -> &[CachedPool] {
+        &self.pools
+    }
+}
+
+impl From<Vec<CachedPool>> for PoolSet {
+    fn from(pools: Vec<CachedPool>) -> Self {
+        Self::new(pools)
+    }
+}
+
+impl From<PoolSet> for Vec<CachedPool> {
+    fn from(set: PoolSet) -> Self {
+        set.pools
+    }
+}
+
+impl FromIterator<CachedPool> for PoolSet {
+    fn from_iter<I: IntoIterator<Item = CachedPool>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl Extend<CachedPool> for PoolSet {
+    fn extend<I: IntoIterator<Item = CachedPool>>(&mut self, iter: I) {
+        self.pools.extend(iter);
+    }
+}
+
+impl IntoIterator for PoolSet {
+    type Item = CachedPool;
+    type IntoIter = std::vec::IntoIter<CachedPool>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pools.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PoolSet {
+    type Item = &'a CachedPool;
+    type IntoIter = std::slice::Iter<'a, CachedPool>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pools.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_helpers() {
+        let (weth, usdc, dai) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let pool = |id: u8, protocol: &str, token0, token1, liquidity_usd| CachedPool {
+            address: Address::repeat_byte(id),
+            protocol: protocol.to_string(),
+            token0,
+            token1,
+            liquidity_usd,
+            ..Default::default()
+        };
+        let set = PoolSet::from(vec![
+            pool(10, "uniswap-v3", weth, usdc, 5.0),
+            pool(11, "sushiswap-v3", usdc, weth, 9.0),
+            pool(12, "uniswap-v3", weth, dai, 1.0),
+        ]);
+
+        assert_eq!(set.by_pair(usdc, weth).len(), 2);
+        assert_eq!(set.by_protocol("uniswap-v3").addresses(), vec![Address::repeat_byte(10), Address::repeat_byte(12)]);
+        assert_eq!(set.by_token(dai).len(), 1);
+        assert_eq!(set.top_by_liquidity(1)[0].address, Address::repeat_byte(11));
+        assert!(set.find(Address::repeat_byte(12)).is_some());
+    }
+}
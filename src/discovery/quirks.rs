@@ -0,0 +1,114 @@
+use crate::rpc::{multicall, RpcProvider};
+use crate::types::{CachedPool, DiscoveryConfig, TokenQuirk};
+use alloy::primitives::{address, Address, Bytes, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use eyre::Result;
+use std::collections::HashMap;
+
+sol! {
+    function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    function balanceOf(address owner) external view returns (uint256);
+}
+
+/// Well-known Ethereum mainnet tokens whose transfers don't conserve amounts.
+const KNOWN_QUIRKS: [(Address, TokenQuirk); 4] = [
+    (address!("ae7ab96520DE3A18E5e111B5EaAb095312D7fE84"), TokenQuirk::Rebasing), // stETH
+    (address!("D46bA6D942050d489DBd938a2C909A5d5039A161"), TokenQuirk::Rebasing), // AMPL
+    (address!("45804880De22913dAFE09f4980848ECE6EcbAf78"), TokenQuirk::FeeOnTransfer), // PAXG
+    (address!("a7DE087329BFcda5639247F96140f9DAbe3DeED1"), TokenQuirk::FeeOnTransfer), // STA
+];
+
+/// Relative gap between a V2 pair's token balance and its stored reserve (in basis points) above
+/// which the token is considered to rebase.
+const DRIFT_THRESHOLD_BPS: u64 = 100;
+
+/// Tag pools whose tokens are fee-on-transfer or rebasing (`CachedPool::token0_quirk` / `token1_quirk`).
+/// Tokens come from the built-in list and `feeOnTransferTokens` / `rebasingTokens`; with a provider,
+/// V2 pairs whose token balance drifted from the stored reserve are tagged as rebasing too.
+/// Returns the number of tagged pools.
+pub async fn tag_token_quirks(
+    provider: Option<&RpcProvider>,
+    pools: &mut [CachedPool],
+    config: &DiscoveryConfig,
+) -> Result<usize> {
+    let mut known: HashMap<Address, TokenQuirk> = KNOWN_QUIRKS.into_iter().collect();
+    known.extend(config.rebasing_tokens.iter().map(|t| (*t, TokenQuirk::Rebasing)));
+    known.extend(config.fee_on_transfer_tokens.iter().map(|t| (*t, TokenQuirk::FeeOnTransfer)));
+
+    for pool in pools.iter_mut() {
+        pool.token0_quirk = pool.token0_quirk.or(known.get(&pool.token0).copied());
+        pool.token1_quirk = pool.token1_quirk.or(known.get(&pool.token1).copied());
+    }
+
+    if let Some(provider) = provider {
+        detect_reserve_drift(provider, pools).await?;
+    }
+
+    Ok(pools.iter().filter(|p| p.has_token_quirks()).count())
+}
+
+/// V2 pairs sync reserves to balances on every swap/mint/burn, so a balance that moved away from
+/// the reserve without a transfer into the pair points at a rebasing token.
+async fn detect_reserve_drift(provider: &RpcProvider, pools: &mut [CachedPool]) -> Result<()> {
+    let v2: Vec<usize> = (0..pools.len())
+        .filter(|i| pools[*i].protocol.to_lowercase().contains("v2"))
+        .collect();
+    if v2.is_empty() {
+        return Ok(());
+    }
+
+    let calls: Vec<(Address, Bytes)> = v2
+        .iter()
+        .flat_map(|i| {
+            let pool = &pools[*i];
+            [
+                (pool.address, getReservesCall {}.abi_encode().into()),
+                (pool.token0, balanceOfCall { owner: pool.address }.abi_encode().into()),
+                (pool.token1, balanceOfCall { owner: pool.address }.abi_encode().into()),
+            ]
+        })
+        .collect();
+    let results = multicall::multicall(provider, &calls).await?;
+
+    for (i, raw) in v2.into_iter().zip(results.chunks(3)) {
+        let Some(reserves) = raw[0].as_ref().and_then(|r| getReservesCall::abi_decode_returns(r, false).ok()) else {
+            continue;
+        };
+        let balance = |r: &Option<Bytes>| {
+            r.as_ref()
+                .and_then(|r| balanceOfCall::abi_decode_returns(r, false).ok())
+                .map(|b| b._0)
+        };
+        let pool = &mut pools[i];
+        if balance(&raw[1]).is_some_and(|b| drifted(U256::from(reserves.reserve0), b)) {
+            pool.token0_quirk.get_or_insert(TokenQuirk::Rebasing);
+        }
+        if balance(&raw[2]).is_some_and(|b| drifted(U256::from(reserves.reserve1), b)) {
+            pool.token1_quirk.get_or_insert(TokenQuirk::Rebasing);
+        }
+    }
+    Ok(())
+}
+
+fn drifted(reserve: U256, balance: U256) -> bool {
+    if reserve.is_zero() {
+        return false;
+    }
+    let diff = if balance > reserve { balance - reserve } else { reserve - balance };
+    diff * U256::from(10_000u64) > reserve * U256::from(DRIFT_THRESHOLD_BPS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_threshold() {
+        let reserve = U256::from(1_000_000u64);
+        assert!(!drifted(reserve, U256::from(1_005_000u64)));
+        assert!(drifted(reserve, U256::from(1_020_000u64)));
+        assert!(drifted(reserve, U256::from(980_000u64)));
+        assert!(!drifted(U256::ZERO, U256::from(1u64)));
+    }
+}
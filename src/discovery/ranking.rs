@@ -0,0 +1,76 @@
+use super::filter::canonical_pair;
+use crate::types::CachedPool;
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// Keep only the best `max_pools_per_pair` pools for every token pair across protocols and fee tiers,
+/// ranked by `liquidity_weight * liquidity_usd + volume_weight * volume_24h_usd`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolRanking {
+    #[serde(rename = "maxPoolsPerPair")]
+    pub max_pools_per_pair: usize,
+    #[serde(rename = "liquidityWeight", default = "default_weight")]
+    pub liquidity_weight: f64,
+    #[serde(rename = "volumeWeight", default = "default_weight")]
+    pub volume_weight: f64,
+}
+
+impl PoolRanking {
+    pub fn score(&self, pool: &CachedPool) -> f64 {
+        self.liquidity_weight * pool.liquidity_usd + self.volume_weight * pool.volume_24h_usd
+    }
+
+    /// Drop duplicate pool addresses, then keep the top `max_pools_per_pair` pools of each pair.
+    /// The result is ordered by score, best first.
+    pub fn apply(&self, pools: Vec<CachedPool>) -> Vec<CachedPool> {
+        let mut seen: HashSet<Address> = HashSet::new();
+        let mut pools: Vec<CachedPool> = pools.into_iter().filter(|p| seen.insert(p.address)).collect();
+        pools.sort_by(|a, b| self.score(b).total_cmp(&self.score(a)));
+
+        let mut per_pair: HashMap<(Address, Address), usize> = HashMap::new();
+        pools
+            .into_iter()
+            .filter(|pool| {
+                let count = per_pair.entry(canonical_pair(pool.token0, pool.token1)).or_default();
+                *count += 1;
+                *count <= self.max_pools_per_pair
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_top_pools_per_pair() {
+        let (weth, usdc) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let pool = |id: u8, token0, token1, liquidity_usd, volume_24h_usd| CachedPool {
+            address: Address::repeat_byte(id),
+            token0,
+            token1,
+            liquidity_usd,
+            volume_24h_usd,
+            ..Default::default()
+        };
+        let ranking = PoolRanking {
+            max_pools_per_pair: 1,
+            liquidity_weight: 1.0,
+            volume_weight: 1.0,
+        };
+        let kept = ranking.apply(vec![
+            pool(10, weth, usdc, 100.0, 0.0),
+            pool(11, usdc, weth, 50.0, 80.0),
+            pool(11, usdc, weth, 50.0, 80.0),
+            pool(12, weth, Address::repeat_byte(3), 1.0, 0.0),
+        ]);
+        let addresses: Vec<Address> = kept.iter().map(|p| p.address).collect();
+        assert_eq!(addresses, vec![Address::repeat_byte(11), Address::repeat_byte(12)]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Scam / honeypot heuristics over discovered pools (`DiscoveryConfig::risk_filter`).
+//!
+//! Two commonly cited checks are replaced by on-chain ones. "Zero holders": the Uniswap subgraphs have no
+//! holder count on their `Token` entities, so `NoExternalHolders` (the pool holds the whole supply) and
+//! `ZeroSupply` stand in for it. "Liquidity held by the pair creator": finding the creator takes the pair's
+//! creation transaction and the LP holders its `Transfer` history, neither of which the batched calls here
+//! can read, so it isn't checked.
+
+use crate::rpc::{multicall, RpcProvider};
+use crate::types::CachedPool;
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use eyre::Result;
+use std::collections::HashMap;
+use std::fmt;
+
+sol! {
+    function decimals() external view returns (uint8);
+    function totalSupply() external view returns (uint256);
+    function balanceOf(address owner) external view returns (uint256);
+}
+
+/// Longest symbol a legitimate token is expected to have.
+const MAX_SYMBOL_LEN: usize = 20;
+/// Largest plausible `decimals()` value.
+const MAX_DECIMALS: u8 = 36;
+
+/// Why a token looks like a scam or honeypot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskFlag {
+    /// `decimals()` reverts or returns an implausible value.
+    UnverifiableDecimals,
+    /// `totalSupply()` reverts or is zero, so nobody can hold the token.
+    ZeroSupply,
+    /// The pool holds the entire supply: no one outside the pool holds the token.
+    NoExternalHolders,
+    /// Empty, overlong, non-printable or link-like symbol (a common airdrop-scam pattern).
+    SuspiciousSymbol(String),
+}
+
+impl fmt::Display for RiskFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskFlag::UnverifiableDecimals => write!(f, "decimals() missing or implausible"),
+            RiskFlag::ZeroSupply => write!(f, "zero total supply"),
+            RiskFlag::NoExternalHolders => write!(f, "entire supply sits in the pool"),
+            RiskFlag::SuspiciousSymbol(symbol) => write!(f, "suspicious symbol {:?}", symbol),
+        }
+    }
+}
+
+/// A pool excluded by `screen_pools`, with the flagged token and the reasons.
+#[derive(Debug, Clone)]
+pub struct RejectedPool {
+    pub pool: CachedPool,
+    pub token: Address,
+    pub flags: Vec<RiskFlag>,
+}
+
+/// Per-token on-chain facts the heuristics need.
+struct TokenFacts {
+    decimals: Option<u8>,
+    total_supply: Option<U256>,
+}
+
+/// Run scam/honeypot heuristics over `pools` and split them into kept and rejected pools.
+/// Reads `decimals()` / `totalSupply()` of every token and each token's balance in its pool in
+/// batched multicalls. Not a security guarantee: it catches the cheap, common patterns only.
+pub async fn screen_pools(
+    provider: &RpcProvider,
+    pools: Vec<CachedPool>,
+) -> Result<(Vec<CachedPool>, Vec<RejectedPool>)> {
+    let mut tokens: Vec<Address> = pools.iter().flat_map(|p| [p.token0, p.token1]).collect();
+    tokens.sort_unstable();
+    tokens.dedup();
+
+    let token_calls: Vec<(Address, Bytes)> = tokens
+        .iter()
+        .flat_map(|token| {
+            [
+                (*token, decimalsCall {}.abi_encode().into()),
+                (*token, totalSupplyCall {}.abi_encode().into()),
+            ]
+        })
+        .collect();
+    let token_results = multicall::multicall(provider, &token_calls).await?;
+    let facts: HashMap<Address, TokenFacts> = tokens
+        .iter()
+        .zip(token_results.chunks(2))
+        .map(|(token, raw)| {
+            let decimals = raw[0]
+                .as_ref()
+                .and_then(|r| decimalsCall::abi_decode_returns(r, false).ok())
+                .map(|r| r._0);
+            let total_supply = raw[1]
+                .as_ref()
+                .and_then(|r| totalSupplyCall::abi_decode_returns(r, false).ok())
+                .map(|r| r._0);
+            (*token, TokenFacts { decimals, total_supply })
+        })
+        .collect();
+
+    let balance_calls: Vec<(Address, Bytes)> = pools
+        .iter()
+        .flat_map(|pool| {
+            [pool.token0, pool.token1].map(|token| (token, balanceOfCall { owner: pool.address }.abi_encode().into()))
+        })
+        .collect();
+    let balances = multicall::multicall(provider, &balance_calls).await?;
+
+    let mut kept = Vec::with_capacity(pools.len());
+    let mut rejected = Vec::new();
+    for (pool, pool_balances) in pools.into_iter().zip(balances.chunks(2)) {
+        let mut rejection = None;
+        for (i, (token, symbol)) in [(pool.token0, &pool.token0_symbol), (pool.token1, &pool.token1_symbol)]
+            .into_iter()
+            .enumerate()
+        {
+            let balance = pool_balances[i]
+                .as_ref()
+                .and_then(|r| balanceOfCall::abi_decode_returns(r, false).ok())
+                .map(|r| r._0);
+            let flags = token_flags(&facts[&token], symbol, balance);
+            if !flags.is_empty() {
+                rejection = Some((token, flags));
+                break;
+            }
+        }
+        match rejection {
+            Some((token, flags)) => rejected.push(RejectedPool { pool, token, flags }),
+            None => kept.push(pool),
+        }
+    }
+    Ok((kept, rejected))
+}
+
+fn token_flags(facts: &TokenFacts, symbol: &str, pool_balance: Option<U256>) -> Vec<RiskFlag> {
+    let mut flags = Vec::new();
+    if facts.decimals.is_none_or(|d| d > MAX_DECIMALS) {
+        flags.push(RiskFlag::UnverifiableDecimals);
+    }
+    match facts.total_supply {
+        None => flags.push(RiskFlag::ZeroSupply),
+        Some(supply) if supply.is_zero() => flags.push(RiskFlag::ZeroSupply),
+        Some(supply) if pool_balance.is_some_and(|b| b >= supply) => flags.push(RiskFlag::NoExternalHolders),
+        Some(_) => {}
+    }
+    if is_suspicious_symbol(symbol) {
+        flags.push(RiskFlag::SuspiciousSymbol(symbol.to_string()));
+    }
+    flags
+}
+
+fn is_suspicious_symbol(symbol: &str) -> bool {
+    let lower = symbol.to_lowercase();
+    symbol.is_empty()
+        || symbol.chars().count() > MAX_SYMBOL_LEN
+        || symbol.chars().any(|c| c.is_control() || c.is_whitespace())
+        || ["http", "www.", ".com", ".io", ".org", "t.me", "claim", "visit"]
+            .iter()
+            .any(|needle| lower.contains(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_flags() {
+        let healthy = TokenFacts {
+            decimals: Some(18),
+            total_supply: Some(U256::from(1_000u64)),
+        };
+        assert!(token_flags(&healthy, "WETH", Some(U256::from(10u64))).is_empty());
+        assert_eq!(
+            token_flags(&healthy, "WETH", Some(U256::from(1_000u64))),
+            vec![RiskFlag::NoExternalHolders]
+        );
+
+        let broken = TokenFacts {
+            decimals: None,
+            total_supply: Some(U256::ZERO),
+        };
+        assert_eq!(
+            token_flags(&broken, "Visit claim-rewards.com", None),
+            vec![
+                RiskFlag::UnverifiableDecimals,
+                RiskFlag::ZeroSupply,
+                RiskFlag::SuspiciousSymbol("Visit claim-rewards.com".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use super::SubgraphClient;
+use crate::types::{CachedPool, DiscoveryConfig, ProtocolConfig};
+use eyre::Result;
+use futures::future::BoxFuture;
+
+/// A backend that lists the pools of one protocol. Implemented by `SubgraphClient` and the public
+/// aggregator APIs (`GeckoTerminalSource`, `DexscreenerSource`); plug a custom one in with
+/// `PoolDiscovery::with_source`.
+pub trait DiscoverySource: Send + Sync {
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+
+    /// Up to `max_pools_per_protocol` pools of `protocol` with at least `min_liquidity_usd`, best first.
+    fn fetch_pools<'a>(
+        &'a self,
+        protocol: &'a ProtocolConfig,
+        config: &'a DiscoveryConfig,
+    ) -> BoxFuture<'a, Result<Vec<CachedPool>>>;
+}
+
+impl DiscoverySource for SubgraphClient {
+    fn name(&self) -> &str {
+        "subgraph"
+    }
+
+    fn fetch_pools<'a>(
+        &'a self,
+        protocol: &'a ProtocolConfig,
+        config: &'a DiscoveryConfig,
+    ) -> BoxFuture<'a, Result<Vec<CachedPool>>> {
+        Box::pin(self.fetch_pools_from_protocol(protocol, config))
+    }
+}
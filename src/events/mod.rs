@@ -0,0 +1,189 @@
+use crate::arbitrage::{ArbOpportunity, CycleOpportunity};
+use crate::metrics::Candle;
+use crate::mev::Sandwich;
+use crate::pricing::{OracleDeviation, PairPrice, SpreadUpdate, TokenIndexPrice};
+use crate::rpc::{DepthProfile, LargeSwap, PriceStale, Swap};
+use crate::spikes::PriceSpike;
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// Capacity of the broadcast event bus. Slow receivers that fall further behind than this
+/// observe `RecvError::Lagged` and skip ahead.
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+pub type PriceChangeCallback = Arc<dyn Fn(CachedPool, PoolPrice, Option<PoolPrice>) + Send + Sync>;
+pub type ErrorCallback = Arc<dyn Fn(ScannerError) + Send + Sync>;
+pub type StatusCallback = Arc<dyn Fn(ScannerStatus) + Send + Sync>;
+
+/// Lifecycle changes of the log subscription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScannerStatus {
+    /// A log subscription was established for `pools` pool addresses.
+    Subscribed { pools: usize },
+    /// The log stream ended (WebSocket closed or the node dropped the subscription).
+    SubscriptionDropped,
+    /// Waiting `delay_ms` before reconnect attempt number `attempt`.
+    Reconnecting { attempt: u32, delay_ms: u64 },
+    /// A new subscription was established after a drop.
+    Reconnected,
+    /// Sent once, after the first subscription is established and the logs missed while starting (the
+    /// `ScannerOptions::state_store` catch-up) are replayed: from here on prices track the chain.
+    Live { pools: usize },
+}
+
+/// Errors raised while the scanner is running. None of these stop the scanner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScannerError {
+    /// Subscribing to logs (or reconnecting the WebSocket) failed.
+    Subscription(String),
+    /// A log for a tracked pool could not be decoded.
+    Parse { pool: Address, message: String },
+    /// Periodic rediscovery failed; the current pool set is kept.
+    Discovery(String),
+    /// A protocol's subgraph trailed the chain head by more than `DiscoveryConfig::max_subgraph_lag_blocks`
+    /// at a rediscovery, so its pool data may be stale.
+    SubgraphLag { protocol: String, lag_blocks: u64 },
+    /// Reading the Chainlink feeds failed; the previous answers are kept.
+    Oracle(String),
+    /// A sink call still failed after `ScannerOptions::sink_retry`; the sink keeps receiving events.
+    Sink { sink: String, message: String },
+    /// A changed config file could not be loaded (`ScannerOptions::watch_config`); the current configuration
+    /// is kept.
+    Config(String),
+}
+
+impl std::fmt::Display for ScannerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScannerError::Subscription(msg) => write!(f, "subscription error: {}", msg),
+            ScannerError::Parse { pool, message } => write!(f, "parse error for pool {:?}: {}", pool, message),
+            ScannerError::Discovery(msg) => write!(f, "discovery error: {}", msg),
+            ScannerError::SubgraphLag { protocol, lag_blocks } => {
+                write!(f, "{} subgraph is {} blocks behind the chain head", protocol, lag_blocks)
+            }
+            ScannerError::Oracle(msg) => write!(f, "oracle error: {}", msg),
+            ScannerError::Sink { sink, message } => write!(f, "{} sink error: {}", sink, message),
+            ScannerError::Config(msg) => write!(f, "config error: {}", msg),
+        }
+    }
+}
+
+/// Everything the scanner publishes on its event bus.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum ScannerEvent {
+    PriceUpdate {
+        pool: CachedPool,
+        price: PoolPrice,
+        previous: Option<PoolPrice>,
+    },
+    Status(ScannerStatus),
+    Error(ScannerError),
+    /// A pool was added to the tracked set while the scanner was running (live discovery of a
+    /// newly created pool, or periodic rediscovery).
+    PoolDiscovered(CachedPool),
+    /// Periodic rediscovery no longer returned this pool, so it is no longer tracked.
+    PoolRemoved(CachedPool),
+    /// Periodic slippage ladder of a pool (`ScannerOptions::depth`).
+    DepthUpdate(DepthProfile),
+    /// Liquidity-weighted price of a token pair across all its pools, after one of them updated
+    /// (`ScannerOptions::pair_prices`).
+    PairPrice(PairPrice),
+    /// Liquidity-weighted price of a token across its pools, after one of them updated
+    /// (`ScannerOptions::token_index`).
+    TokenIndex(TokenIndexPrice),
+    /// Prices of a pair on two pools diverged by at least `ScannerOptions::spread_threshold_bps`.
+    SpreadUpdate(SpreadUpdate),
+    /// A two-pool round trip profitable after fees and gas (`ScannerOptions::arbitrage`).
+    ArbOpportunity(ArbOpportunity),
+    /// A token cycle through the updated pool profitable after fees and gas (`ArbitrageConfig::cycles`).
+    CycleOpportunity(CycleOpportunity),
+    /// A pool's implied USD price diverged from the token's Chainlink feed by at least
+    /// `OracleConfig::deviation_bps` (`ScannerOptions::oracles`).
+    OracleDeviation(OracleDeviation),
+    /// An update moved the price more than `SpikeFilterConfig::max_move_bps` on a small swap
+    /// (`ScannerOptions::spike_filter`).
+    PriceSpike(PriceSpike),
+    /// A pool had no log for `ScannerOptions::stale_after`; reported once until it updates again.
+    PriceStale(PriceStale),
+    /// Every decoded swap of a tracked pool, with signed token amounts.
+    Swap(Swap),
+    /// A single swap worth at least `ScannerOptions::large_swap_usd`.
+    LargeSwap(LargeSwap),
+    /// A front-run and back-run by the same actor around victim swaps in one block of a pool
+    /// (`ScannerOptions::sandwich_detection`).
+    Sandwich(Sandwich),
+    /// A completed OHLCV candle (`MetricsConfig::candle_intervals`).
+    Candle(Candle),
+}
+
+/// Dispatches scanner events to the registered callbacks and the broadcast bus.
+pub(crate) struct EventHub {
+    on_price_change: PriceChangeCallback,
+    on_error: RwLock<Option<ErrorCallback>>,
+    on_status: RwLock<Option<StatusCallback>>,
+    bus: broadcast::Sender<ScannerEvent>,
+}
+
+impl EventHub {
+    pub(crate) fn new(on_price_change: PriceChangeCallback) -> Self {
+        let (bus, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self {
+            on_price_change,
+            on_error: RwLock::new(None),
+            on_status: RwLock::new(None),
+            bus,
+        }
+    }
+
+    pub(crate) fn set_error_callback(&self, callback: ErrorCallback) {
+        *self.on_error.write().unwrap() = Some(callback);
+    }
+
+    pub(crate) fn set_status_callback(&self, callback: StatusCallback) {
+        *self.on_status.write().unwrap() = Some(callback);
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<ScannerEvent> {
+        self.bus.subscribe()
+    }
+
+    /// Publish to the bus only when someone listens, so unobserved events cost no clone.
+    pub(crate) fn publish(&self, event: impl FnOnce() -> ScannerEvent) {
+        if self.bus.receiver_count() > 0 {
+            let _ = self.bus.send(event());
+        }
+    }
+
+    pub(crate) fn price_changed(&self, pool: CachedPool, price: PoolPrice, previous: Option<PoolPrice>) {
+        self.publish(|| ScannerEvent::PriceUpdate {
+            pool: pool.clone(),
+            price: price.clone(),
+            previous: previous.clone(),
+        });
+        (self.on_price_change)(pool, price, previous);
+    }
+
+    pub(crate) fn error(&self, error: ScannerError) {
+        match &error {
+            ScannerError::Parse { pool, .. } => warn!(pool = %pool, "{}", error),
+            _ => warn!("{}", error),
+        }
+        self.publish(|| ScannerEvent::Error(error.clone()));
+        let callback = self.on_error.read().unwrap().clone();
+        if let Some(callback) = callback {
+            callback(error);
+        }
+    }
+
+    pub(crate) fn status(&self, status: ScannerStatus) {
+        self.publish(|| ScannerEvent::Status(status.clone()));
+        let callback = self.on_status.read().unwrap().clone();
+        if let Some(callback) = callback {
+            callback(status);
+        }
+    }
+}
@@ -1,8 +1,31 @@
+pub mod alerts;
+pub mod arbitrage;
 pub mod config;
 pub mod discovery;
+pub mod events;
 pub mod liquidity_pools;
+pub mod metrics;
+pub mod mev;
+pub mod pricing;
 pub mod rpc;
+pub mod sinks;
+pub mod spikes;
+#[cfg(feature = "rocksdb")]
+pub mod state_store;
+#[cfg(feature = "sqlite")]
+pub mod store;
 pub mod types;
 
-pub use rpc::{PriceChangeCallback, Scanner};
-pub use types::{CachedPool, PoolPrice};
+pub use events::{ErrorCallback, ScannerError, ScannerEvent, ScannerStatus, StatusCallback};
+pub use rpc::{BackfillSummary, DepthConfig, DepthProfile, RpcProvider, OverflowPolicy, PipelineConfig, PriceChangeCallback, Replay, ReplaySummary, ScanConfig, Scanner, ScannerOptions, ScannerStats};
+#[cfg(feature = "server")]
+pub use rpc::ServerConfig;
+#[cfg(feature = "grpc")]
+pub use rpc::GrpcConfig;
+pub use alerts::{AlertConfig, AlertRule, AlertSink, RulesFile};
+pub use arbitrage::{ArbitrageConfig, GasPrice};
+pub use metrics::MetricsConfig;
+pub use pricing::{IndexConfig, OracleConfig, PricingConfig};
+pub use spikes::{SpikeAction, SpikeFilterConfig};
+pub use types::{CachedPool, PoolPrice, PoolSnapshot, QuotedPrice};
+pub use tokio_util::sync::CancellationToken;
@@ -0,0 +1,144 @@
+use alloy::primitives::ruint::UintTryFrom;
+use alloy::primitives::{U256, U512};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Exact decimal price with `DecimalPrice::DECIMALS` fractional digits (value = raw / 10^36),
+/// computed from on-chain integers with 512-bit intermediates instead of f64 round-trips.
+/// Serialized as a decimal string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct DecimalPrice(U256);
+
+impl DecimalPrice {
+    /// Fractional digits kept.
+    pub const DECIMALS: u32 = 36;
+
+    pub fn from_raw(raw: U256) -> Self {
+        Self(raw)
+    }
+
+    /// The price scaled by 10^36.
+    pub fn raw(&self) -> U256 {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Token0 price in token1 units from a V3 `sqrtPriceX96`: `sqrtPriceX96² / 2^192`, adjusted by
+    /// `10^(decimals0 - decimals1)`. Zero when the result doesn't fit.
+    pub fn from_sqrt_price_x96(sqrt_price_x96: U256, token0_decimals: u8, token1_decimals: u8) -> Self {
+        let sqrt = U512::from(sqrt_price_x96);
+        let numerator = sqrt * sqrt;
+        let denominator = U512::from(1u8) << 192;
+        Self::from_ratio(numerator, denominator, token0_decimals, token1_decimals)
+    }
+
+    /// Token0 price in token1 units from V2 reserves: `reserve1 / reserve0`, adjusted by
+    /// `10^(decimals0 - decimals1)`. Zero for an empty pool.
+    pub fn from_reserves(reserve0: U256, reserve1: U256, token0_decimals: u8, token1_decimals: u8) -> Self {
+        Self::from_ratio(U512::from(reserve1), U512::from(reserve0), token0_decimals, token1_decimals)
+    }
+
+    /// `numerator / denominator * 10^(decimals0 - decimals1)` at 36 decimals, rounded down.
+    fn from_ratio(numerator: U512, denominator: U512, token0_decimals: u8, token1_decimals: u8) -> Self {
+        if denominator.is_zero() {
+            return Self::default();
+        }
+        let exponent = Self::DECIMALS as i32 + i32::from(token0_decimals) - i32::from(token1_decimals);
+        let (numerator, denominator) = if exponent >= 0 {
+            match pow10(exponent as u32).and_then(|scale| numerator.checked_mul(scale)) {
+                Some(numerator) => (numerator, denominator),
+                None => return Self::default(),
+            }
+        } else {
+            match pow10(exponent.unsigned_abs()).and_then(|scale| denominator.checked_mul(scale)) {
+                Some(denominator) => (numerator, denominator),
+                None => return Self::default(),
+            }
+        };
+        Self(U256::uint_try_from(numerator / denominator).unwrap_or_default())
+    }
+
+    /// `1 / price` (token1 priced in token0), or zero for a zero price.
+    pub fn inverse(&self) -> Self {
+        if self.0.is_zero() {
+            return Self::default();
+        }
+        let one_squared = pow10(2 * Self::DECIMALS).expect("10^72 fits in 512 bits");
+        Self(U256::uint_try_from(one_squared / U512::from(self.0)).unwrap_or_default())
+    }
+
+    /// Nearest f64, for display and quick arithmetic.
+    pub fn to_f64(&self) -> f64 {
+        f64::from(self.0) / 10f64.powi(Self::DECIMALS as i32)
+    }
+}
+
+fn pow10(exponent: u32) -> Option<U512> {
+    U512::from(10u8).checked_pow(U512::from(exponent))
+}
+
+impl fmt::Display for DecimalPrice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = U256::from(10u8).pow(U256::from(Self::DECIMALS));
+        let (integer, fraction) = self.0.div_rem(scale);
+        let fraction = format!("{:0>width$}", fraction.to_string(), width = Self::DECIMALS as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}", integer)
+        } else {
+            write!(f, "{}.{}", integer, fraction)
+        }
+    }
+}
+
+impl FromStr for DecimalPrice {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+        if fraction.len() > Self::DECIMALS as usize {
+            return Err(eyre::eyre!("more than {} decimal places in {:?}", Self::DECIMALS, s));
+        }
+        let digits = format!("{}{:0<width$}", integer, fraction, width = Self::DECIMALS as usize);
+        Ok(Self(U256::from_str_radix(&digits, 10)?))
+    }
+}
+
+impl Serialize for DecimalPrice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DecimalPrice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_prices() {
+        // 1 WETH (18 decimals) = 2000 USDC (6 decimals).
+        let weth = U256::from(10u64).pow(U256::from(18));
+        let usdc = U256::from(2_000_000_000u64);
+        let price = DecimalPrice::from_reserves(weth, usdc, 18, 6);
+        assert_eq!(price.to_string(), "2000");
+        assert_eq!(price.inverse().to_string(), "0.0005");
+        assert_eq!("2000".parse::<DecimalPrice>().unwrap(), price);
+
+        // sqrtPriceX96 = 2^96 is a raw price of exactly 1.
+        let one = DecimalPrice::from_sqrt_price_x96(U256::from(1u8) << 96, 18, 18);
+        assert_eq!(one.to_string(), "1");
+        assert_eq!(one.to_f64(), 1.0);
+        assert!(DecimalPrice::from_reserves(U256::ZERO, usdc, 18, 6).is_zero());
+    }
+}
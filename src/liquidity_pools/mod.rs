@@ -1,12 +1,25 @@
-use alloy::primitives::{Address, B256, U256};
+use alloy::primitives::{b256, Address, B256, I256, U256};
 use alloy::rpc::types::eth::Log;
 use async_trait::async_trait;
 use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+mod fixed_point;
+pub mod positions;
+mod ticks;
+pub mod v3_math;
+
+pub use fixed_point::DecimalPrice;
+pub use positions::{LpPosition, PositionRange, PositionValue};
+pub use ticks::TickLiquidity;
 
 pub struct EthereumLog {
     pub address: Address,
     pub topics: Vec<B256>,
     pub data: Vec<u8>,
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<B256>,
+    pub log_index: Option<u64>,
 }
 
 impl From<Log> for EthereumLog {
@@ -15,18 +28,101 @@ impl From<Log> for EthereumLog {
             address: log.address(),
             topics: log.topics().to_vec(),
             data: log.data().data.to_vec(),
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash,
+            log_index: log.log_index,
         }
     }
 }
 
+/// Which token a swap sold into the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapDirection {
+    /// token0 in, token1 out.
+    ZeroForOne,
+    /// token1 in, token0 out.
+    OneForZero,
+}
+
+impl SwapDirection {
+    /// Direction from signed pool deltas (positive = paid into the pool); `None` when neither side came in.
+    pub fn from_amounts(amount0: I256, amount1: I256) -> Option<Self> {
+        if amount0.is_positive() {
+            Some(SwapDirection::ZeroForOne)
+        } else if amount1.is_positive() {
+            Some(SwapDirection::OneForZero)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SwapEventData {
-    pub amount0: U256,
-    pub amount1: U256,
+    /// Net token0 delta from the pool's point of view: positive = paid into the pool, negative = paid out.
+    /// Zero for V2 `Sync` events; for V3 `Burn` events, the amounts owed to the position owner.
+    pub amount0: I256,
+    /// Net token1 delta, same sign convention as `amount0`.
+    pub amount1: I256,
+    /// `None` for events that aren't swaps (V2 `Sync`, V3 `Mint` / `Burn`).
+    pub direction: Option<SwapDirection>,
     pub price: f64,
+    /// `price` without f64 rounding.
+    pub exact_price: DecimalPrice,
     pub sender: Address,
     pub recipient: Address,
 }
 
+/// Result of quoting an exact-input swap against the pool's current state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quote {
+    /// Output token amount, in raw units.
+    pub amount_out: U256,
+    /// Shortfall of `effective_price` against the marginal price before the trade, in basis
+    /// points. Includes the pool fee.
+    pub price_impact_bps: u32,
+    /// Output per input token (decimal adjusted) actually received.
+    pub effective_price: DecimalPrice,
+    /// V3: part of the swap ran past the last tick the scanner knows (`TickLiquidity` only holds positions
+    /// minted or burned since the pool was first watched), where the in-range liquidity is assumed to
+    /// continue unchanged. The real output may be lower, so don't size trades on such a quote.
+    pub approximate: bool,
+}
+
+impl Quote {
+    /// `mid_price` is the input token's marginal price in output token units.
+    fn new(amount_in: U256, amount_out: U256, mid_price: DecimalPrice, decimals_in: u8, decimals_out: u8) -> Self {
+        let effective_price = DecimalPrice::from_reserves(amount_in, amount_out, decimals_in, decimals_out);
+        let price_impact_bps = if mid_price.is_zero() || effective_price >= mid_price {
+            0
+        } else {
+            let shortfall = (mid_price.raw() - effective_price.raw()) * U256::from(10_000u64) / mid_price.raw();
+            u32::try_from(shortfall).unwrap_or(10_000)
+        };
+        Self {
+            amount_out,
+            price_impact_bps,
+            effective_price,
+            approximate: false,
+        }
+    }
+}
+
+/// Decoder state of a pool, as kept between restarts by a state store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PoolState {
+    V2 {
+        reserve0: U256,
+        reserve1: U256,
+    },
+    V3 {
+        sqrt_price_x96: U256,
+        tick: i32,
+        liquidity: u128,
+        ticks: TickLiquidity,
+    },
+}
+
 #[async_trait]
 pub trait BaseLiquidityPool: Send + Sync {
     /// Parse log and update internal state (e.g. sqrtPriceX96 or reserves). Returns swap data with price.
@@ -35,7 +131,16 @@ pub trait BaseLiquidityPool: Send + Sync {
     fn get_event_signatures(&self) -> Vec<B256>;
     fn get_name(&self) -> &str;
     fn get_current_price(&self) -> f64;
+    /// Liquidity available at the current price: V3 in-range `liquidity`, V2 `sqrt(reserve0 * reserve1)`.
+    /// Lets consumers weight prices by depth.
+    fn get_liquidity(&self) -> u128;
+    /// Quote selling `amount_in` (raw units) of the token given by `direction` against the current state.
+    fn quote_exact_input(&self, direction: SwapDirection, amount_in: U256) -> Result<Quote>;
     fn apply_initial_state(&mut self, result: Vec<u8>) -> Result<()>;
+    /// Everything the decoder derived from logs so far.
+    fn export_state(&self) -> PoolState;
+    /// Resume from a state returned by `export_state`. Fails for the state of the other pool type.
+    fn restore_state(&mut self, state: PoolState) -> Result<()>;
 }
 
 pub struct UniswapV3 {
@@ -43,8 +148,23 @@ pub struct UniswapV3 {
     token0_decimals: u8,
     token1_decimals: u8,
     sqrt_price_x96: U256,
+    /// Fee in hundredths of a basis point (`3000` = 0.3%).
+    fee: u32,
+    tick: i32,
+    liquidity: u128,
+    ticks: TickLiquidity,
 }
 
+/// Fee assumed when the pool's fee isn't known: the V2 fee and the most common V3 tier.
+const DEFAULT_FEE: u32 = 3000;
+
+// keccak256("Swap(address,address,int256,int256,uint160,uint128,int24)")
+const V3_SWAP_TOPIC: B256 = b256!("c42079f94a6350d7e6235f29174924f928cc2ac818eb64fed8004e115fbcca67");
+// keccak256("Mint(address,address,int24,int24,uint128,uint256,uint256)")
+pub(crate) const V3_MINT_TOPIC: B256 = b256!("7a53080ba414158be7ec69b987b5fb7d07dee101fe85488f0853ae16239d0bde");
+// keccak256("Burn(address,int24,int24,uint128,uint256,uint256)")
+pub(crate) const V3_BURN_TOPIC: B256 = b256!("0c396cd989a39f4459b5fa1aed6a9a8dcdbc45908acfd67e028cd568da98982c");
+
 impl UniswapV3 {
     pub fn new(address: Address, token0_decimals: u8, token1_decimals: u8) -> Self {
         Self {
@@ -52,41 +172,101 @@ impl UniswapV3 {
             token0_decimals,
             token1_decimals,
             sqrt_price_x96: U256::ZERO,
+            fee: DEFAULT_FEE,
+            tick: 0,
+            liquidity: 0,
+            ticks: TickLiquidity::new(),
+        }
+    }
+
+    /// Pool fee in hundredths of a basis point, used for quotes (default 0.3%).
+    pub fn with_fee(mut self, fee: u32) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Current tick, as of the last Swap event (or `slot0`).
+    pub fn current_tick(&self) -> i32 {
+        self.tick
+    }
+
+    /// Per-tick liquidity built from the Mint/Burn events seen so far.
+    pub fn tick_liquidity(&self) -> &TickLiquidity {
+        &self.ticks
+    }
+
+    /// Mint(sender, owner indexed, tickLower indexed, tickUpper indexed, amount, amount0, amount1) and
+    /// Burn(owner indexed, tickLower indexed, tickUpper indexed, amount, amount0, amount1): update the tick
+    /// map, and the in-range liquidity when the position spans the current tick.
+    fn parse_liquidity_event(&mut self, log: &EthereumLog, mint: bool) -> Result<SwapEventData> {
+        // Mint has `sender` as its first data word, Burn doesn't.
+        let offset = if mint { 32 } else { 0 };
+        if log.topics.len() < 4 || log.data.len() < offset + 96 {
+            return Err(eyre::eyre!("UniswapV3 {} log too short", if mint { "Mint" } else { "Burn" }));
         }
+        let tick_lower = decode_tick(log.topics[2].as_slice());
+        let tick_upper = decode_tick(log.topics[3].as_slice());
+        let amount = u128::try_from(U256::from_be_slice(&log.data[offset..offset + 32])).unwrap_or(u128::MAX);
+        let delta = i128::try_from(amount).unwrap_or(i128::MAX);
+        let delta = if mint { delta } else { -delta };
+        self.ticks.update(tick_lower, tick_upper, delta);
+        if tick_lower <= self.tick && self.tick < tick_upper {
+            self.liquidity = self.liquidity.saturating_add_signed(delta);
+        }
+
+        let amount0 = I256::from_raw(U256::from_be_slice(&log.data[offset + 32..offset + 64]));
+        let amount1 = I256::from_raw(U256::from_be_slice(&log.data[offset + 64..offset + 96]));
+        let owner = log.topics.get(1).map(|t| Address::from_slice(&t[12..])).unwrap_or_default();
+        let sender = if mint { Address::from_slice(&log.data[12..32]) } else { owner };
+        let exact_price = self.calculate_exact_price(self.sqrt_price_x96);
+        Ok(SwapEventData {
+            amount0: if mint { amount0 } else { -amount0 },
+            amount1: if mint { amount1 } else { -amount1 },
+            direction: None,
+            price: exact_price.to_f64(),
+            exact_price,
+            sender,
+            recipient: owner,
+        })
+    }
+
+    fn calculate_exact_price(&self, sqrt_price_x96: U256) -> DecimalPrice {
+        DecimalPrice::from_sqrt_price_x96(sqrt_price_x96, self.token0_decimals, self.token1_decimals)
     }
 
     fn calculate_price(&self, sqrt_price_x96: U256) -> f64 {
-        let q96 = U256::from(2).pow(U256::from(96));
-        
-        // Use floats for the price calculation to avoid overflow issues with U256
-        let sqrt_price_f = sqrt_price_x96.to_string().parse::<f64>().unwrap_or(0.0) / 
-                          q96.to_string().parse::<f64>().unwrap_or(1.0);
-        
-        let price = sqrt_price_f * sqrt_price_f;
-        let decimal_adjustment = 10f64.powi(self.token0_decimals as i32 - self.token1_decimals as i32);
-        
-        price * decimal_adjustment
+        self.calculate_exact_price(sqrt_price_x96).to_f64()
     }
 }
 
 #[async_trait]
 impl BaseLiquidityPool for UniswapV3 {
     fn parse_swap_event_data(&mut self, log: &EthereumLog) -> Result<SwapEventData> {
+        match log.topics.first() {
+            Some(topic) if *topic == V3_MINT_TOPIC => return self.parse_liquidity_event(log, true),
+            Some(topic) if *topic == V3_BURN_TOPIC => return self.parse_liquidity_event(log, false),
+            _ => {}
+        }
         // Swap(address,address,int256,int256,uint160 sqrtPriceX96,uint128,int24) - sender/recipient in topics, rest in data
         if log.data.len() < 160 {
             return Err(eyre::eyre!("UniswapV3 Swap log data too short"));
         }
         let sqrt_price_x96 = U256::from_be_slice(&log.data[64..96]);
         self.sqrt_price_x96 = sqrt_price_x96;
-        let price = self.calculate_price(sqrt_price_x96);
-        let amount0 = U256::from_be_slice(&log.data[0..32]);
-        let amount1 = U256::from_be_slice(&log.data[32..64]);
+        self.liquidity = U256::from_be_slice(&log.data[96..128]).try_into().unwrap_or(u128::MAX);
+        self.tick = decode_tick(&log.data[128..160]);
+        let exact_price = self.calculate_exact_price(sqrt_price_x96);
+        // amount0/amount1 are int256: negative for the side leaving the pool.
+        let amount0 = I256::from_raw(U256::from_be_slice(&log.data[0..32]));
+        let amount1 = I256::from_raw(U256::from_be_slice(&log.data[32..64]));
         let sender = log.topics.get(1).map(|t| Address::from_slice(&t[12..])).unwrap_or_default();
         let recipient = log.topics.get(2).map(|t| Address::from_slice(&t[12..])).unwrap_or_default();
         Ok(SwapEventData {
             amount0,
             amount1,
-            price,
+            direction: SwapDirection::from_amounts(amount0, amount1),
+            price: exact_price.to_f64(),
+            exact_price,
             sender,
             recipient,
         })
@@ -97,8 +277,7 @@ impl BaseLiquidityPool for UniswapV3 {
     }
 
     fn get_event_signatures(&self) -> Vec<B256> {
-        // keccak256("Swap(address,address,int256,int256,uint160,uint128,int24)")
-        vec!["0xc42079f94a6350d7e6235f29174924f928cc2ac818eb64fed8004e115fbcca67".parse().unwrap()]
+        vec![V3_SWAP_TOPIC, V3_MINT_TOPIC, V3_BURN_TOPIC]
     }
 
     fn get_name(&self) -> &str {
@@ -109,21 +288,88 @@ impl BaseLiquidityPool for UniswapV3 {
         self.calculate_price(self.sqrt_price_x96)
     }
 
+    fn get_liquidity(&self) -> u128 {
+        self.liquidity
+    }
+
+    fn quote_exact_input(&self, direction: SwapDirection, amount_in: U256) -> Result<Quote> {
+        if self.sqrt_price_x96.is_zero() || self.liquidity == 0 {
+            return Err(eyre::eyre!("UniswapV3 pool {} has no known price or liquidity", self.address));
+        }
+        let zero_for_one = direction == SwapDirection::ZeroForOne;
+        let (amount_out, unfilled, beyond_known) = v3_math::simulate_exact_input(
+            self.sqrt_price_x96,
+            self.tick,
+            self.liquidity,
+            &self.ticks,
+            self.fee,
+            amount_in,
+            zero_for_one,
+        );
+        if !unfilled.is_zero() {
+            return Err(eyre::eyre!("Not enough known liquidity in {} to fill {} ({} left)", self.address, amount_in, unfilled));
+        }
+        let mid_price = self.calculate_exact_price(self.sqrt_price_x96);
+        let quote = if zero_for_one {
+            Quote::new(amount_in, amount_out, mid_price, self.token0_decimals, self.token1_decimals)
+        } else {
+            Quote::new(amount_in, amount_out, mid_price.inverse(), self.token1_decimals, self.token0_decimals)
+        };
+        Ok(Quote {
+            approximate: beyond_known,
+            ..quote
+        })
+    }
+
     fn apply_initial_state(&mut self, result: Vec<u8>) -> Result<()> {
-        // Result is the output of slot0()
+        // Result is the output of slot0() (sqrtPriceX96, tick, ...; 7 words), optionally followed by the
+        // output of liquidity(), which slot0 doesn't include.
         if result.len() >= 32 {
             self.sqrt_price_x96 = U256::from_be_slice(&result[0..32]);
         }
+        if result.len() >= 64 {
+            self.tick = decode_tick(&result[32..64]);
+        }
+        if result.len() >= 256 {
+            self.liquidity = U256::from_be_slice(&result[224..256]).try_into().unwrap_or(u128::MAX);
+        }
+        Ok(())
+    }
+
+    fn export_state(&self) -> PoolState {
+        PoolState::V3 {
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            liquidity: self.liquidity,
+            ticks: self.ticks.clone(),
+        }
+    }
+
+    fn restore_state(&mut self, state: PoolState) -> Result<()> {
+        let PoolState::V3 { sqrt_price_x96, tick, liquidity, ticks } = state else {
+            return Err(eyre::eyre!("Not a UniswapV3 state"));
+        };
+        self.sqrt_price_x96 = sqrt_price_x96;
+        self.tick = tick;
+        self.liquidity = liquidity;
+        self.ticks = ticks;
         Ok(())
     }
 }
 
+/// ABI word holding an `int24` tick (sign-extended to 256 bits).
+fn decode_tick(word: &[u8]) -> i32 {
+    i32::try_from(I256::from_raw(U256::from_be_slice(word))).unwrap_or_default()
+}
+
 pub struct UniswapV2 {
     address: Address,
     token0_decimals: u8,
     token1_decimals: u8,
     reserve0: U256,
     reserve1: U256,
+    /// Fee in hundredths of a basis point (`3000` = 0.3%).
+    fee: u32,
 }
 
 impl UniswapV2 {
@@ -134,21 +380,22 @@ impl UniswapV2 {
             token1_decimals,
             reserve0: U256::ZERO,
             reserve1: U256::ZERO,
+            fee: DEFAULT_FEE,
         }
     }
 
+    /// Swap fee in hundredths of a basis point, for forks that don't charge 0.3%.
+    pub fn with_fee(mut self, fee: u32) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    fn calculate_exact_price(&self, reserve0: U256, reserve1: U256) -> DecimalPrice {
+        DecimalPrice::from_reserves(reserve0, reserve1, self.token0_decimals, self.token1_decimals)
+    }
+
     fn calculate_price(&self, reserve0: U256, reserve1: U256) -> f64 {
-        if reserve0.is_zero() {
-            return 0.0;
-        }
-        
-        let r0_f = reserve0.to_string().parse::<f64>().unwrap_or(0.0);
-        let r1_f = reserve1.to_string().parse::<f64>().unwrap_or(0.0);
-        
-        let price = r1_f / r0_f;
-        let decimal_adjustment = 10f64.powi(self.token0_decimals as i32 - self.token1_decimals as i32);
-        
-        price * decimal_adjustment
+        self.calculate_exact_price(reserve0, reserve1).to_f64()
     }
 }
 
@@ -158,7 +405,7 @@ impl BaseLiquidityPool for UniswapV2 {
         // keccak256("Swap(address,uint256,uint256,uint256,uint256,address)")
         let swap_topic: B256 = "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822".parse().unwrap();
         // keccak256("Sync(uint112,uint112)")
-        let sync_topic: B256 = "0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50438f83b4a47a005e0".parse().unwrap();
+        let sync_topic: B256 = "0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1".parse().unwrap();
 
         if log.topics.is_empty() {
             return Err(eyre::eyre!("Log has no topics"));
@@ -171,27 +418,36 @@ impl BaseLiquidityPool for UniswapV2 {
             }
             self.reserve0 = U256::from_be_slice(&log.data[0..32]);
             self.reserve1 = U256::from_be_slice(&log.data[32..64]);
-            let price = self.calculate_price(self.reserve0, self.reserve1);
+            let exact_price = self.calculate_exact_price(self.reserve0, self.reserve1);
             let sender = log.topics.get(1).map(|t| Address::from_slice(&t[12..])).unwrap_or_default();
             Ok(SwapEventData {
-                amount0: U256::ZERO,
-                amount1: U256::ZERO,
-                price,
+                amount0: I256::ZERO,
+                amount1: I256::ZERO,
+                direction: None,
+                price: exact_price.to_f64(),
+                exact_price,
                 sender,
                 recipient: Address::ZERO,
             })
         } else if log.topics[0] == swap_topic {
             // Swap(amount0In, amount1In, amount0Out, amount1Out) - we don't get new reserves; use current price
-            let price = self.calculate_price(self.reserve0, self.reserve1);
-            let amount0 = if log.data.len() >= 32 { U256::from_be_slice(&log.data[0..32]) } else { U256::ZERO };
-            let amount1 = if log.data.len() >= 64 { U256::from_be_slice(&log.data[32..64]) } else { U256::ZERO };
+            if log.data.len() < 128 {
+                return Err(eyre::eyre!("UniswapV2 Swap log data too short"));
+            }
+            let exact_price = self.calculate_exact_price(self.reserve0, self.reserve1);
+            let word = |i: usize| I256::from_raw(U256::from_be_slice(&log.data[i * 32..(i + 1) * 32]));
+            let amount0 = word(0) - word(2);
+            let amount1 = word(1) - word(3);
             let sender = log.topics.get(1).map(|t| Address::from_slice(&t[12..])).unwrap_or_default();
+            let recipient = log.topics.get(2).map(|t| Address::from_slice(&t[12..])).unwrap_or_default();
             Ok(SwapEventData {
                 amount0,
                 amount1,
-                price,
+                direction: SwapDirection::from_amounts(amount0, amount1),
+                price: exact_price.to_f64(),
+                exact_price,
                 sender,
-                recipient: Address::ZERO,
+                recipient,
             })
         } else {
             Err(eyre::eyre!("Not a recognized UniswapV2 event"))
@@ -205,7 +461,7 @@ impl BaseLiquidityPool for UniswapV2 {
     fn get_event_signatures(&self) -> Vec<B256> {
         vec![
             "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822".parse().unwrap(),
-            "0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50438f83b4a47a005e0".parse().unwrap(),
+            "0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1".parse().unwrap(),
         ]
     }
 
@@ -217,6 +473,27 @@ impl BaseLiquidityPool for UniswapV2 {
         self.calculate_price(self.reserve0, self.reserve1)
     }
 
+    fn get_liquidity(&self) -> u128 {
+        (self.reserve0 * self.reserve1).root(2).try_into().unwrap_or(u128::MAX)
+    }
+
+    fn quote_exact_input(&self, direction: SwapDirection, amount_in: U256) -> Result<Quote> {
+        if self.reserve0.is_zero() || self.reserve1.is_zero() {
+            return Err(eyre::eyre!("UniswapV2 pool {} has no known reserves", self.address));
+        }
+        let mid_price = self.calculate_exact_price(self.reserve0, self.reserve1);
+        Ok(match direction {
+            SwapDirection::ZeroForOne => {
+                let amount_out = v3_math::constant_product_amount_out(amount_in, self.reserve0, self.reserve1, self.fee);
+                Quote::new(amount_in, amount_out, mid_price, self.token0_decimals, self.token1_decimals)
+            }
+            SwapDirection::OneForZero => {
+                let amount_out = v3_math::constant_product_amount_out(amount_in, self.reserve1, self.reserve0, self.fee);
+                Quote::new(amount_in, amount_out, mid_price.inverse(), self.token1_decimals, self.token0_decimals)
+            }
+        })
+    }
+
     fn apply_initial_state(&mut self, result: Vec<u8>) -> Result<()> {
         // Result is from getReserves() -> (uint112, uint112, uint32)
         if result.len() >= 64 {
@@ -225,6 +502,22 @@ impl BaseLiquidityPool for UniswapV2 {
         }
         Ok(())
     }
+
+    fn export_state(&self) -> PoolState {
+        PoolState::V2 {
+            reserve0: self.reserve0,
+            reserve1: self.reserve1,
+        }
+    }
+
+    fn restore_state(&mut self, state: PoolState) -> Result<()> {
+        let PoolState::V2 { reserve0, reserve1 } = state else {
+            return Err(eyre::eyre!("Not a UniswapV2 state"));
+        };
+        self.reserve0 = reserve0;
+        self.reserve1 = reserve1;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -249,6 +542,204 @@ mod tests {
         assert!((price - 2000.0).abs() < 1e-6);
     }
 
+    /// Swap log laid out exactly like a mainnet USDC/WETH 0.05% pool swap selling 1 WETH for 2000 USDC
+    /// (token0 = USDC, token1 = WETH): amount0 is negative and must not decode as a huge unsigned value.
+    #[test]
+    fn test_uniswap_v3_swap_decodes_signed_amounts() {
+        let mut pool = UniswapV3::new(address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"), 6, 18);
+        let amount0 = I256::try_from(-2_000_000_000i64).unwrap();
+        let amount1 = I256::try_from(1_000_000_000_000_000_000i128).unwrap();
+        let sqrt_price_x96 = U256::from(1_771_595_571_142_957_166_518_320_255_467_520u128);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&amount0.to_be_bytes::<32>());
+        data.extend_from_slice(&amount1.to_be_bytes::<32>());
+        data.extend_from_slice(&sqrt_price_x96.to_be_bytes::<32>());
+        data.extend_from_slice(&U256::from(1u64 << 60).to_be_bytes::<32>()); // liquidity
+        data.extend_from_slice(&I256::try_from(200_000i64).unwrap().to_be_bytes::<32>()); // tick
+        let router = address!("E592427A0AEce92De3Edee1F18E0157C05861564");
+        let log = EthereumLog {
+            address: pool.get_contract_address(),
+            topics: vec![
+                pool.get_event_signatures()[0],
+                router.into_word(),
+                router.into_word(),
+            ],
+            data,
+            block_number: None,
+            transaction_hash: None,
+            log_index: None,
+        };
+
+        let swap = pool.parse_swap_event_data(&log).unwrap();
+        assert_eq!(swap.amount0, amount0);
+        assert!(swap.amount0.is_negative());
+        assert_eq!(swap.amount1, amount1);
+        assert_eq!(swap.direction, Some(SwapDirection::OneForZero));
+        assert_eq!(swap.sender, router);
+        assert_eq!(pool.current_tick(), 200_000);
+        assert_eq!(pool.get_liquidity(), 1u128 << 60);
+        // ~0.0005 WETH per USDC.
+        assert!((swap.price - 0.0005).abs() < 1e-6);
+    }
+
+    /// The pool events as declared by the Uniswap contracts, encoded by alloy's ABI encoder instead of by
+    /// hand, so a layout or sign-extension mistake in the decoders can't be mirrored by the test.
+    mod abi {
+        pub mod v3 {
+            alloy::sol! {
+                event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
+            }
+        }
+        pub mod v2 {
+            alloy::sol! {
+                event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to);
+                event Sync(uint112 reserve0, uint112 reserve1);
+            }
+        }
+    }
+
+    fn encoded_log(address: Address, data: alloy::primitives::LogData) -> EthereumLog {
+        EthereumLog {
+            address,
+            topics: data.topics().to_vec(),
+            data: data.data.to_vec(),
+            block_number: Some(19_000_000),
+            transaction_hash: Some(B256::repeat_byte(0xab)),
+            log_index: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_abi_encoded_swaps_decode_signed_amounts() {
+        use alloy::sol_types::SolEvent;
+        let router = address!("E592427A0AEce92De3Edee1F18E0157C05861564");
+
+        // USDC/WETH 0.05%: 2,500 USDC sold for 1 WETH at 2,500 USDC per WETH.
+        let mut v3 = UniswapV3::new(address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"), 6, 18);
+        let swap = abi::v3::Swap {
+            sender: router,
+            recipient: router,
+            amount0: I256::try_from(2_500_000_000i64).unwrap(),
+            amount1: -I256::try_from(10u64.pow(18)).unwrap(),
+            sqrtPriceX96: alloy::primitives::U160::from(20_000u64) << 96,
+            liquidity: 10u128.pow(18),
+            tick: alloy::primitives::aliases::I24::try_from(198_080i32).unwrap(),
+        };
+        assert_eq!(abi::v3::Swap::SIGNATURE_HASH, v3.get_event_signatures()[0]);
+        let decoded = v3.parse_swap_event_data(&encoded_log(v3.get_contract_address(), swap.encode_log_data())).unwrap();
+        assert_eq!(decoded.amount0, swap.amount0);
+        assert_eq!(decoded.amount1, swap.amount1);
+        assert_eq!(decoded.direction, Some(SwapDirection::ZeroForOne));
+        assert_eq!((decoded.sender, decoded.recipient), (router, router));
+        assert_eq!(v3.current_tick(), 198_080);
+        assert!((decoded.price - 0.0004).abs() < 1e-9);
+
+        // USDC/WETH V2: 1 WETH sold for 2,500 USDC, after the Sync emitted in the same transaction.
+        let mut v2 = UniswapV2::new(address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"), 6, 18);
+        let address = v2.get_contract_address();
+        let sync = abi::v2::Sync {
+            reserve0: alloy::primitives::Uint::from(25_000_000_000_000u64),
+            reserve1: alloy::primitives::Uint::from(10_000u64) * alloy::primitives::Uint::from(10u64.pow(18)),
+        };
+        assert_eq!(abi::v2::Sync::SIGNATURE_HASH, v2.get_event_signatures()[1]);
+        v2.parse_swap_event_data(&encoded_log(address, sync.encode_log_data())).unwrap();
+        let swap = abi::v2::Swap {
+            sender: router,
+            amount0In: U256::ZERO,
+            amount1In: U256::from(10u64.pow(18)),
+            amount0Out: U256::from(2_500_000_000u64),
+            amount1Out: U256::ZERO,
+            to: router,
+        };
+        assert_eq!(abi::v2::Swap::SIGNATURE_HASH, v2.get_event_signatures()[0]);
+        let decoded = v2.parse_swap_event_data(&encoded_log(address, swap.encode_log_data())).unwrap();
+        assert_eq!(decoded.amount0, -I256::try_from(2_500_000_000i64).unwrap());
+        assert_eq!(decoded.amount1, I256::try_from(10u64.pow(18)).unwrap());
+        assert_eq!(decoded.direction, Some(SwapDirection::OneForZero));
+        assert_eq!((decoded.sender, decoded.recipient), (router, router));
+        assert!((decoded.price - 0.0004).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_uniswap_v3_mint_and_burn_update_tick_liquidity() {
+        assert_eq!(V3_MINT_TOPIC, alloy::primitives::keccak256("Mint(address,address,int24,int24,uint128,uint256,uint256)"));
+        assert_eq!(V3_BURN_TOPIC, alloy::primitives::keccak256("Burn(address,int24,int24,uint128,uint256,uint256)"));
+
+        let mut pool = UniswapV3::new(Address::ZERO, 18, 6);
+        let owner = Address::repeat_byte(7);
+        let tick_topic = |tick: i64| B256::from(I256::try_from(tick).unwrap().to_be_bytes::<32>());
+        let log = |topic: B256, lower: i64, upper: i64, words: &[U256]| EthereumLog {
+            address: Address::ZERO,
+            topics: vec![topic, owner.into_word(), tick_topic(lower), tick_topic(upper)],
+            data: words.iter().flat_map(|w| w.to_be_bytes::<32>()).collect(),
+            block_number: None,
+            transaction_hash: None,
+            log_index: None,
+        };
+        let sender = U256::from_be_slice(owner.as_slice());
+        let (amount, token) = (U256::from(1_000u64), U256::from(5u64));
+
+        // In range (current tick 0) and out of range.
+        pool.parse_swap_event_data(&log(V3_MINT_TOPIC, -60, 60, &[sender, amount, token, token])).unwrap();
+        pool.parse_swap_event_data(&log(V3_MINT_TOPIC, 60, 120, &[sender, amount, token, token])).unwrap();
+        assert_eq!(pool.get_liquidity(), 1_000);
+        assert_eq!(pool.tick_liquidity().liquidity_net(-60), 1_000);
+        assert_eq!(pool.tick_liquidity().liquidity_net(60), 0);
+        assert_eq!(pool.tick_liquidity().liquidity_net(120), -1_000);
+
+        let burn = pool.parse_swap_event_data(&log(V3_BURN_TOPIC, -60, 60, &[amount, token, token])).unwrap();
+        assert_eq!(burn.direction, None);
+        assert!(burn.amount0.is_negative());
+        assert_eq!(pool.get_liquidity(), 0);
+        assert_eq!(pool.tick_liquidity().ticks_above(0).collect::<Vec<_>>(), vec![(60, 1_000), (120, -1_000)]);
+
+        let mut restored = UniswapV3::new(Address::ZERO, 18, 6);
+        restored.restore_state(pool.export_state()).unwrap();
+        assert_eq!(restored.export_state(), pool.export_state());
+        assert_eq!(restored.tick_liquidity().liquidity_net(120), -1_000);
+        let v2 = UniswapV2::new(Address::ZERO, 18, 6).export_state();
+        assert!(restored.restore_state(v2).is_err());
+    }
+
+    #[test]
+    fn test_uniswap_v2_swap_nets_in_and_out_amounts() {
+        let mut pool = UniswapV2::new(Address::ZERO, 18, 6);
+        let words = [U256::from(10u64).pow(U256::from(18)), U256::ZERO, U256::ZERO, U256::from(2_000_000_000u64)];
+        let log = EthereumLog {
+            address: Address::ZERO,
+            topics: vec![pool.get_event_signatures()[0], B256::ZERO, B256::ZERO],
+            data: words.iter().flat_map(|w| w.to_be_bytes::<32>()).collect(),
+            block_number: None,
+            transaction_hash: None,
+            log_index: None,
+        };
+        let swap = pool.parse_swap_event_data(&log).unwrap();
+        assert_eq!(swap.direction, Some(SwapDirection::ZeroForOne));
+        assert_eq!(swap.amount1, -I256::try_from(2_000_000_000i64).unwrap());
+    }
+
+    #[test]
+    fn test_uniswap_v2_quote_exact_input() {
+        let mut pool = UniswapV2::new(Address::ZERO, 18, 6);
+        // 1000 WETH / 2,000,000 USDC.
+        pool.apply_initial_state(
+            [U256::from(1_000u64) * U256::from(10u64).pow(U256::from(18)), U256::from(2_000_000_000_000u64)]
+                .iter()
+                .flat_map(|w| w.to_be_bytes::<32>())
+                .collect(),
+        )
+        .unwrap();
+        let quote = pool
+            .quote_exact_input(SwapDirection::ZeroForOne, U256::from(10u64) * U256::from(10u64).pow(U256::from(18)))
+            .unwrap();
+        // 10 * 0.997 * 2e6 / (1000 + 9.97) ≈ 19743.160687 USDC.
+        assert_eq!(quote.amount_out, U256::from(19_743_160_687u64));
+        // 0.3% fee + ~0.99% price movement.
+        assert_eq!(quote.price_impact_bps, 128);
+        assert!((quote.effective_price.to_f64() - 1974.3160687).abs() < 1e-6);
+    }
+
     #[test]
     fn test_uniswap_v3_price_calculation() {
         let pool = UniswapV3::new(
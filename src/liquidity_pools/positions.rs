@@ -0,0 +1,135 @@
+//! Value of a hypothetical LP position: impermanent loss against holding, and fee-adjusted PnL.
+//! Prices are token0 in token1 units (`PoolPrice::token0_price`), values are in token1.
+
+use super::v3_math::FEE_DENOMINATOR;
+
+/// Price range a position provides liquidity over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionRange {
+    /// `0..∞`, like every V2 position.
+    Full,
+    /// V3 tick range `[lower, upper)`.
+    Ticks { lower: i32, upper: i32 },
+}
+
+/// A position opened at `entry_price` with `deposit` worth of token1 (both tokens in the ratio the
+/// range requires at that price).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LpPosition {
+    pub entry_price: f64,
+    pub range: PositionRange,
+    pub deposit: f64,
+}
+
+/// Position value at a given price. All values in token1 units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionValue {
+    /// Tokens the position holds at the current price.
+    pub amount0: f64,
+    pub amount1: f64,
+    pub lp_value: f64,
+    /// Value of the tokens deposited at entry, had they been held instead.
+    pub hodl_value: f64,
+    /// `lp_value / hodl_value - 1`; zero or negative.
+    pub impermanent_loss: f64,
+    pub fees: f64,
+    /// `lp_value + fees - deposit`.
+    pub pnl: f64,
+    pub in_range: bool,
+}
+
+/// Price of `tick` in token units (`1.0001^tick`, adjusted for decimals).
+pub fn tick_to_price(tick: i32, token0_decimals: u8, token1_decimals: u8) -> f64 {
+    1.0001f64.powi(tick) * 10f64.powi(i32::from(token0_decimals) - i32::from(token1_decimals))
+}
+
+impl LpPosition {
+    /// sqrt of the range bounds in token units; `(0, ∞)` for a full-range position.
+    fn sqrt_bounds(&self, token0_decimals: u8, token1_decimals: u8) -> (f64, f64) {
+        match self.range {
+            PositionRange::Full => (0.0, f64::INFINITY),
+            PositionRange::Ticks { lower, upper } => (
+                tick_to_price(lower, token0_decimals, token1_decimals).sqrt(),
+                tick_to_price(upper, token0_decimals, token1_decimals).sqrt(),
+            ),
+        }
+    }
+
+    /// Token amounts per unit of liquidity at `price` (`x = L(1/√P − 1/√Pb)`, `y = L(√P − √Pa)`,
+    /// with √P clamped to the range).
+    fn amounts_per_liquidity(price: f64, (sqrt_lower, sqrt_upper): (f64, f64)) -> (f64, f64) {
+        let sqrt_price = price.sqrt().clamp(sqrt_lower, sqrt_upper);
+        (1.0 / sqrt_price - 1.0 / sqrt_upper, sqrt_price - sqrt_lower)
+    }
+
+    /// Liquidity `L` of the position, in `sqrt(token0 * token1)` token units.
+    pub fn liquidity(&self, token0_decimals: u8, token1_decimals: u8) -> f64 {
+        let (x, y) = Self::amounts_per_liquidity(self.entry_price, self.sqrt_bounds(token0_decimals, token1_decimals));
+        self.deposit / (x * self.entry_price + y)
+    }
+
+    /// Value at `price`, with `fees` (token1 units) earned so far.
+    pub fn value(&self, price: f64, token0_decimals: u8, token1_decimals: u8, fees: f64) -> PositionValue {
+        let bounds = self.sqrt_bounds(token0_decimals, token1_decimals);
+        let liquidity = self.liquidity(token0_decimals, token1_decimals);
+        let (entry0, entry1) = Self::amounts_per_liquidity(self.entry_price, bounds);
+        let (now0, now1) = Self::amounts_per_liquidity(price, bounds);
+        let (amount0, amount1) = (now0 * liquidity, now1 * liquidity);
+        let lp_value = amount0 * price + amount1;
+        let hodl_value = (entry0 * price + entry1) * liquidity;
+        PositionValue {
+            amount0,
+            amount1,
+            lp_value,
+            hodl_value,
+            impermanent_loss: lp_value / hodl_value - 1.0,
+            fees,
+            pnl: lp_value + fees - self.deposit,
+            in_range: (bounds.0..bounds.1).contains(&price.sqrt()),
+        }
+    }
+}
+
+/// Fees a position with `liquidity` would earn from `volume1` (token1 units, both directions) traded
+/// through a pool with `pool_liquidity` in range (same units), at `fee` (hundredths of a bip). Assumes
+/// the position was in range the whole time and adds it to the pool's liquidity.
+pub fn estimate_fees(volume1: f64, fee: u32, liquidity: f64, pool_liquidity: f64) -> f64 {
+    if liquidity <= 0.0 {
+        return 0.0;
+    }
+    volume1 * f64::from(fee) / f64::from(FEE_DENOMINATOR) * liquidity / (liquidity + pool_liquidity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impermanent_loss() {
+        // Full range, price doubles: the textbook 2√2/3 − 1 ≈ −5.72%.
+        let position = LpPosition {
+            entry_price: 2_000.0,
+            range: PositionRange::Full,
+            deposit: 10_000.0,
+        };
+        let value = position.value(4_000.0, 18, 6, 100.0);
+        assert!((value.impermanent_loss - (2.0 * 2f64.sqrt() / 3.0 - 1.0)).abs() < 1e-12);
+        assert!((value.hodl_value - 15_000.0).abs() < 1e-6);
+        assert!((value.pnl - (value.lp_value + 100.0 - 10_000.0)).abs() < 1e-9);
+
+        // A concentrated range around the entry loses more on the same move, and ends all in token1.
+        let ticks = |price: f64| ((price * 1e-12).ln() / 1.0001f64.ln()) as i32;
+        let concentrated = LpPosition {
+            range: PositionRange::Ticks {
+                lower: ticks(1_500.0),
+                upper: ticks(2_500.0),
+            },
+            ..position
+        };
+        let value = concentrated.value(4_000.0, 18, 6, 0.0);
+        assert!(!value.in_range);
+        assert!(value.amount0.abs() < 1e-9);
+        assert!(value.impermanent_loss < 2.0 * 2f64.sqrt() / 3.0 - 1.0);
+        assert!(concentrated.value(2_000.0, 18, 6, 0.0).impermanent_loss.abs() < 1e-9);
+    }
+}
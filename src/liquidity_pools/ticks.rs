@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Liquidity distribution of a V3 pool: the net liquidity change (`liquidityNet`) at every
+/// initialized tick, built from Mint/Burn events. Crossing a tick upwards adds its net liquidity
+/// to the in-range liquidity, crossing it downwards subtracts it.
+///
+/// Only positions minted or burned while the pool is being watched are known; liquidity that
+/// existed before the subscription started is reflected in the in-range liquidity but not here.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickLiquidity {
+    net: BTreeMap<i32, i128>,
+}
+
+impl TickLiquidity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add (Mint, positive `delta`) or remove (Burn, negative `delta`) liquidity over
+    /// `[tick_lower, tick_upper)`. Ticks whose net liquidity drops back to zero are uninitialized.
+    pub fn update(&mut self, tick_lower: i32, tick_upper: i32, delta: i128) {
+        if delta == 0 || tick_lower >= tick_upper {
+            return;
+        }
+        self.add(tick_lower, delta);
+        self.add(tick_upper, -delta);
+    }
+
+    fn add(&mut self, tick: i32, delta: i128) {
+        let net = self.net.entry(tick).or_default();
+        *net = net.saturating_add(delta);
+        if *net == 0 {
+            self.net.remove(&tick);
+        }
+    }
+
+    /// Net liquidity change at `tick` (zero for uninitialized ticks).
+    pub fn liquidity_net(&self, tick: i32) -> i128 {
+        self.net.get(&tick).copied().unwrap_or_default()
+    }
+
+    /// Initialized ticks above `tick`, in ascending order (the ticks a price increase crosses).
+    pub fn ticks_above(&self, tick: i32) -> impl Iterator<Item = (i32, i128)> + '_ {
+        self.net.range(tick.saturating_add(1)..).map(|(t, n)| (*t, *n))
+    }
+
+    /// Initialized ticks at or below `tick`, in descending order (the ticks a price decrease crosses).
+    pub fn ticks_at_or_below(&self, tick: i32) -> impl Iterator<Item = (i32, i128)> + '_ {
+        self.net.range(..=tick).rev().map(|(t, n)| (*t, *n))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.net.is_empty()
+    }
+
+    /// Number of initialized ticks.
+    pub fn len(&self) -> usize {
+        self.net.len()
+    }
+}
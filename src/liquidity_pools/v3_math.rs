@@ -0,0 +1,269 @@
+//! Uniswap V3 swap math (`TickMath`, `SqrtPriceMath`, `SwapMath`), ported to `U256` with
+//! 512-bit intermediates so results match the contracts to the wei.
+
+use super::TickLiquidity;
+use alloy::primitives::ruint::UintTryFrom;
+use alloy::primitives::{U256, U512};
+
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = 887272;
+/// `sqrt_ratio_at_tick(MIN_TICK)`.
+pub const MIN_SQRT_RATIO: U256 = U256::from_limbs([4295128739, 0, 0, 0]);
+/// `sqrt_ratio_at_tick(MAX_TICK)`.
+pub const MAX_SQRT_RATIO: U256 =
+    U256::from_limbs([0x5d951d5263988d26, 0xefd1fc6a50648849, 0xfffd8963, 0]);
+
+/// Fee denominator: V3 fees are in hundredths of a basis point (`3000` = 0.3%).
+pub const FEE_DENOMINATOR: u32 = 1_000_000;
+
+const RESOLUTION: usize = 96;
+
+/// `1.0001^(tick / 2)` as a Q64.96, exactly as `TickMath.getSqrtRatioAtTick`.
+pub fn sqrt_ratio_at_tick(tick: i32) -> U256 {
+    const FACTORS: [u128; 19] = [
+        0xfff97272373d413259a46990580e213a,
+        0xfff2e50f5f656932ef12357cf3c7fdcc,
+        0xffe5caca7e10e4e61c3624eaa0941cd0,
+        0xffcb9843d60f6159c9db58835c926644,
+        0xff973b41fa98c081472e6896dfb254c0,
+        0xff2ea16466c96a3843ec78b326b52861,
+        0xfe5dee046a99a2a811c461f1969c3053,
+        0xfcbe86c7900a88aedcffc83b479aa3a4,
+        0xf987a7253ac413176f2b074cf7815e54,
+        0xf3392b0822b70005940c7a398e4b70f3,
+        0xe7159475a2c29b7443b29c7fa6e889d9,
+        0xd097f3bdfd2022b8845ad8f792aa5825,
+        0xa9f746462d870fdf8a65dc1f90e061e5,
+        0x70d869a156d2a1b890bb3df62baf32f7,
+        0x31be135f97d08fd981231505542fcfa6,
+        0x9aa508b5b7a84e1c677de54f3e99bc9,
+        0x5d6af8dedb81196699c329225ee604,
+        0x2216e584f5fa1ea926041bedfe98,
+        0x48a170391f7dc42444e8fa2,
+    ];
+    let abs_tick = tick.clamp(MIN_TICK, MAX_TICK).unsigned_abs();
+    let mut ratio = if abs_tick & 1 != 0 {
+        U256::from(0xfffcb933bd6fad37aa2d162d1a594001u128)
+    } else {
+        U256::from(1u8) << 128
+    };
+    for (bit, factor) in FACTORS.iter().enumerate() {
+        if abs_tick & (2 << bit) != 0 {
+            ratio = (ratio * U256::from(*factor)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+    let round_up = !(ratio & U256::from(u32::MAX)).is_zero();
+    (ratio >> 32) + U256::from(round_up as u8)
+}
+
+fn mul_div(a: U256, b: U256, denominator: U256, round_up: bool) -> U256 {
+    let product = U512::from(a) * U512::from(b);
+    let denominator = U512::from(denominator);
+    let (mut quotient, remainder) = product.div_rem(denominator);
+    if round_up && !remainder.is_zero() {
+        quotient += U512::from(1u8);
+    }
+    U256::uint_try_from(quotient).unwrap_or(U256::MAX)
+}
+
+fn div_round_up(a: U256, b: U256) -> U256 {
+    let (quotient, remainder) = a.div_rem(b);
+    quotient + U256::from(!remainder.is_zero() as u8)
+}
+
+/// token0 needed to move between two sqrt prices: `L * (sqrtB - sqrtA) / (sqrtA * sqrtB)`.
+fn amount0_delta(sqrt_a: U256, sqrt_b: U256, liquidity: u128, round_up: bool) -> U256 {
+    let (lower, upper) = if sqrt_a < sqrt_b { (sqrt_a, sqrt_b) } else { (sqrt_b, sqrt_a) };
+    if lower.is_zero() {
+        return U256::ZERO;
+    }
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+    let numerator2 = upper - lower;
+    if round_up {
+        div_round_up(mul_div(numerator1, numerator2, upper, true), lower)
+    } else {
+        mul_div(numerator1, numerator2, upper, false) / lower
+    }
+}
+
+/// token1 needed to move between two sqrt prices: `L * (sqrtB - sqrtA)`.
+fn amount1_delta(sqrt_a: U256, sqrt_b: U256, liquidity: u128, round_up: bool) -> U256 {
+    let (lower, upper) = if sqrt_a < sqrt_b { (sqrt_a, sqrt_b) } else { (sqrt_b, sqrt_a) };
+    mul_div(U256::from(liquidity), upper - lower, U256::from(1u8) << RESOLUTION, round_up)
+}
+
+/// Sqrt price after adding `amount_in` of the input token at constant liquidity.
+fn next_sqrt_price_from_input(sqrt_price: U256, liquidity: u128, amount_in: U256, zero_for_one: bool) -> U256 {
+    let liquidity_q96 = U256::from(liquidity) << RESOLUTION;
+    if zero_for_one {
+        // L * sqrtP / (L + amount * sqrtP), rounded up so the price never overshoots.
+        let product = U512::from(amount_in) * U512::from(sqrt_price);
+        let denominator = U512::from(liquidity_q96) + product;
+        let numerator = U512::from(liquidity_q96) * U512::from(sqrt_price);
+        let (quotient, remainder) = numerator.div_rem(denominator);
+        let quotient = quotient + U512::from(!remainder.is_zero() as u8);
+        U256::uint_try_from(quotient).unwrap_or(U256::MAX)
+    } else {
+        sqrt_price.saturating_add(mul_div(amount_in, U256::from(1u8) << RESOLUTION, U256::from(liquidity), false))
+    }
+}
+
+/// One `SwapMath.computeSwapStep` for an exact-input swap: how far `amount_remaining` moves the
+/// price towards `sqrt_target`. Returns (next sqrt price, amount in incl. fee, amount out).
+fn swap_step(
+    sqrt_current: U256,
+    sqrt_target: U256,
+    liquidity: u128,
+    amount_remaining: U256,
+    fee_pips: u32,
+) -> (U256, U256, U256) {
+    let zero_for_one = sqrt_current >= sqrt_target;
+    let fee_complement = U256::from(FEE_DENOMINATOR - fee_pips.min(FEE_DENOMINATOR - 1));
+    let amount_less_fee = mul_div(amount_remaining, fee_complement, U256::from(FEE_DENOMINATOR), false);
+    let amount_to_target = if zero_for_one {
+        amount0_delta(sqrt_target, sqrt_current, liquidity, true)
+    } else {
+        amount1_delta(sqrt_current, sqrt_target, liquidity, true)
+    };
+
+    let sqrt_next = if amount_less_fee >= amount_to_target {
+        sqrt_target
+    } else {
+        next_sqrt_price_from_input(sqrt_current, liquidity, amount_less_fee, zero_for_one)
+    };
+    let reached_target = sqrt_next == sqrt_target;
+    let (amount_in, amount_out) = if zero_for_one {
+        let amount_in = if reached_target { amount_to_target } else { amount0_delta(sqrt_next, sqrt_current, liquidity, true) };
+        (amount_in, amount1_delta(sqrt_next, sqrt_current, liquidity, false))
+    } else {
+        let amount_in = if reached_target { amount_to_target } else { amount1_delta(sqrt_current, sqrt_next, liquidity, true) };
+        (amount_in, amount0_delta(sqrt_current, sqrt_next, liquidity, false))
+    };
+    let amount_with_fee = if reached_target {
+        amount_in + mul_div(amount_in, U256::from(fee_pips), fee_complement, true)
+    } else {
+        // The whole remainder is spent; whatever isn't swapped is the fee.
+        amount_remaining
+    };
+    (sqrt_next, amount_with_fee, amount_out)
+}
+
+/// Exact-input swap across initialized ticks, like `UniswapV3Pool.swap` without state changes.
+/// Liquidity beyond the last known tick is assumed to continue unchanged up to the price limit.
+/// Returns the amount out, the amount of input that could not be filled, and whether part of the input
+/// was swapped past the last known tick (where that assumption is a guess).
+pub(crate) fn simulate_exact_input(
+    sqrt_price: U256,
+    tick: i32,
+    mut liquidity: u128,
+    ticks: &TickLiquidity,
+    fee_pips: u32,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> (U256, U256, bool) {
+    let boundaries: Vec<(i32, i128)> = if zero_for_one {
+        ticks.ticks_at_or_below(tick).collect()
+    } else {
+        ticks.ticks_above(tick).collect()
+    };
+    let limit = if zero_for_one { MIN_SQRT_RATIO + U256::from(1u8) } else { MAX_SQRT_RATIO - U256::from(1u8) };
+
+    let mut sqrt_current = sqrt_price;
+    let mut remaining = amount_in;
+    let mut amount_out = U256::ZERO;
+    let mut beyond_known = false;
+    let mut next = boundaries.into_iter();
+    while !remaining.is_zero() && sqrt_current != limit {
+        let boundary = next.next();
+        let sqrt_target = match boundary {
+            Some((t, _)) if zero_for_one => sqrt_ratio_at_tick(t).max(limit),
+            Some((t, _)) => sqrt_ratio_at_tick(t).min(limit),
+            None => limit,
+        };
+        if liquidity > 0 {
+            let (sqrt_next, spent, out) = swap_step(sqrt_current, sqrt_target, liquidity, remaining, fee_pips);
+            beyond_known |= boundary.is_none() && !spent.is_zero();
+            remaining -= spent.min(remaining);
+            amount_out += out;
+            sqrt_current = sqrt_next;
+            if sqrt_next != sqrt_target {
+                break;
+            }
+        } else {
+            sqrt_current = sqrt_target;
+        }
+        match boundary {
+            // Crossing downwards removes the tick's net liquidity, crossing upwards adds it.
+            Some((_, net)) => {
+                let net = if zero_for_one { -net } else { net };
+                liquidity = liquidity.saturating_add_signed(net);
+            }
+            None => break,
+        }
+    }
+    (amount_out, remaining, beyond_known)
+}
+
+/// Exact-input swap against a V2 constant-product pool with a fee in hundredths of a basis point
+/// (`getAmountOut` generalized from the hardcoded 0.3%).
+pub fn constant_product_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee_pips: u32) -> U256 {
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return U256::ZERO;
+    }
+    let fee_complement = U256::from(FEE_DENOMINATOR - fee_pips.min(FEE_DENOMINATOR));
+    let amount_in_with_fee = U512::from(amount_in) * U512::from(fee_complement);
+    let numerator = amount_in_with_fee * U512::from(reserve_out);
+    let denominator = U512::from(reserve_in) * U512::from(FEE_DENOMINATOR) + amount_in_with_fee;
+    U256::uint_try_from(numerator / denominator).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqrt_ratio_at_tick() {
+        assert_eq!(sqrt_ratio_at_tick(0), U256::from(1u8) << 96);
+        assert_eq!(sqrt_ratio_at_tick(MIN_TICK), MIN_SQRT_RATIO);
+        assert_eq!(sqrt_ratio_at_tick(MAX_TICK), MAX_SQRT_RATIO);
+        for bit in 0..20 {
+            for tick in [1i32 << bit, -(1i32 << bit)] {
+                let expected = 1.0001f64.powf(tick as f64 / 2.0);
+                let actual = f64::from(sqrt_ratio_at_tick(tick)) / 2f64.powi(96);
+                assert!(((actual - expected) / expected).abs() < 1e-9, "tick {}", tick);
+            }
+        }
+    }
+
+    #[test]
+    fn test_swap_within_one_range_matches_constant_product() {
+        // Full-range-like liquidity with no initialized ticks: behaves like x * y = k with reserves
+        // x = L / sqrtP, y = L * sqrtP.
+        let liquidity = 10u128.pow(24);
+        let sqrt_price = U256::from(1u8) << 96;
+        let amount_in = U256::from(10u64).pow(U256::from(21u8));
+        let (out, unfilled, beyond_known) =
+            simulate_exact_input(sqrt_price, 0, liquidity, &TickLiquidity::new(), 3000, amount_in, true);
+        let expected = constant_product_amount_out(amount_in, U256::from(liquidity), U256::from(liquidity), 3000);
+        assert!(unfilled.is_zero());
+        // No tick is known, so the whole swap relies on the liquidity continuing unchanged.
+        assert!(beyond_known);
+        assert!(out.abs_diff(expected) <= U256::from(1u8));
+    }
+
+    #[test]
+    fn test_swap_stops_when_crossing_out_of_liquidity() {
+        let mut ticks = TickLiquidity::new();
+        ticks.update(-60, 60, 1_000_000_000_000);
+        let (out, unfilled, beyond_known) =
+            simulate_exact_input(U256::from(1u8) << 96, 0, 1_000_000_000_000, &ticks, 500, U256::MAX >> 128, true);
+        assert!(!unfilled.is_zero());
+        assert!(!beyond_known);
+        // Everything between tick 0 and -60: L * (1 - 1.0001^-30).
+        let expected = 1e12 * (1.0 - 1.0001f64.powf(-30.0));
+        assert!((f64::from(out) - expected).abs() / expected < 1e-6);
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// A moving average over the last `n` price updates of a pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MovingAverage {
+    /// Simple (arithmetic) mean of the last `n` updates.
+    Sma(usize),
+    /// Exponential average with smoothing `2 / (n + 1)`, seeded with the first price.
+    Ema(usize),
+}
+
+/// Value of one configured moving average after an update.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MovingAverageValue {
+    pub average: MovingAverage,
+    pub token0_price: f64,
+    pub token1_price: f64,
+}
+
+#[derive(Debug, Clone)]
+enum AverageState {
+    Sma {
+        period: usize,
+        window: VecDeque<(f64, f64)>,
+        sum: (f64, f64),
+    },
+    Ema {
+        alpha: f64,
+        value: Option<(f64, f64)>,
+    },
+}
+
+/// Running state of one moving average.
+#[derive(Debug, Clone)]
+pub(crate) struct AverageTracker {
+    average: MovingAverage,
+    state: AverageState,
+}
+
+impl AverageTracker {
+    pub(crate) fn new(average: MovingAverage) -> Self {
+        let state = match average {
+            MovingAverage::Sma(n) => AverageState::Sma {
+                period: n.max(1),
+                window: VecDeque::new(),
+                sum: (0.0, 0.0),
+            },
+            MovingAverage::Ema(n) => AverageState::Ema {
+                alpha: 2.0 / (n.max(1) as f64 + 1.0),
+                value: None,
+            },
+        };
+        Self { average, state }
+    }
+
+    pub(crate) fn record(&mut self, token0_price: f64, token1_price: f64) -> MovingAverageValue {
+        match &mut self.state {
+            AverageState::Sma { period, window, sum } => {
+                window.push_back((token0_price, token1_price));
+                sum.0 += token0_price;
+                sum.1 += token1_price;
+                if window.len() > *period
+                    && let Some((old0, old1)) = window.pop_front()
+                {
+                    sum.0 -= old0;
+                    sum.1 -= old1;
+                }
+            }
+            AverageState::Ema { alpha, value } => {
+                let next = value.map_or((token0_price, token1_price), |(ema0, ema1)| {
+                    (ema0 + *alpha * (token0_price - ema0), ema1 + *alpha * (token1_price - ema1))
+                });
+                *value = Some(next);
+            }
+        }
+        self.value().expect("a price was just recorded")
+    }
+
+    /// The average as of the last `record`, without adding a price; `None` before the first.
+    pub(crate) fn value(&self) -> Option<MovingAverageValue> {
+        let (token0_price, token1_price) = match &self.state {
+            AverageState::Sma { window, sum, .. } if !window.is_empty() => {
+                let len = window.len() as f64;
+                (sum.0 / len, sum.1 / len)
+            }
+            AverageState::Sma { .. } => return None,
+            AverageState::Ema { value, .. } => (*value)?,
+        };
+        Some(MovingAverageValue {
+            average: self.average,
+            token0_price,
+            token1_price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sma_and_ema() {
+        let mut sma = AverageTracker::new(MovingAverage::Sma(2));
+        let mut ema = AverageTracker::new(MovingAverage::Ema(3));
+        let mut last = (None, None);
+        for price in [10.0, 20.0, 40.0] {
+            last = (Some(sma.record(price, 1.0 / price)), Some(ema.record(price, 1.0 / price)));
+        }
+        assert_eq!(last.0.unwrap().token0_price, 30.0);
+        // alpha = 0.5: 10 -> 15 -> 27.5
+        assert_eq!(last.1.unwrap().token0_price, 27.5);
+    }
+}
@@ -0,0 +1,101 @@
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+
+/// OHLCV candle of a pool's token0 price. Volumes are in token units (decimal adjusted) and count swaps
+/// only; prices also move on V2 `Sync` and V3 `Mint` / `Burn` updates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub pool_address: Address,
+    pub interval_secs: u64,
+    /// Start of the interval (unix seconds, a multiple of `interval_secs`).
+    pub open_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume0: f64,
+    pub volume1: f64,
+    pub trades: u32,
+}
+
+/// The candle currently being built for one interval.
+#[derive(Debug, Clone)]
+pub(crate) struct CandleBuilder {
+    interval_secs: u64,
+    current: Option<Candle>,
+}
+
+impl CandleBuilder {
+    pub(crate) fn new(interval_secs: u64) -> Self {
+        Self {
+            interval_secs: interval_secs.max(1),
+            current: None,
+        }
+    }
+
+    /// Add a price update (with its swap volume, if it was a swap). Returns the previous candle when
+    /// the update falls into a new interval.
+    pub(crate) fn record(
+        &mut self,
+        pool_address: Address,
+        timestamp: u64,
+        price: f64,
+        trade: Option<(f64, f64)>,
+    ) -> Option<Candle> {
+        let open_time = timestamp - timestamp % self.interval_secs;
+        let completed = match &self.current {
+            Some(candle) if candle.open_time != open_time => self.current.take(),
+            _ => None,
+        };
+        let candle = self.current.get_or_insert(Candle {
+            pool_address,
+            interval_secs: self.interval_secs,
+            open_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume0: 0.0,
+            volume1: 0.0,
+            trades: 0,
+        });
+        candle.high = candle.high.max(price);
+        candle.low = candle.low.min(price);
+        candle.close = price;
+        if let Some((volume0, volume1)) = trade {
+            candle.volume0 += volume0;
+            candle.volume1 += volume1;
+            candle.trades += 1;
+        }
+        completed
+    }
+
+    /// Take the current candle if its interval is over at `now`.
+    pub(crate) fn close_if_ended(&mut self, now: u64) -> Option<Candle> {
+        match &self.current {
+            Some(candle) if now >= candle.open_time + self.interval_secs => self.current.take(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candle_aggregation() {
+        let mut builder = CandleBuilder::new(60);
+        assert!(builder.record(Address::ZERO, 120, 10.0, Some((1.0, 10.0))).is_none());
+        assert!(builder.record(Address::ZERO, 130, 12.0, None).is_none());
+        assert!(builder.record(Address::ZERO, 150, 9.0, Some((2.0, 18.0))).is_none());
+
+        let candle = builder.record(Address::ZERO, 185, 11.0, None).unwrap();
+        assert_eq!(candle.open_time, 120);
+        assert_eq!((candle.open, candle.high, candle.low, candle.close), (10.0, 12.0, 9.0, 9.0));
+        assert_eq!((candle.volume0, candle.volume1, candle.trades), (3.0, 28.0, 2));
+
+        assert!(builder.close_if_ended(239).is_none());
+        assert_eq!(builder.close_if_ended(240).unwrap().open, 11.0);
+    }
+}
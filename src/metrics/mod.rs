@@ -0,0 +1,136 @@
+//! Per-pool analytics computed from the live price stream.
+
+use crate::types::PoolPrice;
+use alloy::primitives::Address;
+use std::time::Duration;
+
+mod averages;
+mod candles;
+mod twap;
+mod volatility;
+mod volume;
+
+pub use averages::{MovingAverage, MovingAverageValue};
+pub use candles::Candle;
+pub use twap::Twap;
+pub use volatility::Volatility;
+pub use volume::RollingVolume;
+use averages::AverageTracker;
+use candles::CandleBuilder;
+use twap::TwapTracker;
+use volatility::VolatilityTracker;
+pub(crate) use volume::VolumeTracker;
+
+/// Which metrics the scanner maintains per pool. Set through `ScannerOptions::metrics`.
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    /// TWAP windows, reported in `PoolPrice::twaps` and by `Scanner::twaps`. Empty disables TWAPs.
+    pub twap_windows: Vec<Duration>,
+    /// SMAs / EMAs over the last N price moves, reported in `PoolPrice::moving_averages`. Empty by default.
+    pub moving_averages: Vec<MovingAverage>,
+    /// OHLCV candle intervals (e.g. 1m, 1h). Completed candles are published as `ScannerEvent::Candle`.
+    /// Empty by default.
+    pub candle_intervals: Vec<Duration>,
+    /// Window of the realized volatility reported in `PoolPrice::volatility` and by `Scanner::volatility`.
+    /// `None` (default) disables it.
+    pub volatility_window: Option<Duration>,
+    /// Replay this much history of the tracked pools' logs when the scanner starts, so TWAPs, candles,
+    /// volatility and the 24h volume start warm instead of from the first live update. `None` by default.
+    pub warmup: Option<Duration>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            twap_windows: vec![Duration::from_secs(60), Duration::from_secs(5 * 60), Duration::from_secs(60 * 60)],
+            moving_averages: Vec::new(),
+            candle_intervals: Vec::new(),
+            volatility_window: None,
+            warmup: None,
+        }
+    }
+}
+
+/// Metric state of one pool, updated under the pool's lock on every price update.
+#[derive(Debug, Clone)]
+pub(crate) struct PoolMetrics {
+    pool_address: Address,
+    twap_windows: Vec<u64>,
+    twap: TwapTracker,
+    averages: Vec<AverageTracker>,
+    candles: Vec<CandleBuilder>,
+    volatility: Option<VolatilityTracker>,
+    /// token0 price of the last update, so the averages and volatility only take price moves.
+    last_price: Option<f64>,
+    /// Candles completed by an update, waiting for `take_candles`.
+    completed: Vec<Candle>,
+}
+
+impl PoolMetrics {
+    pub(crate) fn new(config: &MetricsConfig, pool_address: Address) -> Self {
+        let twap_windows: Vec<u64> = config.twap_windows.iter().map(Duration::as_secs).collect();
+        let horizon = twap_windows.iter().copied().max().unwrap_or_default();
+        Self {
+            pool_address,
+            twap_windows,
+            twap: TwapTracker::new(horizon),
+            averages: config.moving_averages.iter().copied().map(AverageTracker::new).collect(),
+            candles: config.candle_intervals.iter().map(|i| CandleBuilder::new(i.as_secs())).collect(),
+            volatility: config.volatility_window.map(|w| VolatilityTracker::new(w.as_secs())),
+            last_price: None,
+            completed: Vec::new(),
+        }
+    }
+
+    /// Record `price` and fill in its metric fields. `trade` is the swap volume (token0, token1) when
+    /// the update came from a swap.
+    pub(crate) fn record(&mut self, price: &mut PoolPrice, trade: Option<(f64, f64)>) {
+        if !self.twap_windows.is_empty() {
+            self.twap.record(price.timestamp, price.token0_price, price.token1_price);
+            price.twaps = self.twaps(price.timestamp);
+        }
+        if price.token0_price.is_finite() && price.token1_price.is_finite() {
+            // A V2 Swap repeats the price of its Sync and V3 Mint / Burn don't move it: counting them again
+            // would weigh the averages towards prices that merely saw more events.
+            let moved = self.last_price.replace(price.token0_price) != Some(price.token0_price);
+            price.moving_averages = if moved {
+                self.averages.iter_mut().map(|average| average.record(price.token0_price, price.token1_price)).collect()
+            } else {
+                self.averages.iter().filter_map(AverageTracker::value).collect()
+            };
+            if let Some(tracker) = &mut self.volatility {
+                tracker.record(price.timestamp, price.token0_price);
+                price.volatility = tracker.volatility();
+            }
+            for builder in &mut self.candles {
+                if let Some(candle) = builder.record(self.pool_address, price.timestamp, price.token0_price, trade) {
+                    self.completed.push(candle);
+                }
+            }
+        }
+    }
+
+    /// Realized volatility over the configured window, if enabled and enough updates were seen.
+    pub(crate) fn volatility(&self) -> Option<Volatility> {
+        self.volatility.as_ref().and_then(VolatilityTracker::volatility)
+    }
+
+    /// Candles completed since the last call, including those whose interval ended by `now`
+    /// without a newer update.
+    pub(crate) fn take_candles(&mut self, now: u64) -> Vec<Candle> {
+        for builder in &mut self.candles {
+            if let Some(candle) = builder.close_if_ended(now) {
+                self.completed.push(candle);
+            }
+        }
+        std::mem::take(&mut self.completed)
+    }
+
+    /// TWAPs over every configured window as of `now`.
+    pub(crate) fn twaps(&self, now: u64) -> Vec<Twap> {
+        self.twap_windows
+            .iter()
+            .filter_map(|window| self.twap.twap(*window, now))
+            .collect()
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Time-weighted average price of a pool over the last `window_secs`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Twap {
+    pub window_secs: u64,
+    pub token0_price: f64,
+    pub token1_price: f64,
+}
+
+/// Price samples of one pool over the longest configured window. Each price holds until the next
+/// sample, so the TWAP is the time integral of that step function divided by the covered time.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TwapTracker {
+    /// (timestamp, token0 price, token1 price), oldest first.
+    samples: VecDeque<(u64, f64, f64)>,
+    /// Longest window; older samples are dropped except the one still in effect at its start.
+    horizon_secs: u64,
+}
+
+impl TwapTracker {
+    pub(crate) fn new(horizon_secs: u64) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            horizon_secs,
+        }
+    }
+
+    pub(crate) fn record(&mut self, timestamp: u64, token0_price: f64, token1_price: f64) {
+        if !(token0_price.is_finite() && token1_price.is_finite()) {
+            return;
+        }
+        // Several updates in the same second: the last one is what held for that second.
+        match self.samples.back_mut() {
+            Some(last) if last.0 >= timestamp => *last = (last.0, token0_price, token1_price),
+            _ => self.samples.push_back((timestamp, token0_price, token1_price)),
+        }
+        let start = timestamp.saturating_sub(self.horizon_secs);
+        while self.samples.len() > 1 && self.samples[1].0 <= start {
+            self.samples.pop_front();
+        }
+    }
+
+    /// TWAP over `[now - window_secs, now]`. When the samples don't reach back that far, averages over
+    /// the time they cover; `None` before the first sample.
+    pub(crate) fn twap(&self, window_secs: u64, now: u64) -> Option<Twap> {
+        let (_, last0, last1) = *self.samples.back()?;
+        let start = now.saturating_sub(window_secs);
+        let (mut sum0, mut sum1, mut covered) = (0.0, 0.0, 0u64);
+        for (i, (t, p0, p1)) in self.samples.iter().enumerate() {
+            let end = self.samples.get(i + 1).map_or(now, |next| next.0).min(now);
+            let from = (*t).max(start);
+            if end > from {
+                let dt = end - from;
+                sum0 += p0 * dt as f64;
+                sum1 += p1 * dt as f64;
+                covered += dt;
+            }
+        }
+        let (token0_price, token1_price) = if covered == 0 {
+            (last0, last1)
+        } else {
+            (sum0 / covered as f64, sum1 / covered as f64)
+        };
+        Some(Twap {
+            window_secs,
+            token0_price,
+            token1_price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_twap_weights_prices_by_time_held() {
+        let mut tracker = TwapTracker::new(60);
+        tracker.record(1_000, 100.0, 0.01);
+        tracker.record(1_030, 200.0, 0.005);
+        // 100 for 30s, 200 for 30s.
+        assert_eq!(tracker.twap(60, 1_060).unwrap().token0_price, 150.0);
+        // Last 10s only saw 200.
+        assert_eq!(tracker.twap(10, 1_060).unwrap().token0_price, 200.0);
+
+        // The 100 sample is still in effect at the start of the 60s window after pruning.
+        tracker.record(1_070, 300.0, 0.0033);
+        assert_eq!(tracker.twap(60, 1_080).unwrap().token0_price, (100.0 * 10.0 + 200.0 * 40.0 + 300.0 * 10.0) / 60.0);
+        assert!(TwapTracker::new(60).twap(60, 0).is_none());
+    }
+}
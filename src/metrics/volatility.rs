@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Realized volatility of a pool's token0 price over the last `window_secs`: the sample standard
+/// deviation of the log returns between consecutive price moves (not annualized).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Volatility {
+    pub window_secs: u64,
+    pub std_dev: f64,
+    /// Log returns in the window. Few returns means a thinly traded pool.
+    pub samples: usize,
+}
+
+/// Log returns of one pool within the volatility window.
+#[derive(Debug, Clone)]
+pub(crate) struct VolatilityTracker {
+    window_secs: u64,
+    last_price: Option<f64>,
+    /// (timestamp, log return), oldest first.
+    returns: VecDeque<(u64, f64)>,
+}
+
+impl VolatilityTracker {
+    pub(crate) fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs,
+            last_price: None,
+            returns: VecDeque::new(),
+        }
+    }
+
+    /// Add the return since the last price. Updates that leave the price where it was (a V2 Swap after its
+    /// Sync, V3 Mint / Burn) add none, so they don't dilute the volatility with zero returns.
+    pub(crate) fn record(&mut self, timestamp: u64, price: f64) {
+        if !(price.is_finite() && price > 0.0) {
+            return;
+        }
+        match self.last_price.replace(price) {
+            Some(last) if last != price => self.returns.push_back((timestamp, (price / last).ln())),
+            _ => {}
+        }
+        let start = timestamp.saturating_sub(self.window_secs);
+        while self.returns.front().is_some_and(|(t, _)| *t < start) {
+            self.returns.pop_front();
+        }
+    }
+
+    /// `None` until the window holds two returns.
+    pub(crate) fn volatility(&self) -> Option<Volatility> {
+        let n = self.returns.len();
+        if n < 2 {
+            return None;
+        }
+        let mean = self.returns.iter().map(|(_, r)| r).sum::<f64>() / n as f64;
+        let variance = self.returns.iter().map(|(_, r)| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        Some(Volatility {
+            window_secs: self.window_secs,
+            std_dev: variance.sqrt(),
+            samples: n,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volatility_of_log_returns() {
+        let mut tracker = VolatilityTracker::new(60);
+        tracker.record(0, 100.0);
+        tracker.record(10, 110.0);
+        assert!(tracker.volatility().is_none());
+        tracker.record(20, 100.0);
+        // Returns ln(1.1) and -ln(1.1): mean 0, sample std-dev ln(1.1) * sqrt(2).
+        let volatility = tracker.volatility().unwrap();
+        assert!((volatility.std_dev - 1.1f64.ln() * 2f64.sqrt()).abs() < 1e-12);
+
+        // The first return (t = 10) falls out of the window.
+        tracker.record(75, 105.0);
+        assert_eq!(tracker.volatility().unwrap().samples, 2);
+    }
+
+    #[test]
+    fn test_unchanged_prices_add_no_return() {
+        let mut tracker = VolatilityTracker::new(60);
+        tracker.record(0, 100.0);
+        // A V2 Sync moves the price and the Swap of the same trade repeats it.
+        tracker.record(10, 110.0);
+        tracker.record(10, 110.0);
+        tracker.record(20, 100.0);
+        assert_eq!(tracker.volatility().unwrap().samples, 2);
+    }
+}
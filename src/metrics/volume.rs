@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Length of the rolling volume window.
+const WINDOW_SECS: u64 = 24 * 60 * 60;
+/// Swaps are summed into buckets of this many seconds; the window slides a bucket at a time.
+const BUCKET_SECS: u64 = 60;
+
+/// Swap volume of a pool over the last 24 hours, from the swaps observed while scanning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RollingVolume {
+    /// token0 traded, in token units (either direction).
+    pub volume0: f64,
+    pub volume1: f64,
+    /// USD value of the swaps that could be valued (see `PoolPrice::token0_price_usd`).
+    pub volume_usd: f64,
+    pub swaps: u64,
+    /// The pool has been watched for the whole window, so the totals cover a full 24 hours.
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: u64,
+    volume0: f64,
+    volume1: f64,
+    volume_usd: f64,
+    swaps: u64,
+}
+
+/// Per-minute swap totals over the last 24 hours.
+#[derive(Debug, Clone)]
+pub(crate) struct VolumeTracker {
+    tracked_since: u64,
+    /// Oldest first.
+    buckets: VecDeque<Bucket>,
+}
+
+impl VolumeTracker {
+    pub(crate) fn new(tracked_since: u64) -> Self {
+        Self {
+            tracked_since,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    /// Count the pool as watched from `since`, for swaps replayed from before tracking started.
+    pub(crate) fn backdate(&mut self, since: u64) {
+        self.tracked_since = self.tracked_since.min(since);
+    }
+
+    pub(crate) fn record(&mut self, timestamp: u64, volume0: f64, volume1: f64, volume_usd: Option<f64>) {
+        let start = timestamp - timestamp % BUCKET_SECS;
+        if self.buckets.back().is_none_or(|bucket| bucket.start != start) {
+            self.buckets.push_back(Bucket {
+                start,
+                volume0: 0.0,
+                volume1: 0.0,
+                volume_usd: 0.0,
+                swaps: 0,
+            });
+        }
+        let bucket = self.buckets.back_mut().expect("bucket pushed above");
+        bucket.volume0 += volume0;
+        bucket.volume1 += volume1;
+        bucket.volume_usd += volume_usd.unwrap_or_default();
+        bucket.swaps += 1;
+        self.evict(timestamp);
+    }
+
+    fn evict(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(WINDOW_SECS);
+        while self.buckets.front().is_some_and(|bucket| bucket.start + BUCKET_SECS <= cutoff) {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Totals of the buckets overlapping the 24 hours before `now`.
+    pub(crate) fn volume(&self, now: u64) -> RollingVolume {
+        let cutoff = now.saturating_sub(WINDOW_SECS);
+        self.buckets
+            .iter()
+            .filter(|bucket| bucket.start + BUCKET_SECS > cutoff)
+            .fold(
+                RollingVolume {
+                    complete: now.saturating_sub(self.tracked_since) >= WINDOW_SECS,
+                    ..Default::default()
+                },
+                |mut total, bucket| {
+                    total.volume0 += bucket.volume0;
+                    total.volume1 += bucket.volume1;
+                    total.volume_usd += bucket.volume_usd;
+                    total.swaps += bucket.swaps;
+                    total
+                },
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_window() {
+        let start = 1_700_000_000;
+        let mut tracker = VolumeTracker::new(start);
+        tracker.record(start + 10, 1.0, 2_000.0, Some(2_000.0));
+        tracker.record(start + 20, 0.5, 1_000.0, None);
+        tracker.record(start + 3_600, 2.0, 4_000.0, Some(4_000.0));
+
+        let volume = tracker.volume(start + 3_600);
+        assert_eq!((volume.volume0, volume.volume1, volume.volume_usd, volume.swaps), (3.5, 7_000.0, 6_000.0, 3));
+        assert!(!volume.complete);
+
+        // A day later the first two swaps have left the window.
+        let volume = tracker.volume(start + WINDOW_SECS + 120);
+        assert_eq!((volume.volume0, volume.swaps), (2.0, 1));
+        assert!(volume.complete);
+
+        let mut warmed = VolumeTracker::new(start + WINDOW_SECS);
+        warmed.backdate(start);
+        assert!(warmed.volume(start + WINDOW_SECS).complete);
+    }
+}
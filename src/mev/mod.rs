@@ -0,0 +1,176 @@
+//! MEV pattern detection over the swaps of a block: sandwiches (front-run → victims → back-run).
+
+use alloy::primitives::{Address, B256, I256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// One swap of a sandwich.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapLeg {
+    pub transaction_hash: Option<B256>,
+    pub log_index: Option<u64>,
+    pub sender: Address,
+    pub recipient: Address,
+    /// Raw token0 delta from the pool's point of view: positive = paid into the pool.
+    pub amount0: I256,
+    pub amount1: I256,
+}
+
+impl SwapLeg {
+    /// token0 paid into the pool.
+    fn zero_for_one(&self) -> bool {
+        self.amount0.is_positive()
+    }
+
+}
+
+/// A front-run and back-run by the same actor around one or more victim swaps, within one block of
+/// one pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sandwich {
+    pub pool_address: Address,
+    pub block_number: u64,
+    /// Recipient shared by the front-run and back-run. The sender isn't used: it is often a router that
+    /// every user of the pool shares.
+    pub attacker: Address,
+    pub front_run: SwapLeg,
+    /// Same-direction swaps between the two attacker legs, in block order.
+    pub victims: Vec<SwapLeg>,
+    pub back_run: SwapLeg,
+    /// The attacker's net token0 from both legs (raw; positive = gained).
+    pub attacker_net0: I256,
+    pub attacker_net1: I256,
+}
+
+/// Swaps of the block currently being received for one pool.
+#[derive(Debug, Default)]
+struct BlockSwaps {
+    block_number: u64,
+    swaps: Vec<SwapLeg>,
+    /// Indexes of swaps already reported as a front-run or back-run.
+    used: Vec<usize>,
+}
+
+/// Per-pool swap sequences of the latest block, checked for sandwiches as each swap arrives.
+#[derive(Debug, Default)]
+pub(crate) struct SandwichDetector {
+    pools: Mutex<HashMap<Address, BlockSwaps>>,
+}
+
+impl SandwichDetector {
+    /// Add a swap (logs of a pool arrive in block order) and return the sandwich it closes, if any: an
+    /// earlier opposite-direction swap of this block, from another transaction but to the same recipient, with
+    /// at least one swap from another transaction and recipient in between in the front-run's direction.
+    pub(crate) fn record(&self, pool: Address, block_number: u64, swap: SwapLeg) -> Option<Sandwich> {
+        let mut pools = self.pools.lock().unwrap();
+        let block = pools.entry(pool).or_default();
+        if block.block_number != block_number {
+            *block = BlockSwaps {
+                block_number,
+                ..Default::default()
+            };
+        }
+
+        let back_index = block.swaps.len();
+        block.swaps.push(swap);
+        let back = &block.swaps[back_index];
+        let found = (0..back_index).rev().find_map(|front_index| {
+            let front = &block.swaps[front_index];
+            if block.used.contains(&front_index)
+                || front.zero_for_one() == back.zero_for_one()
+                || front.transaction_hash == back.transaction_hash
+                || front.recipient.is_zero()
+                || front.recipient != back.recipient
+            {
+                return None;
+            }
+            let attacker = front.recipient;
+            let victims: Vec<SwapLeg> = block.swaps[front_index + 1..back_index]
+                .iter()
+                .filter(|victim| {
+                    victim.zero_for_one() == front.zero_for_one()
+                        && victim.transaction_hash != front.transaction_hash
+                        && victim.transaction_hash != back.transaction_hash
+                        && victim.recipient != attacker
+                })
+                .cloned()
+                .collect();
+            (!victims.is_empty()).then_some((front_index, attacker, victims))
+        });
+
+        let (front_index, attacker, victims) = found?;
+        block.used.extend([front_index, back_index]);
+        let front = block.swaps[front_index].clone();
+        let back = block.swaps[back_index].clone();
+        Some(Sandwich {
+            pool_address: pool,
+            block_number,
+            attacker,
+            attacker_net0: -(front.amount0 + back.amount0),
+            attacker_net1: -(front.amount1 + back.amount1),
+            front_run: front,
+            victims,
+            back_run: back,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(tx: u8, actor: Address, amount0: i64, amount1: i64) -> SwapLeg {
+        SwapLeg {
+            transaction_hash: Some(B256::repeat_byte(tx)),
+            log_index: None,
+            sender: actor,
+            recipient: actor,
+            amount0: I256::try_from(amount0).unwrap(),
+            amount1: I256::try_from(amount1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_detects_sandwich() {
+        let pool = Address::repeat_byte(1);
+        let (bot, user, other) = (Address::repeat_byte(2), Address::repeat_byte(3), Address::repeat_byte(4));
+        let detector = SandwichDetector::default();
+
+        // Bot buys token0 with token1, the user buys too, the bot sells back.
+        assert!(detector.record(pool, 100, leg(1, bot, -10, 20_000)).is_none());
+        assert!(detector.record(pool, 100, leg(2, user, -5, 10_300)).is_none());
+        let sandwich = detector.record(pool, 100, leg(3, bot, 10, -20_500)).unwrap();
+        assert_eq!(sandwich.attacker, bot);
+        assert_eq!(sandwich.victims.len(), 1);
+        assert_eq!(sandwich.attacker_net0, I256::ZERO);
+        assert_eq!(sandwich.attacker_net1, I256::try_from(500).unwrap());
+
+        // A plain round trip with nobody in between is not a sandwich; neither is a new block.
+        assert!(detector.record(pool, 101, leg(4, other, -1, 2_000)).is_none());
+        assert!(detector.record(pool, 101, leg(5, other, 1, -1_990)).is_none());
+    }
+
+    #[test]
+    fn test_ignores_router_and_single_transaction_round_trips() {
+        let pool = Address::repeat_byte(1);
+        let (router, alice, bob, user) =
+            (Address::repeat_byte(2), Address::repeat_byte(3), Address::repeat_byte(4), Address::repeat_byte(5));
+        let detector = SandwichDetector::default();
+        let routed = |tx, recipient, amount0, amount1| SwapLeg {
+            sender: router,
+            recipient,
+            ..leg(tx, recipient, amount0, amount1)
+        };
+
+        // Two unrelated users trading through the same router around a third user.
+        assert!(detector.record(pool, 100, routed(1, alice, -10, 20_000)).is_none());
+        assert!(detector.record(pool, 100, leg(2, user, -5, 10_300)).is_none());
+        assert!(detector.record(pool, 100, routed(3, bob, 10, -20_500)).is_none());
+
+        // Both legs in one transaction (e.g. a multi-hop route) around another swap.
+        assert!(detector.record(pool, 101, leg(4, alice, -10, 20_000)).is_none());
+        assert!(detector.record(pool, 101, leg(5, user, -5, 10_300)).is_none());
+        assert!(detector.record(pool, 101, leg(4, alice, 10, -20_500)).is_none());
+    }
+}
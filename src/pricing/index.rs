@@ -0,0 +1,174 @@
+use super::{is_usd_stablecoin, UsdPricer};
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// What token index prices are expressed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Numeraire {
+    /// USD, through `ScannerOptions::pricing` or stablecoin legs.
+    #[default]
+    Usd,
+    /// Units of this token, e.g. WETH.
+    Token(Address),
+}
+
+/// One price per token across every pool trading it. Set through `ScannerOptions::token_index`.
+#[derive(Debug, Clone, Default)]
+pub struct IndexConfig {
+    pub numeraire: Numeraire,
+}
+
+/// Liquidity-weighted price of a token across its pools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenIndexPrice {
+    pub token: Address,
+    /// Price in the numéraire.
+    pub price: f64,
+    /// `None` for USD.
+    pub numeraire: Option<Address>,
+    /// Pools contributing to `price`.
+    pub pools: usize,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    price: f64,
+    /// The pool's counter-token reserve (within the current range), in the numéraire.
+    weight: f64,
+}
+
+/// Per-token pool prices in the numéraire and their weights.
+#[derive(Debug)]
+pub(crate) struct TokenIndex {
+    numeraire: Numeraire,
+    tokens: Mutex<HashMap<Address, HashMap<Address, IndexEntry>>>,
+    latest: Mutex<HashMap<Address, TokenIndexPrice>>,
+}
+
+impl TokenIndex {
+    pub(crate) fn new(config: IndexConfig) -> Self {
+        Self {
+            numeraire: config.numeraire,
+            tokens: Mutex::new(HashMap::new()),
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Price of `token` (with `symbol`) in the numéraire, when known without any pool of it.
+    fn numeraire_price(&self, token: Address, symbol: &str, pricer: Option<&UsdPricer>) -> Option<f64> {
+        let usd = |token: Address, symbol: &str| {
+            pricer
+                .and_then(|pricer| pricer.token_price_usd(token))
+                .or_else(|| is_usd_stablecoin(symbol).then_some(1.0))
+        };
+        match self.numeraire {
+            Numeraire::Usd => usd(token, symbol),
+            Numeraire::Token(numeraire) if numeraire == token => Some(1.0),
+            Numeraire::Token(numeraire) => Some(usd(token, symbol)? / pricer?.token_price_usd(numeraire)?),
+        }
+    }
+
+    /// Record a pool update for both of its tokens and return their new index prices. Each token is priced
+    /// through the pool as its price in the other token times the other token's numéraire price, and
+    /// weighted by the other token's in-range reserve (`liquidity * sqrt(price)`), valued in the numéraire.
+    /// `liquidity` is in token units. Pools for which `is_tracked` is false are dropped.
+    pub(crate) fn update(
+        &self,
+        pool: &CachedPool,
+        price: &PoolPrice,
+        liquidity: f64,
+        pricer: Option<&UsdPricer>,
+        is_tracked: impl Fn(&Address) -> bool,
+    ) -> Vec<TokenIndexPrice> {
+        let legs = [
+            (pool.token0, price.token0_price, pool.token1, &pool.token1_symbol),
+            (pool.token1, price.token1_price, pool.token0, &pool.token0_symbol),
+        ];
+        let mut tokens = self.tokens.lock().unwrap();
+        let mut updated = Vec::new();
+        for (token, relative, other, other_symbol) in legs {
+            let pools = tokens.entry(token).or_default();
+            let entry = self.numeraire_price(other, other_symbol, pricer).and_then(|other_price| {
+                let entry = IndexEntry {
+                    price: relative * other_price,
+                    weight: liquidity * relative.sqrt() * other_price,
+                };
+                (entry.price.is_finite() && entry.price > 0.0 && entry.weight.is_finite()).then_some(entry)
+            });
+            match entry {
+                Some(entry) => pools.insert(pool.address, entry),
+                None => pools.remove(&pool.address),
+            };
+            pools.retain(|address, _| is_tracked(address));
+
+            let total_weight: f64 = pools.values().map(|entry| entry.weight).sum();
+            let index = if total_weight > 0.0 {
+                pools.values().map(|entry| entry.price * entry.weight).sum::<f64>() / total_weight
+            } else {
+                // No liquidity information: fall back to the plain mean.
+                pools.values().map(|entry| entry.price).sum::<f64>() / pools.len() as f64
+            };
+            if !pools.is_empty() && index.is_finite() {
+                updated.push(TokenIndexPrice {
+                    token,
+                    price: index,
+                    numeraire: match self.numeraire {
+                        Numeraire::Usd => None,
+                        Numeraire::Token(numeraire) => Some(numeraire),
+                    },
+                    pools: pools.len(),
+                    timestamp: price.timestamp,
+                });
+            }
+        }
+        drop(tokens);
+        let mut latest = self.latest.lock().unwrap();
+        for index in &updated {
+            latest.insert(index.token, index.clone());
+        }
+        updated
+    }
+
+    pub(crate) fn get(&self, token: Address) -> Option<TokenIndexPrice> {
+        self.latest.lock().unwrap().get(&token).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_weights_by_reserve() {
+        let (weth, usdc, usdt) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let pool = |address: u8, token1: Address, symbol: &str| CachedPool {
+            address: Address::repeat_byte(address),
+            token0: weth,
+            token0_symbol: "WETH".into(),
+            token1,
+            token1_symbol: symbol.into(),
+            ..Default::default()
+        };
+        let price = |token0_price: f64| PoolPrice {
+            token0_price,
+            token1_price: 1.0 / token0_price,
+            ..Default::default()
+        };
+        let index = TokenIndex::new(IndexConfig::default());
+
+        let first = index.update(&pool(10, usdc, "USDC"), &price(2_000.0), 1_000.0, None, |_| true);
+        // WETH priced through USDC; USDC itself can't be priced (WETH has no USD price without pricing).
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].price, 2_000.0);
+
+        // Same liquidity at a higher price means a larger USD reserve: sqrt(2100/2000) more weight.
+        let second = index.update(&pool(11, usdt, "USDT"), &price(2_100.0), 1_000.0, None, |_| true);
+        let w = (2_100f64 / 2_000.0).sqrt();
+        assert!((second[0].price - (2_000.0 + 2_100.0 * w) / (1.0 + w)).abs() < 1e-9);
+        assert_eq!(second[0].pools, 2);
+    }
+}
@@ -0,0 +1,183 @@
+//! USD prices for every tracked token, derived from designated reference pools, consolidated
+//! prices per token pair and per token, and checks against Chainlink feeds.
+
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::RwLock;
+
+mod index;
+mod oracle;
+mod pairs;
+
+pub(crate) use index::TokenIndex;
+pub use index::{IndexConfig, Numeraire, TokenIndexPrice};
+pub(crate) use oracle::OracleBook;
+pub use oracle::{OracleConfig, OracleDeviation, OraclePrice};
+pub(crate) use pairs::PairBook;
+pub use pairs::{PairPrice, SpreadUpdate};
+
+/// Symbols treated as worth $1 when `PricingConfig::usd_tokens` is empty.
+pub const USD_STABLECOINS: [&str; 9] = ["USDC", "USDT", "DAI", "USDC.E", "USDBC", "BUSD", "FRAX", "LUSD", "PYUSD"];
+
+/// Whether `symbol` is one of `USD_STABLECOINS` (case-insensitive).
+pub fn is_usd_stablecoin(symbol: &str) -> bool {
+    USD_STABLECOINS.contains(&symbol.to_uppercase().as_str())
+}
+
+/// Which pools anchor USD prices. Set through `ScannerOptions::pricing`.
+#[derive(Debug, Clone, Default)]
+pub struct PricingConfig {
+    /// Tracked pools whose prices form the token graph, e.g. WETH/USDC, WBTC/WETH. Keep these
+    /// deep: every USD price is routed through them.
+    pub reference_pools: Vec<Address>,
+    /// Tokens priced at exactly $1. When empty, reference pool tokens with a `USD_STABLECOINS` symbol are used.
+    pub usd_tokens: Vec<Address>,
+}
+
+/// Latest reference pool prices and the USD prices routed through them.
+#[derive(Debug, Default)]
+struct PricerState {
+    /// Reference pool → (token0, token1, token0 price in token1). Ordered so routing is deterministic.
+    edges: BTreeMap<Address, (Address, Address, f64)>,
+    anchors: HashSet<Address>,
+    usd: HashMap<Address, f64>,
+}
+
+/// Converts pool prices to USD. Reference pool updates refresh the token graph; every update gets
+/// `token0_price_usd` / `token1_price_usd` when either of its tokens has a USD price.
+#[derive(Debug)]
+pub struct UsdPricer {
+    reference_pools: HashSet<Address>,
+    /// `usd_tokens` was set, so stablecoins aren't picked by symbol.
+    configured_anchors: bool,
+    state: RwLock<PricerState>,
+}
+
+impl UsdPricer {
+    pub fn new(config: PricingConfig) -> Self {
+        let anchors: HashSet<Address> = config.usd_tokens.into_iter().collect();
+        let usd = route(&BTreeMap::new(), &anchors);
+        Self {
+            reference_pools: config.reference_pools.into_iter().collect(),
+            configured_anchors: !anchors.is_empty(),
+            state: RwLock::new(PricerState {
+                anchors,
+                usd,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Feed a price update of `pool` and fill in its USD prices.
+    pub fn on_price(&self, pool: &CachedPool, price: &mut PoolPrice) {
+        if self.reference_pools.contains(&pool.address) {
+            self.update_reference(pool, price);
+        }
+        let (usd0, usd1) = {
+            let state = self.state.read().unwrap();
+            (state.usd.get(&pool.token0).copied(), state.usd.get(&pool.token1).copied())
+        };
+        // token0_price is token1 per token0, token1_price token0 per token1.
+        let (usd0, usd1) = match (usd0, usd1) {
+            (Some(usd0), Some(usd1)) => (Some(usd0), Some(usd1)),
+            (Some(usd0), None) => (Some(usd0), Some(usd0 * price.token1_price)),
+            (None, Some(usd1)) => (Some(usd1 * price.token0_price), Some(usd1)),
+            (None, None) => (None, None),
+        };
+        price.token0_price_usd = usd0.filter(|p| p.is_finite());
+        price.token1_price_usd = usd1.filter(|p| p.is_finite());
+    }
+
+    /// USD price of `token`, when it is reachable from a USD token through the reference pools.
+    pub fn token_price_usd(&self, token: Address) -> Option<f64> {
+        self.state.read().unwrap().usd.get(&token).copied()
+    }
+
+    fn update_reference(&self, pool: &CachedPool, price: &PoolPrice) {
+        if !(price.token0_price.is_finite() && price.token0_price > 0.0) {
+            return;
+        }
+        let mut state = self.state.write().unwrap();
+        if !self.configured_anchors {
+            for (token, symbol) in [(pool.token0, &pool.token0_symbol), (pool.token1, &pool.token1_symbol)] {
+                if is_usd_stablecoin(symbol) {
+                    state.anchors.insert(token);
+                }
+            }
+        }
+        state
+            .edges
+            .insert(pool.address, (pool.token0, pool.token1, price.token0_price));
+        state.usd = route(&state.edges, &state.anchors);
+    }
+}
+
+/// Breadth-first walk from the USD anchors over the reference pool edges, so every token is priced
+/// through the fewest hops.
+fn route(edges: &BTreeMap<Address, (Address, Address, f64)>, anchors: &HashSet<Address>) -> HashMap<Address, f64> {
+    let mut usd: HashMap<Address, f64> = anchors.iter().map(|token| (*token, 1.0)).collect();
+    loop {
+        let mut discovered = Vec::new();
+        for (token0, token1, token0_price) in edges.values() {
+            match (usd.get(token0), usd.get(token1)) {
+                (Some(usd0), None) => discovered.push((*token1, usd0 / token0_price)),
+                (None, Some(usd1)) => discovered.push((*token0, usd1 * token0_price)),
+                _ => {}
+            }
+        }
+        if discovered.is_empty() {
+            return usd;
+        }
+        for (token, price) in discovered {
+            usd.entry(token).or_insert(price);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(address: u8, token0: (u8, &str), token1: (u8, &str)) -> CachedPool {
+        CachedPool {
+            address: Address::repeat_byte(address),
+            token0: Address::repeat_byte(token0.0),
+            token0_symbol: token0.1.to_string(),
+            token1: Address::repeat_byte(token1.0),
+            token1_symbol: token1.1.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn price(token0_price: f64) -> PoolPrice {
+        PoolPrice {
+            token0_price,
+            token1_price: 1.0 / token0_price,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_routes_usd_prices_through_reference_pools() {
+        let weth_usdc = pool(1, (0xee, "WETH"), (0xcc, "USDC"));
+        let wbtc_weth = pool(2, (0xbb, "WBTC"), (0xee, "WETH"));
+        let pepe_wbtc = pool(3, (0xaa, "PEPE"), (0xbb, "WBTC"));
+        let pricer = UsdPricer::new(PricingConfig {
+            reference_pools: vec![weth_usdc.address, wbtc_weth.address],
+            usd_tokens: Vec::new(),
+        });
+
+        pricer.on_price(&weth_usdc, &mut price(2_000.0));
+        pricer.on_price(&wbtc_weth, &mut price(30.0));
+        assert_eq!(pricer.token_price_usd(weth_usdc.token1), Some(1.0));
+        assert_eq!(pricer.token_price_usd(wbtc_weth.token0), Some(60_000.0));
+
+        // Not a reference pool: priced from its WBTC leg, but doesn't extend the graph.
+        let mut update = price(0.000_000_001);
+        pricer.on_price(&pepe_wbtc, &mut update);
+        assert_eq!(update.token1_price_usd, Some(60_000.0));
+        assert!((update.token0_price_usd.unwrap() - 0.000_06).abs() < 1e-12);
+        assert_eq!(pricer.token_price_usd(pepe_wbtc.token0), None);
+    }
+}
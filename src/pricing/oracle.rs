@@ -0,0 +1,150 @@
+use super::{is_usd_stablecoin, UsdPricer};
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Chainlink USD feeds to compare pool prices against. Set through `ScannerOptions::oracles`.
+#[derive(Debug, Clone)]
+pub struct OracleConfig {
+    /// Token → its `<TOKEN> / USD` aggregator (proxy) address, e.g. WETH → ETH/USD.
+    pub feeds: HashMap<Address, Address>,
+    /// Publish `ScannerEvent::OracleDeviation` when a pool's implied USD price is at least this far
+    /// from the oracle, in basis points.
+    pub deviation_bps: f64,
+    /// How often `latestRoundData()` is read from every feed.
+    pub poll_interval: Duration,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            feeds: HashMap::new(),
+            deviation_bps: 200.0,
+            poll_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Latest answer of a feed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OraclePrice {
+    pub feed: Address,
+    /// Answer scaled by the feed's `decimals()`, in USD.
+    pub price_usd: f64,
+    /// `updatedAt` of the round, unix seconds.
+    pub updated_at: u64,
+}
+
+/// A pool's implied USD price of `token` diverged from its Chainlink feed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OracleDeviation {
+    pub pool_address: Address,
+    pub token: Address,
+    /// The pool's price of `token` in the other token, times the other token's USD price.
+    pub pool_price_usd: f64,
+    pub oracle: OraclePrice,
+    /// `pool_price_usd / oracle.price_usd - 1`, in basis points.
+    pub deviation_bps: f64,
+    pub timestamp: u64,
+}
+
+/// Latest feed answers per token, written by the poller.
+#[derive(Debug)]
+pub(crate) struct OracleBook {
+    config: OracleConfig,
+    prices: RwLock<HashMap<Address, OraclePrice>>,
+}
+
+impl OracleBook {
+    pub(crate) fn new(config: OracleConfig) -> Self {
+        Self {
+            config,
+            prices: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &OracleConfig {
+        &self.config
+    }
+
+    pub(crate) fn update(&self, token: Address, price: OraclePrice) {
+        self.prices.write().unwrap().insert(token, price);
+    }
+
+    pub(crate) fn get(&self, token: Address) -> Option<OraclePrice> {
+        self.prices.read().unwrap().get(&token).copied()
+    }
+
+    /// Compare the pool's implied USD price of a token that has a feed with that feed. The other
+    /// token's USD price comes from its own feed, else $1 for a stablecoin, else `pricer`. token0 is
+    /// checked when it can be, token1 otherwise (with both on feeds, the two checks are the same ratio).
+    pub(crate) fn check(&self, pool: &CachedPool, price: &PoolPrice, pricer: Option<&UsdPricer>) -> Option<OracleDeviation> {
+        let usd_reference = |token: Address, symbol: &str| {
+            self.get(token)
+                .map(|oracle| oracle.price_usd)
+                .or_else(|| is_usd_stablecoin(symbol).then_some(1.0))
+                .or_else(|| pricer.and_then(|pricer| pricer.token_price_usd(token)))
+        };
+        let legs = [
+            (pool.token0, price.token0_price, pool.token1, &pool.token1_symbol),
+            (pool.token1, price.token1_price, pool.token0, &pool.token0_symbol),
+        ];
+        let (token, pool_price_usd, oracle) = legs.into_iter().find_map(|(token, relative, other, other_symbol)| {
+            let oracle = self.get(token)?;
+            Some((token, relative * usd_reference(other, other_symbol)?, oracle))
+        })?;
+        if !(pool_price_usd.is_finite() && oracle.price_usd > 0.0) {
+            return None;
+        }
+        let deviation_bps = (pool_price_usd / oracle.price_usd - 1.0) * 10_000.0;
+        (deviation_bps.abs() >= self.config.deviation_bps).then_some(OracleDeviation {
+            pool_address: pool.address,
+            token,
+            pool_price_usd,
+            oracle,
+            deviation_bps,
+            timestamp: price.timestamp,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deviation_against_feed() {
+        let (weth, usdc) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let pool = CachedPool {
+            token0: weth,
+            token0_symbol: "WETH".into(),
+            token1: usdc,
+            token1_symbol: "USDC".into(),
+            ..Default::default()
+        };
+        let book = OracleBook::new(OracleConfig::default());
+        let price = |token0_price: f64| PoolPrice {
+            token0_price,
+            token1_price: 1.0 / token0_price,
+            ..Default::default()
+        };
+        // No feed yet.
+        assert!(book.check(&pool, &price(2_100.0), None).is_none());
+
+        book.update(
+            weth,
+            OraclePrice {
+                feed: Address::repeat_byte(9),
+                price_usd: 2_000.0,
+                updated_at: 0,
+            },
+        );
+        assert!(book.check(&pool, &price(2_030.0), None).is_none());
+        let deviation = book.check(&pool, &price(2_100.0), None).unwrap();
+        assert_eq!(deviation.token, weth);
+        assert!((deviation.deviation_bps - 500.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::discovery::canonical_pair;
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Consolidated price of one token pair across every tracked pool trading it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairPrice {
+    /// The lower of the two token addresses.
+    pub token0: Address,
+    pub token1: Address,
+    /// token0 price in token1 units, weighted by each pool's liquidity at its current price.
+    pub price: f64,
+    /// Pools contributing to `price`.
+    pub pools: usize,
+    pub timestamp: u64,
+}
+
+/// Best venues to sell and buy token0 of a pair tracked on several pools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpreadUpdate {
+    /// The lower of the two token addresses.
+    pub token0: Address,
+    pub token1: Address,
+    /// Pool with the highest token0 price (where to sell token0).
+    pub bid_pool: Address,
+    pub bid_price: f64,
+    /// Pool with the lowest token0 price (where to buy token0).
+    pub ask_pool: Address,
+    pub ask_price: f64,
+    /// `bid_price / ask_price - 1`, in basis points.
+    pub spread_bps: f64,
+    /// The spread left after paying both pools' fees; positive means a round trip is profitable before gas.
+    pub net_spread_bps: f64,
+    /// Estimated USD cost of the two-swap round trip at the latest gas price, when the scanner tracks gas
+    /// (`ScannerOptions::arbitrage`) and the native token has a USD price.
+    pub gas_cost_usd: Option<f64>,
+    pub timestamp: u64,
+}
+
+/// Fee assumed for pools whose fee discovery didn't report (V2 pairs): 0.3%.
+const DEFAULT_FEE: u32 = 3000;
+
+/// Latest price and liquidity of one pool, oriented to its canonical pair.
+#[derive(Debug, Clone, Copy)]
+struct PoolEntry {
+    price: f64,
+    liquidity: f64,
+    /// Fee in hundredths of a basis point.
+    fee: u32,
+}
+
+/// Per-pair consolidation of pool prices.
+#[derive(Debug, Default)]
+pub(crate) struct PairBook {
+    pairs: Mutex<HashMap<(Address, Address), HashMap<Address, PoolEntry>>>,
+    latest: Mutex<HashMap<(Address, Address), PairPrice>>,
+}
+
+impl PairBook {
+    /// Record a pool update and return the pair's new consolidated price. `liquidity` is the pool's
+    /// in-range liquidity (`BaseLiquidityPool::get_liquidity`); V2 and V3 values of the same pair are
+    /// in the same `sqrt(amount0 * amount1)` units, so they weigh against each other directly. Pools
+    /// for which `is_tracked` is false are dropped from the pair.
+    pub(crate) fn update(
+        &self,
+        pool: &CachedPool,
+        price: &PoolPrice,
+        liquidity: u128,
+        is_tracked: impl Fn(&Address) -> bool,
+    ) -> Option<PairPrice> {
+        let key = canonical_pair(pool.token0, pool.token1);
+        let oriented = if key.0 == pool.token0 { price.token0_price } else { price.token1_price };
+        if !(oriented.is_finite() && oriented > 0.0) {
+            return None;
+        }
+
+        let mut pairs = self.pairs.lock().unwrap();
+        let pools = pairs.entry(key).or_default();
+        pools.insert(
+            pool.address,
+            PoolEntry {
+                price: oriented,
+                liquidity: liquidity as f64,
+                fee: if pool.fee == 0 { DEFAULT_FEE } else { pool.fee },
+            },
+        );
+        pools.retain(|address, _| is_tracked(address));
+
+        let pair = PairPrice {
+            token0: key.0,
+            token1: key.1,
+            price: weighted_price(pools.values()),
+            pools: pools.len(),
+            timestamp: price.timestamp,
+        };
+        drop(pairs);
+        self.latest.lock().unwrap().insert(key, pair.clone());
+        Some(pair)
+    }
+
+    /// Highest-vs-lowest price of `pool`'s pair across its pools, when the spread is at least
+    /// `threshold_bps`. Uses the prices recorded by `update`.
+    pub(crate) fn spread(&self, pool: &CachedPool, threshold_bps: f64, timestamp: u64) -> Option<SpreadUpdate> {
+        let key = canonical_pair(pool.token0, pool.token1);
+        let pairs = self.pairs.lock().unwrap();
+        let pools = pairs.get(&key)?;
+        let by_price = |a: &(&Address, &PoolEntry), b: &(&Address, &PoolEntry)| a.1.price.total_cmp(&b.1.price);
+        let (bid_pool, bid) = pools.iter().max_by(by_price)?;
+        let (ask_pool, ask) = pools.iter().min_by(by_price)?;
+        if bid_pool == ask_pool {
+            return None;
+        }
+        let spread_bps = (bid.price / ask.price - 1.0) * 10_000.0;
+        if spread_bps < threshold_bps {
+            return None;
+        }
+        let after_fee = |entry: &PoolEntry| 1.0 - f64::from(entry.fee) / 1_000_000.0;
+        let net_spread_bps = (bid.price * after_fee(bid) * after_fee(ask) / ask.price - 1.0) * 10_000.0;
+        Some(SpreadUpdate {
+            token0: key.0,
+            token1: key.1,
+            bid_pool: *bid_pool,
+            bid_price: bid.price,
+            ask_pool: *ask_pool,
+            ask_price: ask.price,
+            spread_bps,
+            net_spread_bps,
+            gas_cost_usd: None,
+            timestamp,
+        })
+    }
+
+    pub(crate) fn get(&self, token_a: Address, token_b: Address) -> Option<PairPrice> {
+        self.latest.lock().unwrap().get(&canonical_pair(token_a, token_b)).cloned()
+    }
+}
+
+/// Liquidity-weighted mean; a plain mean when no pool reports liquidity.
+fn weighted_price<'a>(entries: impl Iterator<Item = &'a PoolEntry> + Clone) -> f64 {
+    let total: f64 = entries.clone().map(|e| e.liquidity).sum();
+    if total > 0.0 {
+        entries.map(|e| e.price * e.liquidity).sum::<f64>() / total
+    } else {
+        let (sum, count) = entries.fold((0.0, 0usize), |(sum, count), e| (sum + e.price, count + 1));
+        sum / count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_price_is_liquidity_weighted() {
+        let book = PairBook::default();
+        let (weth, usdc) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let pool = |address: u8| CachedPool {
+            address: Address::repeat_byte(address),
+            token0: weth,
+            token1: usdc,
+            ..Default::default()
+        };
+        let price = |token0_price: f64| PoolPrice {
+            token0_price,
+            token1_price: 1.0 / token0_price,
+            ..Default::default()
+        };
+
+        book.update(&pool(10), &price(2_000.0), 300, |_| true);
+        let pair = book.update(&pool(11), &price(2_100.0), 100, |_| true).unwrap();
+        assert_eq!(pair.price, 2_025.0);
+        assert_eq!(pair.pools, 2);
+
+        // Pool 10 is no longer tracked.
+        let pair = book.update(&pool(11), &price(2_200.0), 100, |a| *a != Address::repeat_byte(10)).unwrap();
+        assert_eq!((pair.price, pair.pools), (2_200.0, 1));
+        assert_eq!(book.get(usdc, weth), Some(pair));
+    }
+
+    #[test]
+    fn test_spread_between_venues() {
+        let book = PairBook::default();
+        let pool = |address: u8, fee: u32| CachedPool {
+            address: Address::repeat_byte(address),
+            token0: Address::repeat_byte(1),
+            token1: Address::repeat_byte(2),
+            fee,
+            ..Default::default()
+        };
+        let price = |token0_price: f64| PoolPrice {
+            token0_price,
+            token1_price: 1.0 / token0_price,
+            ..Default::default()
+        };
+        book.update(&pool(10, 500), &price(2_000.0), 1, |_| true);
+        assert!(book.spread(&pool(10, 500), 0.0, 0).is_none());
+
+        book.update(&pool(11, 0), &price(2_020.0), 1, |_| true);
+        let spread = book.spread(&pool(11, 0), 50.0, 7).unwrap();
+        assert_eq!((spread.bid_pool, spread.ask_pool), (Address::repeat_byte(11), Address::repeat_byte(10)));
+        assert!((spread.spread_bps - 100.0).abs() < 1e-9);
+        // 0.05% + 0.3% fees: 1.01 * 0.9995 * 0.997 - 1.
+        assert!((spread.net_spread_bps - (1.01 * 0.9995 * 0.997 - 1.0) * 10_000.0).abs() < 1e-6);
+        assert!(book.spread(&pool(11, 0), 150.0, 7).is_none());
+    }
+}
@@ -0,0 +1,138 @@
+use super::depth::{publish_depth_updates, DepthConfig};
+use super::logs::LogChunks;
+use super::{fanout, handle_log_at, log_filter, RpcProvider, ScannerContext, BACKFILL_CHUNK_BLOCKS};
+use crate::events::{ScannerError, ScannerEvent};
+use alloy::eips::BlockNumberOrTag;
+use alloy::rpc::types::eth::{BlockTransactionsKind, Log};
+use eyre::{bail, eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::info;
+
+/// What `Scanner::scan_range` or `Scanner::backfill` replayed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillSummary {
+    pub from_block: u64,
+    pub to_block: u64,
+    /// Swap/Sync/Mint/Burn logs fetched for the tracked pools.
+    pub logs: u64,
+    pub price_updates: u64,
+    pub candles: u64,
+}
+
+/// Replay the tracked pools' logs from `from_block` to `to_block` in `BACKFILL_CHUNK_BLOCKS` spans,
+/// timestamped with their block's time. Candles, and with `depth` the depth updates, are published on block
+/// time too. Every event is handed to the sinks right away rather than through the bus, which would drop
+/// events a sink can't keep up with.
+pub(crate) async fn scan_range(
+    ctx: &ScannerContext,
+    provider: &RpcProvider,
+    from_block: u64,
+    to_block: u64,
+    depth: Option<&DepthConfig>,
+) -> Result<BackfillSummary> {
+    let addresses = ctx.state.addresses();
+    if addresses.is_empty() {
+        bail!("no pools to scan");
+    }
+    let filter = log_filter(addresses);
+    let mut events = ctx.events.subscribe();
+    let mut summary = BackfillSummary {
+        from_block,
+        to_block,
+        ..Default::default()
+    };
+
+    let mut chunks = LogChunks::new(provider, &filter, from_block, to_block, BACKFILL_CHUNK_BLOCKS);
+    let mut timestamp = 0;
+    let mut next_depth = None;
+    while let Some((from, to, logs)) = chunks.next().await? {
+        let mut block_times = HashMap::new();
+        for log in logs {
+            timestamp = block_timestamp(provider, &log, &mut block_times).await?;
+            replay_log(ctx, log, timestamp, &mut summary);
+            publish_candles(ctx, timestamp, &mut summary);
+            if let Some(config) = depth {
+                let interval = config.interval.as_secs().max(1);
+                if *next_depth.get_or_insert(timestamp + interval) <= timestamp {
+                    publish_depth_updates(ctx, config, timestamp);
+                    next_depth = Some(timestamp + interval);
+                }
+            }
+            fanout::deliver(ctx, &mut events).await;
+        }
+        for sink in &ctx.sinks {
+            fanout::retry(ctx, sink.name(), || sink.flush()).await;
+        }
+        info!("Backfilled blocks {} to {}", from, to);
+    }
+
+    // Candles of quiet pools that ended before the last block; the ones still open at the end are partial.
+    if timestamp > 0 {
+        let end = provider
+            .get_block_by_number(BlockNumberOrTag::Number(to_block), BlockTransactionsKind::Hashes)
+            .await?
+            .map_or(timestamp, |block| block.header.timestamp);
+        publish_candles(ctx, end, &mut summary);
+        fanout::deliver(ctx, &mut events).await;
+    }
+    for sink in &ctx.sinks {
+        fanout::retry(ctx, sink.name(), || sink.close()).await;
+    }
+    Ok(summary)
+}
+
+/// Timestamp of `log`'s block: from the log when the node includes it, otherwise from the block header.
+pub(super) async fn block_timestamp(provider: &RpcProvider, log: &Log, cache: &mut HashMap<u64, u64>) -> Result<u64> {
+    if let Some(timestamp) = log.block_timestamp {
+        return Ok(timestamp);
+    }
+    let number = log.block_number.ok_or_else(|| eyre!("log without a block number"))?;
+    if let Some(timestamp) = cache.get(&number) {
+        return Ok(*timestamp);
+    }
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Number(number), BlockTransactionsKind::Hashes)
+        .await?
+        .ok_or_else(|| eyre!("block {} not found", number))?;
+    cache.insert(number, block.header.timestamp);
+    Ok(block.header.timestamp)
+}
+
+/// Decode `log` and deliver its update, recording the same stats as the live pipeline.
+fn replay_log(ctx: &ScannerContext, log: Log, timestamp: u64, summary: &mut BackfillSummary) {
+    let pool = log.address();
+    summary.logs += 1;
+    ctx.stats.record_log(log.block_number);
+    let started = Instant::now();
+    let handled = handle_log_at(ctx, log, timestamp);
+    ctx.stats.record_parse(started.elapsed());
+    match handled {
+        Ok(Some(update)) => {
+            summary.price_updates += 1;
+            let started = Instant::now();
+            ctx.events.price_changed(update.pool, update.price, update.previous);
+            ctx.stats.record_update(pool, started.elapsed());
+        }
+        Ok(None) => {}
+        Err(e) => {
+            ctx.stats.record_parse_error();
+            ctx.events.error(ScannerError::Parse {
+                pool,
+                message: e.to_string(),
+            });
+        }
+    }
+}
+
+/// Publish the candles that ended by `now`, in block time.
+fn publish_candles(ctx: &ScannerContext, now: u64, summary: &mut BackfillSummary) {
+    for address in ctx.state.addresses() {
+        let Some(slot) = ctx.state.get(&address) else { continue };
+        for candle in slot.take_candles(now) {
+            summary.candles += 1;
+            ctx.events.publish(|| ScannerEvent::Candle(candle));
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// How often pools are checked for completed candles.
+const CANDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Publish every completed candle as `ScannerEvent::Candle`. Candles are closed here once their
+/// interval is over, so quiet pools don't hold a candle back until their next swap.
+pub(crate) async fn run_candle_publisher(ctx: Arc<ScannerContext>) {
+    let mut interval = tokio::time::interval(CANDLE_POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let now = chrono::Utc::now().timestamp() as u64;
+        for address in ctx.state.addresses() {
+            let Some(slot) = ctx.state.get(&address) else { continue };
+            for candle in slot.take_candles(now) {
+                ctx.events.publish(|| ScannerEvent::Candle(candle));
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use crate::state_store::StateStore;
+use alloy::primitives::Address;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+/// How often pool checkpoints are written to the state store.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Restore tracked pools from their stored checkpoints. Returns the block to backfill from: the
+/// oldest checkpoint's block, or `None` when no pool could be restored.
+pub(crate) fn restore_pools(ctx: &ScannerContext, store: &StateStore) -> Option<u64> {
+    let checkpoints = match store.load_all() {
+        Ok(checkpoints) => checkpoints,
+        Err(e) => {
+            warn!("Failed to load pool checkpoints: {}", e);
+            return None;
+        }
+    };
+    let mut resume_from: Option<u64> = None;
+    let mut restored = 0;
+    for (address, checkpoint) in checkpoints {
+        let Some(slot) = ctx.state.get(&address) else { continue };
+        let block_number = checkpoint.block_number;
+        match slot.restore(checkpoint) {
+            Ok(()) => {
+                restored += 1;
+                resume_from = Some(resume_from.map_or(block_number, |from| from.min(block_number)));
+            }
+            Err(e) => warn!("Ignoring checkpoint of pool {}: {}", address, e),
+        }
+    }
+    if let Some(from) = resume_from {
+        info!("Restored {} pools from the state store; backfilling from block {}", restored, from);
+    }
+    resume_from
+}
+
+/// Checkpoint every tracked pool on an interval and once more on shutdown, and drop the checkpoints
+/// of pools removed by rediscovery. Writes run on the blocking pool.
+pub(crate) async fn run_checkpoint_writer(ctx: Arc<ScannerContext>, store: Arc<StateStore>) {
+    let mut events = ctx.events.subscribe();
+    let mut interval = tokio::time::interval(CHECKPOINT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => {
+                save_checkpoints(&ctx, &store).await;
+                return;
+            }
+            _ = interval.tick() => save_checkpoints(&ctx, &store).await,
+            event = events.recv() => match event {
+                Ok(ScannerEvent::PoolRemoved(pool)) => remove_checkpoints(&store, vec![pool.address]).await,
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => warn!("Checkpoint writer lagged, skipped {} events", skipped),
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+async fn save_checkpoints(ctx: &ScannerContext, store: &Arc<StateStore>) {
+    let checkpoints: Vec<_> = ctx
+        .state
+        .addresses()
+        .into_iter()
+        .filter_map(|address| Some((address, ctx.state.get(&address)?.checkpoint()?)))
+        .collect();
+    if checkpoints.is_empty() {
+        return;
+    }
+    let store = Arc::clone(store);
+    match tokio::task::spawn_blocking(move || store.save(&checkpoints)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Failed to write pool checkpoints: {}", e),
+        Err(e) => warn!("State store writer panicked: {}", e),
+    }
+}
+
+async fn remove_checkpoints(store: &Arc<StateStore>, pools: Vec<Address>) {
+    let store = Arc::clone(store);
+    match tokio::task::spawn_blocking(move || store.remove(&pools)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Failed to remove pool checkpoints: {}", e),
+        Err(e) => warn!("State store writer panicked: {}", e),
+    }
+}
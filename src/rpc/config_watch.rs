@@ -0,0 +1,80 @@
+use super::rediscovery::ScanConfig;
+use super::{load_config, RpcProvider, ScannerContext};
+use crate::config;
+use crate::events::ScannerError;
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, info, warn};
+
+/// Quiet time after a change before the files are read, so an editor's save (truncate, write, rename) is
+/// read once and complete.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watch the config files (`config_file`, or the protocols and tokens files, and the secrets file) and send every configuration that
+/// loads and differs from the current one on `configs`. The directories are watched rather than the files, so
+/// files replaced by a rename, or created later, are seen too.
+pub(crate) async fn run_config_watcher(
+    ctx: Arc<ScannerContext>,
+    provider: RpcProvider,
+    config_file: Option<PathBuf>,
+    configs: watch::Sender<ScanConfig>,
+) {
+    let mut files: Vec<PathBuf> = match &config_file {
+        Some(path) => vec![path.clone()],
+        None => vec![
+            config::config_path("PROTOCOLS_JSON", config::PROTOCOLS_FILES),
+            config::config_path("TOKENS_JSON", config::TOKENS_FILES),
+        ],
+    };
+    // Secret references are resolved when the files are read.
+    files.push(config::config_path("SCANNER_SECRETS", config::SECRETS_FILES));
+    let files: HashSet<PathBuf> = files.into_iter().filter_map(|file| std::path::absolute(file).ok()).collect();
+    let dirs: HashSet<PathBuf> = files.iter().filter_map(|file| file.parent().map(PathBuf::from)).collect();
+
+    let (changes, mut changed) = mpsc::unbounded_channel();
+    let watched = files.clone();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && !event.kind.is_access()
+            && event.paths.iter().any(|path| watched.contains(path))
+        {
+            let _ = changes.send(());
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => return warn!("Cannot watch the config files: {}", e),
+    };
+    for dir in &dirs {
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            return warn!("Cannot watch {}: {}", dir.display(), e);
+        }
+    }
+    info!("Watching {} config files for changes", files.len());
+
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            change = changed.recv() => if change.is_none() { return },
+        }
+        loop {
+            tokio::select! {
+                _ = ctx.cancel.cancelled() => return,
+                _ = tokio::time::sleep(DEBOUNCE) => break,
+                change = changed.recv() => if change.is_none() { return },
+            }
+        }
+        match load_config(&provider, config_file.as_deref()).await {
+            Ok(config) if *configs.borrow() == config => debug!("Config files changed without effect"),
+            Ok(config) => {
+                info!("Config files changed; applying the new configuration");
+                configs.send_replace(config);
+            }
+            Err(e) => ctx.events.error(ScannerError::Config(format!("{:#}", e))),
+        }
+    }
+}
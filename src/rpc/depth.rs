@@ -0,0 +1,111 @@
+use super::state::PoolSlot;
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use crate::liquidity_pools::Quote;
+use crate::pricing::is_usd_stablecoin;
+use alloy::primitives::{Address, U256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// Periodic slippage/depth profiles. Set `ScannerOptions::depth` to publish a
+/// `ScannerEvent::DepthUpdate` for every tracked pool each `interval`.
+#[derive(Debug, Clone)]
+pub struct DepthConfig {
+    /// Trade sizes of the ladder, in USD.
+    pub sizes_usd: Vec<f64>,
+    pub interval: Duration,
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        Self {
+            sizes_usd: vec![1_000.0, 10_000.0, 100_000.0, 1_000_000.0],
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Quote for one rung of the ladder: selling `size_usd` worth of `token_in`.
+#[derive(Debug, Clone)]
+pub struct DepthLevel {
+    pub size_usd: f64,
+    pub token_in: Address,
+    /// `size_usd` converted to `token_in` raw units.
+    pub amount_in: U256,
+    /// `None` when the pool can't fill the trade (e.g. not enough known V3 liquidity).
+    pub quote: Option<Quote>,
+}
+
+/// Output of a ladder of trade sizes in both directions of one pool.
+#[derive(Debug, Clone)]
+pub struct DepthProfile {
+    pub pool_address: Address,
+    pub timestamp: u64,
+    /// Selling token0, then selling token1, each in ascending size.
+    pub levels: Vec<DepthLevel>,
+}
+
+/// Depth profile of one pool. Sizes are converted to token amounts with the update's USD prices
+/// (`ScannerOptions::pricing`), or else through the pool's own price when one of its tokens is a USD
+/// stablecoin; `None` otherwise, or before the pool's first update.
+pub(crate) fn depth_profile(slot: &PoolSlot, sizes_usd: &[f64], timestamp: u64) -> Option<DepthProfile> {
+    let pool = &slot.pool;
+    let price = slot.current_price()?;
+    let (usd0, usd1) = if let (Some(usd0), Some(usd1)) = (price.token0_price_usd, price.token1_price_usd) {
+        (usd0, usd1)
+    } else if is_usd_stablecoin(&pool.token1_symbol) {
+        (price.token0_price, 1.0)
+    } else if is_usd_stablecoin(&pool.token0_symbol) {
+        (1.0, price.token1_price)
+    } else {
+        return None;
+    };
+    if !(usd0.is_finite() && usd0 > 0.0 && usd1.is_finite() && usd1 > 0.0) {
+        return None;
+    }
+
+    let levels = [(pool.token0, pool.token0_decimals, usd0), (pool.token1, pool.token1_decimals, usd1)]
+        .into_iter()
+        .flat_map(|(token_in, decimals, usd_price)| {
+            sizes_usd.iter().map(move |size_usd| {
+                // Saturating float-to-int cast; u128 covers $1B of any token priced above 10^-9 at 18 decimals.
+                let amount_in = U256::from((size_usd / usd_price * 10f64.powi(i32::from(decimals))) as u128);
+                DepthLevel {
+                    size_usd: *size_usd,
+                    token_in,
+                    amount_in,
+                    quote: slot.quote_exact_input(token_in, amount_in).ok(),
+                }
+            })
+        })
+        .collect();
+    Some(DepthProfile {
+        pool_address: pool.address,
+        timestamp,
+        levels,
+    })
+}
+
+/// Publish a `DepthUpdate` for every tracked pool with a USD price each `config.interval`.
+pub(crate) async fn run_depth_updates(ctx: Arc<ScannerContext>, config: DepthConfig) {
+    let mut interval = tokio::time::interval(config.interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        publish_depth_updates(&ctx, &config, chrono::Utc::now().timestamp() as u64);
+    }
+}
+
+/// Publish a `DepthUpdate` as of `timestamp` for every tracked pool with a USD price.
+pub(crate) fn publish_depth_updates(ctx: &ScannerContext, config: &DepthConfig, timestamp: u64) {
+    for address in ctx.state.addresses() {
+        let Some(slot) = ctx.state.get(&address) else { continue };
+        if let Some(profile) = depth_profile(&slot, &config.sizes_usd, timestamp) {
+            ctx.events.publish(|| ScannerEvent::DepthUpdate(profile));
+        }
+    }
+}
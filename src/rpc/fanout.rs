@@ -0,0 +1,82 @@
+use super::ScannerContext;
+use crate::events::{ScannerError, ScannerEvent};
+use crate::sinks::{next_batch, Sink};
+use eyre::Result;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::time::MissedTickBehavior;
+use tracing::warn;
+
+/// How often a sink is flushed while no events arrive, so time-based batches still go out.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Feed every event on the bus to `sink`, flushing after each burst and once per second, and close it
+/// when the scanner stops. Each sink has its own task and bus receiver, so a slow or failing sink only
+/// lags (and skips events) itself.
+pub(crate) async fn run_sink(ctx: Arc<ScannerContext>, sink: Arc<dyn Sink>) {
+    let mut events = ctx.events.subscribe();
+    let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+    flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => break,
+            _ = flush.tick() => retry(&ctx, sink.name(), || sink.flush()).await,
+            batch = next_batch(&mut events, sink.name()) => {
+                let Some(batch) = batch else { break };
+                for event in &batch {
+                    retry(&ctx, sink.name(), || sink.publish(event)).await;
+                }
+                retry(&ctx, sink.name(), || sink.flush()).await;
+            }
+        }
+    }
+    retry(&ctx, sink.name(), || sink.close()).await;
+}
+
+/// Hand the events received on `events` so far to every sink, in order. Used where the events come from a
+/// replay that must not lose any, rather than from the live bus.
+pub(crate) async fn deliver(ctx: &ScannerContext, events: &mut broadcast::Receiver<ScannerEvent>) {
+    loop {
+        let event = match events.try_recv() {
+            Ok(event) => event,
+            Err(TryRecvError::Lagged(skipped)) => {
+                warn!("Skipped {} events", skipped);
+                continue;
+            }
+            Err(TryRecvError::Empty | TryRecvError::Closed) => return,
+        };
+        for sink in &ctx.sinks {
+            retry(ctx, sink.name(), || sink.publish(&event)).await;
+        }
+    }
+}
+
+/// Run `call` until it succeeds or `ScannerOptions::sink_retry` is exhausted, backing off exponentially
+/// between tries; the last failure is reported as `ScannerError::Sink`.
+pub(crate) async fn retry<F>(ctx: &ScannerContext, sink: &str, mut call: impl FnMut() -> F)
+where
+    F: Future<Output = Result<()>>,
+{
+    let mut backoff = ctx.sink_retry.backoff;
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Ok(()) => return,
+            Err(e) if attempt >= ctx.sink_retry.attempts => {
+                ctx.events.error(ScannerError::Sink {
+                    sink: sink.to_string(),
+                    message: format!("{:#}", e),
+                });
+                return;
+            }
+            Err(_) => {
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+                attempt += 1;
+            }
+        }
+    }
+}
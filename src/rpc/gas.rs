@@ -0,0 +1,55 @@
+use super::{RpcProvider, ScannerContext};
+use crate::arbitrage::GasPrice;
+use crate::events::ScannerError;
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Delay before resubscribing to new heads after the stream ends or fails.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Follow new block headers and record each block's base fee with the node's current priority fee
+/// suggestion. Pre-London blocks (no base fee) are skipped.
+pub(crate) async fn run_gas_tracker(ctx: Arc<ScannerContext>, provider: RpcProvider) {
+    let Some(oracle) = &ctx.gas else { return };
+    loop {
+        let subscription = tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            subscription = provider.subscribe_blocks() => subscription,
+        };
+        match subscription {
+            Ok(sub) => {
+                let mut stream = sub.into_stream();
+                loop {
+                    let header = tokio::select! {
+                        _ = ctx.cancel.cancelled() => return,
+                        header = stream.next() => header,
+                    };
+                    let Some(header) = header else { break };
+                    let Some(base_fee) = header.base_fee_per_gas else { continue };
+                    let priority_fee = match provider.get_max_priority_fee_per_gas().await {
+                        Ok(fee) => fee,
+                        Err(e) => {
+                            debug!("eth_maxPriorityFeePerGas failed: {}", e);
+                            oracle.latest().map_or(0, |gas| gas.priority_fee)
+                        }
+                    };
+                    oracle.update(GasPrice {
+                        block_number: header.number,
+                        base_fee: u128::from(base_fee),
+                        priority_fee,
+                    });
+                }
+            }
+            Err(e) => ctx
+                .events
+                .error(ScannerError::Subscription(format!("new heads subscription: {}", e))),
+        }
+
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            _ = tokio::time::sleep(RESUBSCRIBE_DELAY) => {}
+        }
+    }
+}
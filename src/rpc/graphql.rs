@@ -0,0 +1,400 @@
+use super::pair_filter::PairFilter;
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use crate::metrics::Candle as MetricsCandle;
+use crate::types::{CachedPool, PoolPrice, PoolSnapshot};
+use alloy::primitives::Address;
+use async_graphql::{Context, EmptyMutation, Object, Result, Schema, SimpleObject, Subscription};
+use dashmap::DashMap;
+use futures::{Stream, StreamExt};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Completed candles kept per pool (across intervals) for the `candles` query.
+const CANDLE_HISTORY: usize = 1_000;
+
+pub(crate) type ScannerSchema = Schema<Query, EmptyMutation, Subscription>;
+
+/// Build the schema served at `/graphql`, and start recording completed candles for the `candles` query.
+pub(crate) fn schema(ctx: Arc<ScannerContext>, stale_after: Option<Duration>) -> ScannerSchema {
+    let candles = Arc::new(CandleHistory::default());
+    ctx.spawn(record_candles(Arc::clone(&ctx), Arc::clone(&candles)));
+    Schema::build(Query, EmptyMutation, Subscription)
+        .data(GraphqlState { ctx, stale_after, candles })
+        .finish()
+}
+
+struct GraphqlState {
+    ctx: Arc<ScannerContext>,
+    stale_after: Option<Duration>,
+    candles: Arc<CandleHistory>,
+}
+
+/// The most recent completed candles of every pool, oldest first.
+#[derive(Default)]
+struct CandleHistory(DashMap<Address, VecDeque<MetricsCandle>>);
+
+impl CandleHistory {
+    fn record(&self, candle: MetricsCandle) {
+        let mut candles = self.0.entry(candle.pool_address).or_default();
+        if candles.len() == CANDLE_HISTORY {
+            candles.pop_front();
+        }
+        candles.push_back(candle);
+    }
+
+    /// Up to `first` candles of `pool`, newest first, optionally of one interval only.
+    fn recent(&self, pool: &Address, interval_secs: Option<u64>, first: usize) -> Vec<MetricsCandle> {
+        self.0
+            .get(pool)
+            .map(|candles| {
+                candles
+                    .iter()
+                    .rev()
+                    .filter(|candle| interval_secs.is_none_or(|interval| candle.interval_secs == interval))
+                    .take(first)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+async fn record_candles(ctx: Arc<ScannerContext>, history: Arc<CandleHistory>) {
+    let mut events = ctx.events.subscribe();
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            event = events.recv() => match event {
+                Ok(ScannerEvent::Candle(candle)) => history.record(candle),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+fn parse_address(address: &str) -> Result<Address> {
+    address.parse().map_err(|_| format!("invalid address {address:?}").into())
+}
+
+#[derive(SimpleObject)]
+struct Token {
+    id: String,
+    symbol: String,
+    decimals: u8,
+}
+
+/// A tracked pool and its latest state.
+#[derive(SimpleObject)]
+struct Pool {
+    /// Pool address.
+    id: String,
+    protocol: String,
+    token0: Token,
+    token1: Token,
+    /// Fee in hundredths of a basis point (3000 = 0.3%); 0 when unknown.
+    fee_tier: u32,
+    liquidity_usd: f64,
+    /// Rolling 24h swap volume (the discovery-time figure until a full day has been observed).
+    volume_usd: f64,
+    /// Last price; null before the pool's first update.
+    price: Option<Price>,
+    /// Unix time of the pool's last log, or when tracking started.
+    last_activity: u64,
+    stale: bool,
+}
+
+impl From<PoolSnapshot> for Pool {
+    fn from(snapshot: PoolSnapshot) -> Self {
+        let pool = snapshot.pool;
+        Self {
+            id: pool.address.to_string(),
+            token0: Token {
+                id: pool.token0.to_string(),
+                symbol: pool.token0_symbol,
+                decimals: pool.token0_decimals,
+            },
+            token1: Token {
+                id: pool.token1.to_string(),
+                symbol: pool.token1_symbol,
+                decimals: pool.token1_decimals,
+            },
+            protocol: pool.protocol,
+            fee_tier: pool.fee,
+            liquidity_usd: pool.liquidity_usd,
+            volume_usd: pool.volume_24h_usd,
+            price: snapshot.price.as_ref().map(Into::into),
+            last_activity: snapshot.last_activity,
+            stale: snapshot.stale,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Price {
+    pool: String,
+    /// token0 in token1 units.
+    token0_price: f64,
+    token1_price: f64,
+    token0_price_usd: Option<f64>,
+    token1_price_usd: Option<f64>,
+    timestamp: u64,
+    block_number: Option<u64>,
+    /// Swapped amounts from the pool's point of view (positive = paid into the pool); null for non-swaps.
+    amount0: Option<f64>,
+    amount1: Option<f64>,
+    /// Flagged by the spike filter.
+    suspect: bool,
+}
+
+impl From<&PoolPrice> for Price {
+    fn from(price: &PoolPrice) -> Self {
+        Self {
+            pool: price.pool_address.to_string(),
+            token0_price: price.token0_price,
+            token1_price: price.token1_price,
+            token0_price_usd: price.token0_price_usd,
+            token1_price_usd: price.token1_price_usd,
+            timestamp: price.timestamp,
+            block_number: price.block_number,
+            amount0: price.amount0,
+            amount1: price.amount1,
+            suspect: price.suspect,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct PriceUpdate {
+    pool: String,
+    pair: String,
+    price: Price,
+    previous: Option<Price>,
+}
+
+/// An OHLCV candle of token0 priced in token1.
+#[derive(SimpleObject)]
+struct Candle {
+    pool: String,
+    interval: u64,
+    /// Start of the interval (unix seconds).
+    open_time: u64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume0: f64,
+    volume1: f64,
+    trades: u32,
+}
+
+impl From<MetricsCandle> for Candle {
+    fn from(candle: MetricsCandle) -> Self {
+        Self {
+            pool: candle.pool_address.to_string(),
+            interval: candle.interval_secs,
+            open_time: candle.open_time,
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume0: candle.volume0,
+            volume1: candle.volume1,
+            trades: candle.trades,
+        }
+    }
+}
+
+pub(crate) struct Query;
+
+#[Object]
+impl Query {
+    /// Tracked pools, most liquid first, optionally of one protocol or trading one `A/B` pair.
+    async fn pools(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 100)] first: usize,
+        #[graphql(default)] skip: usize,
+        protocol: Option<String>,
+        pair: Option<String>,
+    ) -> Vec<Pool> {
+        let state = ctx.data_unchecked::<GraphqlState>();
+        let pair = pair.map(|pair| PairFilter::parse(&pair)).unwrap_or_default();
+        let mut pools: Vec<PoolSnapshot> = state
+            .ctx
+            .snapshot(state.stale_after)
+            .into_iter()
+            .filter(|snapshot| protocol.as_ref().is_none_or(|protocol| &snapshot.pool.protocol == protocol))
+            .filter(|snapshot| pair.matches(&snapshot.pool))
+            .collect();
+        pools.sort_by(|a, b| b.pool.liquidity_usd.total_cmp(&a.pool.liquidity_usd));
+        pools.into_iter().skip(skip).take(first).map(Into::into).collect()
+    }
+
+    /// One tracked pool; null when the address isn't tracked.
+    async fn pool(&self, ctx: &Context<'_>, id: String) -> Result<Option<Pool>> {
+        let state = ctx.data_unchecked::<GraphqlState>();
+        let now = chrono::Utc::now().timestamp() as u64;
+        let slot = state.ctx.state.get(&parse_address(&id)?);
+        Ok(slot.map(|slot| slot.snapshot(now, state.stale_after).into()))
+    }
+
+    /// The last price of every pool that has one.
+    async fn prices(&self, ctx: &Context<'_>) -> Vec<Price> {
+        let state = ctx.data_unchecked::<GraphqlState>();
+        state
+            .ctx
+            .state
+            .addresses()
+            .iter()
+            .filter_map(|address| state.ctx.state.get(address)?.current_price())
+            .map(|price| (&price).into())
+            .collect()
+    }
+
+    /// Completed candles of a pool since the scanner started, newest first (`MetricsConfig::candle_intervals`).
+    async fn candles(
+        &self,
+        ctx: &Context<'_>,
+        pool: String,
+        #[graphql(desc = "Interval in seconds; every configured interval when omitted.")] interval: Option<u64>,
+        #[graphql(default = 100)] first: usize,
+    ) -> Result<Vec<Candle>> {
+        let state = ctx.data_unchecked::<GraphqlState>();
+        let candles = state.candles.recent(&parse_address(&pool)?, interval, first);
+        Ok(candles.into_iter().map(Into::into).collect())
+    }
+}
+
+pub(crate) struct Subscription;
+
+/// Pools selected by a subscription: listed addresses (any when omitted) trading a listed pair.
+struct Selection {
+    pools: HashSet<Address>,
+    pairs: PairFilter,
+}
+
+impl Selection {
+    fn parse(pools: Option<Vec<String>>, pairs: Option<Vec<String>>) -> Result<Self> {
+        let pools = pools.unwrap_or_default().iter().map(|pool| parse_address(pool)).collect::<Result<_>>()?;
+        let pairs = PairFilter::from_pairs(pairs.iter().flatten().map(String::as_str));
+        Ok(Self { pools, pairs })
+    }
+
+    fn matches(&self, pool: &CachedPool) -> bool {
+        (self.pools.is_empty() || self.pools.contains(&pool.address)) && self.pairs.matches(pool)
+    }
+}
+
+/// The events `select` maps to an item, until the scanner stops. A subscriber lagging the event bus skips events.
+fn events<T>(ctx: &ScannerContext, select: impl Fn(ScannerEvent) -> Option<T> + Send + 'static) -> impl Stream<Item = T> {
+    futures::stream::unfold(ctx.events.subscribe(), |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => return Some((event, events)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter_map(move |event| std::future::ready(select(event)))
+    .take_until(ctx.cancel.clone().cancelled_owned())
+}
+
+#[Subscription]
+impl Subscription {
+    /// Price updates of the selected pools (every pool when both filters are omitted).
+    async fn prices(
+        &self,
+        ctx: &Context<'_>,
+        pools: Option<Vec<String>>,
+        #[graphql(desc = "`A/B` pairs of token symbols or addresses, in either order.")] pairs: Option<Vec<String>>,
+    ) -> Result<impl Stream<Item = PriceUpdate>> {
+        let selection = Selection::parse(pools, pairs)?;
+        let state = ctx.data_unchecked::<GraphqlState>();
+        Ok(events(&state.ctx, move |event| match event {
+            ScannerEvent::PriceUpdate { pool, price, previous } if selection.matches(&pool) => Some(PriceUpdate {
+                pool: pool.address.to_string(),
+                pair: format!("{}/{}", pool.token0_symbol, pool.token1_symbol),
+                price: (&price).into(),
+                previous: previous.as_ref().map(Into::into),
+            }),
+            _ => None,
+        }))
+    }
+
+    /// Candles of the selected pools as they complete, optionally of one interval (seconds) only.
+    async fn candles(
+        &self,
+        ctx: &Context<'_>,
+        pools: Option<Vec<String>>,
+        interval: Option<u64>,
+    ) -> Result<impl Stream<Item = Candle>> {
+        let selection = Selection::parse(pools, None)?;
+        let state = ctx.data_unchecked::<GraphqlState>();
+        Ok(events(&state.ctx, move |event| match event {
+            ScannerEvent::Candle(candle)
+                if (selection.pools.is_empty() || selection.pools.contains(&candle.pool_address))
+                    && interval.is_none_or(|interval| candle.interval_secs == interval) =>
+            {
+                Some(candle.into())
+            }
+            _ => None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(interval_secs: u64, open_time: u64) -> MetricsCandle {
+        MetricsCandle {
+            pool_address: Address::repeat_byte(1),
+            interval_secs,
+            open_time,
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume0: 10.0,
+            volume1: 15.0,
+            trades: 3,
+        }
+    }
+
+    #[test]
+    fn test_candle_history() {
+        let history = CandleHistory::default();
+        for i in 0..CANDLE_HISTORY as u64 + 10 {
+            history.record(candle(if i % 2 == 0 { 60 } else { 3600 }, i));
+        }
+        let pool = Address::repeat_byte(1);
+        let recent = history.recent(&pool, Some(60), 3);
+        assert_eq!(recent.iter().map(|c| c.open_time).collect::<Vec<_>>(), vec![1008, 1006, 1004]);
+        assert_eq!(history.recent(&pool, None, usize::MAX).len(), CANDLE_HISTORY);
+        assert_eq!(history.recent(&pool, None, usize::MAX).last().unwrap().open_time, 10);
+        assert!(history.recent(&Address::ZERO, None, 10).is_empty());
+
+        let converted = Candle::from(candle(60, 120));
+        assert_eq!((converted.interval, converted.open_time, converted.trades), (60, 120, 3));
+        assert_eq!(converted.pool, pool.to_string());
+    }
+
+    #[test]
+    fn test_selection() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            ..Default::default()
+        };
+        assert!(Selection::parse(None, None).unwrap().matches(&pool));
+        assert!(Selection::parse(Some(vec![pool.address.to_string()]), Some(vec!["usdc/weth".to_string()])).unwrap().matches(&pool));
+        assert!(!Selection::parse(None, Some(vec!["WBTC/USDC".to_string()])).unwrap().matches(&pool));
+        assert!(Selection::parse(Some(vec!["nope".to_string()]), None).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use super::pair_filter::PairFilter;
+use super::whales::Swap;
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use crate::types::{CachedPool, PoolPrice, PoolSnapshot};
+use alloy::primitives::Address;
+use eyre::Result;
+use proto::scanner_service_server::{ScannerService, ScannerServiceServer};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+use tracing::{debug, info, warn};
+
+/// Messages and service stubs generated from `proto/scanner.proto`.
+pub mod proto {
+    tonic::include_proto!("dexscanner.v1");
+}
+
+/// Messages a subscriber may fall behind by before the stream waits for it.
+const STREAM_BUFFER: usize = 256;
+
+/// Address of the gRPC service (`ScannerOptions::grpc`).
+#[derive(Debug, Clone)]
+pub struct GrpcConfig {
+    pub addr: SocketAddr,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self { addr: SocketAddr::from(([127, 0, 0, 1], 50051)) }
+    }
+}
+
+/// Bind `config.addr`, so a taken port fails `Scanner::start`.
+pub(crate) async fn bind(config: &GrpcConfig) -> Result<TcpListener> {
+    let listener = TcpListener::bind(config.addr).await?;
+    info!("Serving gRPC on {}", listener.local_addr()?);
+    Ok(listener)
+}
+
+/// Serve `ScannerService` until the scanner shuts down.
+pub(crate) async fn run_grpc(ctx: Arc<ScannerContext>, listener: TcpListener, stale_after: Option<Duration>) {
+    let cancel = ctx.cancel.clone();
+    let service = ScannerServiceServer::new(GrpcService { ctx, stale_after });
+    if let Err(e) = tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), cancel.cancelled_owned())
+        .await
+    {
+        warn!("gRPC server failed: {}", e);
+    }
+}
+
+struct GrpcService {
+    ctx: Arc<ScannerContext>,
+    stale_after: Option<Duration>,
+}
+
+/// Pools selected by a `SubscribeRequest`: listed addresses (any when empty) trading a listed pair.
+struct Selection {
+    pools: HashSet<Address>,
+    pairs: PairFilter,
+}
+
+impl Selection {
+    fn parse(request: proto::SubscribeRequest) -> Result<Self, Status> {
+        let pools = request.pools.iter().map(|pool| parse_address(pool)).collect::<Result<_, _>>()?;
+        let pairs = PairFilter::from_pairs(request.pairs.iter().map(String::as_str));
+        Ok(Self { pools, pairs })
+    }
+
+    fn matches(&self, pool: &CachedPool) -> bool {
+        (self.pools.is_empty() || self.pools.contains(&pool.address)) && self.pairs.matches(pool)
+    }
+}
+
+fn parse_address(address: &str) -> Result<Address, Status> {
+    address
+        .parse()
+        .map_err(|_| Status::invalid_argument(format!("invalid address {address:?}")))
+}
+
+impl GrpcService {
+    /// Forward the events `select` maps to a message until the client goes away or the scanner stops.
+    /// A bounded channel holds the client to its own pace; a client lagging the event bus skips events.
+    fn stream<T, F>(&self, select: F) -> ReceiverStream<Result<T, Status>>
+    where
+        T: Send + 'static,
+        F: Fn(ScannerEvent) -> Option<T> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let mut events = self.ctx.events.subscribe();
+        let cancel = self.ctx.cancel.clone();
+        self.ctx.spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => return,
+                    _ = tx.closed() => return,
+                    event = events.recv() => match event {
+                        Ok(event) => {
+                            if let Some(message) = select(event)
+                                && tx.send(Ok(message)).await.is_err()
+                            {
+                                return;
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => debug!("gRPC subscriber lagged, skipped {} events", skipped),
+                        Err(RecvError::Closed) => return,
+                    },
+                }
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+}
+
+#[tonic::async_trait]
+impl ScannerService for GrpcService {
+    async fn list_pools(
+        &self,
+        _request: Request<proto::ListPoolsRequest>,
+    ) -> Result<Response<proto::ListPoolsResponse>, Status> {
+        let pools = self.ctx.snapshot(self.stale_after).into_iter().map(Into::into).collect();
+        Ok(Response::new(proto::ListPoolsResponse { pools }))
+    }
+
+    async fn get_pool(&self, request: Request<proto::GetPoolRequest>) -> Result<Response<proto::PoolState>, Status> {
+        let address = parse_address(&request.get_ref().address)?;
+        let slot = self
+            .ctx
+            .state
+            .get(&address)
+            .ok_or_else(|| Status::not_found(format!("pool {address} is not tracked")))?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        Ok(Response::new(slot.snapshot(now, self.stale_after).into()))
+    }
+
+    type SubscribePricesStream = ReceiverStream<Result<proto::PriceUpdate, Status>>;
+
+    async fn subscribe_prices(
+        &self,
+        request: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribePricesStream>, Status> {
+        let selection = Selection::parse(request.into_inner())?;
+        Ok(Response::new(self.stream(move |event| match event {
+            ScannerEvent::PriceUpdate { pool, price, previous } if selection.matches(&pool) => Some(proto::PriceUpdate {
+                pool: Some((&pool).into()),
+                price: Some((&price).into()),
+                previous: previous.as_ref().map(Into::into),
+            }),
+            _ => None,
+        })))
+    }
+
+    type SubscribeSwapsStream = ReceiverStream<Result<proto::Swap, Status>>;
+
+    async fn subscribe_swaps(
+        &self,
+        request: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeSwapsStream>, Status> {
+        let selection = Selection::parse(request.into_inner())?;
+        let ctx = Arc::clone(&self.ctx);
+        Ok(Response::new(self.stream(move |event| match event {
+            ScannerEvent::Swap(swap) => {
+                let slot = ctx.state.get(&swap.pool_address)?;
+                selection.matches(&slot.pool).then(|| (&swap).into())
+            }
+            _ => None,
+        })))
+    }
+}
+
+impl From<&CachedPool> for proto::Pool {
+    fn from(pool: &CachedPool) -> Self {
+        Self {
+            address: pool.address.to_string(),
+            protocol: pool.protocol.clone(),
+            token0: pool.token0.to_string(),
+            token1: pool.token1.to_string(),
+            token0_symbol: pool.token0_symbol.clone(),
+            token1_symbol: pool.token1_symbol.clone(),
+            token0_decimals: pool.token0_decimals.into(),
+            token1_decimals: pool.token1_decimals.into(),
+            fee: pool.fee,
+            liquidity_usd: pool.liquidity_usd,
+            volume_24h_usd: pool.volume_24h_usd,
+        }
+    }
+}
+
+impl From<&PoolPrice> for proto::Price {
+    fn from(price: &PoolPrice) -> Self {
+        Self {
+            pool_address: price.pool_address.to_string(),
+            token0_price: price.token0_price,
+            token1_price: price.token1_price,
+            timestamp: price.timestamp,
+            block_number: price.block_number,
+            token0_price_usd: price.token0_price_usd,
+            token1_price_usd: price.token1_price_usd,
+            amount0: price.amount0,
+            amount1: price.amount1,
+            suspect: price.suspect,
+        }
+    }
+}
+
+impl From<PoolSnapshot> for proto::PoolState {
+    fn from(snapshot: PoolSnapshot) -> Self {
+        Self {
+            pool: Some((&snapshot.pool).into()),
+            price: snapshot.price.as_ref().map(Into::into),
+            last_activity: snapshot.last_activity,
+            stale: snapshot.stale,
+        }
+    }
+}
+
+impl From<&Swap> for proto::Swap {
+    fn from(swap: &Swap) -> Self {
+        Self {
+            pool_address: swap.pool_address.to_string(),
+            token0: swap.token0.to_string(),
+            token1: swap.token1.to_string(),
+            amount0: swap.amount0,
+            amount1: swap.amount1,
+            zero_for_one: swap.zero_for_one,
+            price: swap.price,
+            volume_usd: swap.volume_usd,
+            sender: swap.sender.to_string(),
+            recipient: swap.recipient.to_string(),
+            transaction_hash: swap.transaction_hash.map(|hash| hash.to_string()),
+            log_index: swap.log_index,
+            block_number: swap.block_number,
+            timestamp: swap.timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_and_conversion() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            token0_decimals: 18,
+            token1_decimals: 6,
+            fee: 500,
+            ..Default::default()
+        };
+        let other = CachedPool { address: Address::repeat_byte(2), ..pool.clone() };
+
+        let selection = Selection::parse(proto::SubscribeRequest {
+            pools: vec![pool.address.to_string()],
+            pairs: vec!["usdc/weth".to_string()],
+        })
+        .unwrap();
+        assert!(selection.matches(&pool));
+        assert!(!selection.matches(&other));
+        assert!(Selection::parse(proto::SubscribeRequest::default()).unwrap().matches(&other));
+        let invalid = proto::SubscribeRequest { pools: vec!["0x12".to_string()], ..Default::default() };
+        assert_eq!(Selection::parse(invalid).err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let message = proto::Pool::from(&pool);
+        assert_eq!(message.address, pool.address.to_string());
+        assert_eq!((message.token0_decimals, message.token1_decimals, message.fee), (18, 6, 500));
+        let price = PoolPrice { token0_price: 3000.0, block_number: Some(7), ..Default::default() };
+        let message = proto::Price::from(&price);
+        assert_eq!((message.token0_price, message.block_number, message.amount0), (3000.0, Some(7), None));
+    }
+}
@@ -0,0 +1,116 @@
+use super::rediscovery::{next_config, ScanConfig};
+use super::{RpcProvider, ScannerContext};
+use crate::discovery::{creation_event_signature, FactoryClient};
+use crate::events::{ScannerError, ScannerEvent};
+use crate::types::ProtocolConfig;
+use alloy::primitives::Address;
+use alloy::rpc::types::eth::Filter;
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, info};
+
+/// Delay before resubscribing to factory events after the stream ends or fails.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Watch factory `PairCreated` / `PoolCreated` events and add qualifying new pools to the scanner.
+/// Pools must pass the token/pair whitelists. They are created empty, so the USD liquidity threshold is not applied.
+/// With a ranking, a new pool is ranked together with the tracked pools of its pair (`PoolFilter::admit`): it is
+/// skipped when the pair is full of better pools, and evicts the pools it outranks.
+/// A configuration received on `configs` replaces the protocols and filter; the factory subscription is renewed
+/// when the set of factories changed.
+pub(crate) async fn run_live_discovery(
+    ctx: Arc<ScannerContext>,
+    provider: RpcProvider,
+    mut configs: watch::Receiver<ScanConfig>,
+) {
+    let client = FactoryClient::new(Arc::clone(&provider));
+    let mut config = configs.borrow_and_update().clone();
+
+    loop {
+        let protocols = with_factory(&config);
+        if protocols.is_empty() {
+            tokio::select! {
+                _ = ctx.cancel.cancelled() => return,
+                reloaded = next_config(&mut configs) => config = reloaded,
+            }
+            continue;
+        }
+        let factories: Vec<Address> = protocols.iter().map(|p| p.factory).collect();
+        let topics: Vec<_> = protocols.iter().map(|p| creation_event_signature(&p.pool_type)).collect();
+        let filter = Filter::new().address(factories).event_signature(topics);
+
+        let subscription = tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            subscription = provider.subscribe_logs(&filter) => subscription,
+        };
+        let mut resubscribe_now = false;
+        match subscription {
+            Ok(sub) => {
+                info!("Watching {} factories for new pools", protocols.len());
+                let mut stream = sub.into_stream();
+                loop {
+                    let log = tokio::select! {
+                        _ = ctx.cancel.cancelled() => return,
+                        reloaded = next_config(&mut configs) => {
+                            config = reloaded;
+                            resubscribe_now = with_factory(&config) != protocols;
+                            if resubscribe_now {
+                                break;
+                            }
+                            continue;
+                        }
+                        log = stream.next() => log,
+                    };
+                    let Some(log) = log else { break };
+                    let Some(protocol) = protocols.iter().find(|p| p.factory == log.address()) else {
+                        continue;
+                    };
+                    let pool = match client.pool_from_creation_log(protocol, &log).await {
+                        Ok(Some(pool)) => pool,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            debug!("Undecodable factory log from {}: {}", protocol.name, e);
+                            continue;
+                        }
+                    };
+                    let tracked = ctx.state.pair_pools(pool.token0, pool.token1);
+                    let Some(evicted) = config.filter.admit(&pool, &tracked) else {
+                        continue;
+                    };
+                    let added = ctx.state.insert_all(vec![pool]);
+                    for pool in ctx.state.remove_all(&evicted) {
+                        info!(
+                            "Pool {:?} {}/{} outranked by a new pool of its pair",
+                            pool.address, pool.token0_symbol, pool.token1_symbol
+                        );
+                        ctx.events.publish(|| ScannerEvent::PoolRemoved(pool.clone()));
+                    }
+                    for pool in added {
+                        info!(
+                            "New pool {:?} {}/{} on {}",
+                            pool.address, pool.token0_symbol, pool.token1_symbol, protocol.name
+                        );
+                        ctx.events.publish(|| ScannerEvent::PoolDiscovered(pool.clone()));
+                    }
+                }
+            }
+            Err(e) => ctx
+                .events
+                .error(ScannerError::Subscription(format!("factory subscription: {}", e))),
+        }
+
+        if !resubscribe_now {
+            tokio::select! {
+                _ = ctx.cancel.cancelled() => return,
+                _ = tokio::time::sleep(RESUBSCRIBE_DELAY) => {}
+            }
+        }
+    }
+}
+
+/// The protocols of `config` whose factory can be watched.
+fn with_factory(config: &ScanConfig) -> Vec<ProtocolConfig> {
+    config.protocols.iter().filter(|p| !p.factory.is_zero()).cloned().collect()
+}
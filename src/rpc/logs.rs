@@ -0,0 +1,67 @@
+use super::RpcProvider;
+use alloy::rpc::types::eth::{Filter, Log};
+use eyre::Result;
+use tracing::debug;
+
+/// `eth_getLogs` over a block range in spans of at most `chunk` blocks. Most nodes cap the block range or
+/// result size of one request; a failed request is retried with half the span, down to single blocks.
+pub(crate) struct LogChunks<'a> {
+    provider: &'a RpcProvider,
+    filter: &'a Filter,
+    /// Blocks not fetched yet, inclusive.
+    from: u64,
+    to: u64,
+    chunk: u64,
+    backwards: bool,
+    done: bool,
+}
+
+impl<'a> LogChunks<'a> {
+    /// The logs matching `filter` from `from` to `to` (inclusive), oldest span first.
+    pub(crate) fn new(provider: &'a RpcProvider, filter: &'a Filter, from: u64, to: u64, chunk: u64) -> Self {
+        Self {
+            provider,
+            filter,
+            from,
+            to,
+            chunk: chunk.max(1),
+            backwards: false,
+            done: from > to,
+        }
+    }
+
+    /// Walk the range from `to` down to `from` instead, newest span first.
+    pub(crate) fn backwards(mut self) -> Self {
+        self.backwards = true;
+        self
+    }
+
+    /// The next span (`from`, `to`) and its logs in block order, or `None` once the range is covered.
+    pub(crate) async fn next(&mut self) -> Result<Option<(u64, u64, Vec<Log>)>> {
+        while !self.done {
+            let (from, to) = if self.backwards {
+                (self.to.saturating_sub(self.chunk - 1).max(self.from), self.to)
+            } else {
+                (self.from, self.from.saturating_add(self.chunk - 1).min(self.to))
+            };
+            match self.provider.get_logs(&self.filter.clone().from_block(from).to_block(to)).await {
+                Ok(logs) => {
+                    if self.backwards {
+                        self.done = from == self.from;
+                        self.to = from.saturating_sub(1);
+                    } else {
+                        self.done = to == self.to;
+                        self.from = to.saturating_add(1);
+                    }
+                    return Ok(Some((from, to, logs)));
+                }
+                Err(e) if self.chunk > 1 => {
+                    self.chunk /= 2;
+                    debug!("eth_getLogs failed ({}); retrying with {} block chunks", e, self.chunk);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+}
@@ -1,179 +1,1172 @@
+use crate::arbitrage::{self, ArbOpportunity, ArbitrageConfig, CycleOpportunity, GasOracle, GasPrice, TokenGraph};
 use crate::config;
-use crate::discovery::{filter_pools_by_token_whitelist, PoolDiscovery};
-use crate::liquidity_pools::{BaseLiquidityPool, EthereumLog, UniswapV2, UniswapV3};
-use crate::types::{CachedPool, PoolPrice};
-use alloy::primitives::Address;
+use crate::discovery::{PoolDiscovery, PoolFilter};
+use crate::events::{ErrorCallback, EventHub, ScannerError, ScannerEvent, ScannerStatus, StatusCallback};
+use crate::liquidity_pools::positions::estimate_fees;
+use crate::liquidity_pools::{EthereumLog, LpPosition, PositionValue, Quote};
+use crate::metrics::{MetricsConfig, Twap, Volatility};
+use crate::mev::{SandwichDetector, SwapLeg};
+use crate::pricing::{
+    is_usd_stablecoin, IndexConfig, OracleBook, OracleConfig, OraclePrice, PairBook, PairPrice, PricingConfig,
+    SpreadUpdate, TokenIndex, TokenIndexPrice, UsdPricer,
+};
+use crate::sinks::{Sink, SinkRetry};
+#[cfg(feature = "sqlite")]
+use crate::store::PoolStore;
+use crate::spikes::SpikeFilterConfig;
+#[cfg(feature = "rocksdb")]
+use crate::state_store::StateStore;
+use crate::types::{CachedPool, DiscoveryConfig, PoolSnapshot, ProtocolConfig};
+use alloy::primitives::{Address, U256};
 use alloy::providers::{Provider, ProviderBuilder, WsConnect};
 use alloy::pubsub::PubSubFrontend;
 use alloy::rpc::types::eth::{Filter, Log};
 use eyre::Result;
 use futures::StreamExt;
-use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
 use tracing::{info, warn};
 
-pub type PriceChangeCallback = Arc<dyn Fn(CachedPool, PoolPrice, Option<PoolPrice>) + Send + Sync>;
+mod backfill;
+mod candles;
+#[cfg(feature = "rocksdb")]
+mod checkpoint;
+#[cfg(feature = "hot-reload")]
+mod config_watch;
+mod depth;
+mod fanout;
+mod gas;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod live_discovery;
+pub(crate) mod logs;
+pub mod multicall;
+mod oracle;
+pub(crate) mod pair_filter;
+#[cfg(feature = "sqlite")]
+mod persist;
+mod pipeline;
+#[cfg(feature = "server")]
+mod prometheus;
+mod rediscovery;
+mod replay;
+#[cfg(feature = "server")]
+mod rest;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+mod sse;
+mod staleness;
+mod state;
+mod stats;
+mod warmup;
+#[cfg(feature = "server")]
+mod websocket;
+mod whales;
 
-struct ScannerState {
-    pools: Vec<CachedPool>,
-    liquidity_pools: HashMap<Address, Box<dyn BaseLiquidityPool>>,
-    current_prices: HashMap<Address, PoolPrice>,
-    on_price_change: PriceChangeCallback,
+pub use crate::events::PriceChangeCallback;
+pub use backfill::BackfillSummary;
+pub use replay::{Replay, ReplaySummary};
+pub use depth::{DepthConfig, DepthLevel, DepthProfile};
+#[cfg(feature = "grpc")]
+pub use grpc::{proto, GrpcConfig};
+pub use pipeline::{OverflowPolicy, PipelineConfig};
+pub use staleness::PriceStale;
+use pipeline::{LogRouter, Pipeline, PriceUpdate};
+use logs::LogChunks;
+pub use rediscovery::ScanConfig;
+use state::{AppliedLog, ScannerState};
+#[cfg(feature = "server")]
+pub use server::ServerConfig;
+pub use stats::{LatencyHistogram, ScannerStats};
+pub use whales::{LargeSwap, Swap};
+use stats::StatsCollector;
+
+/// Shared WebSocket provider handle used by the scanner and on-chain discovery.
+pub type RpcProvider = Arc<dyn Provider<PubSubFrontend>>;
+
+/// Delay before the first reconnect attempt; doubles per attempt up to `MAX_RECONNECT_DELAY`.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// Block span of each `eth_getLogs` request while backfilling; halved when the node rejects a range.
+const BACKFILL_CHUNK_BLOCKS: u64 = 2_000;
+
+/// Tuning knobs for a `Scanner`. `ScannerOptions::default()` is what `Scanner::new` uses.
+#[derive(Debug, Clone, Default)]
+pub struct ScannerOptions {
+    /// Protocols, discovery settings and pool filter to use instead of `scanner.toml` or `protocols.json` and
+    /// `tokens.json`; no config file is read.
+    pub config: Option<ScanConfig>,
+    /// WebSocket RPC endpoint; replaces `RPC_URL` and `rpc.url` of `scanner.toml`.
+    pub rpc_url: Option<String>,
+    pub pipeline: PipelineConfig,
+    /// Parent token: cancelling it tears the scanner down like `Scanner::shutdown`.
+    pub cancellation_token: Option<CancellationToken>,
+    /// Watch factory `PairCreated` / `PoolCreated` events and start tracking new pools that pass
+    /// the token/pair whitelists. Each addition is published as `ScannerEvent::PoolDiscovered`.
+    pub live_discovery: bool,
+    /// Publish a `ScannerEvent::DepthUpdate` slippage ladder for every tracked pool on an interval.
+    pub depth: Option<DepthConfig>,
+    /// Per-pool metrics computed from the price stream (TWAPs, moving averages, candles).
+    pub metrics: MetricsConfig,
+    /// Attach USD prices to every update by routing through these reference pools.
+    pub pricing: Option<PricingConfig>,
+    /// Maintain a liquidity-weighted price per token pair across pools and publish it as
+    /// `ScannerEvent::PairPrice` on every update of one of the pair's pools.
+    pub pair_prices: bool,
+    /// Quote tokens in priority order, e.g. `[USDC, USDT, WETH]`. Every update of a pool trading one of
+    /// them carries `PoolPrice::quoted`: the price of the other token in the first listed one it trades.
+    pub quote_tokens: Vec<Address>,
+    /// Publish `ScannerEvent::SpreadUpdate` whenever an update leaves the highest and lowest price of a
+    /// pair tracked on several pools at least this many basis points apart.
+    pub spread_threshold_bps: Option<f64>,
+    /// Publish `ScannerEvent::ArbOpportunity` whenever an update opens a round trip between two pools
+    /// of a pair that is profitable after both fees and the configured gas cost.
+    pub arbitrage: Option<ArbitrageConfig>,
+    /// Poll Chainlink feeds and publish `ScannerEvent::OracleDeviation` when a pool's implied USD price
+    /// strays from them.
+    pub oracles: Option<OracleConfig>,
+    /// Flag or withhold updates that move the price far on a small swap, publishing each as
+    /// `ScannerEvent::PriceSpike`.
+    pub spike_filter: Option<SpikeFilterConfig>,
+    /// Publish `ScannerEvent::PriceStale` for pools without a log for this long, and mark them stale in
+    /// `Scanner::snapshot`.
+    pub stale_after: Option<Duration>,
+    /// Publish `ScannerEvent::LargeSwap` for every swap worth at least this much USD.
+    pub large_swap_usd: Option<f64>,
+    /// Correlate each pool's swaps within a block and publish `ScannerEvent::Sandwich` for
+    /// front-run → victim → back-run patterns.
+    pub sandwich_detection: bool,
+    /// Maintain one liquidity-weighted price per token across all its pools and publish it as
+    /// `ScannerEvent::TokenIndex` on every update of one of those pools.
+    pub token_index: Option<IndexConfig>,
+    /// Persist discovered pools, token metadata and last-seen prices. When discovery fails at
+    /// startup (e.g. no network), the scanner falls back to the pools in the store.
+    #[cfg(feature = "sqlite")]
+    pub store: Option<Arc<PoolStore>>,
+    /// Checkpoint every pool's decoder state (reserves, sqrtPrice, tick, known liquidity) with the block
+    /// it was last updated at. On start, tracked pools resume from their checkpoints and the logs of the
+    /// blocks missed while the scanner was down are backfilled before live logs.
+    #[cfg(feature = "rocksdb")]
+    pub state_store: Option<Arc<StateStore>>,
+    /// Outputs fed every event (files, databases, message brokers; see `crate::sinks`). Each runs in
+    /// its own task, so a slow or failing sink never holds up the others.
+    pub sinks: Vec<Arc<dyn Sink>>,
+    /// Retry policy for failing sink calls.
+    pub sink_retry: SinkRetry,
+    /// Built-in HTTP server, e.g. Prometheus metrics at `/metrics`.
+    #[cfg(feature = "server")]
+    pub server: Option<ServerConfig>,
+    /// gRPC service with pool queries and price / swap subscriptions (`proto/scanner.proto`).
+    #[cfg(feature = "grpc")]
+    pub grpc: Option<GrpcConfig>,
+    /// Reload `protocols.json` and `tokens.json` (or `scanner.toml`) when they change and apply them to the
+    /// running scanner: newly enabled or changed protocols are discovered, pools of disabled protocols and pools
+    /// the new token filter rejects are removed, and changed discovery settings or filters trigger a full
+    /// rediscovery. A file that fails to load is reported as `ScannerError::Config` and the running
+    /// configuration is kept. Ignored with `config`.
+    #[cfg(feature = "hot-reload")]
+    pub watch_config: bool,
 }
 
-pub struct Scanner {
-    provider: Arc<dyn Provider<PubSubFrontend>>,
-    state: Arc<Mutex<ScannerState>>,
+/// State shared between the scanner handle and its background tasks.
+struct ScannerContext {
+    state: ScannerState,
+    events: EventHub,
+    stats: StatsCollector,
+    pipeline: Pipeline,
+    pricing: Option<UsdPricer>,
+    /// Present when pair prices, the spread monitor or the arbitrage detector are on.
+    pairs: Option<PairBook>,
+    publish_pair_prices: bool,
+    spread_threshold_bps: Option<f64>,
+    arbitrage: Option<ArbitrageConfig>,
+    /// Present when `ArbitrageConfig::cycles` is on.
+    graph: Option<TokenGraph>,
+    /// Latest gas price; present with `ScannerOptions::arbitrage`.
+    gas: Option<GasOracle>,
+    oracles: Option<OracleBook>,
+    spike_filter: Option<SpikeFilterConfig>,
+    large_swap_usd: Option<f64>,
+    sandwiches: Option<SandwichDetector>,
+    token_index: Option<TokenIndex>,
+    cancel: CancellationToken,
+    tasks: TaskTracker,
+    #[cfg(feature = "sqlite")]
+    store: Option<Arc<PoolStore>>,
+    #[cfg(feature = "rocksdb")]
+    state_store: Option<Arc<StateStore>>,
+    sinks: Vec<Arc<dyn Sink>>,
+    sink_retry: SinkRetry,
 }
 
-fn config_path(env_key: &str, default: &str) -> std::path::PathBuf {
-    std::env::var(env_key)
-        .map(std::path::PathBuf::from)
-        .unwrap_or_else(|_| std::env::current_dir().unwrap().join(default))
+impl ScannerContext {
+    fn new(on_price_change: PriceChangeCallback, options: ScannerOptions) -> Self {
+        Self {
+            state: ScannerState::new(options.metrics, options.quote_tokens),
+            events: EventHub::new(on_price_change),
+            stats: StatsCollector::default(),
+            pipeline: Pipeline::new(options.pipeline),
+            pricing: options.pricing.map(UsdPricer::new),
+            pairs: (options.pair_prices || options.spread_threshold_bps.is_some() || options.arbitrage.is_some())
+                .then(PairBook::default),
+            publish_pair_prices: options.pair_prices,
+            spread_threshold_bps: options.spread_threshold_bps,
+            graph: options.arbitrage.as_ref().filter(|config| config.cycles).map(|_| TokenGraph::default()),
+            gas: options.arbitrage.as_ref().map(|_| GasOracle::default()),
+            arbitrage: options.arbitrage,
+            oracles: options.oracles.map(OracleBook::new),
+            spike_filter: options.spike_filter,
+            large_swap_usd: options.large_swap_usd,
+            sandwiches: options.sandwich_detection.then(SandwichDetector::default),
+            token_index: options.token_index.map(TokenIndex::new),
+            cancel: options
+                .cancellation_token
+                .map(|parent| parent.child_token())
+                .unwrap_or_default(),
+            tasks: TaskTracker::new(),
+            #[cfg(feature = "sqlite")]
+            store: options.store,
+            #[cfg(feature = "rocksdb")]
+            state_store: options.state_store,
+            sinks: options.sinks,
+            sink_retry: options.sink_retry,
+        }
+    }
+
+    /// Spawn a background task that is tracked for `Scanner::shutdown`.
+    fn spawn<F>(&self, task: F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    fn stats(&self) -> ScannerStats {
+        let pipeline = &self.pipeline;
+        ScannerStats {
+            log_queue_depth: pipeline.log_queue_depth.load(Ordering::Relaxed),
+            update_queue_depth: pipeline.updates.len() as u64,
+            dropped_updates: pipeline.updates.dropped.load(Ordering::Relaxed),
+            coalesced_updates: pipeline.updates.coalesced.load(Ordering::Relaxed),
+            ..self.stats.snapshot()
+        }
+    }
+
+    fn snapshot(&self, stale_after: Option<Duration>) -> Vec<PoolSnapshot> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.state
+            .addresses()
+            .iter()
+            .filter_map(|address| self.state.get(address))
+            .map(|slot| slot.snapshot(now, stale_after))
+            .collect()
+    }
+}
+
+pub struct Scanner {
+    rpc_url: String,
+    /// `scanner.toml`, re-read like the JSON files on every discovery.
+    config_file: Option<PathBuf>,
+    /// `ScannerOptions::config`, used in place of the files.
+    config: Option<ScanConfig>,
+    provider: RpcProvider,
+    ctx: Arc<ScannerContext>,
+    live_discovery: bool,
+    depth: Option<DepthConfig>,
+    has_candles: bool,
+    /// `MetricsConfig::warmup`.
+    warmup: Option<Duration>,
+    stale_after: Option<Duration>,
+    #[cfg(feature = "server")]
+    server: Option<ServerConfig>,
+    #[cfg(feature = "grpc")]
+    grpc: Option<GrpcConfig>,
+    #[cfg(feature = "hot-reload")]
+    watch_config: bool,
 }
 
 impl Scanner {
-    /// Create a scanner with a price-change callback. Reads `RPC_URL` from the environment.
+    /// Create a scanner with a price-change callback. Reads `RPC_URL` from the environment, or `rpc.url` from
+    /// `scanner.toml`.
     pub async fn new(on_price_change: PriceChangeCallback) -> Result<Self> {
-        let rpc_url = std::env::var("RPC_URL").map_err(|_| eyre::eyre!("RPC_URL must be set"))?;
-        let ws = WsConnect::new(rpc_url);
-        let provider = ProviderBuilder::new().on_ws(ws).await?;
+        Self::with_options(on_price_change, ScannerOptions::default()).await
+    }
+
+    /// Create a scanner with a price-change callback and explicit options. Reads `RPC_URL` from the environment,
+    /// or `rpc.url` from `scanner.toml` (`config::scanner_file_path`), whose sinks and alert rules are added to
+    /// `options.sinks`. With `options.config`, no file is read and the endpoint is `options.rpc_url` or `RPC_URL`.
+    pub async fn with_options(on_price_change: PriceChangeCallback, mut options: ScannerOptions) -> Result<Self> {
+        let config = options.config.take();
+        let config_file = config::scanner_file_path().filter(|_| config.is_none());
+        let file = config_file.as_ref().map(config::ScannerFile::load).transpose()?;
+        let rpc_url = match (options.rpc_url.take(), &file) {
+            (Some(url), _) => Some(url),
+            (None, Some(file)) => file.rpc_url(),
+            (None, None) => config::secret("RPC_URL"),
+        }
+        .ok_or_else(|| eyre::eyre!("RPC_URL must be set"))?;
+        if let Some(file) = &file {
+            options.sinks.extend(file.open_sinks().await?);
+        }
+        let provider = connect(&rpc_url).await?;
 
         Ok(Self {
-            provider: Arc::new(provider),
-            state: Arc::new(Mutex::new(ScannerState {
-                pools: vec![],
-                liquidity_pools: HashMap::new(),
-                current_prices: HashMap::new(),
-                on_price_change,
-            })),
+            rpc_url,
+            config_file,
+            config,
+            provider,
+            live_discovery: options.live_discovery,
+            depth: options.depth.take(),
+            has_candles: !options.metrics.candle_intervals.is_empty(),
+            warmup: options.metrics.warmup,
+            stale_after: options.stale_after,
+            #[cfg(feature = "server")]
+            server: options.server.take(),
+            #[cfg(feature = "grpc")]
+            grpc: options.grpc.take(),
+            #[cfg(feature = "hot-reload")]
+            watch_config: options.watch_config,
+            ctx: Arc::new(ScannerContext::new(on_price_change, options)),
         })
     }
 
+    /// Register a callback for runtime errors (subscription failures, undecodable logs).
+    /// Errors are still logged with `tracing::warn!`.
+    pub fn on_error(&self, callback: ErrorCallback) {
+        self.ctx.events.set_error_callback(callback);
+    }
+
+    /// Register a callback for subscription lifecycle changes (subscribed, dropped, reconnecting).
+    pub fn on_status(&self, callback: StatusCallback) {
+        self.ctx.events.set_status_callback(callback);
+    }
+
+    /// Subscribe to the scanner event bus. Receives price updates, status changes and errors.
+    pub fn subscribe(&self) -> broadcast::Receiver<ScannerEvent> {
+        self.ctx.events.subscribe()
+    }
+
+    /// Token cancelled when the scanner shuts down. Cancel it (or a parent passed via
+    /// `ScannerOptions::cancellation_token`) to stop every background task.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.ctx.cancel.clone()
+    }
+
+    /// Cancel the subscription, reconnect, parser and delivery tasks and wait until all of them exited.
+    pub async fn shutdown(&self) {
+        self.ctx.cancel.cancel();
+        self.ctx.tasks.close();
+        self.ctx.tasks.wait().await;
+    }
+
+    /// Health counters: logs received, parse errors, last block, subscription uptime,
+    /// callback latency, per-pool update counts and queue depths. Use `since_last_log` to detect a stalled feed.
+    pub fn stats(&self) -> ScannerStats {
+        self.ctx.stats()
+    }
+
+    /// Start tracking additional pools. Already tracked addresses are ignored; the log
+    /// subscription is re-established to include the new pools. Returns the pools that were added.
+    pub fn add_pools(&self, pools: Vec<CachedPool>) -> Vec<CachedPool> {
+        self.ctx.state.insert_all(pools)
+    }
+
+    /// Stop tracking the given pool addresses; the log subscription is re-established without them.
+    /// Returns the pools that were removed.
+    pub fn remove_pools(&self, addresses: &[Address]) -> Vec<CachedPool> {
+        self.ctx.state.remove_all(addresses)
+    }
+
+    /// What selling `amount_in` (raw units) of `token_in` into a tracked `pool` would return right now:
+    /// amount out, effective price and price impact. V2 pools use the constant-product formula with the
+    /// pool fee; V3 pools walk the ticks known from Mint/Burn events. Fails for untracked pools, pools
+    /// that haven't seen an update yet, and V3 trades larger than the known liquidity.
+    pub fn quote_exact_input(&self, pool: Address, token_in: Address, amount_in: U256) -> Result<Quote> {
+        let slot = self.ctx.state.get(&pool).ok_or_else(|| eyre::eyre!("Pool {} is not tracked", pool))?;
+        slot.quote_exact_input(token_in, amount_in)
+    }
+
+    /// Every tracked pool with its last delivered price and, with `ScannerOptions::stale_after`, whether
+    /// that price is stale.
+    pub fn snapshot(&self) -> Vec<PoolSnapshot> {
+        self.ctx.snapshot(self.stale_after)
+    }
+
+    /// Value, impermanent loss and PnL of a hypothetical LP `position` in a tracked pool at its current
+    /// price. `fees` is an estimate of one day's earnings: the last 24 hours of observed volume at the pool's
+    /// fee, shared pro rata with the pool's in-range liquidity (zero when the position is out of range).
+    /// Fails until the pool has seen an update.
+    pub fn position_value(&self, pool: Address, position: &LpPosition) -> Result<PositionValue> {
+        let slot = self.ctx.state.get(&pool).ok_or_else(|| eyre::eyre!("pool {} is not tracked", pool))?;
+        let price = slot
+            .current_price()
+            .ok_or_else(|| eyre::eyre!("pool {} has no price yet", pool))?;
+        let (decimals0, decimals1) = (slot.pool.token0_decimals, slot.pool.token1_decimals);
+        let value = position.value(price.token0_price, decimals0, decimals1, 0.0);
+        if !value.in_range {
+            return Ok(value);
+        }
+        let volume = slot.volume(chrono::Utc::now().timestamp() as u64);
+        let pool_liquidity = slot.liquidity() as f64 / 10f64.powf((f64::from(decimals0) + f64::from(decimals1)) / 2.0);
+        let fee = if slot.pool.fee == 0 { 3000 } else { slot.pool.fee };
+        let fees = estimate_fees(volume.volume1, fee, position.liquidity(decimals0, decimals1), pool_liquidity);
+        Ok(position.value(price.token0_price, decimals0, decimals1, fees))
+    }
+
+    /// Liquidity-weighted price of `token` across its pools (`ScannerOptions::token_index`). `None` when
+    /// the index is off or no pool of the token could be priced in the numéraire yet.
+    pub fn token_index(&self, token: Address) -> Option<TokenIndexPrice> {
+        self.ctx.token_index.as_ref().and_then(|index| index.get(token))
+    }
+
+    /// Base and priority fee of the latest block. `None` without `ScannerOptions::arbitrage` or before the
+    /// first block header.
+    pub fn gas_price(&self) -> Option<GasPrice> {
+        self.ctx.gas.as_ref().and_then(|gas| gas.latest())
+    }
+
+    /// Latest Chainlink answer for `token` (`ScannerOptions::oracles`). `None` for tokens without a feed
+    /// or before the first poll.
+    pub fn oracle_price(&self, token: Address) -> Option<OraclePrice> {
+        self.ctx.oracles.as_ref().and_then(|oracles| oracles.get(token))
+    }
+
+    /// USD price of `token`, routed through `ScannerOptions::pricing` reference pools. `None` when pricing
+    /// is off or the token isn't reachable from a USD token through them.
+    pub fn token_price_usd(&self, token: Address) -> Option<f64> {
+        self.ctx.pricing.as_ref().and_then(|pricer| pricer.token_price_usd(token))
+    }
+
+    /// Latest liquidity-weighted price of a token pair across its pools (`ScannerOptions::pair_prices`),
+    /// as token0 (the lower address) in token1 units.
+    pub fn pair_price(&self, token_a: Address, token_b: Address) -> Option<PairPrice> {
+        self.ctx.pairs.as_ref().and_then(|pairs| pairs.get(token_a, token_b))
+    }
+
+    /// Time-weighted average prices of a tracked pool over `ScannerOptions::metrics` TWAP windows, as of now.
+    /// Empty for untracked pools and before the pool's first update.
+    pub fn twaps(&self, pool: Address) -> Vec<Twap> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.ctx.state.get(&pool).map(|slot| slot.twaps(now)).unwrap_or_default()
+    }
+
+    /// Realized volatility of a tracked pool over `MetricsConfig::volatility_window`. `None` when disabled,
+    /// for untracked pools, and until the window holds two price changes.
+    pub fn volatility(&self, pool: Address) -> Option<Volatility> {
+        self.ctx.state.get(&pool).and_then(|slot| slot.volatility())
+    }
+
+    /// Amount out and price impact of a ladder of trade sizes (in USD) in both directions of a tracked
+    /// pool. Sizes are converted to token amounts with the pool's USD prices (`ScannerOptions::pricing`), or
+    /// through the pool's own price when one of its tokens is a USD stablecoin.
+    pub fn depth_profile(&self, pool: Address, sizes_usd: &[f64]) -> Result<DepthProfile> {
+        let slot = self.ctx.state.get(&pool).ok_or_else(|| eyre::eyre!("Pool {} is not tracked", pool))?;
+        let timestamp = chrono::Utc::now().timestamp() as u64;
+        depth::depth_profile(&slot, sizes_usd, timestamp)
+            .ok_or_else(|| eyre::eyre!("Pool {} has no price yet or no USD price", pool))
+    }
+
+    /// `ScannerOptions::config`, or the protocols, discovery settings and pool filter from `scanner.toml`, or
+    /// `protocols.json` and `tokens.json`.
+    async fn load_config(&self) -> Result<ScanConfig> {
+        match &self.config {
+            Some(config) => Ok(config.clone()),
+            None => load_config(&self.provider, self.config_file.as_deref()).await,
+        }
+    }
+
+    /// Discover pools and apply the token filter. With a store, the result is saved, and used in place of a
+    /// failed discovery.
+    async fn discover_with(
+        &self,
+        discovery: &PoolDiscovery,
+        protocol_configs: &[ProtocolConfig],
+        discovery_config: &DiscoveryConfig,
+        filter: &PoolFilter,
+    ) -> Result<Vec<CachedPool>> {
+        let discovered = discovery.discover_pools(protocol_configs, discovery_config).await;
+        #[cfg(feature = "sqlite")]
+        let discovered = match (&self.ctx.store, discovered) {
+            (Some(store), Ok(pools)) => {
+                let (store, stored) = (Arc::clone(store), pools.clone());
+                if let Ok(Err(e)) = tokio::task::spawn_blocking(move || store.upsert_pools(&stored)).await {
+                    warn!("Failed to store discovered pools: {}", e);
+                }
+                Ok(pools)
+            }
+            (Some(store), Err(e)) => {
+                warn!("Discovery failed ({:#}); using pools from the store", e);
+                let store = Arc::clone(store);
+                tokio::task::spawn_blocking(move || store.load_pools()).await?.map(Into::into)
+            }
+            (None, discovered) => discovered,
+        };
+        Ok(filter.apply(discovered?.into_vec()))
+    }
+
+    /// Run discovery only: the pools `start` would track, from the same configuration, without subscribing.
+    pub async fn discover(&self) -> Result<Vec<CachedPool>> {
+        let config = self.load_config().await?;
+        let discovery = PoolDiscovery::with_provider(Arc::clone(&self.provider));
+        self.discover_with(&discovery, &config.protocols, &config.discovery, &config.filter).await
+    }
+
+    /// Process the discovered pools' Swap/Sync/Mint/Burn logs from `from_block` to `to_block` (inclusive) with
+    /// chunked `eth_getLogs` through the same decoding and analytics as live logs, instead of `start`ing (no
+    /// subscription). Updates are timestamped with their block's time and delivered in block and log order to
+    /// the price-change callback, the event bus and every sink of `ScannerOptions::sinks`, with the swaps,
+    /// spreads, arbitrage and other events they cause, candles (`MetricsConfig::candle_intervals`) completed
+    /// by the end of the range and depth updates (`ScannerOptions::depth`) on block time. `Scanner::stats`
+    /// counts the logs and updates. The sinks are closed when the range is done. Fails when `to_block` is
+    /// past the chain head.
+    pub async fn scan_range(&self, from_block: u64, to_block: u64) -> Result<BackfillSummary> {
+        let head = self.provider.get_block_number().await?;
+        if to_block > head {
+            eyre::bail!("to block {} is after the chain head {}", to_block, head);
+        }
+        if from_block > to_block {
+            eyre::bail!("from block {} is after to block {}", from_block, to_block);
+        }
+        let config = self.load_config().await?;
+        let discovery = PoolDiscovery::with_provider(Arc::clone(&self.provider));
+        let pools = self.discover_with(&discovery, &config.protocols, &config.discovery, &config.filter).await?;
+        info!("Scanning blocks {} to {} for {} pools", from_block, to_block, pools.len());
+        self.ctx.state.insert_all(pools);
+        backfill::scan_range(&self.ctx, &self.provider, from_block, to_block, self.depth.as_ref()).await
+    }
+
+    /// `scan_range` up to `to_block`, or to the chain head when `None` or past it.
+    pub async fn backfill(&self, from_block: u64, to_block: Option<u64>) -> Result<BackfillSummary> {
+        let head = self.provider.get_block_number().await?;
+        self.scan_range(from_block, to_block.unwrap_or(head).min(head)).await
+    }
+
     /// Load config from `protocols.json` and `tokens.json`, discover pools, filter by token whitelist, and subscribe to price changes.
     /// Config paths: `PROTOCOLS_JSON` (default `protocols.json`), `TOKENS_JSON` (default `tokens.json`), relative to current directory.
+    /// A `scanner.toml` (or the file named by `SCANNER_CONFIG`) replaces both; `ScannerOptions::config` replaces the files.
     pub async fn start(&mut self) -> Result<()> {
-        let protocols_path = config_path("PROTOCOLS_JSON", "protocols.json");
-        let tokens_path = config_path("TOKENS_JSON", "tokens.json");
+        let config = self.load_config().await?;
+
+        #[cfg(feature = "server")]
+        let listener = match &self.server {
+            Some(config) => Some(server::bind(config).await?),
+            None => None,
+        };
+        #[cfg(feature = "grpc")]
+        let grpc_listener = match &self.grpc {
+            Some(config) => Some(grpc::bind(config).await?),
+            None => None,
+        };
+
+        let discovery = PoolDiscovery::with_provider(Arc::clone(&self.provider));
+        let pools = self.discover_with(&discovery, &config.protocols, &config.discovery, &config.filter).await?;
+
+        info!("Starting scanner for {} pools", pools.len());
 
-        let (protocol_configs, discovery_config) =
-            config::load_protocols_file(protocols_path.to_str().unwrap())?;
-        let tokens = config::load_tokens_file(tokens_path.to_str().unwrap()).unwrap_or_default();
-        let token_whitelist: HashSet<Address> = tokens.into_values().collect();
+        let discovered = pools.clone();
+        self.ctx.state.insert_all(pools);
+        #[cfg(feature = "rocksdb")]
+        let backfill_from = self
+            .ctx
+            .state_store
+            .as_ref()
+            .and_then(|store| checkpoint::restore_pools(&self.ctx, store));
+        #[cfg(not(feature = "rocksdb"))]
+        let backfill_from = None;
 
-        if protocol_configs.is_empty() {
-            warn!(
-                "No enabled protocols (or THE_GRAPH_API_KEY unset). Set THE_GRAPH_API_KEY and enable protocols in protocols.json."
-            );
+        let (router, log_receivers) = LogRouter::new(&self.ctx.pipeline.config);
+        self.ctx.spawn(run_log_subscription(
+            self.rpc_url.clone(),
+            Arc::clone(&self.provider),
+            Arc::clone(&self.ctx),
+            router,
+            self.warmup,
+            backfill_from,
+        ));
+        for log_rx in log_receivers {
+            self.ctx.spawn(run_parser(Arc::clone(&self.ctx), log_rx));
         }
+        self.ctx.spawn(run_delivery(Arc::clone(&self.ctx)));
 
-        let discovery = PoolDiscovery::new();
-        let all_pools = discovery
-            .discover_pools(&protocol_configs, &discovery_config)
-            .await?;
-        let pools = filter_pools_by_token_whitelist(all_pools, &token_whitelist);
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &self.ctx.store {
+            self.ctx.spawn(persist::run_store_writer(Arc::clone(&self.ctx), Arc::clone(store)));
+        }
+        #[cfg(feature = "rocksdb")]
+        if let Some(store) = &self.ctx.state_store {
+            self.ctx.spawn(checkpoint::run_checkpoint_writer(Arc::clone(&self.ctx), Arc::clone(store)));
+        }
+        for sink in &self.ctx.sinks {
+            self.ctx.spawn(fanout::run_sink(Arc::clone(&self.ctx), Arc::clone(sink)));
+        }
 
-        info!("Starting scanner for {} pools", pools.len());
+        // Without a config watcher the sender is dropped and the tasks keep the initial configuration.
+        let refresh = config.discovery.cache_refresh_minutes > 0;
+        let (configs, config_updates) = watch::channel(config);
+        #[cfg(feature = "hot-reload")]
+        let watching = self.watch_config && self.config.is_none();
+        #[cfg(not(feature = "hot-reload"))]
+        let watching = false;
+        if refresh || watching {
+            self.ctx.spawn(rediscovery::run_rediscovery(
+                Arc::clone(&self.ctx),
+                discovery,
+                config_updates.clone(),
+                discovered,
+            ));
+        }
+        #[cfg(feature = "hot-reload")]
+        if watching {
+            self.ctx.spawn(config_watch::run_config_watcher(
+                Arc::clone(&self.ctx),
+                Arc::clone(&self.provider),
+                self.config_file.clone(),
+                configs,
+            ));
+        }
+        #[cfg(not(feature = "hot-reload"))]
+        drop(configs);
 
-        let addresses: Vec<Address> = pools.iter().map(|p| p.address).collect();
-
-        let mut lp_map: HashMap<Address, Box<dyn BaseLiquidityPool>> = HashMap::new();
-        for pool in &pools {
-            let lp: Box<dyn BaseLiquidityPool> = if pool.protocol.to_lowercase().contains("v2") {
-                Box::new(UniswapV2::new(
-                    pool.address,
-                    pool.token0_decimals,
-                    pool.token1_decimals,
-                ))
-            } else {
-                Box::new(UniswapV3::new(
-                    pool.address,
-                    pool.token0_decimals,
-                    pool.token1_decimals,
-                ))
-            };
-            lp_map.insert(pool.address, lp);
+        if self.has_candles {
+            self.ctx.spawn(candles::run_candle_publisher(Arc::clone(&self.ctx)));
         }
 
-        {
-            let mut state = self.state.lock().await;
-            state.pools = pools;
-            state.liquidity_pools = lp_map;
+        if let Some(window) = self.stale_after {
+            self.ctx.spawn(staleness::run_stale_monitor(Arc::clone(&self.ctx), window));
         }
 
-        let filter = Filter::new()
-            .address(addresses)
-            .events([
-                "Swap(address,address,int256,int256,uint160,uint128,int24)".as_bytes(),   // V3
-                "Swap(address,uint256,uint256,uint256,uint256,address)".as_bytes(),       // V2
-                "Sync(uint112,uint112)".as_bytes(),                                        // V2
-            ]);
+        if let Some(depth) = self.depth.clone() {
+            self.ctx.spawn(depth::run_depth_updates(Arc::clone(&self.ctx), depth));
+        }
 
-        let provider = Arc::clone(&self.provider);
-        let state = Arc::clone(&self.state);
+        if self.ctx.gas.is_some() {
+            self.ctx.spawn(gas::run_gas_tracker(Arc::clone(&self.ctx), Arc::clone(&self.provider)));
+        }
 
-        tokio::spawn(async move {
-            if let Err(e) = run_log_subscription(provider, state, filter).await {
-                warn!("Log subscription ended with error: {:?}", e);
-            }
-        });
+        if self.ctx.oracles.is_some() {
+            self.ctx.spawn(oracle::run_oracle_poller(Arc::clone(&self.ctx), Arc::clone(&self.provider)));
+        }
+
+        #[cfg(feature = "server")]
+        if let (Some(config), Some(listener)) = (self.server.clone(), listener) {
+            self.ctx.spawn(server::run_server(Arc::clone(&self.ctx), listener, config, self.stale_after));
+        }
+        #[cfg(feature = "grpc")]
+        if let Some(listener) = grpc_listener {
+            self.ctx.spawn(grpc::run_grpc(Arc::clone(&self.ctx), listener, self.stale_after));
+        }
+
+        if self.live_discovery {
+            self.ctx.spawn(live_discovery::run_live_discovery(
+                Arc::clone(&self.ctx),
+                Arc::clone(&self.provider),
+                config_updates,
+            ));
+        }
 
         Ok(())
     }
 }
 
+/// Protocols, discovery settings and pool filter from `config_file` (a `scanner.toml`), or `protocols.json` and
+/// `tokens.json`.
+async fn load_config(provider: &RpcProvider, config_file: Option<&Path>) -> Result<ScanConfig> {
+    // Token lists and tokens files with chain sections may span several chains; keep the connected chain's tokens.
+    let chain_id = provider.get_chain_id().await.ok();
+    let (protocol_configs, discovery_config, tokens, pair_whitelist, blacklist) = match config_file {
+        Some(path) => {
+            let file = config::ScannerFile::load(path)?;
+            let (protocol_configs, discovery_config) = file.protocols()?;
+            (protocol_configs, discovery_config, file.tokens(chain_id), file.token_pairs(chain_id)?, file.blacklist())
+        }
+        None => {
+            let protocols_path = config::config_path("PROTOCOLS_JSON", config::PROTOCOLS_FILES);
+            let tokens_path = config::config_path("TOKENS_JSON", config::TOKENS_FILES);
+            // The problems of both files in one error.
+            let protocols = config::load_protocols_file(protocols_path.to_str().unwrap());
+            let tokens = config::load_tokens_file_for_chain(tokens_path.to_str().unwrap(), chain_id);
+            let ((protocol_configs, discovery_config), tokens) = match (protocols, tokens) {
+                (Ok(protocols), Ok(tokens)) => (protocols, tokens),
+                (protocols, tokens) => {
+                    let errors: Vec<String> =
+                        [protocols.err(), tokens.err()].into_iter().flatten().map(|e| format!("{:#}", e)).collect();
+                    eyre::bail!("{}", errors.join("\n"));
+                }
+            };
+            let pairs = config::load_token_pairs_for_chain(tokens_path.to_str().unwrap(), chain_id)?;
+            let blacklist = config::load_blacklist_for_chain(tokens_path.to_str().unwrap(), chain_id)?;
+            (protocol_configs, discovery_config, tokens, pairs, blacklist)
+        }
+    };
+    let filter = PoolFilter {
+        token_whitelist: tokens.into_values().collect(),
+        pair_whitelist,
+        blacklist,
+        ranking: discovery_config.ranking.clone(),
+    };
+
+    if protocol_configs.is_empty() {
+        warn!("No enabled protocols. Enable protocols in protocols.json (or scanner.toml).");
+    }
+    Ok(ScanConfig {
+        protocols: protocol_configs,
+        discovery: discovery_config,
+        filter,
+    })
+}
+
+/// Swap/Sync/Mint/Burn log filter for the given pool addresses.
+fn log_filter(addresses: Vec<Address>) -> Filter {
+    Filter::new()
+        .address(addresses)
+        .events([
+            "Swap(address,address,int256,int256,uint160,uint128,int24)".as_bytes(),   // V3
+            "Mint(address,address,int24,int24,uint128,uint256,uint256)".as_bytes(),    // V3
+            "Burn(address,int24,int24,uint128,uint256,uint256)".as_bytes(),            // V3
+            "Swap(address,uint256,uint256,uint256,uint256,address)".as_bytes(),       // V2
+            "Sync(uint112,uint112)".as_bytes(),                                        // V2
+        ])
+}
+
+/// Route the logs matching `filter` from `from_block` to the chain head to the parser queues, in
+/// `BACKFILL_CHUNK_BLOCKS` spans. Returns the last block replayed.
+async fn backfill_logs(
+    provider: &RpcProvider,
+    ctx: &ScannerContext,
+    router: &LogRouter,
+    filter: &Filter,
+    from_block: u64,
+) -> Result<u64> {
+    let head = provider.get_block_number().await?;
+    let mut chunks = LogChunks::new(provider, filter, from_block, head, BACKFILL_CHUNK_BLOCKS);
+    while let Some((_, _, logs)) = chunks.next().await? {
+        for log in logs {
+            ctx.stats.record_log(log.block_number);
+            ctx.pipeline.log_queue_depth.fetch_add(1, Ordering::Relaxed);
+            if !router.send(log).await {
+                return Err(eyre::eyre!("parser queues closed"));
+            }
+        }
+    }
+    if from_block <= head {
+        info!("Backfilled blocks {} to {}", from_block, head);
+    }
+    Ok(head)
+}
+
+pub(crate) async fn connect(rpc_url: &str) -> Result<RpcProvider> {
+    let ws = WsConnect::new(rpc_url);
+    let provider = ProviderBuilder::new().on_ws(ws).await?;
+    Ok(Arc::new(provider))
+}
+
+/// Keep a log subscription alive: subscribe, forward logs to the parser queue, and on any drop
+/// reconnect with exponential backoff (fresh WebSocket connection) and resubscribe. When the
+/// tracked pool set changes, resubscribe immediately with the new address filter. With `warmup`, that much
+/// history is replayed into the pools' metrics first (see `warmup::warm_up`); with `backfill_from`, the logs
+/// from that block up to the head are replayed before the first subscription.
 async fn run_log_subscription(
-    provider: Arc<dyn Provider<PubSubFrontend>>,
-    state: Arc<Mutex<ScannerState>>,
-    filter: Filter,
-) -> Result<()> {
-    let sub = provider.subscribe_logs(&filter).await?;
-    let mut stream = sub.into_stream();
+    rpc_url: String,
+    mut provider: RpcProvider,
+    ctx: Arc<ScannerContext>,
+    router: LogRouter,
+    mut warmup: Option<Duration>,
+    mut backfill_from: Option<u64>,
+) {
+    let mut attempt: u32 = 0;
+    let mut live = false;
+
+    loop {
+        // Register before subscribing so pool changes during the subscribe call are not missed.
+        let pools_changed = ctx.state.pools_changed.notified();
+        tokio::pin!(pools_changed);
+        let addresses = ctx.state.addresses();
+        let pool_count = addresses.len();
+        // An empty address filter would match every Swap/Sync log on the chain.
+        if addresses.is_empty() {
+            tokio::select! {
+                _ = ctx.cancel.cancelled() => return,
+                _ = &mut pools_changed => continue,
+            }
+        }
+        let filter = log_filter(addresses);
+
+        // Replay most of the gap before subscribing: the subscription only buffers a handful of logs.
+        let mut catch_up_from = None;
+        if let Some(window) = warmup.take() {
+            let warmed = tokio::select! {
+                _ = ctx.cancel.cancelled() => return,
+                warmed = warmup::warm_up(&ctx, &provider, &filter, window) => warmed,
+            };
+            match warmed {
+                Ok(head) => catch_up_from = Some(head + 1),
+                Err(e) => ctx.events.error(ScannerError::Subscription(format!("metrics warm-up failed: {}", e))),
+            }
+        }
+        if let Some(from_block) = backfill_from.take() {
+            let backfilled = tokio::select! {
+                _ = ctx.cancel.cancelled() => return,
+                backfilled = backfill_logs(&provider, &ctx, &router, &filter, from_block) => backfilled,
+            };
+            match backfilled {
+                Ok(head) => catch_up_from = Some(head + 1),
+                Err(e) => ctx.events.error(ScannerError::Subscription(format!("backfill failed: {}", e))),
+            }
+        }
+
+        let subscription = tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            subscription = provider.subscribe_logs(&filter) => subscription,
+        };
+        match subscription {
+            Ok(sub) => {
+                ctx.stats.record_subscribed(attempt > 0);
+                if attempt > 0 {
+                    ctx.events.status(ScannerStatus::Reconnected);
+                }
+                attempt = 0;
+                ctx.events.status(ScannerStatus::Subscribed { pools: pool_count });
 
-    while let Some(log) = stream.next().await {
-        if let Err(e) = handle_log_event(&state, log).await {
-            warn!("handle_log_event error: {:?}", e);
+                // Blocks mined while backfilling; logs that overlap the live stream are skipped by the parser.
+                if let Some(from_block) = catch_up_from {
+                    let caught_up = tokio::select! {
+                        _ = ctx.cancel.cancelled() => return,
+                        caught_up = backfill_logs(&provider, &ctx, &router, &filter, from_block) => caught_up,
+                    };
+                    if let Err(e) = caught_up {
+                        ctx.events.error(ScannerError::Subscription(format!("backfill failed: {}", e)));
+                    }
+                }
+                if !live {
+                    live = true;
+                    ctx.events.status(ScannerStatus::Live { pools: pool_count });
+                }
+                let mut stream = sub.into_stream();
+                let mut resubscribe = false;
+                loop {
+                    let log = tokio::select! {
+                        _ = ctx.cancel.cancelled() => return,
+                        _ = &mut pools_changed => {
+                            resubscribe = true;
+                            break;
+                        }
+                        log = stream.next() => log,
+                    };
+                    let Some(log) = log else { break };
+                    ctx.stats.record_log(log.block_number);
+                    ctx.pipeline.log_queue_depth.fetch_add(1, Ordering::Relaxed);
+                    if !router.send(log).await {
+                        return;
+                    }
+                }
+                if resubscribe {
+                    continue;
+                }
+                ctx.stats.record_dropped();
+                ctx.events.status(ScannerStatus::SubscriptionDropped);
+            }
+            Err(e) => ctx.events.error(ScannerError::Subscription(e.to_string())),
+        }
+
+        attempt += 1;
+        let delay = INITIAL_RECONNECT_DELAY
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(MAX_RECONNECT_DELAY);
+        ctx.events.status(ScannerStatus::Reconnecting {
+            attempt,
+            delay_ms: delay.as_millis() as u64,
+        });
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        let reconnected = tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            reconnected = connect(&rpc_url) => reconnected,
+        };
+        match reconnected {
+            Ok(p) => provider = p,
+            Err(e) => ctx.events.error(ScannerError::Subscription(format!("reconnect failed: {}", e))),
         }
     }
+}
 
-    Ok(())
+/// Parser worker: decode this worker's share of the logs and hand the resulting price updates
+/// to the delivery queue. Each pool maps to exactly one worker, so its updates stay in order.
+async fn run_parser(ctx: Arc<ScannerContext>, mut log_rx: mpsc::Receiver<Log>) {
+    loop {
+        let log = tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            log = log_rx.recv() => log,
+        };
+        let Some(log) = log else { return };
+        ctx.pipeline.log_queue_depth.fetch_sub(1, Ordering::Relaxed);
+        let pool = log.address();
+        let started = std::time::Instant::now();
+        let handled = handle_log_event(&ctx, log);
+        ctx.stats.record_parse(started.elapsed());
+        match handled {
+            Ok(Some(update)) => tokio::select! {
+                _ = ctx.cancel.cancelled() => return,
+                _ = ctx.pipeline.updates.push(update) => {}
+            },
+            // The pool was removed after the log was received, or the log was already applied.
+            Ok(None) => {}
+            Err(e) => {
+                ctx.stats.record_parse_error();
+                ctx.events.error(ScannerError::Parse {
+                    pool,
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Delivery stage: invoke the price-change callback (and event bus) for each queued update.
+async fn run_delivery(ctx: Arc<ScannerContext>) {
+    loop {
+        let update = tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            update = ctx.pipeline.updates.pop() => update,
+        };
+        let pool_address = update.pool.address;
+        let started = std::time::Instant::now();
+        ctx.events.price_changed(update.pool, update.price, update.previous);
+        ctx.stats.record_update(pool_address, started.elapsed());
+    }
 }
 
-async fn handle_log_event(state: &Arc<Mutex<ScannerState>>, log: Log) -> Result<()> {
+/// Decode a live log, timestamped with the current time (see `handle_log_at`).
+fn handle_log_event(ctx: &ScannerContext, log: Log) -> Result<Option<PriceUpdate>> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    handle_log_at(ctx, log, timestamp)
+}
+
+/// Decode a log under its pool's lock and return the resulting price update, timestamped `timestamp`.
+/// Large swaps, sandwiches, oracle deviations, token index, pair prices, spreads and arbitrage
+/// opportunities are published right away.
+fn handle_log_at(ctx: &ScannerContext, log: Log, timestamp: u64) -> Result<Option<PriceUpdate>> {
     let pool_address = log.address();
     let eth_log = EthereumLog::from(log);
 
-    let (swap_data, cached_pool) = {
-        let mut guard = state.lock().await;
-        let lp = guard.liquidity_pools.get_mut(&pool_address).ok_or_else(|| {
-            eyre::eyre!("No liquidity pool for address {:?}", pool_address)
-        })?;
-        let swap_data = lp.parse_swap_event_data(&eth_log)?;
-        let cached_pool = guard
-            .pools
-            .iter()
-            .find(|p| p.address == pool_address)
-            .cloned()
-            .ok_or_else(|| eyre::eyre!("No cached pool for address {:?}", pool_address))?;
-        (swap_data, cached_pool)
+    let Some(slot) = ctx.state.get(&pool_address) else {
+        return Ok(None);
+    };
+    let Some(AppliedLog {
+        price,
+        previous,
+        spike,
+        swap,
+        volume_usd,
+    }) = slot.apply_log(&eth_log, timestamp, ctx.pricing.as_ref(), ctx.spike_filter.as_ref())?
+    else {
+        return Ok(None);
     };
+    if let Some(swap) = &swap {
+        ctx.events.publish(|| ScannerEvent::Swap(whales::swap(&slot.pool, &eth_log, swap, &price, volume_usd)));
+    }
+    if let Some(threshold) = ctx.large_swap_usd
+        && let (Some(swap), Some(notional)) = (&swap, volume_usd)
+        && let Some(whale) = whales::large_swap(&slot.pool, &eth_log, swap, notional, threshold, timestamp)
+    {
+        ctx.events.publish(|| ScannerEvent::LargeSwap(whale));
+    }
+    if let Some(detector) = &ctx.sandwiches
+        && let (Some(swap), Some(block_number)) = (&swap, eth_log.block_number)
+    {
+        let leg = SwapLeg {
+            transaction_hash: eth_log.transaction_hash,
+            log_index: eth_log.log_index,
+            sender: swap.sender,
+            recipient: swap.recipient,
+            amount0: swap.amount0,
+            amount1: swap.amount1,
+        };
+        if let Some(sandwich) = detector.record(slot.pool.address, block_number, leg) {
+            ctx.events.publish(|| ScannerEvent::Sandwich(sandwich));
+        }
+    }
+    if let Some(spike) = spike {
+        let suppressed = spike.suppressed;
+        ctx.events.publish(|| ScannerEvent::PriceSpike(spike));
+        if suppressed {
+            return Ok(None);
+        }
+    }
+    if let Some(oracles) = &ctx.oracles
+        && let Some(deviation) = oracles.check(&slot.pool, &price, ctx.pricing.as_ref())
+    {
+        ctx.events.publish(|| ScannerEvent::OracleDeviation(deviation));
+    }
+    if let Some(index) = &ctx.token_index {
+        let decimals = f64::from(slot.pool.token0_decimals) + f64::from(slot.pool.token1_decimals);
+        let liquidity = slot.liquidity() as f64 / 10f64.powf(decimals / 2.0);
+        for token_price in index.update(&slot.pool, &price, liquidity, ctx.pricing.as_ref(), |a| ctx.state.contains(a)) {
+            ctx.events.publish(|| ScannerEvent::TokenIndex(token_price));
+        }
+    }
+    if let Some(pairs) = &ctx.pairs
+        && let Some(pair) = pairs.update(&slot.pool, &price, slot.liquidity(), |a| ctx.state.contains(a))
+    {
+        if ctx.publish_pair_prices {
+            ctx.events.publish(|| ScannerEvent::PairPrice(pair));
+        }
+        if let Some(threshold) = ctx.spread_threshold_bps
+            && let Some(mut spread) = pairs.spread(&slot.pool, threshold, timestamp)
+        {
+            if let Some(config) = &ctx.arbitrage
+                && let Some(gas) = ctx.gas.as_ref().and_then(GasOracle::latest)
+                && let Some(native_usd) = native_usd_price(ctx, config)
+            {
+                spread.gas_cost_usd = Some(config.execution_cost_usd(2, Some(gas), Some(native_usd)));
+            }
+            ctx.events.publish(|| ScannerEvent::SpreadUpdate(spread));
+        }
+        if let Some(config) = &ctx.arbitrage
+            && let Some(spread) = pairs.spread(&slot.pool, 0.0, timestamp)
+            && spread.net_spread_bps > 0.0
+            && let Some(opportunity) = arbitrage_opportunity(ctx, config, &spread)
+        {
+            ctx.events.publish(|| ScannerEvent::ArbOpportunity(opportunity));
+        }
+    }
+    if let Some(graph) = &ctx.graph
+        && let Some(config) = &ctx.arbitrage
+    {
+        graph.update(&slot.pool, price.token0_price, |a| ctx.state.contains(a));
+        if let Some(opportunity) = cycle_opportunity(ctx, config, graph, slot.pool.address, timestamp) {
+            ctx.events.publish(|| ScannerEvent::CycleOpportunity(opportunity));
+        }
+    }
 
-    let new_price = PoolPrice {
-        pool_address,
-        token0_price: swap_data.price,
-        token1_price: 1.0 / swap_data.price,
-        timestamp: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs(),
+    Ok(Some(PriceUpdate {
+        pool: slot.pool.clone(),
+        price,
+        previous,
+    }))
+}
+
+/// USD price of `token`, one of `pool`'s tokens: from pricing, or 1 for a USD stablecoin, or
+/// `price_in_other` (its price in the pool's other token) when that other token is a stablecoin.
+fn token_usd_price(ctx: &ScannerContext, pool: &CachedPool, token: Address, price_in_other: f64) -> Option<f64> {
+    let (symbol, other_symbol) = if token == pool.token0 {
+        (&pool.token0_symbol, &pool.token1_symbol)
+    } else {
+        (&pool.token1_symbol, &pool.token0_symbol)
     };
+    ctx.pricing
+        .as_ref()
+        .and_then(|pricer| pricer.token_price_usd(token))
+        .or_else(|| is_usd_stablecoin(symbol).then_some(1.0))
+        .or_else(|| is_usd_stablecoin(other_symbol).then_some(price_in_other))
+        .filter(|usd| usd.is_finite() && *usd > 0.0)
+}
+
+/// USD price of the token gas is paid in, from pricing.
+fn native_usd_price(ctx: &ScannerContext, config: &ArbitrageConfig) -> Option<f64> {
+    ctx.pricing.as_ref().and_then(|pricer| pricer.token_price_usd(config.native_token))
+}
+
+/// Estimated USD cost of a transaction making `swaps` swaps (`ArbitrageConfig::execution_cost_usd`).
+fn execution_cost_usd(ctx: &ScannerContext, config: &ArbitrageConfig, swaps: usize) -> f64 {
+    let gas = ctx.gas.as_ref().and_then(GasOracle::latest);
+    config.execution_cost_usd(swaps, gas, native_usd_price(ctx, config))
+}
+
+/// Raw amount of a token worth `usd`; zero when the token has no USD price.
+fn usd_to_raw(usd: f64, token_usd: Option<f64>, unit: f64) -> U256 {
+    U256::from(token_usd.map_or(0.0, |price| usd / price * unit) as u128)
+}
 
-    let old_price = {
-        let mut guard = state.lock().await;
-        guard.current_prices.insert(pool_address, new_price.clone())
+/// Size the round trip behind a positive net spread with each pool's own quoting. Gas and the
+/// minimum profit are converted to token1 with its USD price (`token_usd_price`).
+fn arbitrage_opportunity(ctx: &ScannerContext, config: &ArbitrageConfig, spread: &SpreadUpdate) -> Option<ArbOpportunity> {
+    let buy = ctx.state.get(&spread.ask_pool)?;
+    let sell = ctx.state.get(&spread.bid_pool)?;
+    let pool = &buy.pool;
+    let decimals = if spread.token1 == pool.token1 { pool.token1_decimals } else { pool.token0_decimals };
+    let unit = 10f64.powi(i32::from(decimals));
+    let token1_usd = token_usd_price(ctx, pool, spread.token1, 1.0 / spread.ask_price);
+    let to_token1 = |usd: f64| usd_to_raw(usd, token1_usd, unit);
+
+    let round_trip = |size: U256| {
+        let bought = buy.quote_exact_input(spread.token1, size).ok()?.amount_out;
+        Some(sell.quote_exact_input(spread.token0, bought).ok()?.amount_out)
     };
+    let gas_cost_usd = execution_cost_usd(ctx, config, 2);
+    let (size, expected_profit) = arbitrage::best_size(round_trip, unit, to_token1(gas_cost_usd))?;
+    if expected_profit < to_token1(config.min_profit_usd) {
+        return None;
+    }
+    Some(ArbOpportunity {
+        token0: spread.token0,
+        token1: spread.token1,
+        buy_pool: spread.ask_pool,
+        sell_pool: spread.bid_pool,
+        size,
+        expected_profit,
+        expected_profit_usd: token1_usd.map(|price| f64::from(expected_profit) / unit * price),
+        gas_cost_usd,
+        timestamp: spread.timestamp,
+    })
+}
 
-    (state.lock().await.on_price_change)(cached_pool, new_price, old_price);
+/// Find a cycle through `pool_address` in the token graph and size it by chaining the pools' quotes.
+/// Gas and the minimum profit are converted to the cycle's first token with its USD price.
+fn cycle_opportunity(
+    ctx: &ScannerContext,
+    config: &ArbitrageConfig,
+    graph: &TokenGraph,
+    pool_address: Address,
+    timestamp: u64,
+) -> Option<CycleOpportunity> {
+    let cycle = graph.find_cycle(pool_address)?;
+    let slots = cycle.pools.iter().map(|pool| ctx.state.get(pool)).collect::<Option<Vec<_>>>()?;
+    let start = cycle.tokens[0];
+    let first = &slots[0].pool;
+    let (decimals, price_in_other) = {
+        let price = slots[0].current_price()?;
+        if start == first.token0 {
+            (first.token0_decimals, price.token0_price)
+        } else {
+            (first.token1_decimals, price.token1_price)
+        }
+    };
+    let unit = 10f64.powi(i32::from(decimals));
+    let start_usd = token_usd_price(ctx, first, start, price_in_other);
+    let to_start = |usd: f64| usd_to_raw(usd, start_usd, unit);
 
-    Ok(())
+    let round_trip = |size: U256| {
+        slots
+            .iter()
+            .zip(&cycle.tokens)
+            .try_fold(size, |amount, (slot, token_in)| Some(slot.quote_exact_input(*token_in, amount).ok()?.amount_out))
+    };
+    let gas_cost_usd = execution_cost_usd(ctx, config, cycle.pools.len());
+    let (size, expected_profit) = arbitrage::best_size(round_trip, unit, to_start(gas_cost_usd))?;
+    if expected_profit < to_start(config.min_profit_usd) {
+        return None;
+    }
+    Some(CycleOpportunity {
+        tokens: cycle.tokens,
+        pools: cycle.pools,
+        profit_bps: cycle.profit_bps,
+        size,
+        expected_profit,
+        expected_profit_usd: start_usd.map(|price| f64::from(expected_profit) / unit * price),
+        gas_cost_usd,
+        timestamp,
+    })
 }
@@ -0,0 +1,57 @@
+use super::RpcProvider;
+use alloy::primitives::{address, Address, Bytes};
+use alloy::rpc::types::eth::TransactionRequest;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use eyre::Result;
+
+/// Multicall3, deployed at the same address on virtually every EVM chain.
+pub const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+/// Calls per `aggregate3` request; keeps each eth_call well under typical gas/response limits.
+const MULTICALL_BATCH_SIZE: usize = 500;
+
+sol! {
+    struct Call3 {
+        address target;
+        bool allowFailure;
+        bytes callData;
+    }
+
+    struct Result3 {
+        bool success;
+        bytes returnData;
+    }
+
+    function aggregate3(Call3[] calls) external payable returns (Result3[] returnData);
+}
+
+/// Execute `calls` (target, calldata) through Multicall3 in batches. Individual failures are
+/// allowed and returned as `None`, so one reverting target doesn't fail the batch.
+pub async fn multicall(provider: &RpcProvider, calls: &[(Address, Bytes)]) -> Result<Vec<Option<Bytes>>> {
+    let mut results = Vec::with_capacity(calls.len());
+    for batch in calls.chunks(MULTICALL_BATCH_SIZE) {
+        let call = aggregate3Call {
+            calls: batch
+                .iter()
+                .map(|(target, data)| Call3 {
+                    target: *target,
+                    allowFailure: true,
+                    callData: data.clone(),
+                })
+                .collect(),
+        };
+        let tx = TransactionRequest::default()
+            .to(MULTICALL3_ADDRESS)
+            .input(call.abi_encode().into());
+        let raw = provider.call(&tx).await?;
+        let decoded = aggregate3Call::abi_decode_returns(&raw, false)?;
+        results.extend(
+            decoded
+                .returnData
+                .into_iter()
+                .map(|r| r.success.then_some(r.returnData)),
+        );
+    }
+    Ok(results)
+}
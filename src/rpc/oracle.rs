@@ -0,0 +1,78 @@
+use super::multicall::multicall;
+use super::{RpcProvider, ScannerContext};
+use crate::events::ScannerError;
+use crate::pricing::OraclePrice;
+use alloy::primitives::{Address, Bytes};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::MissedTickBehavior;
+use tracing::debug;
+
+sol! {
+    function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    function decimals() external view returns (uint8);
+}
+
+/// Read `latestRoundData()` from every configured feed each `poll_interval`. Feed decimals are read
+/// once; feeds that revert or answer a non-positive price are skipped until the next poll.
+pub(crate) async fn run_oracle_poller(ctx: Arc<ScannerContext>, provider: RpcProvider) {
+    let Some(oracles) = &ctx.oracles else { return };
+    let feeds: Vec<(Address, Address)> = oracles.config().feeds.iter().map(|(token, feed)| (*token, *feed)).collect();
+    let mut decimals: HashMap<Address, u8> = HashMap::new();
+    let mut interval = tokio::time::interval(oracles.config().poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        let missing: Vec<Address> = feeds.iter().map(|(_, feed)| *feed).filter(|feed| !decimals.contains_key(feed)).collect();
+        if !missing.is_empty() {
+            let calls: Vec<(Address, Bytes)> = missing.iter().map(|feed| (*feed, decimalsCall {}.abi_encode().into())).collect();
+            match multicall(&provider, &calls).await {
+                Ok(results) => {
+                    for (feed, raw) in missing.iter().zip(results) {
+                        if let Some(value) = raw.and_then(|raw| decimalsCall::abi_decode_returns(&raw, false).ok()) {
+                            decimals.insert(*feed, value._0);
+                        }
+                    }
+                }
+                Err(e) => {
+                    ctx.events.error(ScannerError::Oracle(format!("decimals: {}", e)));
+                    continue;
+                }
+            }
+        }
+
+        let calls: Vec<(Address, Bytes)> = feeds.iter().map(|(_, feed)| (*feed, latestRoundDataCall {}.abi_encode().into())).collect();
+        let results = match multicall(&provider, &calls).await {
+            Ok(results) => results,
+            Err(e) => {
+                ctx.events.error(ScannerError::Oracle(format!("latestRoundData: {}", e)));
+                continue;
+            }
+        };
+        for ((token, feed), raw) in feeds.iter().zip(results) {
+            let Some(feed_decimals) = decimals.get(feed) else { continue };
+            let Some(round) = raw.and_then(|raw| latestRoundDataCall::abi_decode_returns(&raw, false).ok()) else {
+                debug!("latestRoundData failed for feed {:?}", feed);
+                continue;
+            };
+            let Ok(answer) = u128::try_from(round.answer) else { continue };
+            if answer == 0 {
+                continue;
+            }
+            oracles.update(
+                *token,
+                OraclePrice {
+                    feed: *feed,
+                    price_usd: answer as f64 / 10f64.powi(i32::from(*feed_decimals)),
+                    updated_at: round.updatedAt.saturating_to(),
+                },
+            );
+        }
+    }
+}
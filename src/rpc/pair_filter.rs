@@ -0,0 +1,59 @@
+use crate::types::CachedPool;
+
+/// Pairs a stream client (WebSocket, gRPC) or alert rule wants updates for. Each side is a token symbol or
+/// address, matched case-insensitively and in either order; an empty filter passes every pool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PairFilter(Vec<(String, String)>);
+
+impl PairFilter {
+    /// Parse `WETH/USDC,0xc02a…/0xa0b8…`; entries without a `/` are ignored.
+    #[cfg(any(feature = "server", test))]
+    pub(crate) fn parse(spec: &str) -> Self {
+        Self::from_pairs(spec.split(','))
+    }
+
+    pub(crate) fn from_pairs<'a>(pairs: impl IntoIterator<Item = &'a str>) -> Self {
+        Self(
+            pairs
+                .into_iter()
+                .filter_map(|pair| pair.split_once('/'))
+                .map(|(a, b)| (a.trim().to_string(), b.trim().to_string()))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn matches(&self, pool: &CachedPool) -> bool {
+        let side = |spec: &str, symbol: &str, address: &str| {
+            spec.eq_ignore_ascii_case(symbol) || spec.eq_ignore_ascii_case(address)
+        };
+        let (address0, address1) = (pool.token0.to_string(), pool.token1.to_string());
+        self.0.is_empty()
+            || self.0.iter().any(|(a, b)| {
+                (side(a, &pool.token0_symbol, &address0) && side(b, &pool.token1_symbol, &address1))
+                    || (side(a, &pool.token1_symbol, &address1) && side(b, &pool.token0_symbol, &address0))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_pair_filter() {
+        let pool = CachedPool {
+            token0: Address::repeat_byte(0xaa),
+            token1: Address::repeat_byte(0xbb),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            ..Default::default()
+        };
+        assert!(PairFilter::default().matches(&pool));
+        assert!(PairFilter::parse("usdc/weth").matches(&pool));
+        assert!(PairFilter::parse(&format!("WBTC/WETH, WETH/{}", pool.token1)).matches(&pool));
+        assert!(PairFilter::parse(&format!("{}/usdc", pool.token0.to_string().to_lowercase())).matches(&pool));
+        assert!(!PairFilter::parse("WBTC/USDC").matches(&pool));
+        assert_eq!(PairFilter::parse("WETH,WBTC/USDC"), PairFilter(vec![("WBTC".to_string(), "USDC".to_string())]));
+    }
+}
@@ -0,0 +1,80 @@
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use crate::metrics::Candle;
+use crate::store::PoolStore;
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+/// How often buffered prices and pool changes are written to the store.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Pool and price changes buffered between flushes.
+#[derive(Default)]
+struct PendingWrites {
+    prices: HashMap<Address, PoolPrice>,
+    upserts: Vec<CachedPool>,
+    removals: Vec<Address>,
+    candles: Vec<Candle>,
+}
+
+/// Mirror scanner events into the store: last-seen prices (only the latest per pool is kept per
+/// flush), completed candles, pools added at runtime and pools removed by rediscovery. Writes are batched and run on
+/// the blocking pool so the event bus is never held up by disk I/O.
+pub(crate) async fn run_store_writer(ctx: Arc<ScannerContext>, store: Arc<PoolStore>) {
+    let mut events = ctx.events.subscribe();
+    let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+    let mut pending = PendingWrites::default();
+
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => {
+                flush_pending(&store, std::mem::take(&mut pending)).await;
+                return;
+            }
+            _ = flush.tick() => flush_pending(&store, std::mem::take(&mut pending)).await,
+            event = events.recv() => match event {
+                Ok(ScannerEvent::PriceUpdate { price, .. }) => {
+                    pending.prices.insert(price.pool_address, price);
+                }
+                Ok(ScannerEvent::PoolDiscovered(pool)) => pending.upserts.push(pool),
+                Ok(ScannerEvent::Candle(candle)) => pending.candles.push(candle),
+                Ok(ScannerEvent::PoolRemoved(pool)) => {
+                    pending.prices.remove(&pool.address);
+                    pending.removals.push(pool.address);
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => warn!("Store writer lagged, skipped {} events", skipped),
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+async fn flush_pending(store: &Arc<PoolStore>, pending: PendingWrites) {
+    if pending.prices.is_empty()
+        && pending.upserts.is_empty()
+        && pending.removals.is_empty()
+        && pending.candles.is_empty()
+    {
+        return;
+    }
+    let store = Arc::clone(store);
+    let result = tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+        store.upsert_pools(&pending.upserts)?;
+        store.remove_pools(&pending.removals)?;
+        store.record_candles(&pending.candles)?;
+        let prices: Vec<PoolPrice> = pending.prices.into_values().collect();
+        store.record_prices(&prices)
+    })
+    .await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Failed to write to pool store: {}", e),
+        Err(e) => warn!("Pool store writer panicked: {}", e),
+    }
+}
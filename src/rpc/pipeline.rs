@@ -0,0 +1,237 @@
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use alloy::rpc::types::eth::Log;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::{mpsc, Notify};
+
+/// What the parse stage does when the update queue (parsed prices waiting for the callback) is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait for the callback to catch up. Backpressure propagates to the log queue and,
+    /// once that is full too, to the WebSocket read loop.
+    #[default]
+    Block,
+    /// Discard the oldest pending update.
+    DropOldest,
+    /// Merge into the pending update for the same pool (keeping its `previous` price), so
+    /// consumers see the latest price per pool. Drops the oldest update if none is pending for that pool.
+    CoalescePerPool,
+}
+
+/// Queue sizes, parallelism and overflow behavior of the ingestion → parse → callback pipeline.
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    /// Number of parser workers. Logs are sharded by pool address, so each pool is always
+    /// handled by the same worker and per-pool ordering is preserved.
+    pub workers: usize,
+    /// Raw logs buffered between the WebSocket read loop and each parser worker.
+    pub log_queue_capacity: usize,
+    /// Parsed price updates buffered between the parser and the price-change callback.
+    pub update_queue_capacity: usize,
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            workers: 1,
+            log_queue_capacity: 4096,
+            update_queue_capacity: 4096,
+            overflow_policy: OverflowPolicy::Block,
+        }
+    }
+}
+
+/// A parsed price change waiting to be delivered.
+pub(crate) struct PriceUpdate {
+    pub(crate) pool: CachedPool,
+    pub(crate) price: PoolPrice,
+    pub(crate) previous: Option<PoolPrice>,
+}
+
+/// Shared pipeline state: the update queue plus the log queue depth gauge.
+pub(crate) struct Pipeline {
+    pub(crate) config: PipelineConfig,
+    pub(crate) updates: UpdateQueue,
+    pub(crate) log_queue_depth: AtomicU64,
+}
+
+impl Pipeline {
+    pub(crate) fn new(config: PipelineConfig) -> Self {
+        let updates = UpdateQueue::new(config.update_queue_capacity, config.overflow_policy);
+        Self {
+            config,
+            updates,
+            log_queue_depth: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Routes raw logs to parser workers by pool address.
+#[derive(Clone)]
+pub(crate) struct LogRouter {
+    workers: Vec<mpsc::Sender<Log>>,
+}
+
+impl LogRouter {
+    /// Create `config.workers` bounded log queues; returns the router and one receiver per worker.
+    pub(crate) fn new(config: &PipelineConfig) -> (Self, Vec<mpsc::Receiver<Log>>) {
+        let (workers, receivers) = (0..config.workers.max(1))
+            .map(|_| mpsc::channel(config.log_queue_capacity.max(1)))
+            .unzip();
+        (Self { workers }, receivers)
+    }
+
+    fn shard(&self, address: &Address) -> usize {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&address.as_slice()[12..]);
+        (u64::from_be_bytes(bytes) % self.workers.len() as u64) as usize
+    }
+
+    /// Queue `log` on its pool's worker. Returns `false` once the workers are gone.
+    pub(crate) async fn send(&self, log: Log) -> bool {
+        let shard = self.shard(&log.address());
+        self.workers[shard].send(log).await.is_ok()
+    }
+}
+
+/// Bounded queue of price updates applying an `OverflowPolicy` when full. Single consumer.
+pub(crate) struct UpdateQueue {
+    items: Mutex<VecDeque<PriceUpdate>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    not_empty: Notify,
+    not_full: Notify,
+    pub(crate) dropped: AtomicU64,
+    pub(crate) coalesced: AtomicU64,
+}
+
+impl UpdateQueue {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            items: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+            policy,
+            not_empty: Notify::new(),
+            not_full: Notify::new(),
+            dropped: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.items.lock().unwrap().len()
+    }
+
+    pub(crate) async fn push(&self, update: PriceUpdate) {
+        let mut update = Some(update);
+        loop {
+            // Register for wakeups before checking, so a pop between check and await is not missed.
+            let not_full = self.not_full.notified();
+            {
+                let mut items = self.items.lock().unwrap();
+                if items.len() < self.capacity {
+                    items.push_back(update.take().unwrap());
+                    drop(items);
+                    self.not_empty.notify_one();
+                    return;
+                }
+                match self.policy {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropOldest => {
+                        items.pop_front();
+                        items.push_back(update.take().unwrap());
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    OverflowPolicy::CoalescePerPool => {
+                        let update = update.take().unwrap();
+                        let address = update.pool.address;
+                        if let Some(pending) = items.iter_mut().find(|u| u.pool.address == address) {
+                            pending.pool = update.pool;
+                            pending.price = update.price;
+                            self.coalesced.fetch_add(1, Ordering::Relaxed);
+                        } else {
+                            items.pop_front();
+                            items.push_back(update);
+                            self.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        return;
+                    }
+                }
+            }
+            not_full.await;
+        }
+    }
+
+    pub(crate) async fn pop(&self) -> PriceUpdate {
+        loop {
+            if let Some(update) = self.items.lock().unwrap().pop_front() {
+                self.not_full.notify_waiters();
+                return update;
+            }
+            self.not_empty.notified().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    fn update(pool: u8, price: f64) -> PriceUpdate {
+        let address = Address::repeat_byte(pool);
+        PriceUpdate {
+            pool: CachedPool {
+                address,
+                ..Default::default()
+            },
+            price: PoolPrice {
+                pool_address: address,
+                token0_price: price,
+                token1_price: 1.0 / price,
+                timestamp: 0,
+                ..Default::default()
+            },
+            previous: None,
+        }
+    }
+
+    #[test]
+    fn test_log_router_shards_by_address() {
+        let config = PipelineConfig {
+            workers: 4,
+            ..Default::default()
+        };
+        let (router, receivers) = LogRouter::new(&config);
+        assert_eq!(receivers.len(), 4);
+        let address = Address::repeat_byte(7);
+        assert_eq!(router.shard(&address), router.shard(&address));
+        assert!(router.shard(&address) < 4);
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_keeps_newest() {
+        let queue = UpdateQueue::new(2, OverflowPolicy::DropOldest);
+        queue.push(update(1, 1.0)).await;
+        queue.push(update(2, 2.0)).await;
+        queue.push(update(3, 3.0)).await;
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 1);
+        assert_eq!(queue.pop().await.price.token0_price, 2.0);
+        assert_eq!(queue.pop().await.price.token0_price, 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_per_pool_replaces_pending_price() {
+        let queue = UpdateQueue::new(2, OverflowPolicy::CoalescePerPool);
+        queue.push(update(1, 1.0)).await;
+        queue.push(update(2, 2.0)).await;
+        queue.push(update(1, 1.5)).await;
+        assert_eq!(queue.coalesced.load(Ordering::Relaxed), 1);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop().await.price.token0_price, 1.5);
+    }
+}
@@ -0,0 +1,148 @@
+use super::stats::{LatencyHistogram, ScannerStats};
+use crate::types::{CachedPool, PoolSnapshot};
+use std::fmt::Write as _;
+
+/// Escape a label value: backslashes, double quotes and newlines.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// `pool="…",protocol="…",pair="…"`.
+fn pool_labels(pool: &CachedPool) -> String {
+    format!(
+        "pool=\"{}\",protocol=\"{}\",pair=\"{}/{}\"",
+        pool.address,
+        escape_label(&pool.protocol),
+        escape_label(&pool.token0_symbol),
+        escape_label(&pool.token1_symbol)
+    )
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    header(out, name, kind, help);
+    let _ = writeln!(out, "{name} {value}");
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, histogram: &LatencyHistogram) {
+    header(out, name, "histogram", help);
+    for (bound, count) in &histogram.buckets {
+        let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {count}");
+    }
+    let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count);
+    let _ = writeln!(out, "{name}_sum {}", histogram.sum.as_secs_f64());
+    let _ = writeln!(out, "{name}_count {}", histogram.count);
+}
+
+/// Scanner health and per-pool prices in the Prometheus text exposition format.
+pub(crate) fn render(stats: &ScannerStats, pools: &[PoolSnapshot]) -> String {
+    let mut out = String::new();
+    metric(&mut out, "dex_scanner_logs_received_total", "counter", "Logs received from the subscription.", stats.logs_received);
+    metric(&mut out, "dex_scanner_parse_errors_total", "counter", "Logs that could not be decoded.", stats.parse_errors);
+    metric(&mut out, "dex_scanner_price_updates_total", "counter", "Price updates delivered.", stats.price_updates);
+    metric(&mut out, "dex_scanner_reconnects_total", "counter", "Subscriptions re-established after a drop.", stats.reconnects);
+    metric(
+        &mut out,
+        "dex_scanner_subscribed",
+        "gauge",
+        "1 while the log subscription is up.",
+        u8::from(stats.subscription_uptime.is_some()),
+    );
+    metric(
+        &mut out,
+        "dex_scanner_subscription_uptime_seconds",
+        "gauge",
+        "Time since the current subscription was established.",
+        stats.subscription_uptime.unwrap_or_default().as_secs_f64(),
+    );
+    if let Some(block) = stats.last_block {
+        metric(&mut out, "dex_scanner_last_block", "gauge", "Highest block number seen in a log.", block);
+    }
+    if let Some(idle) = stats.since_last_log {
+        metric(&mut out, "dex_scanner_seconds_since_last_log", "gauge", "Time since the last log.", idle.as_secs_f64());
+    }
+    metric(&mut out, "dex_scanner_log_queue_depth", "gauge", "Raw logs waiting to be parsed.", stats.log_queue_depth);
+    metric(&mut out, "dex_scanner_update_queue_depth", "gauge", "Parsed updates waiting for delivery.", stats.update_queue_depth);
+    metric(&mut out, "dex_scanner_dropped_updates_total", "counter", "Updates discarded by the overflow policy.", stats.dropped_updates);
+    metric(&mut out, "dex_scanner_coalesced_updates_total", "counter", "Updates merged into a pending one.", stats.coalesced_updates);
+    histogram(&mut out, "dex_scanner_parse_latency_seconds", "Time to decode a log.", &stats.parse_latency);
+    histogram(&mut out, "dex_scanner_callback_latency_seconds", "Time spent in the price-change callback.", &stats.callback_latency);
+
+    header(&mut out, "dex_scanner_pool_updates_total", "counter", "Price updates delivered per pool.");
+    for snapshot in pools {
+        let updates = stats.pool_updates.get(&snapshot.pool.address).copied().unwrap_or(0);
+        let _ = writeln!(out, "dex_scanner_pool_updates_total{{{}}} {updates}", pool_labels(&snapshot.pool));
+    }
+    header(&mut out, "dex_scanner_pool_price", "gauge", "Last token0 price in token1 per pool.");
+    for snapshot in pools {
+        if let Some(price) = snapshot.price.as_ref().filter(|price| price.token0_price.is_finite()) {
+            let _ = writeln!(out, "dex_scanner_pool_price{{{}}} {}", pool_labels(&snapshot.pool), price.token0_price);
+        }
+    }
+    header(&mut out, "dex_scanner_pool_last_update_timestamp_seconds", "gauge", "Unix time of the last price update per pool.");
+    for snapshot in pools {
+        if let Some(price) = &snapshot.price {
+            let _ = writeln!(out, "dex_scanner_pool_last_update_timestamp_seconds{{{}}} {}", pool_labels(&snapshot.pool), price.timestamp);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PoolPrice;
+    use alloy::primitives::Address;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            protocol: "uniswap-v3".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "US\"D".to_string(),
+            ..Default::default()
+        };
+        let stats = ScannerStats {
+            logs_received: 10,
+            reconnects: 2,
+            subscription_uptime: Some(Duration::from_secs(5)),
+            pool_updates: HashMap::from([(pool.address, 7)]),
+            callback_latency: LatencyHistogram {
+                buckets: vec![(0.001, 3), (0.01, 4)],
+                count: 5,
+                sum: Duration::from_millis(40),
+            },
+            ..Default::default()
+        };
+        let snapshot = PoolSnapshot {
+            price: Some(PoolPrice {
+                token0_price: 3000.5,
+                timestamp: 1_714_521_600,
+                ..Default::default()
+            }),
+            pool,
+            volume_24h: Default::default(),
+            last_activity: 1_714_521_600,
+            stale: false,
+        };
+        let text = render(&stats, &[snapshot]);
+        let labels = format!("pool=\"{}\",protocol=\"uniswap-v3\",pair=\"WETH/US\\\"D\"", Address::repeat_byte(1));
+
+        assert!(text.contains("# TYPE dex_scanner_reconnects_total counter\ndex_scanner_reconnects_total 2\n"));
+        assert!(text.contains("dex_scanner_subscribed 1\n"));
+        assert!(!text.contains("dex_scanner_last_block"));
+        assert!(text.contains("dex_scanner_callback_latency_seconds_bucket{le=\"0.01\"} 4\n"));
+        assert!(text.contains("dex_scanner_callback_latency_seconds_bucket{le=\"+Inf\"} 5\n"));
+        assert!(text.contains("dex_scanner_callback_latency_seconds_sum 0.04\n"));
+        assert!(text.contains(&format!("dex_scanner_pool_updates_total{{{labels}}} 7\n")));
+        assert!(text.contains(&format!("dex_scanner_pool_price{{{labels}}} 3000.5\n")));
+        assert!(text.contains(&format!("dex_scanner_pool_last_update_timestamp_seconds{{{labels}}} 1714521600\n")));
+    }
+}
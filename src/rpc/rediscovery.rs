@@ -0,0 +1,378 @@
+use super::ScannerContext;
+use crate::config;
+use crate::discovery::{PoolDiscovery, PoolFilter, PoolSet};
+use crate::events::{ScannerError, ScannerEvent};
+use crate::types::{CachedPool, DiscoveryConfig, ProtocolConfig};
+use alloy::primitives::Address;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::info;
+
+/// What the scanner loads from its config files: the enabled protocols, the discovery settings and the
+/// pool filter. The running tasks receive a new one through a `watch` channel when the files change.
+/// `ScannerOptions::config` hands one to the scanner instead of the files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanConfig {
+    pub protocols: Vec<ProtocolConfig>,
+    pub discovery: DiscoveryConfig,
+    pub filter: PoolFilter,
+}
+
+impl ScanConfig {
+    /// The enabled `protocols` with `discovery`, tracking every discovered pool (apart from `discovery.ranking`)
+    /// until `with_filter`. Fails listing every problem: what a protocol lacks for the discovery mode (e.g. a
+    /// `subgraph_url`), duplicate ids, invalid discovery settings.
+    pub fn new(protocols: Vec<ProtocolConfig>, discovery: DiscoveryConfig) -> eyre::Result<Self> {
+        let protocols: Vec<ProtocolConfig> = protocols.into_iter().filter(|p| p.enabled).collect();
+        config::ensure_valid_scan(&protocols, &discovery)?;
+        let filter = PoolFilter {
+            ranking: discovery.ranking.clone(),
+            ..PoolFilter::default()
+        };
+        Ok(Self { protocols, discovery, filter })
+    }
+
+    /// Track only the pools `filter` allows, like the whitelists and blacklist of tokens.json.
+    pub fn with_filter(mut self, filter: PoolFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+/// The next configuration sent on `configs`. Never resolves when nothing watches the files (the sender is
+/// gone).
+pub(crate) async fn next_config(configs: &mut watch::Receiver<ScanConfig>) -> ScanConfig {
+    if configs.changed().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+    configs.borrow_and_update().clone()
+}
+
+/// Re-run discovery every `cache_refresh_minutes` and reconcile the tracked pool set: pools that
+/// appear are added, pools that dropped out of the results (e.g. fell below `minLiquidityUSD`) are
+/// removed, unless their protocol failed to refresh this round. Only pools that came from discovery are ever removed; pools added through
+/// `Scanner::add_pools` or live discovery are left alone. With `incremental_discovery`, only every
+/// `full_refresh_every`-th run is a full discovery; the others use `PoolDiscovery::refresh_incremental`.
+/// A configuration received on `configs` is applied right away (see `apply_reload`) and used from then on.
+pub(crate) async fn run_rediscovery(
+    ctx: Arc<ScannerContext>,
+    discovery: PoolDiscovery,
+    mut configs: watch::Receiver<ScanConfig>,
+    mut discovered: Vec<CachedPool>,
+) {
+    let mut config = configs.borrow_and_update().clone();
+    let mut interval = refresh_interval(&config.discovery);
+    let mut run = 0u32;
+
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            _ = tick(&mut interval) => {}
+            reloaded = next_config(&mut configs) => {
+                let previous = std::mem::replace(&mut config, reloaded);
+                if previous.discovery.cache_refresh_minutes != config.discovery.cache_refresh_minutes {
+                    interval = refresh_interval(&config.discovery);
+                }
+                tokio::select! {
+                    _ = ctx.cancel.cancelled() => return,
+                    _ = apply_reload(&ctx, &discovery, &previous, &config, &mut discovered) => {}
+                }
+                continue;
+            }
+        }
+
+        let ScanConfig { protocols, discovery: settings, filter } = &config;
+        run = run.wrapping_add(1);
+        let full = !settings.incremental_discovery || run.is_multiple_of(settings.full_refresh_every.max(1));
+        let refresh = async {
+            if full {
+                discovery.refresh_pools(protocols, settings).await
+            } else {
+                discovery.refresh_incremental(protocols, settings, &discovered).await
+            }
+        };
+        let result = tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            result = refresh => result,
+        };
+        match result {
+            Ok(pools) => {
+                // Refresh liquidity/volume of pools that are already stored, not only new ones.
+                #[cfg(feature = "sqlite")]
+                if let Some(store) = &ctx.store {
+                    let store = Arc::clone(store);
+                    let all = pools.clone();
+                    if let Ok(Err(e)) = tokio::task::spawn_blocking(move || store.upsert_pools(&all)).await {
+                        tracing::warn!("Failed to store rediscovered pools: {}", e);
+                    }
+                }
+                report_subgraph_lag(&ctx, &discovery, settings);
+                apply_rediscovery(&ctx, filter, &mut discovered, pools);
+            }
+            Err(e) => ctx.events.error(ScannerError::Discovery(format!("{:#}", e))),
+        }
+    }
+}
+
+/// Reconcile the tracked pools with a rediscovery result: pools of a protocol that was fetched but are no
+/// longer among its results are removed. The pools of protocols whose discovery failed this round are kept
+/// until a later run fetches them again.
+fn apply_rediscovery(ctx: &ScannerContext, filter: &PoolFilter, discovered: &mut Vec<CachedPool>, pools: PoolSet) {
+    let failed: HashSet<String> = pools.failed_protocols().iter().cloned().collect();
+    let pools = filter.apply(pools.into_vec());
+    let current: HashSet<Address> = pools.iter().map(|p| p.address).collect();
+    reconcile(ctx, "Rediscovery", discovered, pools, |pool| {
+        !failed.contains(&pool.protocol) && !current.contains(&pool.address)
+    });
+}
+
+/// Apply a reloaded configuration. Discovered pools of protocols that are no longer enabled, and those the
+/// new filter rejects, are removed right away. When the discovery settings or the filter changed, a full
+/// discovery with the new configuration follows; otherwise only the protocols that were enabled or changed
+/// are discovered.
+async fn apply_reload(
+    ctx: &ScannerContext,
+    discovery: &PoolDiscovery,
+    previous: &ScanConfig,
+    config: &ScanConfig,
+    discovered: &mut Vec<CachedPool>,
+) {
+    let enabled: HashSet<&str> = config.protocols.iter().map(|p| p.id.as_str()).collect();
+    reconcile(ctx, "Config reload", discovered, Vec::new(), |pool| {
+        !enabled.contains(pool.protocol.as_str()) || !config.filter.allows(pool)
+    });
+
+    // The refresh interval alone doesn't change what is discovered.
+    let settings_changed = DiscoveryConfig {
+        cache_refresh_minutes: config.discovery.cache_refresh_minutes,
+        ..previous.discovery.clone()
+    } != config.discovery;
+    let targets: Vec<ProtocolConfig> = if settings_changed || previous.filter != config.filter {
+        config.protocols.clone()
+    } else {
+        config.protocols.iter().filter(|p| !previous.protocols.contains(p)).cloned().collect()
+    };
+    if targets.is_empty() {
+        return;
+    }
+    info!("Config reload: discovering {} protocols", targets.len());
+    let pools = match discovery.refresh_pools(&targets, &config.discovery).await {
+        Ok(pools) => pools,
+        Err(e) => return ctx.events.error(ScannerError::Discovery(format!("{:#}", e))),
+    };
+    // A protocol that failed keeps its pools; only those fetched successfully are reconciled.
+    let searched: HashSet<&str> = targets
+        .iter()
+        .map(|p| p.id.as_str())
+        .filter(|id| !pools.failed_protocols().iter().any(|failed| failed == id))
+        .collect();
+    let pools = config.filter.apply(pools.into_vec());
+    let found: HashSet<Address> = pools.iter().map(|p| p.address).collect();
+    reconcile(ctx, "Config reload", discovered, pools, |pool| {
+        searched.contains(pool.protocol.as_str()) && !found.contains(&pool.address)
+    });
+}
+
+/// Track `found` and stop tracking the `discovered` pools `stale` selects, keeping `discovered` up to date and
+/// publishing `PoolDiscovered` / `PoolRemoved` for the changes.
+fn reconcile(
+    ctx: &ScannerContext,
+    label: &str,
+    discovered: &mut Vec<CachedPool>,
+    found: Vec<CachedPool>,
+    stale: impl Fn(&CachedPool) -> bool,
+) {
+    let stale: Vec<Address> = discovered.iter().filter(|pool| stale(pool)).map(|pool| pool.address).collect();
+    // `found` replaces the entries it shares with `discovered`, so their liquidity/volume are current.
+    let replaced: HashSet<Address> = stale.iter().copied().chain(found.iter().map(|pool| pool.address)).collect();
+    discovered.retain(|pool| !replaced.contains(&pool.address));
+    discovered.extend(found.iter().cloned());
+
+    let count = found.len();
+    let added = ctx.state.insert_all(found);
+    let removed = ctx.state.remove_all(&stale);
+    if count == 0 && removed.is_empty() {
+        return;
+    }
+    info!("{}: {} pools found, {} added, {} removed", label, count, added.len(), removed.len());
+    for pool in added {
+        ctx.events.publish(|| ScannerEvent::PoolDiscovered(pool.clone()));
+    }
+    for pool in removed {
+        ctx.events.publish(|| ScannerEvent::PoolRemoved(pool.clone()));
+    }
+}
+
+/// Ticks every `cache_refresh_minutes`, starting one period from now; `None` when rediscovery is off.
+fn refresh_interval(config: &DiscoveryConfig) -> Option<Interval> {
+    if config.cache_refresh_minutes == 0 {
+        return None;
+    }
+    let period = Duration::from_secs(u64::from(config.cache_refresh_minutes) * 60);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    Some(interval)
+}
+
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Report every subgraph that trailed the chain head by more than `max_subgraph_lag_blocks` at the last
+/// discovery as `ScannerError::SubgraphLag`.
+fn report_subgraph_lag(ctx: &ScannerContext, discovery: &PoolDiscovery, config: &DiscoveryConfig) {
+    let Some(max_lag) = config.max_subgraph_lag_blocks else { return };
+    for health in discovery.subgraph_health() {
+        if health.lag_blocks > max_lag {
+            ctx.events.error(ScannerError::SubgraphLag {
+                protocol: health.protocol,
+                lag_blocks: health.lag_blocks,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::DiscoverySource;
+    use crate::rpc::ScannerOptions;
+    use futures::future::BoxFuture;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// One pool per protocol: `0x11..` (tokens `0x01..`/`0x0a..`) for `a`, `0x22..` (`0x01..`/`0x0b..`) otherwise.
+    struct FixedSource;
+
+    impl DiscoverySource for FixedSource {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn fetch_pools<'a>(
+            &'a self,
+            protocol: &'a ProtocolConfig,
+            _config: &'a DiscoveryConfig,
+        ) -> BoxFuture<'a, eyre::Result<Vec<CachedPool>>> {
+            let (address, token1) = if protocol.id == "a" { (0x11, 0x0a) } else { (0x22, 0x0b) };
+            Box::pin(async move {
+                Ok(vec![CachedPool {
+                    address: Address::repeat_byte(address),
+                    protocol: protocol.id.clone(),
+                    token0: Address::repeat_byte(0x01),
+                    token1: Address::repeat_byte(token1),
+                    ..Default::default()
+                }])
+            })
+        }
+    }
+
+    /// `FixedSource`, but protocol `b` fails while `failing` is set.
+    struct FlakySource {
+        failing: Arc<AtomicBool>,
+    }
+
+    impl DiscoverySource for FlakySource {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn fetch_pools<'a>(
+            &'a self,
+            protocol: &'a ProtocolConfig,
+            config: &'a DiscoveryConfig,
+        ) -> BoxFuture<'a, eyre::Result<Vec<CachedPool>>> {
+            if protocol.id == "b" && self.failing.load(Ordering::Relaxed) {
+                return Box::pin(async { Err(eyre::eyre!("subgraph unavailable")) });
+            }
+            FixedSource.fetch_pools(protocol, config)
+        }
+    }
+
+    fn protocol(id: &str) -> ProtocolConfig {
+        serde_json::from_value(json!({
+            "id": id, "name": id, "subgraph_url": "", "pool_type": "UniswapV2", "enabled": true
+        }))
+        .unwrap()
+    }
+
+    fn tracked(ctx: &ScannerContext) -> Vec<Address> {
+        let mut addresses = ctx.state.addresses();
+        addresses.sort();
+        addresses
+    }
+
+    #[tokio::test]
+    async fn test_apply_reload() {
+        let ctx = ScannerContext::new(Arc::new(|_, _, _| {}), ScannerOptions::default());
+        let discovery = PoolDiscovery::new().with_source(FixedSource);
+        let settings: DiscoveryConfig = serde_json::from_value(json!({
+            "min_liquidity_usd": 0.0, "max_pools_per_protocol": 10,
+            "verify_token_metadata": false, "detect_token_quirks": false
+        }))
+        .unwrap();
+        let initial = ScanConfig {
+            protocols: vec![protocol("a")],
+            discovery: settings,
+            filter: PoolFilter::default(),
+        };
+        let mut discovered = Vec::new();
+        let pools = discovery.refresh_pools(&initial.protocols, &initial.discovery).await.unwrap();
+        reconcile(&ctx, "Discovery", &mut discovered, pools.into_vec(), |_| false);
+        assert_eq!(tracked(&ctx), vec![Address::repeat_byte(0x11)]);
+
+        // `a` disabled, `b` enabled: `a`'s pool goes, `b` is discovered.
+        let swapped = ScanConfig {
+            protocols: vec![protocol("b")],
+            ..initial.clone()
+        };
+        apply_reload(&ctx, &discovery, &initial, &swapped, &mut discovered).await;
+        assert_eq!(tracked(&ctx), vec![Address::repeat_byte(0x22)]);
+
+        // Both enabled, but token 0x0b..'s pools are no longer whitelisted.
+        let mut whitelist = ScanConfig {
+            protocols: vec![protocol("a"), protocol("b")],
+            ..initial.clone()
+        };
+        whitelist.filter.token_whitelist = [Address::repeat_byte(0x01), Address::repeat_byte(0x0a)].into();
+        apply_reload(&ctx, &discovery, &swapped, &whitelist, &mut discovered).await;
+        assert_eq!(tracked(&ctx), vec![Address::repeat_byte(0x11)]);
+        assert_eq!(discovered.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rediscovery_keeps_pools_of_failed_protocols() {
+        let ctx = ScannerContext::new(Arc::new(|_, _, _| {}), ScannerOptions::default());
+        let failing = Arc::new(AtomicBool::new(false));
+        let discovery = PoolDiscovery::new().with_source(FlakySource {
+            failing: Arc::clone(&failing),
+        });
+        let settings: DiscoveryConfig = serde_json::from_value(json!({
+            "min_liquidity_usd": 0.0, "max_pools_per_protocol": 10,
+            "verify_token_metadata": false, "detect_token_quirks": false
+        }))
+        .unwrap();
+        let protocols = vec![protocol("a"), protocol("b")];
+        let filter = PoolFilter::default();
+        let mut discovered = Vec::new();
+        let pools = discovery.refresh_pools(&protocols, &settings).await.unwrap();
+        apply_rediscovery(&ctx, &filter, &mut discovered, pools);
+        assert_eq!(tracked(&ctx), vec![Address::repeat_byte(0x11), Address::repeat_byte(0x22)]);
+
+        // `b` fails this round: the result is partial, but `b`'s pool stays tracked.
+        failing.store(true, Ordering::Relaxed);
+        let pools = discovery.refresh_pools(&protocols, &settings).await.unwrap();
+        assert_eq!(pools.failed_protocols(), ["b".to_string()]);
+        apply_rediscovery(&ctx, &filter, &mut discovered, pools);
+        assert_eq!(tracked(&ctx), vec![Address::repeat_byte(0x11), Address::repeat_byte(0x22)]);
+        assert_eq!(discovered.len(), 2);
+    }
+}
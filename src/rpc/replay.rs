@@ -0,0 +1,150 @@
+use super::{fanout, PriceChangeCallback, ScannerContext, ScannerOptions};
+use crate::events::ScannerEvent;
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// What `Replay::run` delivered.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaySummary {
+    pub price_updates: u64,
+    pub pools: usize,
+    /// Timestamps of the first and last update; `None` when there were none.
+    pub first_timestamp: Option<u64>,
+    pub last_timestamp: Option<u64>,
+    /// Cancelled before the last update.
+    pub cancelled: bool,
+}
+
+/// Recorded price updates (`sinks::json_lines::read_prices`, `sinks::parquet::read_prices`) played back
+/// through the same price-change callback, event bus and `ScannerOptions::sinks` as a live `Scanner`, without
+/// an RPC. Only the callback, bus and sinks of the options are used.
+pub struct Replay {
+    ctx: ScannerContext,
+    speed: Option<f64>,
+}
+
+impl Replay {
+    /// A replay as fast as the callback and sinks take the updates.
+    pub fn new(on_price_change: PriceChangeCallback, options: ScannerOptions) -> Self {
+        Self {
+            ctx: ScannerContext::new(on_price_change, options),
+            speed: None,
+        }
+    }
+
+    /// Pace the updates by their timestamps instead: `1.0` is the recorded pace, `60.0` plays an hour a minute.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = Some(speed).filter(|speed| *speed > 0.0);
+        self
+    }
+
+    /// Subscribe to the replay's event bus, like `Scanner::subscribe`.
+    pub fn subscribe(&self) -> broadcast::Receiver<ScannerEvent> {
+        self.ctx.events.subscribe()
+    }
+
+    /// Token that stops `run` after the update being delivered.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.ctx.cancel.clone()
+    }
+
+    /// Deliver `updates` in order, each with the pool's previous replayed price as `previous`. Every event
+    /// reaches every sink; they are closed when the replay ends.
+    pub async fn run(&self, updates: impl IntoIterator<Item = (CachedPool, PoolPrice)>) -> Result<ReplaySummary> {
+        let ctx = &self.ctx;
+        let mut events = ctx.events.subscribe();
+        let mut summary = ReplaySummary::default();
+        let mut previous: HashMap<Address, PoolPrice> = HashMap::new();
+        for (pool, price) in updates {
+            if let (Some(speed), Some(last)) = (self.speed, summary.last_timestamp) {
+                let gap = Duration::from_secs(price.timestamp.saturating_sub(last)).div_f64(speed);
+                tokio::select! {
+                    _ = ctx.cancel.cancelled() => {}
+                    _ = tokio::time::sleep(gap) => {}
+                }
+            }
+            if ctx.cancel.is_cancelled() {
+                summary.cancelled = true;
+                break;
+            }
+            summary.price_updates += 1;
+            summary.first_timestamp.get_or_insert(price.timestamp);
+            summary.last_timestamp = Some(price.timestamp);
+            let last = previous.insert(pool.address, price.clone());
+            ctx.events.price_changed(pool, price, last);
+            fanout::deliver(ctx, &mut events).await;
+        }
+        summary.pools = previous.len();
+        for sink in &ctx.sinks {
+            fanout::retry(ctx, sink.name(), || sink.close()).await;
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::Sink;
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct Recorder(Mutex<Vec<(f64, Option<f64>)>>);
+
+    #[async_trait]
+    impl Sink for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+            if let ScannerEvent::PriceUpdate { price, previous, .. } = event {
+                let previous = previous.as_ref().map(|previous| previous.token0_price);
+                self.0.lock().unwrap().push((price.token0_price, previous));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let recorder = Arc::new(Recorder::default());
+        let options = ScannerOptions {
+            sinks: vec![recorder.clone()],
+            ..Default::default()
+        };
+        let replay = Replay::new(Arc::new(|_, _, _| {}), options).with_speed(6_000.0);
+        let update = |pool: u8, timestamp, token0_price| {
+            let pool = CachedPool {
+                address: Address::repeat_byte(pool),
+                ..Default::default()
+            };
+            let price = PoolPrice {
+                pool_address: pool.address,
+                token0_price,
+                timestamp,
+                ..Default::default()
+            };
+            (pool, price)
+        };
+
+        let started = std::time::Instant::now();
+        let summary = replay
+            .run([update(1, 1_000, 10.0), update(2, 1_060, 20.0), update(1, 1_600, 11.0)])
+            .await
+            .unwrap();
+        // 600 recorded seconds at 6000x.
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(summary.price_updates, 3);
+        assert_eq!(summary.pools, 2);
+        assert_eq!((summary.first_timestamp, summary.last_timestamp), (Some(1_000), Some(1_600)));
+        assert_eq!(*recorder.0.lock().unwrap(), vec![(10.0, None), (20.0, None), (11.0, Some(10.0))]);
+    }
+}
@@ -0,0 +1,65 @@
+use super::server::AppState;
+use super::pair_filter::PairFilter;
+use crate::pricing::PairPrice;
+use crate::types::{PoolPrice, PoolSnapshot};
+use alloy::primitives::Address;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+
+/// `GET /pairs/{a}/{b}`: every tracked pool of the pair plus, with `ScannerOptions::pair_prices` (or the
+/// spread monitor / arbitrage detector), the pair's liquidity-weighted price.
+#[derive(Debug, Serialize)]
+pub(crate) struct PairResponse {
+    price: Option<PairPrice>,
+    pools: Vec<PoolSnapshot>,
+}
+
+/// `GET /pools`: every tracked pool with its latest state (`Scanner::snapshot`).
+pub(crate) async fn pools(State(state): State<AppState>) -> Json<Vec<PoolSnapshot>> {
+    Json(state.ctx.snapshot(state.stale_after))
+}
+
+/// `GET /pools/{address}`.
+pub(crate) async fn pool(
+    State(state): State<AppState>,
+    Path(address): Path<Address>,
+) -> Result<Json<PoolSnapshot>, StatusCode> {
+    let slot = state.ctx.state.get(&address).ok_or(StatusCode::NOT_FOUND)?;
+    let now = chrono::Utc::now().timestamp() as u64;
+    Ok(Json(slot.snapshot(now, state.stale_after)))
+}
+
+/// `GET /prices`: the last price of every pool that has one.
+pub(crate) async fn prices(State(state): State<AppState>) -> Json<Vec<PoolPrice>> {
+    let prices = state
+        .ctx
+        .state
+        .addresses()
+        .iter()
+        .filter_map(|address| state.ctx.state.get(address)?.current_price())
+        .collect();
+    Json(prices)
+}
+
+/// `GET /pairs/{a}/{b}`, each side a token symbol or address. 404 when no tracked pool trades the pair.
+pub(crate) async fn pair(
+    State(state): State<AppState>,
+    Path((token_a, token_b)): Path<(String, String)>,
+) -> Result<Json<PairResponse>, StatusCode> {
+    let filter = PairFilter::parse(&format!("{token_a}/{token_b}"));
+    let pools: Vec<PoolSnapshot> = state
+        .ctx
+        .snapshot(state.stale_after)
+        .into_iter()
+        .filter(|snapshot| filter.matches(&snapshot.pool))
+        .collect();
+    let first = pools.first().ok_or(StatusCode::NOT_FOUND)?;
+    let price = state
+        .ctx
+        .pairs
+        .as_ref()
+        .and_then(|pairs| pairs.get(first.pool.token0, first.pool.token1));
+    Ok(Json(PairResponse { price, pools }))
+}
@@ -0,0 +1,137 @@
+use super::pair_filter::PairFilter;
+#[cfg(feature = "graphql")]
+use super::graphql;
+use super::websocket;
+use super::sse::{self, StreamFilter, StreamParams};
+use super::{prometheus, rest, ScannerContext};
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use eyre::Result;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+/// Address and endpoints of the built-in HTTP server (`ScannerOptions::server`).
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub addr: SocketAddr,
+    /// Serve Prometheus metrics at `/metrics`.
+    pub prometheus: bool,
+    /// Stream price updates as JSON to WebSocket clients at `/ws` (`/ws?pairs=WETH/USDC,WBTC/WETH`).
+    pub websocket: bool,
+    /// Serve the in-memory pools and prices as JSON: `/pools`, `/pools/{address}`, `/prices` and
+    /// `/pairs/{a}/{b}`.
+    pub rest: bool,
+    /// Stream price updates as server-sent events at `/stream` (`/stream?pairs=WETH/USDC&min_change_bps=5`).
+    pub sse: bool,
+    /// Serve a GraphQL API over the pools, prices and candles at `/graphql`, with subscriptions over
+    /// WebSocket at `/graphql/ws`.
+    #[cfg(feature = "graphql")]
+    pub graphql: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 9100)),
+            prometheus: true,
+            websocket: false,
+            rest: false,
+            sse: false,
+            #[cfg(feature = "graphql")]
+            graphql: false,
+        }
+    }
+}
+
+/// What the request handlers read from.
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub(crate) ctx: Arc<ScannerContext>,
+    pub(crate) stale_after: Option<Duration>,
+}
+
+/// Bind `config.addr`, so a taken port fails `Scanner::start`.
+pub(crate) async fn bind(config: &ServerConfig) -> Result<TcpListener> {
+    let listener = TcpListener::bind(config.addr).await?;
+    info!("Serving HTTP on {}", listener.local_addr()?);
+    Ok(listener)
+}
+
+/// Serve the configured endpoints until the scanner shuts down.
+pub(crate) async fn run_server(
+    ctx: Arc<ScannerContext>,
+    listener: TcpListener,
+    config: ServerConfig,
+    stale_after: Option<Duration>,
+) {
+    let mut router = Router::new();
+    if config.prometheus {
+        router = router.route("/metrics", get(metrics));
+    }
+    if config.websocket {
+        router = router.route("/ws", get(websocket));
+    }
+    if config.rest {
+        router = router
+            .route("/pools", get(rest::pools))
+            .route("/pools/{address}", get(rest::pool))
+            .route("/prices", get(rest::prices))
+            .route("/pairs/{a}/{b}", get(rest::pair));
+    }
+    if config.sse {
+        router = router.route("/stream", get(stream));
+    }
+    #[cfg(feature = "graphql")]
+    if config.graphql {
+        let schema = graphql::schema(Arc::clone(&ctx), stale_after);
+        router = router
+            .route_service("/graphql", async_graphql_axum::GraphQL::new(schema.clone()))
+            .route_service("/graphql/ws", async_graphql_axum::GraphQLSubscription::new(schema));
+    }
+    let cancel = ctx.cancel.clone();
+    let router = router.with_state(AppState { ctx, stale_after });
+    if let Err(e) = axum::serve(listener, router)
+        .with_graceful_shutdown(cancel.cancelled_owned())
+        .await
+    {
+        warn!("HTTP server failed: {}", e);
+    }
+}
+
+#[derive(Deserialize)]
+struct WebSocketParams {
+    /// Comma-separated `A/B` pairs of token symbols or addresses.
+    pairs: Option<String>,
+}
+
+async fn websocket(
+    upgrade: WebSocketUpgrade,
+    Query(params): Query<WebSocketParams>,
+    State(state): State<AppState>,
+) -> Response {
+    let filter = PairFilter::parse(params.pairs.as_deref().unwrap_or_default());
+    upgrade.on_upgrade(move |socket| websocket::stream_prices(state.ctx, socket, filter))
+}
+
+async fn stream(Query(params): Query<StreamParams>, State(state): State<AppState>) -> Response {
+    match StreamFilter::new(params) {
+        Ok(filter) => Sse::new(sse::price_events(state.ctx, filter))
+            .keep_alive(KeepAlive::default())
+            .into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let body = prometheus::render(&state.ctx.stats(), &state.ctx.snapshot(state.stale_after));
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
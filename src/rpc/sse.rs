@@ -0,0 +1,127 @@
+use super::pair_filter::PairFilter;
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use crate::sinks::json_lines::price_line;
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use axum::response::sse::Event;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+/// `/stream` query parameters, e.g. `?pairs=WETH/USDC&min_change_bps=5`.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct StreamParams {
+    /// Comma-separated pool addresses.
+    pools: Option<String>,
+    /// Comma-separated `A/B` pairs of token symbols or addresses.
+    pairs: Option<String>,
+    /// Skip updates moving a pool's price less than this since the last one sent to the client.
+    min_change_bps: Option<f64>,
+}
+
+/// Per-client filter of the price stream.
+#[derive(Debug)]
+pub(crate) struct StreamFilter {
+    pools: HashSet<Address>,
+    pairs: PairFilter,
+    min_change_bps: f64,
+    /// token0 price last sent per pool, for `min_change_bps`.
+    sent: HashMap<Address, f64>,
+}
+
+impl StreamFilter {
+    /// Parse the query; unparsable pool addresses are an error.
+    pub(crate) fn new(params: StreamParams) -> Result<Self, String> {
+        let pools = params
+            .pools
+            .iter()
+            .flat_map(|pools| pools.split(','))
+            .map(str::trim)
+            .filter(|pool| !pool.is_empty())
+            .map(|pool| pool.parse().map_err(|_| format!("invalid pool address {pool:?}")))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            pools,
+            pairs: PairFilter::parse(params.pairs.as_deref().unwrap_or_default()),
+            min_change_bps: params.min_change_bps.unwrap_or_default(),
+            sent: HashMap::new(),
+        })
+    }
+
+    /// Whether to send this update, remembering its price if so.
+    fn accept(&mut self, pool: &CachedPool, price: &PoolPrice) -> bool {
+        if !(self.pools.is_empty() || self.pools.contains(&pool.address)) || !self.pairs.matches(pool) {
+            return false;
+        }
+        if let Some(last) = self.sent.get(&pool.address)
+            && *last != 0.0
+            && ((price.token0_price - last) / last).abs() * 10_000.0 < self.min_change_bps
+        {
+            return false;
+        }
+        self.sent.insert(pool.address, price.token0_price);
+        true
+    }
+}
+
+/// Price updates passing `filter` as `price` events whose data is a JSON line (the `JsonLinesSink` format),
+/// until the scanner stops. A client too slow to keep up skips updates.
+pub(crate) fn price_events(
+    ctx: Arc<ScannerContext>,
+    filter: StreamFilter,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let cancel = ctx.cancel.clone();
+    futures::stream::unfold((ctx.events.subscribe(), filter), |(mut events, mut filter)| async move {
+        loop {
+            match events.recv().await {
+                Ok(ScannerEvent::PriceUpdate { pool, price, .. }) if filter.accept(&pool, &price) => {
+                    let Ok(line) = price_line(&pool, &price) else { continue };
+                    return Some((Ok(Event::default().event("price").data(line)), (events, filter)));
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => debug!("SSE client lagged, skipped {} events", skipped),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .take_until(cancel.cancelled_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_filter() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            ..Default::default()
+        };
+        let other = CachedPool { address: Address::repeat_byte(2), ..pool.clone() };
+        let price = |token0_price| PoolPrice { token0_price, ..Default::default() };
+
+        let mut filter = StreamFilter::new(StreamParams {
+            pools: Some(format!("{}, ", pool.address)),
+            pairs: Some("usdc/weth".to_string()),
+            min_change_bps: Some(10.0),
+        })
+        .unwrap();
+        assert!(!filter.accept(&other, &price(3000.0)));
+        assert!(filter.accept(&pool, &price(3000.0)));
+        // 5 bps since the last sent price: skipped, and the reference stays at 3000.
+        assert!(!filter.accept(&pool, &price(3001.5)));
+        assert!(filter.accept(&pool, &price(3003.0)));
+
+        let mut everything = StreamFilter::new(StreamParams::default()).unwrap();
+        assert!(everything.accept(&other, &price(1.0)));
+        assert!(everything.accept(&other, &price(1.0)));
+        assert!(StreamFilter::new(StreamParams { pools: Some("0x12".to_string()), ..Default::default() }).is_err());
+    }
+}
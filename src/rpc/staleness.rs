@@ -0,0 +1,69 @@
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// A tracked pool had no log for `ScannerOptions::stale_after`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceStale {
+    pub pool_address: Address,
+    /// Timestamp of the pool's last delivered price; `None` if it never updated.
+    pub last_update: Option<u64>,
+    /// Seconds since the pool's last log (or since tracking started).
+    pub idle_secs: u64,
+    pub timestamp: u64,
+}
+
+/// Check every pool for staleness a few times per window (at most every minute, at least every second)
+/// and publish `ScannerEvent::PriceStale` for each pool that just went stale.
+pub(crate) async fn run_stale_monitor(ctx: Arc<ScannerContext>, window: Duration) {
+    let period = (window / 10).clamp(Duration::from_secs(1), Duration::from_secs(60));
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let now = chrono::Utc::now().timestamp() as u64;
+        for address in ctx.state.addresses() {
+            let Some(slot) = ctx.state.get(&address) else { continue };
+            if let Some(stale) = slot.check_stale(now, window) {
+                ctx.events.publish(|| ScannerEvent::PriceStale(stale));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::state::tests::{slot, trade};
+    use super::*;
+
+    #[test]
+    fn test_stale_once_until_the_next_log() {
+        let window = Duration::from_secs(60);
+        let slot = slot();
+        let start = slot.snapshot(0, None).last_activity;
+        assert!(slot.check_stale(start + 59, window).is_none());
+        assert!(!slot.snapshot(start + 59, Some(window)).stale);
+
+        let stale = slot.check_stale(start + 60, window).unwrap();
+        assert_eq!((stale.last_update, stale.idle_secs), (None, 60));
+        assert!(slot.snapshot(start + 60, Some(window)).stale);
+        // Reported once while the pool stays quiet.
+        assert!(slot.check_stale(start + 120, window).is_none());
+
+        // The next log re-arms the check.
+        let [sync, _] = trade(1, 1, (1_000, 2_000_000), 0);
+        slot.apply_log(&sync, start + 200, None, None).unwrap();
+        assert!(!slot.snapshot(start + 200, Some(window)).stale);
+        assert!(slot.check_stale(start + 259, window).is_none());
+        let stale = slot.check_stale(start + 260, window).unwrap();
+        assert_eq!((stale.last_update, stale.idle_secs), (Some(start + 200), 60));
+        assert!(!slot.snapshot(start + 260, None).stale);
+    }
+}
@@ -0,0 +1,496 @@
+use crate::discovery::canonical_pair;
+use crate::liquidity_pools::{
+    BaseLiquidityPool, EthereumLog, Quote, SwapDirection, SwapEventData, UniswapV2, UniswapV3,
+};
+use crate::metrics::{Candle, MetricsConfig, PoolMetrics, RollingVolume, Twap, Volatility, VolumeTracker};
+use crate::pricing::{is_usd_stablecoin, UsdPricer};
+use super::staleness::PriceStale;
+#[cfg(feature = "rocksdb")]
+use crate::state_store::PoolCheckpoint;
+use crate::spikes::{PriceSpike, SpikeAction, SpikeFilterConfig};
+use crate::types::{CachedPool, PoolPrice, PoolSnapshot};
+use alloy::primitives::{Address, B256, I256, U256};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use eyre::Result;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Mutable per-pool state, guarded by the pool's own lock.
+struct PoolSlotState {
+    liquidity_pool: Box<dyn BaseLiquidityPool>,
+    current_price: Option<PoolPrice>,
+    metrics: PoolMetrics,
+    /// Swaps of the last 24 hours, including ones suppressed by the spike filter.
+    volume: VolumeTracker,
+    /// Unix time of the last decoded log, or when tracking started.
+    last_activity: u64,
+    /// A `PriceStale` was published and no log has arrived since.
+    stale: bool,
+    /// Block and log index of the last decoded log; logs at or before it are skipped.
+    last_log: Option<(u64, u64)>,
+    /// Transaction of the last no-trade update (a V2 `Sync`) and the token0 price before it, which the spike
+    /// filter judges that transaction's `Swap` from.
+    pre_trade: Option<(B256, f64)>,
+}
+
+/// Outcome of decoding one log.
+pub(crate) struct AppliedLog {
+    pub(crate) price: PoolPrice,
+    pub(crate) previous: Option<PoolPrice>,
+    /// Set when the spike filter caught the update; with `suppressed`, `price` must not be delivered.
+    pub(crate) spike: Option<PriceSpike>,
+    /// The decoded event, when it was a swap.
+    pub(crate) swap: Option<SwapEventData>,
+    /// USD value of the swap; `None` for other events or when neither token has a USD price.
+    pub(crate) volume_usd: Option<f64>,
+}
+
+/// One tracked pool: immutable discovery metadata plus its own lock for decoder state.
+pub(crate) struct PoolSlot {
+    pub(crate) pool: CachedPool,
+    /// Quote token prices are oriented to (`ScannerOptions::quote_tokens`).
+    quote: Option<Address>,
+    state: Mutex<PoolSlotState>,
+}
+
+impl PoolSlot {
+    fn new(pool: CachedPool, metrics: &MetricsConfig, quote_tokens: &[Address]) -> Self {
+        let liquidity_pool = build_liquidity_pool(&pool);
+        let metrics = PoolMetrics::new(metrics, pool.address);
+        let now = chrono::Utc::now().timestamp() as u64;
+        Self {
+            quote: pool.quote_token(quote_tokens),
+            pool,
+            state: Mutex::new(PoolSlotState {
+                liquidity_pool,
+                current_price: None,
+                metrics,
+                volume: VolumeTracker::new(now),
+                last_activity: now,
+                stale: false,
+                last_log: None,
+                pre_trade: None,
+            }),
+        }
+    }
+
+    /// Decode `log` into the pool and swap in the new price. Returns the new and previous price, or
+    /// `None` for a log the pool already reflects (e.g. seen during a backfill). Only this pool's lock
+    /// is held, and only for the duration of the decode (plus the pricer's lock while USD prices are
+    /// attached). An update suppressed by the spike filter still moves the decoder
+    /// but leaves the current price, metrics and pricer untouched. Updates without a swap aren't judged; under
+    /// `SpikeAction::Suppress` one that moves the price too far (a V2 `Sync`) is held back the same way, with
+    /// `None`, until the `Swap` of its transaction is judged on its volume.
+    pub(crate) fn apply_log(
+        &self,
+        log: &EthereumLog,
+        timestamp: u64,
+        pricing: Option<&UsdPricer>,
+        spike_filter: Option<&SpikeFilterConfig>,
+    ) -> Result<Option<AppliedLog>> {
+        let mut state = self.state.lock().unwrap();
+        let position = log.block_number.zip(log.log_index);
+        if position.is_some() && position <= state.last_log {
+            return Ok(None);
+        }
+        let swap_data = state.liquidity_pool.parse_swap_event_data(log)?;
+        state.last_log = position.or(state.last_log);
+        state.last_activity = timestamp;
+        state.stale = false;
+        let mut new_price = PoolPrice {
+            pool_address: self.pool.address,
+            token0_price: swap_data.price,
+            token1_price: 1.0 / swap_data.price,
+            timestamp,
+            token0_price_exact: Some(swap_data.exact_price),
+            token1_price_exact: Some(swap_data.exact_price.inverse()),
+            block_number: log.block_number,
+            log_index: log.log_index,
+            ..Default::default()
+        };
+        if swap_data.direction.is_some() {
+            new_price.amount0 = Some(signed_token_amount(swap_data.amount0, self.pool.token0_decimals));
+            new_price.amount1 = Some(signed_token_amount(swap_data.amount1, self.pool.token1_decimals));
+        }
+        new_price.quoted = self.quote.map(|quote| new_price.quoted(&self.pool, quote));
+        let trade = swap_data.direction.map(|_| {
+            (
+                token_amount(swap_data.amount0, self.pool.token0_decimals),
+                token_amount(swap_data.amount1, self.pool.token1_decimals),
+            )
+        });
+
+        let volume_usd = self.volume_usd(trade, pricing);
+        if let Some((volume0, volume1)) = trade {
+            state.volume.record(timestamp, volume0, volume1, volume_usd);
+        }
+        let swap = swap_data.direction.is_some().then_some(swap_data);
+
+        // A V2 Sync moves the price ahead of its Swap: judge the Swap from the price before the Sync.
+        let last_price = state.current_price.as_ref().map(|price| price.token0_price);
+        let previous_price = match (trade, log.transaction_hash) {
+            (None, Some(tx)) => {
+                if state.pre_trade.is_none_or(|(pending, _)| pending != tx) {
+                    state.pre_trade = last_price.map(|price| (tx, price));
+                }
+                None
+            }
+            (None, None) => None,
+            (Some(_), tx) => {
+                let pre_trade = state.pre_trade.take().filter(|(pending, _)| Some(*pending) == tx);
+                pre_trade.map(|(_, price)| price).or(last_price)
+            }
+        };
+        if let Some(filter) = spike_filter.filter(|filter| filter.action == SpikeAction::Suppress)
+            && trade.is_none()
+            && last_price.is_some_and(|last| filter.large_move(last, new_price.token0_price).is_some())
+        {
+            return Ok(None);
+        }
+
+        let spike = spike_filter.zip(previous_price).and_then(|(filter, previous_price)| {
+            let (move_bps, volume_usd) = filter.check(previous_price, new_price.token0_price, volume_usd)?;
+            Some(PriceSpike {
+                pool_address: self.pool.address,
+                previous_price,
+                price: new_price.token0_price,
+                move_bps,
+                volume_usd,
+                suppressed: filter.action == SpikeAction::Suppress,
+                timestamp,
+            })
+        });
+        if let Some(spike) = &spike {
+            if spike.suppressed {
+                return Ok(Some(AppliedLog {
+                    price: new_price,
+                    previous: state.current_price.clone(),
+                    spike: Some(spike.clone()),
+                    swap,
+                    volume_usd,
+                }));
+            }
+            new_price.suspect = true;
+        }
+
+        state.metrics.record(&mut new_price, trade);
+        if let Some(pricer) = pricing {
+            pricer.on_price(&self.pool, &mut new_price);
+        }
+        let old_price = state.current_price.replace(new_price.clone());
+        Ok(Some(AppliedLog {
+            price: new_price,
+            previous: old_price,
+            spike,
+            swap,
+            volume_usd,
+        }))
+    }
+
+    /// USD value of a swap's token amounts, valued with the pricer or a stablecoin leg. `None` for updates
+    /// without a swap, which trade nothing the spike filter could judge, and when neither token has a USD price.
+    fn volume_usd(&self, trade: Option<(f64, f64)>, pricing: Option<&UsdPricer>) -> Option<f64> {
+        let (volume0, volume1) = trade?;
+        let usd = |token: Address, symbol: &str| {
+            pricing
+                .and_then(|pricer| pricer.token_price_usd(token))
+                .or_else(|| is_usd_stablecoin(symbol).then_some(1.0))
+        };
+        usd(self.pool.token1, &self.pool.token1_symbol)
+            .map(|price| volume1 * price)
+            .or_else(|| usd(self.pool.token0, &self.pool.token0_symbol).map(|price| volume0 * price))
+    }
+
+    /// After a metrics warm-up from `since` to `now` (block times): count the replayed swaps as watched and
+    /// drop the candles completed during the replay, which are history rather than live candles.
+    pub(crate) fn finish_warmup(&self, since: u64, now: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.volume.backdate(since);
+        state.metrics.take_candles(now);
+    }
+
+    /// Mark the pool stale when no log has arrived for `window` as of `now`. Returns the event for a pool
+    /// that just went stale; a stale pool isn't reported again until its next log.
+    pub(crate) fn check_stale(&self, now: u64, window: Duration) -> Option<PriceStale> {
+        let mut state = self.state.lock().unwrap();
+        let idle_secs = now.saturating_sub(state.last_activity);
+        if state.stale || idle_secs < window.as_secs() {
+            return None;
+        }
+        state.stale = true;
+        Some(PriceStale {
+            pool_address: self.pool.address,
+            last_update: state.current_price.as_ref().map(|price| price.timestamp),
+            idle_secs,
+            timestamp: now,
+        })
+    }
+
+    /// Current state for `Scanner::snapshot`. With `stale_after`, the entry is stale once no log has
+    /// arrived for that long. Once the pool has been watched for a day, the discovery-time
+    /// `volume_24h_usd` is replaced by the live rolling volume.
+    pub(crate) fn snapshot(&self, now: u64, stale_after: Option<Duration>) -> PoolSnapshot {
+        let state = self.state.lock().unwrap();
+        let volume_24h = state.volume.volume(now);
+        let mut pool = self.pool.clone();
+        if volume_24h.complete {
+            pool.volume_24h_usd = volume_24h.volume_usd;
+        }
+        PoolSnapshot {
+            pool,
+            volume_24h,
+            price: state.current_price.clone(),
+            last_activity: state.last_activity,
+            stale: stale_after.is_some_and(|window| now.saturating_sub(state.last_activity) >= window.as_secs()),
+        }
+    }
+
+    /// Swap volume over the 24 hours before `now`.
+    pub(crate) fn volume(&self, now: u64) -> RollingVolume {
+        self.state.lock().unwrap().volume.volume(now)
+    }
+
+    /// Price after the last decoded log, if any.
+    pub(crate) fn current_price(&self) -> Option<PoolPrice> {
+        self.state.lock().unwrap().current_price.clone()
+    }
+
+    pub(crate) fn volatility(&self) -> Option<Volatility> {
+        self.state.lock().unwrap().metrics.volatility()
+    }
+
+    /// Candles completed since the last call (see `PoolMetrics::take_candles`).
+    pub(crate) fn take_candles(&self, now: u64) -> Vec<Candle> {
+        self.state.lock().unwrap().metrics.take_candles(now)
+    }
+
+    /// In-range liquidity of the decoder (see `BaseLiquidityPool::get_liquidity`).
+    pub(crate) fn liquidity(&self) -> u128 {
+        self.state.lock().unwrap().liquidity_pool.get_liquidity()
+    }
+
+    /// TWAPs over the configured windows as of `now`.
+    pub(crate) fn twaps(&self, now: u64) -> Vec<Twap> {
+        self.state.lock().unwrap().metrics.twaps(now)
+    }
+
+    /// Decoder state as of the last decoded log. `None` until a log with a known position was decoded.
+    #[cfg(feature = "rocksdb")]
+    pub(crate) fn checkpoint(&self) -> Option<PoolCheckpoint> {
+        let state = self.state.lock().unwrap();
+        let (block_number, log_index) = state.last_log?;
+        Some(PoolCheckpoint {
+            block_number,
+            log_index,
+            state: state.liquidity_pool.export_state(),
+            price: state.current_price.clone(),
+        })
+    }
+
+    /// Resume from a stored checkpoint; logs it already covers are skipped from now on.
+    #[cfg(feature = "rocksdb")]
+    pub(crate) fn restore(&self, checkpoint: PoolCheckpoint) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.liquidity_pool.restore_state(checkpoint.state)?;
+        state.current_price = checkpoint.price;
+        state.last_log = Some((checkpoint.block_number, checkpoint.log_index));
+        Ok(())
+    }
+
+    /// Quote selling `amount_in` of `token_in` (one of the pool's tokens) against the pool's current state.
+    pub(crate) fn quote_exact_input(&self, token_in: Address, amount_in: U256) -> Result<Quote> {
+        let direction = if token_in == self.pool.token0 {
+            SwapDirection::ZeroForOne
+        } else if token_in == self.pool.token1 {
+            SwapDirection::OneForZero
+        } else {
+            return Err(eyre::eyre!("{} is not a token of pool {}", token_in, self.pool.address));
+        };
+        self.state.lock().unwrap().liquidity_pool.quote_exact_input(direction, amount_in)
+    }
+}
+
+/// Absolute raw amount in token units.
+fn token_amount(amount: I256, decimals: u8) -> f64 {
+    f64::from(amount.unsigned_abs()) / 10f64.powi(i32::from(decimals))
+}
+
+/// Signed raw amount in token units.
+fn signed_token_amount(amount: I256, decimals: u8) -> f64 {
+    let value = token_amount(amount, decimals);
+    if amount.is_negative() { -value } else { value }
+}
+
+/// Pick the decoder for a discovered pool based on its protocol id.
+fn build_liquidity_pool(pool: &CachedPool) -> Box<dyn BaseLiquidityPool> {
+    // Discovery reports fee 0 when it doesn't know the fee (e.g. V2 subgraphs); keep the decoder default then.
+    if pool.protocol.to_lowercase().contains("v2") {
+        let decoder = UniswapV2::new(pool.address, pool.token0_decimals, pool.token1_decimals);
+        Box::new(if pool.fee == 0 { decoder } else { decoder.with_fee(pool.fee) })
+    } else {
+        let decoder = UniswapV3::new(pool.address, pool.token0_decimals, pool.token1_decimals);
+        Box::new(if pool.fee == 0 { decoder } else { decoder.with_fee(pool.fee) })
+    }
+}
+
+/// Sharded map of tracked pools. Lookups lock a single shard briefly to clone the slot `Arc`;
+/// decoding then only locks that pool, so logs for different pools never contend.
+#[derive(Default)]
+pub(crate) struct ScannerState {
+    pools: DashMap<Address, Arc<PoolSlot>>,
+    /// Signalled whenever the tracked pool set changes, so the log subscription can resubscribe.
+    pub(crate) pools_changed: Notify,
+    metrics: MetricsConfig,
+    quote_tokens: Vec<Address>,
+}
+
+impl ScannerState {
+    pub(crate) fn new(metrics: MetricsConfig, quote_tokens: Vec<Address>) -> Self {
+        Self {
+            metrics,
+            quote_tokens,
+            ..Default::default()
+        }
+    }
+
+    /// Track `pools`, skipping ones already tracked. Returns the newly added pools.
+    pub(crate) fn insert_all(&self, pools: Vec<CachedPool>) -> Vec<CachedPool> {
+        let mut added = Vec::new();
+        for pool in pools {
+            if let Entry::Vacant(entry) = self.pools.entry(pool.address) {
+                added.push(pool.clone());
+                entry.insert(Arc::new(PoolSlot::new(pool, &self.metrics, &self.quote_tokens)));
+            }
+        }
+        if !added.is_empty() {
+            self.pools_changed.notify_waiters();
+        }
+        added
+    }
+
+    /// Stop tracking `addresses`. Returns the pools that were removed.
+    pub(crate) fn remove_all(&self, addresses: &[Address]) -> Vec<CachedPool> {
+        let removed: Vec<CachedPool> = addresses
+            .iter()
+            .filter_map(|address| self.pools.remove(address))
+            .map(|(_, slot)| slot.pool.clone())
+            .collect();
+        if !removed.is_empty() {
+            self.pools_changed.notify_waiters();
+        }
+        removed
+    }
+
+    /// The tracked pools trading `token_a` and `token_b`, in either order.
+    pub(crate) fn pair_pools(&self, token_a: Address, token_b: Address) -> Vec<CachedPool> {
+        let pair = canonical_pair(token_a, token_b);
+        self.pools
+            .iter()
+            .filter(|slot| canonical_pair(slot.pool.token0, slot.pool.token1) == pair)
+            .map(|slot| slot.pool.clone())
+            .collect()
+    }
+
+    pub(crate) fn addresses(&self) -> Vec<Address> {
+        self.pools.iter().map(|slot| *slot.key()).collect()
+    }
+
+    pub(crate) fn contains(&self, address: &Address) -> bool {
+        self.pools.contains_key(address)
+    }
+
+    pub(crate) fn get(&self, address: &Address) -> Option<Arc<PoolSlot>> {
+        self.pools.get(address).map(|slot| Arc::clone(slot.value()))
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use alloy::sol_types::SolEvent;
+
+    alloy::sol! {
+        event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to);
+        event Sync(uint112 reserve0, uint112 reserve1);
+    }
+
+    /// A WETH/USDC V2 pair.
+    pub(in crate::rpc) fn slot() -> PoolSlot {
+        let pool = CachedPool {
+            address: Address::repeat_byte(0x11),
+            protocol: "uniswap-v2".into(),
+            token0: Address::repeat_byte(0xee),
+            token0_symbol: "WETH".into(),
+            token0_decimals: 18,
+            token1: Address::repeat_byte(0xcc),
+            token1_symbol: "USDC".into(),
+            token1_decimals: 6,
+            ..Default::default()
+        };
+        PoolSlot::new(pool, &MetricsConfig::default(), &[])
+    }
+
+    /// The Sync and Swap logs of one trade, transaction `tx` of block `block`.
+    pub(in crate::rpc) fn trade(block: u64, tx: u8, reserves: (u128, u128), usdc_in: u64) -> [EthereumLog; 2] {
+        let log = |data: alloy::primitives::LogData, log_index| EthereumLog {
+            address: Address::repeat_byte(0x11),
+            topics: data.topics().to_vec(),
+            data: data.data.to_vec(),
+            block_number: Some(block),
+            transaction_hash: Some(B256::repeat_byte(tx)),
+            log_index: Some(log_index),
+        };
+        let sync = Sync {
+            reserve0: alloy::primitives::Uint::<112, 2>::from(reserves.0 * 10u128.pow(18)),
+            reserve1: alloy::primitives::Uint::<112, 2>::from(reserves.1 * 10u128.pow(6)),
+        };
+        let swap = Swap {
+            sender: Address::repeat_byte(0x22),
+            amount0In: U256::ZERO,
+            amount1In: U256::from(usdc_in) * U256::from(10u64.pow(6)),
+            amount0Out: U256::from(1),
+            amount1Out: U256::ZERO,
+            to: Address::repeat_byte(0x22),
+        };
+        [log(sync.encode_log_data(), 0), log(swap.encode_log_data(), 1)]
+    }
+
+    #[test]
+    fn test_spike_filter_judges_v2_moves_on_the_swap() {
+        for action in [SpikeAction::Flag, SpikeAction::Suppress] {
+            let filter = SpikeFilterConfig { action, ..Default::default() };
+            let slot = slot();
+            let apply = |log: &EthereumLog| slot.apply_log(log, 1_000, None, Some(&filter)).unwrap();
+            for log in trade(1, 1, (1_000, 2_000_000), 1_000) {
+                assert!(apply(&log).unwrap().spike.is_none());
+            }
+
+            // +23% on a $222k swap: the Sync isn't judged on its own (suppressing holds it back) and the Swap
+            // backs the move.
+            let [sync, swap] = trade(2, 2, (900, 2_222_222), 222_222);
+            match action {
+                SpikeAction::Flag => {
+                    let applied = apply(&sync).unwrap();
+                    assert!(applied.spike.is_none() && applied.volume_usd.is_none());
+                }
+                SpikeAction::Suppress => assert!(apply(&sync).is_none()),
+            }
+            assert!(apply(&swap).unwrap().spike.is_none());
+            let price = slot.current_price().unwrap().token0_price;
+
+            // -19% on a $100 swap: caught on the Swap, from the price before the Sync.
+            let [sync, swap] = trade(3, 3, (1_000, 2_000_000), 100);
+            match action {
+                SpikeAction::Flag => assert!(apply(&sync).unwrap().spike.is_none()),
+                SpikeAction::Suppress => assert!(apply(&sync).is_none()),
+            }
+            let spike = apply(&swap).unwrap().spike.unwrap();
+            assert_eq!((spike.previous_price, spike.volume_usd), (price, 100.0));
+            assert_eq!(spike.suppressed, action == SpikeAction::Suppress);
+            let current = slot.current_price().unwrap().token0_price;
+            assert_eq!(current == price, action == SpikeAction::Suppress);
+        }
+    }
+}
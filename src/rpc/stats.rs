@@ -0,0 +1,173 @@
+use alloy::primitives::Address;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0,
+];
+
+/// Distribution of observed durations.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    /// Upper bound in seconds and the number of observations at or below it, ascending and cumulative.
+    pub buckets: Vec<(f64, u64)>,
+    pub count: u64,
+    pub sum: Duration,
+}
+
+/// Point-in-time view of scanner health, returned by `Scanner::stats()`.
+#[derive(Debug, Clone, Default)]
+pub struct ScannerStats {
+    /// Logs received from the subscription since start.
+    pub logs_received: u64,
+    /// Logs that could not be decoded.
+    pub parse_errors: u64,
+    /// Price updates delivered to the callback.
+    pub price_updates: u64,
+    /// Number of times the subscription was re-established after a drop.
+    pub reconnects: u64,
+    /// Highest block number seen in a received log.
+    pub last_block: Option<u64>,
+    /// Time since the current subscription was established; `None` while disconnected.
+    pub subscription_uptime: Option<Duration>,
+    /// Time since the last log was received; `None` if no log arrived yet.
+    pub since_last_log: Option<Duration>,
+    /// Mean time spent inside the price-change callback.
+    pub avg_callback_latency: Duration,
+    /// Slowest price-change callback invocation.
+    pub max_callback_latency: Duration,
+    /// Time spent inside the price-change callback.
+    pub callback_latency: LatencyHistogram,
+    /// Time spent decoding a log and computing its derived events.
+    pub parse_latency: LatencyHistogram,
+    /// Price updates per pool address.
+    pub pool_updates: HashMap<Address, u64>,
+    /// Raw logs waiting to be parsed.
+    pub log_queue_depth: u64,
+    /// Parsed updates waiting for the price-change callback.
+    pub update_queue_depth: u64,
+    /// Updates discarded by `OverflowPolicy::DropOldest` / `CoalescePerPool`.
+    pub dropped_updates: u64,
+    /// Updates merged into a pending update for the same pool.
+    pub coalesced_updates: u64,
+}
+
+/// Lock-free histogram behind `LatencyHistogram`.
+#[derive(Default)]
+struct HistogramCollector {
+    /// Per-bucket (not cumulative) counts; the last slot counts observations above every bound.
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_nanos: AtomicU64,
+}
+
+impl HistogramCollector {
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencyHistogram {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        LatencyHistogram {
+            buckets,
+            count: cumulative + self.buckets[LATENCY_BUCKETS.len()].load(Ordering::Relaxed),
+            sum: Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Lock-light counters updated from the subscription task.
+#[derive(Default)]
+pub(crate) struct StatsCollector {
+    logs_received: AtomicU64,
+    parse_errors: AtomicU64,
+    price_updates: AtomicU64,
+    reconnects: AtomicU64,
+    last_block: AtomicU64,
+    callback_nanos_total: AtomicU64,
+    callback_nanos_max: AtomicU64,
+    callback_latency: HistogramCollector,
+    parse_latency: HistogramCollector,
+    subscribed_at: Mutex<Option<Instant>>,
+    last_log_at: Mutex<Option<Instant>>,
+    pool_updates: Mutex<HashMap<Address, u64>>,
+}
+
+impl StatsCollector {
+    pub(crate) fn record_log(&self, block_number: Option<u64>) {
+        self.logs_received.fetch_add(1, Ordering::Relaxed);
+        if let Some(block) = block_number {
+            self.last_block.fetch_max(block, Ordering::Relaxed);
+        }
+        *self.last_log_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub(crate) fn record_parse(&self, latency: Duration) {
+        self.parse_latency.observe(latency);
+    }
+
+    pub(crate) fn record_parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_subscribed(&self, reconnected: bool) {
+        if reconnected {
+            self.reconnects.fetch_add(1, Ordering::Relaxed);
+        }
+        *self.subscribed_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub(crate) fn record_dropped(&self) {
+        *self.subscribed_at.lock().unwrap() = None;
+    }
+
+    pub(crate) fn record_update(&self, pool: Address, callback_latency: Duration) {
+        self.price_updates.fetch_add(1, Ordering::Relaxed);
+        let nanos = callback_latency.as_nanos() as u64;
+        self.callback_nanos_total.fetch_add(nanos, Ordering::Relaxed);
+        self.callback_nanos_max.fetch_max(nanos, Ordering::Relaxed);
+        self.callback_latency.observe(callback_latency);
+        *self.pool_updates.lock().unwrap().entry(pool).or_default() += 1;
+    }
+
+    pub(crate) fn snapshot(&self) -> ScannerStats {
+        let price_updates = self.price_updates.load(Ordering::Relaxed);
+        let avg_nanos = self
+            .callback_nanos_total
+            .load(Ordering::Relaxed)
+            .checked_div(price_updates)
+            .unwrap_or(0);
+        let last_block = self.last_block.load(Ordering::Relaxed);
+        ScannerStats {
+            logs_received: self.logs_received.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            price_updates,
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            last_block: (last_block > 0).then_some(last_block),
+            subscription_uptime: self.subscribed_at.lock().unwrap().map(|t| t.elapsed()),
+            since_last_log: self.last_log_at.lock().unwrap().map(|t| t.elapsed()),
+            avg_callback_latency: Duration::from_nanos(avg_nanos),
+            max_callback_latency: Duration::from_nanos(self.callback_nanos_max.load(Ordering::Relaxed)),
+            callback_latency: self.callback_latency.snapshot(),
+            parse_latency: self.parse_latency.snapshot(),
+            pool_updates: self.pool_updates.lock().unwrap().clone(),
+            ..Default::default()
+        }
+    }
+}
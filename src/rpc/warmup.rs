@@ -0,0 +1,98 @@
+use super::backfill::block_timestamp;
+use super::logs::LogChunks;
+use super::{RpcProvider, ScannerContext, BACKFILL_CHUNK_BLOCKS};
+use crate::liquidity_pools::{EthereumLog, V3_BURN_TOPIC, V3_MINT_TOPIC};
+use alloy::eips::BlockNumberOrTag;
+use alloy::rpc::types::eth::{BlockTransactionsKind, Filter};
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Replay the Swap/Sync logs matching `filter` of the last `window` (in block time) into the tracked pools
+/// before the first subscription (`MetricsConfig::warmup`). Each log is decoded with its block's timestamp, moving the
+/// pool's price, metrics and 24h volume like a live log, but nothing is published: no price updates, swaps
+/// or other events, and the candles completed during the window are dropped. Returns the last block replayed;
+/// the live stream skips logs the pools already reflect.
+pub(crate) async fn warm_up(
+    ctx: &ScannerContext,
+    provider: &RpcProvider,
+    filter: &Filter,
+    window: Duration,
+) -> Result<u64> {
+    let head = provider.get_block_number().await?;
+    let now = block_time(provider, head).await?;
+    let since = now.saturating_sub(window.as_secs());
+    let from_block = first_block_at(since, head, |number| block_time(provider, number)).await?;
+
+    let mut chunks = LogChunks::new(provider, filter, from_block, head, BACKFILL_CHUNK_BLOCKS);
+    let mut replayed = 0u64;
+    while let Some((_, _, logs)) = chunks.next().await? {
+        let mut block_times = HashMap::new();
+        for log in logs {
+            // Swap and Sync logs carry the pool's state; Mint/Burn would add positions to the tick map
+            // that the in-range liquidity of the next Swap already reflects.
+            if matches!(log.topic0(), Some(topic) if *topic == V3_MINT_TOPIC || *topic == V3_BURN_TOPIC) {
+                continue;
+            }
+            let timestamp = block_timestamp(provider, &log, &mut block_times).await?;
+            let Some(slot) = ctx.state.get(&log.address()) else { continue };
+            let applied = slot.apply_log(&EthereumLog::from(log), timestamp, ctx.pricing.as_ref(), ctx.spike_filter.as_ref());
+            match applied {
+                Ok(_) => replayed += 1,
+                Err(e) => debug!("Skipping undecodable log of {} in the warm-up: {}", slot.pool.address, e),
+            }
+        }
+    }
+
+    for address in ctx.state.addresses() {
+        if let Some(slot) = ctx.state.get(&address) {
+            slot.finish_warmup(since, now);
+        }
+    }
+    info!("Warmed up metrics with {} logs of blocks {} to {}", replayed, from_block, head);
+    Ok(head)
+}
+
+async fn block_time(provider: &RpcProvider, number: u64) -> Result<u64> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Number(number), BlockTransactionsKind::Hashes)
+        .await?
+        .ok_or_else(|| eyre!("block {} not found", number))?;
+    Ok(block.header.timestamp)
+}
+
+/// The first block up to `head` mined at or after `timestamp` (`head` when none is), by binary search over
+/// `block_time`.
+async fn first_block_at<F, T>(timestamp: u64, head: u64, block_time: F) -> Result<u64>
+where
+    F: Fn(u64) -> T,
+    T: Future<Output = Result<u64>>,
+{
+    let (mut low, mut high) = (0, head);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if block_time(middle).await? < timestamp {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_first_block_at() {
+        // 12 second blocks from t = 1000.
+        let block_time = |number: u64| async move { Ok(1_000 + number * 12) };
+        assert_eq!(first_block_at(1_000 + 120, 100, block_time).await.unwrap(), 10);
+        assert_eq!(first_block_at(1_000 + 121, 100, block_time).await.unwrap(), 11);
+        assert_eq!(first_block_at(0, 100, block_time).await.unwrap(), 0);
+        assert_eq!(first_block_at(u64::MAX, 100, block_time).await.unwrap(), 100);
+    }
+}
@@ -0,0 +1,53 @@
+use super::pair_filter::PairFilter;
+use super::ScannerContext;
+use crate::events::ScannerEvent;
+use crate::sinks::json_lines::price_line;
+use axum::extract::ws::{Message, WebSocket};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+/// A client message replacing the connection's filter, e.g. `{"pairs": ["WETH/USDC"]}`.
+#[derive(Deserialize)]
+struct ClientMessage {
+    pairs: Vec<String>,
+}
+
+/// Send every price update passing `filter` to the client as a JSON line (the `JsonLinesSink` format)
+/// until either side closes. The client may replace its filter at any time.
+pub(crate) async fn stream_prices(ctx: Arc<ScannerContext>, mut socket: WebSocket, mut filter: PairFilter) {
+    let mut events = ctx.events.subscribe();
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => {
+                let _ = socket.send(Message::Close(None)).await;
+                return;
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(message) => filter = PairFilter::from_pairs(message.pairs.iter().map(String::as_str)),
+                    Err(e) => {
+                        let error = serde_json::json!({ "error": e.to_string() }).to_string();
+                        if socket.send(Message::Text(error.into())).await.is_err() {
+                            return;
+                        }
+                    }
+                },
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+            event = events.recv() => match event {
+                Ok(ScannerEvent::PriceUpdate { pool, price, .. }) if filter.matches(&pool) => {
+                    let Ok(line) = price_line(&pool, &price) else { continue };
+                    if socket.send(Message::Text(line.into())).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => debug!("WebSocket client lagged, skipped {} events", skipped),
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::liquidity_pools::{EthereumLog, SwapDirection, SwapEventData};
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::{Address, B256, I256};
+use serde::{Deserialize, Serialize};
+
+/// A decoded swap, as published on the event bus for every swap of a tracked pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Swap {
+    pub pool_address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    /// token0 delta in token units, from the pool's point of view: positive = paid into the pool.
+    pub amount0: f64,
+    /// token1 delta in token units, same sign convention as `amount0`.
+    pub amount1: f64,
+    /// `true` when token0 was sold into the pool.
+    pub zero_for_one: bool,
+    /// token0 price after the swap.
+    pub price: f64,
+    /// USD value of the swap; `None` when neither token has a USD price.
+    pub volume_usd: Option<f64>,
+    pub sender: Address,
+    pub recipient: Address,
+    pub transaction_hash: Option<B256>,
+    pub log_index: Option<u64>,
+    pub block_number: Option<u64>,
+    pub timestamp: u64,
+}
+
+/// The `Swap` event of a swap log whose update is `price` (which carries the swap's token amounts).
+pub(crate) fn swap(
+    pool: &CachedPool,
+    log: &EthereumLog,
+    swap: &SwapEventData,
+    price: &PoolPrice,
+    volume_usd: Option<f64>,
+) -> Swap {
+    Swap {
+        pool_address: pool.address,
+        token0: pool.token0,
+        token1: pool.token1,
+        amount0: price.amount0.unwrap_or_default(),
+        amount1: price.amount1.unwrap_or_default(),
+        zero_for_one: swap.direction == Some(SwapDirection::ZeroForOne),
+        price: price.token0_price,
+        volume_usd,
+        sender: swap.sender,
+        recipient: swap.recipient,
+        transaction_hash: log.transaction_hash,
+        log_index: log.log_index,
+        block_number: log.block_number,
+        timestamp: price.timestamp,
+    }
+}
+
+/// A single swap worth at least `ScannerOptions::large_swap_usd`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LargeSwap {
+    pub pool_address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    /// Raw token0 delta from the pool's point of view: positive = paid into the pool, negative = paid out.
+    pub amount0: I256,
+    /// Raw token1 delta, same sign convention as `amount0`.
+    pub amount1: I256,
+    /// `true` when token0 was sold into the pool.
+    pub zero_for_one: bool,
+    pub sender: Address,
+    pub recipient: Address,
+    pub transaction_hash: Option<B256>,
+    pub block_number: Option<u64>,
+    pub notional_usd: f64,
+    pub timestamp: u64,
+}
+
+/// `Some` when `swap` is worth at least `threshold_usd`.
+pub(crate) fn large_swap(
+    pool: &CachedPool,
+    log: &EthereumLog,
+    swap: &SwapEventData,
+    notional_usd: f64,
+    threshold_usd: f64,
+    timestamp: u64,
+) -> Option<LargeSwap> {
+    if notional_usd < threshold_usd {
+        return None;
+    }
+    Some(LargeSwap {
+        pool_address: pool.address,
+        token0: pool.token0,
+        token1: pool.token1,
+        amount0: swap.amount0,
+        amount1: swap.amount1,
+        zero_for_one: swap.direction == Some(SwapDirection::ZeroForOne),
+        sender: swap.sender,
+        recipient: swap.recipient,
+        transaction_hash: log.transaction_hash,
+        block_number: log.block_number,
+        notional_usd,
+        timestamp,
+    })
+}
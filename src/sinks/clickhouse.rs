@@ -0,0 +1,236 @@
+//! Raw decoded swaps in ClickHouse, batched over the HTTP interface, for analytical queries over
+//! millions of events.
+
+use super::{Pending, Sink};
+use crate::events::ScannerEvent;
+use crate::rpc::Swap;
+use async_trait::async_trait;
+use eyre::{Result, WrapErr};
+use reqwest::Client;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Server, table and batching settings of a `ClickHouseSink`.
+#[derive(Debug, Clone)]
+pub struct ClickHouseConfig {
+    /// HTTP interface, e.g. `http://localhost:8123`.
+    pub url: String,
+    pub database: String,
+    pub table: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// Swaps buffered before a batch is inserted early; batches are otherwise inserted every `flush_interval`.
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+}
+
+impl Default for ClickHouseConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8123".to_string(),
+            database: "default".to_string(),
+            table: "swaps".to_string(),
+            user: None,
+            password: None,
+            batch_size: 10_000,
+            flush_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ClickHouseConfig {
+    /// `CREATE TABLE IF NOT EXISTS` statement for the swap table: a MergeTree partitioned by month and
+    /// sorted by pool and time, so per-pool range scans read few granules.
+    pub fn ddl(&self) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {}.{} (
+    timestamp         DateTime('UTC') CODEC(Delta, ZSTD),
+    pool_address      LowCardinality(String),
+    token0            LowCardinality(String),
+    token1            LowCardinality(String),
+    block_number      UInt64 CODEC(Delta, ZSTD),
+    transaction_hash  String,
+    log_index         UInt32,
+    sender            String,
+    recipient         String,
+    amount0           Float64,
+    amount1           Float64,
+    zero_for_one      Bool,
+    price             Float64,
+    volume_usd        Nullable(Float64)
+)
+ENGINE = MergeTree
+PARTITION BY toYYYYMM(timestamp)
+ORDER BY (pool_address, timestamp, block_number, log_index)",
+            self.database, self.table
+        )
+    }
+}
+
+/// One `JSONEachRow` row of the swap table. Unknown block numbers, log indexes and hashes are stored as
+/// zero / empty, since sorting key columns can't be nullable.
+#[derive(Serialize)]
+struct SwapRow {
+    timestamp: u64,
+    pool_address: String,
+    token0: String,
+    token1: String,
+    block_number: u64,
+    transaction_hash: String,
+    log_index: u64,
+    sender: String,
+    recipient: String,
+    amount0: f64,
+    amount1: f64,
+    zero_for_one: bool,
+    price: f64,
+    volume_usd: Option<f64>,
+}
+
+impl From<&Swap> for SwapRow {
+    fn from(swap: &Swap) -> Self {
+        Self {
+            timestamp: swap.timestamp,
+            pool_address: swap.pool_address.to_string(),
+            token0: swap.token0.to_string(),
+            token1: swap.token1.to_string(),
+            block_number: swap.block_number.unwrap_or_default(),
+            transaction_hash: swap.transaction_hash.map(|hash| hash.to_string()).unwrap_or_default(),
+            log_index: swap.log_index.unwrap_or_default(),
+            sender: swap.sender.to_string(),
+            recipient: swap.recipient.to_string(),
+            amount0: swap.amount0,
+            amount1: swap.amount1,
+            zero_for_one: swap.zero_for_one,
+            price: swap.price,
+            volume_usd: swap.volume_usd.filter(|usd| usd.is_finite()),
+        }
+    }
+}
+
+/// Inserts `ScannerEvent::Swap`s into one ClickHouse table.
+#[derive(Debug)]
+pub struct ClickHouseSink {
+    config: ClickHouseConfig,
+    client: Client,
+    pending: Pending<Swap>,
+}
+
+impl ClickHouseSink {
+    pub fn new(config: ClickHouseConfig) -> Self {
+        Self {
+            client: Client::new(),
+            pending: Pending::new(config.batch_size, config.flush_interval),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ClickHouseConfig {
+        &self.config
+    }
+
+    /// Run `ClickHouseConfig::ddl`.
+    pub async fn create_table(&self) -> Result<()> {
+        self.execute(None, self.config.ddl()).await
+    }
+
+    /// Insert swaps with one request.
+    pub async fn insert(&self, swaps: &[Swap]) -> Result<()> {
+        if swaps.is_empty() {
+            return Ok(());
+        }
+        let mut body = String::new();
+        for swap in swaps {
+            body.push_str(&serde_json::to_string(&SwapRow::from(swap))?);
+            body.push('\n');
+        }
+        let query = format!("INSERT INTO {}.{} FORMAT JSONEachRow", self.config.database, self.config.table);
+        self.execute(Some(query), body).await
+    }
+
+    /// POST `body` to the HTTP interface, with `query` as the statement it completes.
+    async fn execute(&self, query: Option<String>, body: String) -> Result<()> {
+        let mut request = self.client.post(&self.config.url).body(body);
+        if let Some(query) = query {
+            request = request.query(&[("query", query)]);
+        }
+        if let Some(user) = &self.config.user {
+            request = request.header("X-ClickHouse-User", user);
+        }
+        if let Some(password) = &self.config.password {
+            request = request.header("X-ClickHouse-Key", password);
+        }
+        let response = request.send().await.wrap_err("ClickHouse request failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            eyre::bail!("ClickHouse returned {}: {}", status, body.trim());
+        }
+        Ok(())
+    }
+}
+
+/// Buffers every `Swap` and inserts batches of `ClickHouseConfig::batch_size`, or every `flush_interval`.
+#[async_trait]
+impl Sink for ClickHouseSink {
+    fn name(&self) -> &str {
+        "clickhouse"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        if let ScannerEvent::Swap(swap) = event {
+            self.pending.push(swap.clone());
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.pending.flush(false, async |swaps| self.insert(&swaps).await).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.pending.flush(true, async |swaps| self.insert(&swaps).await).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_swap_row_matches_ddl() {
+        let swap = Swap {
+            pool_address: Address::repeat_byte(1),
+            token0: Address::repeat_byte(2),
+            token1: Address::repeat_byte(3),
+            amount0: -1.5,
+            amount1: 4500.0,
+            zero_for_one: false,
+            price: 3000.0,
+            volume_usd: Some(4500.0),
+            sender: Address::repeat_byte(4),
+            recipient: Address::repeat_byte(5),
+            transaction_hash: None,
+            log_index: Some(7),
+            block_number: Some(19_000_000),
+            timestamp: 1_714_521_600,
+        };
+        let row = serde_json::to_value(SwapRow::from(&swap)).unwrap();
+        assert_eq!(row["block_number"], 19_000_000);
+        assert_eq!(row["transaction_hash"], "");
+
+        // The serialized row has exactly the columns declared in the DDL.
+        let ddl = ClickHouseConfig::default().ddl();
+        assert!(ddl.starts_with("CREATE TABLE IF NOT EXISTS default.swaps"));
+        let columns: Vec<&str> = ddl
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.starts_with(')'))
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        let keys: Vec<&String> = row.as_object().unwrap().keys().collect();
+        assert_eq!(columns.len(), keys.len());
+        assert!(columns.iter().all(|column| keys.iter().any(|key| key == column)));
+    }
+}
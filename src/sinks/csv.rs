@@ -0,0 +1,352 @@
+//! CSV files of price updates or candles, rotated by time and size, for analysis in a spreadsheet or
+//! pandas without a database.
+
+use super::{Pending, Sink};
+use crate::events::ScannerEvent;
+use crate::metrics::Candle;
+use crate::types::{CachedPool, PoolPrice};
+use async_trait::async_trait;
+use chrono::DateTime;
+use eyre::Result;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often buffered rows are written.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+const PRICE_HEADER: &str = "timestamp,time,pool_address,token0_symbol,token1_symbol,block_number,token0_price,\
+token1_price,token0_price_usd,token1_price_usd,amount0,amount1";
+const CANDLE_HEADER: &str = "open_time,time,pool_address,interval_secs,open,high,low,close,volume0,volume1,trades";
+const POOL_HEADER: &str = "pool_address,protocol,token0,token0_symbol,token0_decimals,token1,token1_symbol,\
+token1_decimals,fee,liquidity_usd,volume_24h_usd";
+
+/// What a `CsvSink` writes, one row each.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvRows {
+    #[default]
+    PriceUpdates,
+    Candles,
+}
+
+impl CsvRows {
+    fn name(self) -> &'static str {
+        match self {
+            Self::PriceUpdates => "prices",
+            Self::Candles => "candles",
+        }
+    }
+
+    fn header(self) -> &'static str {
+        match self {
+            Self::PriceUpdates => PRICE_HEADER,
+            Self::Candles => CANDLE_HEADER,
+        }
+    }
+}
+
+/// Output directory and rotation policy of a `CsvSink`.
+#[derive(Debug, Clone)]
+pub struct CsvConfig {
+    pub dir: PathBuf,
+    pub rows: CsvRows,
+    /// Start a new file at every multiple of this interval in unix time (daily by default).
+    pub rotate_every: Duration,
+    /// Also start a new file once the current one reaches this size.
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for CsvConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("csv"),
+            rows: CsvRows::PriceUpdates,
+            rotate_every: Duration::from_secs(86_400),
+            max_bytes: None,
+        }
+    }
+}
+
+struct CsvFile {
+    writer: BufWriter<File>,
+    period_start: u64,
+    bytes: u64,
+}
+
+/// Writes `<prices|candles>-<YYYYmmdd-HHMMSS>.csv` files with a header row. The write methods are
+/// blocking; as a `Sink`, writes run on the blocking pool.
+pub struct CsvSink {
+    files: Arc<Files>,
+    /// Formatted rows with their timestamps.
+    pending: Pending<(u64, String)>,
+}
+
+impl std::fmt::Debug for CsvSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CsvSink").field("config", &self.files.config).finish_non_exhaustive()
+    }
+}
+
+/// The output files of a `CsvSink`, shared with its writes on the blocking pool.
+struct Files {
+    config: CsvConfig,
+    current: Mutex<Option<CsvFile>>,
+}
+
+impl CsvSink {
+    pub fn new(config: CsvConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        Ok(Self {
+            files: Arc::new(Files {
+                config,
+                current: Mutex::new(None),
+            }),
+            pending: Pending::new(usize::MAX, FLUSH_INTERVAL),
+        })
+    }
+
+    pub fn config(&self) -> &CsvConfig {
+        &self.files.config
+    }
+
+    /// Append price updates. Ignored unless the sink writes `CsvRows::PriceUpdates`.
+    pub fn write_prices(&self, updates: &[(CachedPool, PoolPrice)]) -> Result<()> {
+        if self.files.config.rows != CsvRows::PriceUpdates {
+            return Ok(());
+        }
+        self.files.write_rows(updates.iter().map(|(pool, price)| (price.timestamp, price_row(pool, price))))
+    }
+
+    /// Append candles. Ignored unless the sink writes `CsvRows::Candles`.
+    pub fn write_candles(&self, candles: &[Candle]) -> Result<()> {
+        if self.files.config.rows != CsvRows::Candles {
+            return Ok(());
+        }
+        self.files.write_rows(candles.iter().map(|candle| (candle.open_time, candle_row(candle))))
+    }
+
+    async fn write_pending(&self, force: bool) -> Result<()> {
+        self.pending
+            .flush(force, async |rows| {
+                let files = Arc::clone(&self.files);
+                tokio::task::spawn_blocking(move || files.write_rows(rows.into_iter())).await?
+            })
+            .await
+    }
+}
+
+impl Files {
+    fn write_rows(&self, rows: impl Iterator<Item = (u64, String)>) -> Result<()> {
+        let mut current = self.current.lock().unwrap();
+        for (timestamp, row) in rows {
+            let period_start = period_start(timestamp, self.config.rotate_every);
+            let rotate = current.as_ref().is_some_and(|file| {
+                file.period_start != period_start || self.config.max_bytes.is_some_and(|max| file.bytes >= max)
+            });
+            if rotate && let Some(mut file) = current.take() {
+                file.writer.flush()?;
+            }
+            let file = match current.as_mut() {
+                Some(file) => file,
+                None => current.insert(self.create(timestamp, period_start)?),
+            };
+            file.writer.write_all(row.as_bytes())?;
+            file.writer.write_all(b"\n")?;
+            file.bytes += row.len() as u64 + 1;
+        }
+        if let Some(file) = current.as_mut() {
+            file.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Create a new file named after `timestamp` (with a `-<n>` suffix if taken) and write the header.
+    fn create(&self, timestamp: u64, period_start: u64) -> Result<CsvFile> {
+        let time = DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+        let stem = format!("{}-{}", self.config.rows.name(), time.format("%Y%m%d-%H%M%S"));
+        let mut suffix = 0;
+        let file = loop {
+            let name = match suffix {
+                0 => format!("{stem}.csv"),
+                n => format!("{stem}-{n}.csv"),
+            };
+            match OpenOptions::new().write(true).create_new(true).open(self.config.dir.join(name)) {
+                Ok(file) => break file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => suffix += 1,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        let mut writer = BufWriter::new(file);
+        let header = self.config.rows.header();
+        writeln!(writer, "{header}")?;
+        Ok(CsvFile {
+            writer,
+            period_start,
+            bytes: header.len() as u64 + 1,
+        })
+    }
+}
+
+fn period_start(timestamp: u64, every: Duration) -> u64 {
+    let every = every.as_secs().max(1);
+    timestamp - timestamp % every
+}
+
+fn iso_time(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+/// Quote a free-text field (token symbols) when it contains a delimiter, quote or line break.
+fn text(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Empty field for `None`.
+fn optional<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn price_row(pool: &CachedPool, price: &PoolPrice) -> String {
+    let mut row = String::new();
+    let _ = write!(
+        row,
+        "{},{},{},{},{},{},{},{},{},{},{},{}",
+        price.timestamp,
+        iso_time(price.timestamp),
+        price.pool_address,
+        text(&pool.token0_symbol),
+        text(&pool.token1_symbol),
+        optional(price.block_number),
+        price.token0_price,
+        price.token1_price,
+        optional(price.token0_price_usd),
+        optional(price.token1_price_usd),
+        optional(price.amount0),
+        optional(price.amount1),
+    );
+    row
+}
+
+fn candle_row(candle: &Candle) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{}",
+        candle.open_time,
+        iso_time(candle.open_time),
+        candle.pool_address,
+        candle.interval_secs,
+        candle.open,
+        candle.high,
+        candle.low,
+        candle.close,
+        candle.volume0,
+        candle.volume1,
+        candle.trades,
+    )
+}
+
+fn pool_row(pool: &CachedPool) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{}",
+        pool.address,
+        text(&pool.protocol),
+        pool.token0,
+        text(&pool.token0_symbol),
+        pool.token0_decimals,
+        pool.token1,
+        text(&pool.token1_symbol),
+        pool.token1_decimals,
+        pool.fee,
+        pool.liquidity_usd,
+        pool.volume_24h_usd,
+    )
+}
+
+/// A pool list as CSV with a header row, e.g. the result of `Scanner::discover` for review in a spreadsheet.
+pub fn write_pools(mut writer: impl Write, pools: &[CachedPool]) -> Result<()> {
+    writeln!(writer, "{POOL_HEADER}")?;
+    for pool in pools {
+        writeln!(writer, "{}", pool_row(pool))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Appends every `PriceUpdate` or `Candle` (per `CsvConfig::rows`), once per second.
+#[async_trait]
+impl Sink for CsvSink {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        match (event, self.files.config.rows) {
+            (ScannerEvent::PriceUpdate { pool, price, .. }, CsvRows::PriceUpdates) => {
+                self.pending.push((price.timestamp, price_row(pool, price)));
+            }
+            (ScannerEvent::Candle(candle), CsvRows::Candles) => {
+                self.pending.push((candle.open_time, candle_row(candle)));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.write_pending(false).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.write_pending(true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_price_row() {
+        let pool = CachedPool {
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "US\"D,C".to_string(),
+            ..Default::default()
+        };
+        let price = PoolPrice {
+            pool_address: Address::repeat_byte(1),
+            token0_price: 3000.5,
+            token1_price: 0.25,
+            timestamp: 1_714_521_600,
+            block_number: Some(19_000_000),
+            amount0: Some(-1.5),
+            ..Default::default()
+        };
+        let row = price_row(&pool, &price);
+        assert_eq!(
+            row,
+            format!(
+                "1714521600,2024-05-01T00:00:00Z,{},WETH,\"US\"\"D,C\",19000000,3000.5,0.25,,,-1.5,",
+                Address::repeat_byte(1)
+            )
+        );
+        assert_eq!(row.matches(',').count() - 1, PRICE_HEADER.matches(',').count());
+        assert_eq!(period_start(1_714_521_600 + 5_000, Duration::from_secs(3_600)), 1_714_521_600 + 3_600);
+
+        let mut pools = Vec::new();
+        write_pools(&mut pools, &[pool]).unwrap();
+        let pools = String::from_utf8(pools).unwrap();
+        let lines: Vec<_> = pools.lines().collect();
+        assert_eq!(lines[0], POOL_HEADER);
+        assert_eq!(lines[1], format!("{0},,{0},WETH,0,{0},\"US\"\"D,C\",0,0,0,0", Address::ZERO));
+    }
+}
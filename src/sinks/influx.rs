@@ -0,0 +1,266 @@
+//! Price and volume points in InfluxDB line protocol, written through the v2 HTTP API, so Grafana can
+//! chart pools straight from a bucket.
+
+use super::{Pending, Sink};
+use crate::events::ScannerEvent;
+use crate::types::{CachedPool, PoolPrice};
+use async_trait::async_trait;
+use eyre::{Result, WrapErr};
+use reqwest::Client;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Optional tags added to every point, besides the always-present `pool` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfluxTag {
+    /// `protocol=uniswap-v3`
+    Protocol,
+    /// `pair=WETH/USDC`
+    Pair,
+    /// `chain=<InfluxConfig::chain>`
+    Chain,
+}
+
+/// Server, destination and tagging of an `InfluxSink`.
+#[derive(Debug, Clone)]
+pub struct InfluxConfig {
+    /// Base URL of the server, e.g. `http://localhost:8086`.
+    pub url: String,
+    pub org: String,
+    pub bucket: String,
+    /// API token, sent as `Authorization: Token …`.
+    pub token: Option<String>,
+    pub tags: Vec<InfluxTag>,
+    /// Value of the `chain` tag.
+    pub chain: String,
+    /// Points buffered before a batch is written early; batches are otherwise written every `flush_interval`.
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8086".to_string(),
+            org: String::new(),
+            bucket: "dex".to_string(),
+            token: None,
+            tags: vec![InfluxTag::Protocol, InfluxTag::Pair, InfluxTag::Chain],
+            chain: "1".to_string(),
+            batch_size: 5_000,
+            flush_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Escape a tag key or value: commas, equals signs and spaces are backslash-escaped.
+fn escape_tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Time of an update's points in nanoseconds. InfluxDB keeps one point per series and time, and updates only
+/// carry seconds, so the log's position is added within the second: the block modulo 1000 in milliseconds and
+/// the log index in nanoseconds. Updates of the same second then stay distinct as long as fewer than 1000
+/// blocks fit in a second.
+fn point_time(price: &PoolPrice) -> u64 {
+    let offset = match (price.block_number, price.log_index) {
+        (Some(block), Some(log_index)) => block % 1_000 * 1_000_000 + log_index % 1_000_000,
+        _ => 0,
+    };
+    price.timestamp * 1_000_000_000 + offset
+}
+
+/// `key=value` pairs of the known, finite values, comma-separated.
+fn float_fields(values: &[(&str, Option<f64>)]) -> String {
+    let mut fields = String::new();
+    for (key, value) in values {
+        if let Some(value) = value.filter(|value| value.is_finite()) {
+            let separator = if fields.is_empty() { "" } else { "," };
+            let _ = write!(fields, "{separator}{key}={value}");
+        }
+    }
+    fields
+}
+
+impl InfluxConfig {
+    /// `pool=…` plus the configured tags, comma-separated and escaped.
+    fn tag_set(&self, pool: &CachedPool) -> String {
+        let mut tags = format!("pool={}", pool.address);
+        for tag in &self.tags {
+            let (key, value) = match tag {
+                InfluxTag::Protocol => ("protocol", pool.protocol.clone()),
+                InfluxTag::Pair => ("pair", format!("{}/{}", pool.token0_symbol, pool.token1_symbol)),
+                InfluxTag::Chain => ("chain", self.chain.clone()),
+            };
+            if !value.is_empty() {
+                let _ = write!(tags, ",{}={}", key, escape_tag(&value));
+            }
+        }
+        tags
+    }
+
+    /// A `price` point for every update and a `volume` point for swaps, in line protocol with nanosecond
+    /// precision (see `point_time`). Non-finite values are left out, since a single unparsable line fails the
+    /// whole batch.
+    fn points(&self, pool: &CachedPool, price: &PoolPrice) -> Vec<String> {
+        let tags = self.tag_set(pool);
+        let time = point_time(price);
+        let mut points = Vec::new();
+        let fields = float_fields(&[
+            ("token0_price", Some(price.token0_price)),
+            ("token1_price", Some(price.token1_price)),
+            ("token0_price_usd", price.token0_price_usd),
+            ("token1_price_usd", price.token1_price_usd),
+        ]);
+        if !fields.is_empty() {
+            let block = price.block_number.map(|block| format!(",block_number={block}i")).unwrap_or_default();
+            points.push(format!("price,{tags} {fields}{block} {time}"));
+        }
+
+        if let (Some(amount0), Some(amount1)) = (price.amount0, price.amount1) {
+            let (volume0, volume1) = (amount0.abs(), amount1.abs());
+            let volume_usd = price
+                .token1_price_usd
+                .map(|usd| volume1 * usd)
+                .or_else(|| price.token0_price_usd.map(|usd| volume0 * usd));
+            let fields = float_fields(&[
+                ("volume0", Some(volume0)),
+                ("volume1", Some(volume1)),
+                ("volume_usd", volume_usd),
+            ]);
+            if !fields.is_empty() {
+                points.push(format!("volume,{tags} {fields} {time}"));
+            }
+        }
+        points
+    }
+}
+
+/// Writes points to one bucket with the v2 `/api/v2/write` endpoint.
+#[derive(Debug)]
+pub struct InfluxSink {
+    config: InfluxConfig,
+    client: Client,
+    pending: Pending<String>,
+}
+
+impl InfluxSink {
+    pub fn new(config: InfluxConfig) -> Self {
+        Self {
+            client: Client::new(),
+            pending: Pending::new(config.batch_size, config.flush_interval),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &InfluxConfig {
+        &self.config
+    }
+
+    /// Write line-protocol points (nanosecond precision) in one request.
+    pub async fn write(&self, lines: &[String]) -> Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let url = format!("{}/api/v2/write", self.config.url.trim_end_matches('/'));
+        let mut request = self
+            .client
+            .post(url)
+            .query(&[
+                ("org", self.config.org.as_str()),
+                ("bucket", self.config.bucket.as_str()),
+                ("precision", "ns"),
+            ])
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(lines.join("\n"));
+        if let Some(token) = &self.config.token {
+            request = request.header("Authorization", format!("Token {token}"));
+        }
+        let response = request.send().await.wrap_err("InfluxDB write failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            eyre::bail!("InfluxDB write returned {}: {}", status, body);
+        }
+        Ok(())
+    }
+}
+
+/// Turns every `PriceUpdate` into points and writes them in batches of `InfluxConfig::batch_size`, or
+/// every `flush_interval`.
+#[async_trait]
+impl Sink for InfluxSink {
+    fn name(&self) -> &str {
+        "influxdb"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        if let ScannerEvent::PriceUpdate { pool, price, .. } = event {
+            for point in self.config.points(pool, price) {
+                self.pending.push(point);
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.pending.flush(false, async |lines| self.write(&lines).await).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.pending.flush(true, async |lines| self.write(&lines).await).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_points() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            protocol: "uniswap-v3".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USD Coin".to_string(),
+            ..Default::default()
+        };
+        let price = PoolPrice {
+            pool_address: pool.address,
+            token0_price: 3000.0,
+            token1_price: 0.5,
+            timestamp: 1_714_521_600,
+            token1_price_usd: Some(1.0),
+            block_number: Some(19_000_123),
+            log_index: Some(45),
+            amount0: Some(-2.0),
+            amount1: Some(6000.0),
+            ..Default::default()
+        };
+        let config = InfluxConfig {
+            tags: vec![InfluxTag::Pair, InfluxTag::Chain],
+            chain: "base".to_string(),
+            ..Default::default()
+        };
+        let tags = format!("pool={},pair=WETH/USD\\ Coin,chain=base", pool.address);
+        assert_eq!(
+            config.points(&pool, &price),
+            vec![
+                format!("price,{tags} token0_price=3000,token1_price=0.5,token1_price_usd=1,block_number=19000123i 1714521600123000045"),
+                format!("volume,{tags} volume0=2,volume1=6000,volume_usd=6000 1714521600123000045"),
+            ]
+        );
+
+        // A second swap of the same second, later in the block, gets its own point.
+        let next = PoolPrice { log_index: Some(46), ..price.clone() };
+        assert!(config.points(&pool, &next)[1].ends_with(" 1714521600123000046"));
+    }
+}
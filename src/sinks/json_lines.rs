@@ -0,0 +1,185 @@
+//! Price updates as JSON lines on stdout or in a file, for piping into jq, Vector or any log shipper, and
+//! read back by `read_prices` for a `Replay`.
+
+use super::{Pending, Sink};
+use crate::events::ScannerEvent;
+use crate::types::{CachedPool, PoolPrice};
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// One output line: the pool's identity followed by every `PoolPrice` field.
+#[derive(Serialize)]
+struct PriceLine<'a> {
+    protocol: &'a str,
+    token0: Address,
+    token1: Address,
+    token0_symbol: &'a str,
+    token1_symbol: &'a str,
+    #[serde(flatten)]
+    price: &'a PoolPrice,
+}
+
+pub(crate) fn price_line(pool: &CachedPool, price: &PoolPrice) -> serde_json::Result<String> {
+    serde_json::to_string(&PriceLine {
+        protocol: &pool.protocol,
+        token0: pool.token0,
+        token1: pool.token1,
+        token0_symbol: &pool.token0_symbol,
+        token1_symbol: &pool.token1_symbol,
+        price,
+    })
+}
+
+/// A line read back: `PriceLine` with owned fields.
+#[derive(Deserialize)]
+struct RecordedLine {
+    protocol: String,
+    token0: Address,
+    token1: Address,
+    token0_symbol: String,
+    token1_symbol: String,
+    #[serde(flatten)]
+    price: PoolPrice,
+}
+
+/// Parse the price updates written by a `JsonLinesSink`, in file order. The pools carry the identity
+/// recorded on each line (address, protocol, tokens and symbols); other `CachedPool` fields are defaults.
+pub fn read_prices(reader: impl BufRead) -> Result<Vec<(CachedPool, PoolPrice)>> {
+    let mut updates = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let recorded: RecordedLine = serde_json::from_str(&line).wrap_err_with(|| format!("line {}", number + 1))?;
+        let pool = CachedPool {
+            address: recorded.price.pool_address,
+            protocol: recorded.protocol,
+            token0: recorded.token0,
+            token1: recorded.token1,
+            token0_symbol: recorded.token0_symbol,
+            token1_symbol: recorded.token1_symbol,
+            ..Default::default()
+        };
+        updates.push((pool, recorded.price));
+    }
+    Ok(updates)
+}
+
+/// Writes one JSON object per price update, newline-delimited. Writes run on the blocking pool; the lines
+/// of a burst of events are written together, so a burst costs one flush.
+pub struct JsonLinesSink {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+    pending: Pending<String>,
+}
+
+impl std::fmt::Debug for JsonLinesSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonLinesSink").finish_non_exhaustive()
+    }
+}
+
+impl JsonLinesSink {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+
+    /// Append to the file at `path`, creating it if missing.
+    pub fn file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(OpenOptions::new().create(true).append(true).open(path)?))
+    }
+
+    /// Write to any destination, e.g. a socket or an in-memory buffer.
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Arc::new(Mutex::new(Box::new(out))),
+            // Written after every burst.
+            pending: Pending::new(usize::MAX, Duration::ZERO),
+        }
+    }
+}
+
+/// Write already serialized lines and flush.
+fn write_lines(out: &Mutex<Box<dyn Write + Send>>, lines: &[String]) -> std::io::Result<()> {
+    let mut out = out.lock().unwrap();
+    for line in lines {
+        out.write_all(line.as_bytes())?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+}
+
+#[async_trait]
+impl Sink for JsonLinesSink {
+    fn name(&self) -> &str {
+        "json-lines"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        if let ScannerEvent::PriceUpdate { pool, price, .. } = event {
+            self.pending.push(price_line(pool, price)?);
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.pending
+            .flush(true, async |lines| {
+                let out = Arc::clone(&self.out);
+                Ok(tokio::task::spawn_blocking(move || write_lines(&out, &lines)).await??)
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_line() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            protocol: "uniswap-v3".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            ..Default::default()
+        };
+        let price = PoolPrice {
+            pool_address: pool.address,
+            token0_price: 3000.0,
+            token1_price: 1.0 / 3000.0,
+            timestamp: 1_714_521_600,
+            block_number: Some(19_000_000),
+            ..Default::default()
+        };
+        let line = price_line(&pool, &price).unwrap();
+        assert!(!line.contains('\n'));
+
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["protocol"], "uniswap-v3");
+        assert_eq!(value["token0_symbol"], "WETH");
+        assert_eq!(value["token0_price"], 3000.0);
+        assert_eq!(value["block_number"], 19_000_000);
+        assert_eq!(value["amount0"], serde_json::Value::Null);
+        // The flattened price keeps the pool address alongside the pool's identity.
+        let back: PoolPrice = serde_json::from_value(value).unwrap();
+        assert_eq!(back.pool_address, pool.address);
+
+        let input = format!("{line}\n\n{line}\n");
+        let updates = read_prices(input.as_bytes()).unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].0.address, pool.address);
+        assert_eq!(updates[0].0.token1_symbol, "USDC");
+        assert_eq!(updates[1].1.token0_price, 3000.0);
+        assert_eq!(updates[1].1.block_number, Some(19_000_000));
+        assert!(read_prices("{}\n".as_bytes()).unwrap_err().to_string().contains("line 1"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Price updates, swaps and candles as JSON messages on Kafka topics, keyed by pool address so each
+//! pool's messages stay ordered within one partition. Enabled with the `kafka` feature.
+
+use super::json_lines::price_line;
+use super::{Pending, Sink};
+use crate::events::ScannerEvent;
+use async_trait::async_trait;
+use eyre::{eyre, Result, WrapErr};
+use futures::future::join_all;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How long a message may wait for room in the producer queue before it is dropped.
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Brokers, topics and producer settings of a `KafkaSink`. A `None` topic turns that stream off.
+#[derive(Debug, Clone)]
+pub struct KafkaConfig {
+    /// `bootstrap.servers`, e.g. `localhost:9092`.
+    pub brokers: String,
+    pub price_topic: Option<String>,
+    pub swap_topic: Option<String>,
+    pub candle_topic: Option<String>,
+    /// Extra librdkafka producer properties, e.g. `compression.type` or `security.protocol`.
+    pub properties: HashMap<String, String>,
+}
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            brokers: "localhost:9092".to_string(),
+            price_topic: Some("dex.prices".to_string()),
+            swap_topic: Some("dex.swaps".to_string()),
+            candle_topic: Some("dex.candles".to_string()),
+            properties: HashMap::new(),
+        }
+    }
+}
+
+/// A message ready to send: topic, key (the pool address) and JSON payload.
+type Message = (String, String, String);
+
+impl KafkaConfig {
+    /// Topic, key and payload of `event`; `None` for events without a configured topic.
+    fn message(&self, event: &ScannerEvent) -> Option<serde_json::Result<Message>> {
+        let (topic, pool, payload) = match event {
+            ScannerEvent::PriceUpdate { pool, price, .. } => {
+                (self.price_topic.as_deref()?, pool.address, price_line(pool, price))
+            }
+            ScannerEvent::Swap(swap) => (self.swap_topic.as_deref()?, swap.pool_address, serde_json::to_string(swap)),
+            ScannerEvent::Candle(candle) => {
+                (self.candle_topic.as_deref()?, candle.pool_address, serde_json::to_string(candle))
+            }
+            _ => return None,
+        };
+        Some(payload.map(|payload| (topic.to_string(), pool.to_string(), payload)))
+    }
+}
+
+/// Kafka producer for scanner events. Price updates use the `JsonLinesSink` format; swaps and candles
+/// are their serde representation.
+pub struct KafkaSink {
+    config: KafkaConfig,
+    producer: FutureProducer,
+    pending: Pending<Message>,
+}
+
+impl std::fmt::Debug for KafkaSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KafkaSink").field("config", &self.config).finish_non_exhaustive()
+    }
+}
+
+impl KafkaSink {
+    /// Create the producer. Brokers are contacted lazily, so this succeeds while they are unreachable.
+    pub fn new(config: KafkaConfig) -> Result<Self> {
+        let mut client = ClientConfig::new();
+        client.set("bootstrap.servers", &config.brokers);
+        for (key, value) in &config.properties {
+            client.set(key, value);
+        }
+        let producer = client.create().wrap_err("Failed to create Kafka producer")?;
+        Ok(Self {
+            config,
+            producer,
+            pending: Pending::new(usize::MAX, Duration::ZERO),
+        })
+    }
+
+    pub fn config(&self) -> &KafkaConfig {
+        &self.config
+    }
+}
+
+/// Sends price updates, swaps and candles that have a topic, a burst at a time, and waits for their
+/// delivery. Messages that fail are kept for the next flush.
+#[async_trait]
+impl Sink for KafkaSink {
+    fn name(&self) -> &str {
+        "kafka"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        if let Some(message) = self.config.message(event) {
+            self.pending.push(message?);
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let messages = self.pending.take(true);
+        let deliveries = messages.iter().map(|(topic, key, payload)| {
+            self.producer.send(FutureRecord::to(topic).key(key).payload(payload), QUEUE_TIMEOUT)
+        });
+        let results = join_all(deliveries).await;
+        let mut failed = Vec::new();
+        let mut error = None;
+        for (message, result) in messages.into_iter().zip(results) {
+            if let Err((e, _)) = result {
+                error = Some(e);
+                failed.push(message);
+            }
+        }
+        match error {
+            None => Ok(()),
+            Some(e) => {
+                let count = failed.len();
+                self.pending.restore(failed);
+                Err(eyre!("Failed to deliver {} messages: {}", count, e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Candle;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_message_routing() {
+        let candle = Candle {
+            pool_address: Address::repeat_byte(1),
+            interval_secs: 60,
+            open_time: 120,
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume0: 10.0,
+            volume1: 15.0,
+            trades: 3,
+        };
+        let event = ScannerEvent::Candle(candle.clone());
+
+        let config = KafkaConfig::default();
+        let (topic, key, payload) = config.message(&event).unwrap().unwrap();
+        assert_eq!(topic, "dex.candles");
+        assert_eq!(key, Address::repeat_byte(1).to_string());
+        assert_eq!(serde_json::from_str::<Candle>(&payload).unwrap(), candle);
+
+        let config = KafkaConfig {
+            candle_topic: None,
+            ..Default::default()
+        };
+        assert!(config.message(&event).is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! Outputs that record scanner events outside the process. Sinks that need extra dependencies are
+//! behind their own feature; each is fed from the event bus by a background task, so a slow destination
+//! never holds up price delivery.
+
+use crate::events::ScannerEvent;
+use async_trait::async_trait;
+use eyre::Result;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::RecvError, error::TryRecvError};
+use tracing::warn;
+
+pub mod clickhouse;
+pub mod csv;
+pub mod influx;
+pub mod json_lines;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use clickhouse::{ClickHouseConfig, ClickHouseSink};
+pub use csv::{CsvConfig, CsvRows, CsvSink};
+pub use influx::{InfluxConfig, InfluxSink, InfluxTag};
+pub use json_lines::JsonLinesSink;
+#[cfg(feature = "kafka")]
+pub use kafka::{KafkaConfig, KafkaSink};
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttConfig, MqttSink};
+#[cfg(feature = "parquet")]
+pub use parquet::{ParquetConfig, ParquetSink, Partition};
+#[cfg(feature = "postgres")]
+pub use postgres::{PostgresConfig, PostgresSink};
+#[cfg(feature = "redis")]
+pub use redis::{RedisConfig, RedisSink};
+#[cfg(feature = "s3")]
+pub use s3::{S3Config, S3Sink};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteSink;
+
+/// Rows a batching sink drops, oldest first, while its writes keep failing.
+const MAX_PENDING: usize = 100_000;
+
+/// A destination for scanner events. Add sinks to `ScannerOptions::sinks`; the scanner runs each in its own
+/// task with its own view of the event bus, so a slow or failing sink never holds up the others or price
+/// delivery. Calls that fail are retried per `ScannerOptions::sink_retry`, then reported as
+/// `ScannerError::Sink`.
+#[async_trait]
+pub trait Sink: std::fmt::Debug + Send + Sync {
+    /// Label for logs and `ScannerError::Sink`.
+    fn name(&self) -> &str;
+
+    /// Handle one event. Sinks that batch only buffer here and write in `flush`.
+    async fn publish(&self, event: &ScannerEvent) -> Result<()>;
+
+    /// Called after every burst of events and at least once per second. Batching sinks write what they
+    /// buffered once a batch is due.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once when the scanner stops: write everything still buffered.
+    async fn close(&self) -> Result<()> {
+        self.flush().await
+    }
+}
+
+/// How often a failing sink call is tried before it is reported.
+#[derive(Debug, Clone)]
+pub struct SinkRetry {
+    /// Tries per call, including the first.
+    pub attempts: u32,
+    /// Delay before the first retry; doubled for each further one.
+    pub backoff: Duration,
+}
+
+impl Default for SinkRetry {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Rows a batching sink buffers between writes. A batch is due once `batch_size` rows are pending or
+/// `interval` has passed since the previous one. A batch whose write fails is put back, so the sink's
+/// next flush (or the scanner's retry) writes it again.
+#[derive(Debug)]
+pub(crate) struct Pending<T> {
+    state: Mutex<(Vec<T>, Instant)>,
+    batch_size: usize,
+    interval: Duration,
+}
+
+impl<T: Clone> Pending<T> {
+    pub(crate) fn new(batch_size: usize, interval: Duration) -> Self {
+        Self {
+            state: Mutex::new((Vec::new(), Instant::now())),
+            batch_size: batch_size.max(1),
+            interval,
+        }
+    }
+
+    pub(crate) fn push(&self, row: T) {
+        self.state.lock().unwrap().0.push(row);
+    }
+
+    /// The buffered rows if a batch is due, or all of them with `force`.
+    pub(crate) fn take(&self, force: bool) -> Vec<T> {
+        let mut state = self.state.lock().unwrap();
+        let (rows, last) = &mut *state;
+        if rows.is_empty() || !(force || rows.len() >= self.batch_size || last.elapsed() >= self.interval) {
+            return Vec::new();
+        }
+        *last = Instant::now();
+        std::mem::take(rows)
+    }
+
+    /// Put a failed batch back ahead of the rows buffered since, dropping the oldest beyond `MAX_PENDING`.
+    pub(crate) fn restore(&self, mut batch: Vec<T>) {
+        let mut state = self.state.lock().unwrap();
+        batch.append(&mut state.0);
+        if batch.len() > MAX_PENDING {
+            let dropped = batch.len() - MAX_PENDING;
+            warn!("Sink writes keep failing, dropped {} buffered rows", dropped);
+            batch.drain(..dropped);
+        }
+        state.0 = batch;
+    }
+
+    /// Hand a due batch (every pending row with `force`) to `write`; keep it for the next flush if the
+    /// write fails.
+    pub(crate) async fn flush(&self, force: bool, write: impl AsyncFnOnce(Vec<T>) -> Result<()>) -> Result<()> {
+        let batch = self.take(force);
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let result = write(batch.clone()).await;
+        if result.is_err() {
+            self.restore(batch);
+        }
+        result
+    }
+}
+
+/// Wait for the next event, then take every event already queued behind it, so a burst is handled as
+/// one batch. `None` once the bus is closed. Cancel-safe: nothing is lost when dropped while waiting.
+/// `sink` names the sink in lag warnings.
+pub(crate) async fn next_batch(events: &mut broadcast::Receiver<ScannerEvent>, sink: &str) -> Option<Vec<ScannerEvent>> {
+    let mut batch = Vec::new();
+    let mut next = match events.recv().await {
+        Ok(event) => Ok(event),
+        Err(RecvError::Lagged(skipped)) => Err(TryRecvError::Lagged(skipped)),
+        Err(RecvError::Closed) => return None,
+    };
+    loop {
+        match next {
+            Ok(event) => batch.push(event),
+            Err(TryRecvError::Lagged(skipped)) => warn!("{} sink lagged, skipped {} events", sink, skipped),
+            Err(TryRecvError::Empty | TryRecvError::Closed) => return Some(batch),
+        }
+        next = events.try_recv();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pending_batches_and_restores() {
+        let pending = Pending::new(3, Duration::from_secs(3600));
+        pending.push(1);
+        pending.push(2);
+        // Not due yet: below the batch size and within the interval.
+        assert!(pending.flush(false, async |_| panic!("not due")).await.is_ok());
+
+        pending.push(3);
+        let failed = pending.flush(false, async |_| Err(eyre::eyre!("down"))).await;
+        assert!(failed.is_err());
+
+        // The failed batch is kept ahead of newer rows and written on the next forced flush.
+        pending.push(4);
+        let mut written = Vec::new();
+        pending
+            .flush(true, async |batch| {
+                written = batch;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(written, vec![1, 2, 3, 4]);
+        assert!(pending.take(true).is_empty());
+    }
+}
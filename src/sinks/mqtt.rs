@@ -0,0 +1,162 @@
+//! Price updates on MQTT topics, one per pair and pool (`dex/WETH-USDC/0x…`), so dashboards, Home
+//! Assistant or embedded devices can subscribe to the pools they care about. Enabled with the `mqtt`
+//! feature.
+
+use super::json_lines::price_line;
+use super::Sink;
+use crate::events::ScannerEvent;
+use crate::types::CachedPool;
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use rumqttc::{AsyncClient, EventLoop, MqttOptions};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+pub use rumqttc::QoS;
+
+/// Outgoing messages buffered while the broker is slow or unreachable; further publishes fail.
+const QUEUE_CAPACITY: usize = 1_000;
+/// Pause before polling again after a connection error, which makes the client reconnect.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Broker and topic settings of an `MqttSink`.
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Username and password.
+    pub credentials: Option<(String, String)>,
+    /// First topic level.
+    pub prefix: String,
+    pub qos: QoS,
+    /// Keep the last update of each topic on the broker, so new subscribers get the current price at once.
+    pub retain: bool,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "dex-pool-scanner".to_string(),
+            credentials: None,
+            prefix: "dex".to_string(),
+            qos: QoS::AtMostOnce,
+            retain: true,
+        }
+    }
+}
+
+impl MqttConfig {
+    /// `{prefix}/{token0_symbol}-{token1_symbol}/{pool}`. MQTT wildcard and separator characters in
+    /// symbols are replaced with `_`, so `dex/WETH-USDC/#` selects every pool of a pair.
+    pub fn topic(&self, pool: &CachedPool) -> String {
+        let level = |symbol: &str| symbol.replace(['/', '+', '#', '\0'], "_");
+        format!(
+            "{}/{}-{}/{}",
+            self.prefix,
+            level(&pool.token0_symbol),
+            level(&pool.token1_symbol),
+            pool.address
+        )
+    }
+}
+
+/// Publishes every price update as JSON (the `JsonLinesSink` format) to its pair topic.
+pub struct MqttSink {
+    config: MqttConfig,
+    client: AsyncClient,
+    /// Taken and driven by a background task from the first publish on.
+    eventloop: Mutex<Option<EventLoop>>,
+    /// Stops that task on close.
+    stop: CancellationToken,
+}
+
+impl std::fmt::Debug for MqttSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqttSink").field("config", &self.config).finish_non_exhaustive()
+    }
+}
+
+impl MqttSink {
+    /// Create the client. The broker is connected on the first update, and reconnected after drops.
+    pub fn new(config: MqttConfig) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some((username, password)) = &config.credentials {
+            options.set_credentials(username, password);
+        }
+        let (client, eventloop) = AsyncClient::new(options, QUEUE_CAPACITY);
+        Self {
+            config,
+            client,
+            eventloop: Mutex::new(Some(eventloop)),
+            stop: CancellationToken::new(),
+        }
+    }
+
+    pub fn config(&self) -> &MqttConfig {
+        &self.config
+    }
+
+    /// Drive the connection in the background, reconnecting after errors, unless already started.
+    fn connect(&self) {
+        let Some(mut eventloop) = self.eventloop.lock().unwrap().take() else { return };
+        let stop = self.stop.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => return,
+                    event = eventloop.poll() => if let Err(e) = event {
+                        warn!("MQTT connection error: {}; reconnecting in {:?}", e, RECONNECT_DELAY);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Queues every `PriceUpdate` for publishing; fails while the outgoing queue is full.
+#[async_trait]
+impl Sink for MqttSink {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        let ScannerEvent::PriceUpdate { pool, price, .. } = event else { return Ok(()) };
+        self.connect();
+        let payload = price_line(pool, price)?;
+        self.client
+            .try_publish(self.config.topic(pool), self.config.qos, self.config.retain, payload)
+            .map_err(|e| eyre!("Failed to queue price update: {}", e))
+    }
+
+    async fn close(&self) -> Result<()> {
+        let _ = self.client.try_disconnect();
+        self.stop.cancel();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_topic() {
+        let pool = CachedPool {
+            address: Address::repeat_byte(1),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USD+/#".to_string(),
+            ..Default::default()
+        };
+        let topic = MqttConfig::default().topic(&pool);
+        assert_eq!(topic, format!("dex/WETH-USD___/{}", Address::repeat_byte(1)));
+    }
+}
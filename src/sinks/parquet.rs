@@ -0,0 +1,487 @@
+//! Rolling Parquet archive of price updates and swaps, partitioned by day or hour in Hive layout
+//! (`prices/date=2024-05-01/hour=13/…`) so Polars, DuckDB or Spark can read a directory tree directly.
+//! `read_prices` loads an archive back for a `Replay`. Enabled with the `parquet` feature.
+
+use super::{Pending, Sink};
+use crate::events::ScannerEvent;
+use crate::rpc::Swap;
+use crate::types::PoolPrice;
+use arrow_array::{Array, ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, TimestampSecondArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use async_trait::async_trait;
+use chrono::{DateTime, Timelike};
+use eyre::{eyre, Result, WrapErr};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Suffix of a file whose partition is still being written; renamed away once the file is complete.
+const IN_PROGRESS: &str = "inprogress";
+
+/// Time span covered by one Parquet file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Partition {
+    Hourly,
+    #[default]
+    Daily,
+}
+
+impl Partition {
+    fn secs(self) -> u64 {
+        match self {
+            Self::Hourly => 3_600,
+            Self::Daily => 86_400,
+        }
+    }
+
+    /// Start of the partition containing `timestamp`.
+    fn start(self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.secs()
+    }
+
+    /// Hive-style directory of the partition containing `timestamp`, relative to the table directory.
+    fn dir(self, timestamp: u64) -> PathBuf {
+        let time = DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+        let date = PathBuf::from(format!("date={}", time.format("%Y-%m-%d")));
+        match self {
+            Self::Hourly => date.join(format!("hour={:02}", time.hour())),
+            Self::Daily => date,
+        }
+    }
+}
+
+/// Where and how often a `ParquetSink` writes.
+#[derive(Debug, Clone)]
+pub struct ParquetConfig {
+    /// Root of the archive; `prices/` and `swaps/` are created below it.
+    pub dir: PathBuf,
+    pub partition: Partition,
+    /// Buffered rows are written as one row group per table this often.
+    pub flush_interval: Duration,
+}
+
+impl Default for ParquetConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("archive"),
+            partition: Partition::Daily,
+            flush_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A Parquet file being written; it becomes readable once closed.
+struct OpenFile {
+    partition_start: u64,
+    path: PathBuf,
+    writer: ArrowWriter<File>,
+}
+
+impl OpenFile {
+    fn close(self) -> Result<()> {
+        self.writer.close()?;
+        std::fs::rename(&self.path, self.path.with_extension(""))?;
+        Ok(())
+    }
+}
+
+/// One table of the archive: a schema and the file of the current partition.
+struct Table {
+    name: &'static str,
+    schema: SchemaRef,
+    current: Option<OpenFile>,
+}
+
+impl Table {
+    fn new(name: &'static str, fields: Vec<Field>) -> Self {
+        Self {
+            name,
+            schema: Arc::new(Schema::new(fields)),
+            current: None,
+        }
+    }
+
+    /// Append `columns` (rows sorted by time, all in the partition starting at `partition_start`),
+    /// rolling over to a new file when the partition changed.
+    fn write(&mut self, root: &Path, partition: Partition, timestamp: u64, columns: Vec<ArrayRef>) -> Result<()> {
+        let partition_start = partition.start(timestamp);
+        if self.current.as_ref().is_some_and(|file| file.partition_start != partition_start) {
+            self.close()?;
+        }
+        if self.current.is_none() {
+            let dir = root.join(self.name).join(partition.dir(timestamp));
+            std::fs::create_dir_all(&dir)?;
+            let path = dir.join(format!("{}-{}.parquet.{}", self.name, timestamp, IN_PROGRESS));
+            let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+            self.current = Some(OpenFile {
+                partition_start,
+                writer: ArrowWriter::try_new(File::create(&path)?, Arc::clone(&self.schema), Some(properties))?,
+                path,
+            });
+        }
+        let batch = RecordBatch::try_new(Arc::clone(&self.schema), columns)?;
+        let file = self.current.as_mut().expect("opened above");
+        file.writer.write(&batch)?;
+        file.writer.flush()?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        match self.current.take() {
+            Some(file) => file.close(),
+            None => Ok(()),
+        }
+    }
+
+    /// Close the current file once its partition is over as of `now`.
+    fn close_expired(&mut self, partition: Partition, now: u64) -> Result<()> {
+        if self.current.as_ref().is_some_and(|file| file.partition_start + partition.secs() <= now) {
+            self.close()?;
+        }
+        Ok(())
+    }
+}
+
+struct Tables {
+    prices: Table,
+    swaps: Table,
+}
+
+/// Archives price updates (`prices/`) and swaps (`swaps/`) as Snappy-compressed Parquet files, one per
+/// partition and table. The write methods are blocking; as a `Sink`, writes run on the blocking pool.
+pub struct ParquetSink {
+    archive: Arc<Archive>,
+    prices: Pending<PoolPrice>,
+    swaps: Pending<Swap>,
+}
+
+impl std::fmt::Debug for ParquetSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParquetSink").field("config", &self.archive.config).finish_non_exhaustive()
+    }
+}
+
+/// The files of a `ParquetSink`, shared with its writes on the blocking pool.
+struct Archive {
+    config: ParquetConfig,
+    tables: Mutex<Tables>,
+}
+
+fn timestamp_field() -> Field {
+    Field::new("timestamp", DataType::Timestamp(TimeUnit::Second, Some("UTC".into())), false)
+}
+
+impl ParquetSink {
+    pub fn new(config: ParquetConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        let prices = Table::new(
+            "prices",
+            vec![
+                timestamp_field(),
+                Field::new("pool_address", DataType::Utf8, false),
+                Field::new("block_number", DataType::UInt64, true),
+                Field::new("token0_price", DataType::Float64, false),
+                Field::new("token1_price", DataType::Float64, false),
+                Field::new("token0_price_usd", DataType::Float64, true),
+                Field::new("token1_price_usd", DataType::Float64, true),
+                Field::new("amount0", DataType::Float64, true),
+                Field::new("amount1", DataType::Float64, true),
+            ],
+        );
+        let swaps = Table::new(
+            "swaps",
+            vec![
+                timestamp_field(),
+                Field::new("pool_address", DataType::Utf8, false),
+                Field::new("token0", DataType::Utf8, false),
+                Field::new("token1", DataType::Utf8, false),
+                Field::new("block_number", DataType::UInt64, true),
+                Field::new("transaction_hash", DataType::Utf8, true),
+                Field::new("log_index", DataType::UInt64, true),
+                Field::new("sender", DataType::Utf8, false),
+                Field::new("recipient", DataType::Utf8, false),
+                Field::new("amount0", DataType::Float64, false),
+                Field::new("amount1", DataType::Float64, false),
+                Field::new("zero_for_one", DataType::Boolean, false),
+                Field::new("price", DataType::Float64, false),
+                Field::new("volume_usd", DataType::Float64, true),
+            ],
+        );
+        Ok(Self {
+            prices: Pending::new(usize::MAX, config.flush_interval),
+            swaps: Pending::new(usize::MAX, config.flush_interval),
+            archive: Arc::new(Archive {
+                config,
+                tables: Mutex::new(Tables { prices, swaps }),
+            }),
+        })
+    }
+
+    pub fn config(&self) -> &ParquetConfig {
+        &self.archive.config
+    }
+
+    /// Append price updates, split across partitions by timestamp.
+    pub fn write_prices(&self, prices: &[PoolPrice]) -> Result<()> {
+        self.archive.write_prices(prices)
+    }
+
+    /// Append swaps, split across partitions by timestamp.
+    pub fn write_swaps(&self, swaps: &[Swap]) -> Result<()> {
+        self.archive.write_swaps(swaps)
+    }
+
+    /// Complete the files of partitions that ended before `now`.
+    pub fn close_expired(&self, now: u64) -> Result<()> {
+        self.archive.close_expired(now)
+    }
+
+    /// Complete the open files; later writes start new ones.
+    pub fn close(&self) -> Result<()> {
+        self.archive.close()
+    }
+
+    /// Append due (or, with `force`, all) buffered rows, then complete the files of ended partitions (or,
+    /// with `force`, every open file).
+    async fn write_pending(&self, force: bool) -> Result<()> {
+        self.prices
+            .flush(force, async |prices| {
+                let archive = Arc::clone(&self.archive);
+                tokio::task::spawn_blocking(move || archive.write_prices(&prices)).await?
+            })
+            .await?;
+        self.swaps
+            .flush(force, async |swaps| {
+                let archive = Arc::clone(&self.archive);
+                tokio::task::spawn_blocking(move || archive.write_swaps(&swaps)).await?
+            })
+            .await?;
+        let archive = Arc::clone(&self.archive);
+        let now = chrono::Utc::now().timestamp() as u64;
+        tokio::task::spawn_blocking(move || if force { archive.close() } else { archive.close_expired(now) }).await?
+    }
+}
+
+impl Archive {
+    fn write_prices(&self, prices: &[PoolPrice]) -> Result<()> {
+        let mut tables = self.tables.lock().unwrap();
+        for chunk in self.partitions(prices, |price| price.timestamp) {
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(timestamps(chunk.iter().map(|p| p.timestamp))),
+                Arc::new(StringArray::from_iter_values(chunk.iter().map(|p| p.pool_address.to_string()))),
+                Arc::new(UInt64Array::from_iter(chunk.iter().map(|p| p.block_number))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|p| p.token0_price))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|p| p.token1_price))),
+                Arc::new(Float64Array::from_iter(chunk.iter().map(|p| p.token0_price_usd))),
+                Arc::new(Float64Array::from_iter(chunk.iter().map(|p| p.token1_price_usd))),
+                Arc::new(Float64Array::from_iter(chunk.iter().map(|p| p.amount0))),
+                Arc::new(Float64Array::from_iter(chunk.iter().map(|p| p.amount1))),
+            ];
+            tables.prices.write(&self.config.dir, self.config.partition, chunk[0].timestamp, columns)?;
+        }
+        Ok(())
+    }
+
+    fn write_swaps(&self, swaps: &[Swap]) -> Result<()> {
+        let mut tables = self.tables.lock().unwrap();
+        for chunk in self.partitions(swaps, |swap| swap.timestamp) {
+            let address = |f: fn(&Swap) -> String| StringArray::from_iter_values(chunk.iter().map(f));
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(timestamps(chunk.iter().map(|s| s.timestamp))),
+                Arc::new(address(|s| s.pool_address.to_string())),
+                Arc::new(address(|s| s.token0.to_string())),
+                Arc::new(address(|s| s.token1.to_string())),
+                Arc::new(UInt64Array::from_iter(chunk.iter().map(|s| s.block_number))),
+                Arc::new(StringArray::from_iter(chunk.iter().map(|s| s.transaction_hash.map(|h| h.to_string())))),
+                Arc::new(UInt64Array::from_iter(chunk.iter().map(|s| s.log_index))),
+                Arc::new(address(|s| s.sender.to_string())),
+                Arc::new(address(|s| s.recipient.to_string())),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|s| s.amount0))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|s| s.amount1))),
+                Arc::new(BooleanArray::from_iter(chunk.iter().map(|s| Some(s.zero_for_one)))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|s| s.price))),
+                Arc::new(Float64Array::from_iter(chunk.iter().map(|s| s.volume_usd))),
+            ];
+            tables.swaps.write(&self.config.dir, self.config.partition, chunk[0].timestamp, columns)?;
+        }
+        Ok(())
+    }
+
+    fn close_expired(&self, now: u64) -> Result<()> {
+        let mut tables = self.tables.lock().unwrap();
+        tables.prices.close_expired(self.config.partition, now)?;
+        tables.swaps.close_expired(self.config.partition, now)
+    }
+
+    fn close(&self) -> Result<()> {
+        let mut tables = self.tables.lock().unwrap();
+        tables.prices.close()?;
+        tables.swaps.close()
+    }
+
+    /// Consecutive runs of `rows` in the same partition.
+    fn partitions<'a, T>(&self, rows: &'a [T], timestamp: fn(&T) -> u64) -> impl Iterator<Item = &'a [T]> {
+        let partition = self.config.partition;
+        rows.chunk_by(move |a, b| partition.start(timestamp(a)) == partition.start(timestamp(b)))
+    }
+}
+
+fn timestamps(values: impl Iterator<Item = u64>) -> TimestampSecondArray {
+    TimestampSecondArray::from_iter_values(values.map(|t| t as i64)).with_timezone("UTC")
+}
+
+/// Load the price updates of the completed `prices/` files below the archive root `dir`, ordered by time
+/// (then block). Files still being written are skipped.
+pub fn read_prices(dir: &Path) -> Result<Vec<PoolPrice>> {
+    let mut files = Vec::new();
+    collect_files(&dir.join("prices"), &mut files)?;
+    let mut prices = Vec::new();
+    for path in files {
+        read_price_file(&path, &mut prices).wrap_err_with(|| format!("{}", path.display()))?;
+    }
+    prices.sort_by_key(|price| (price.timestamp, price.block_number));
+    Ok(prices)
+}
+
+/// The `.parquet` files below `dir`, recursively.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "parquet") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn read_price_file(path: &Path, prices: &mut Vec<PoolPrice>) -> Result<()> {
+    for batch in ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()? {
+        let batch = batch?;
+        let column = |name: &str| batch.column_by_name(name).ok_or_else(|| eyre!("no {} column", name));
+        let floats = |name: &str| -> Result<&Float64Array> {
+            column(name)?.as_any().downcast_ref().ok_or_else(|| eyre!("{} is not a float column", name))
+        };
+        let timestamp: &TimestampSecondArray = column("timestamp")?
+            .as_any()
+            .downcast_ref()
+            .ok_or_else(|| eyre!("timestamp is not a timestamp column"))?;
+        let pool_address: &StringArray = column("pool_address")?
+            .as_any()
+            .downcast_ref()
+            .ok_or_else(|| eyre!("pool_address is not a string column"))?;
+        let block_number: &UInt64Array = column("block_number")?
+            .as_any()
+            .downcast_ref()
+            .ok_or_else(|| eyre!("block_number is not an integer column"))?;
+        let (token0_price, token1_price) = (floats("token0_price")?, floats("token1_price")?);
+        let (token0_price_usd, token1_price_usd) = (floats("token0_price_usd")?, floats("token1_price_usd")?);
+        let (amount0, amount1) = (floats("amount0")?, floats("amount1")?);
+        let optional = |array: &Float64Array, row| array.is_valid(row).then(|| array.value(row));
+        for row in 0..batch.num_rows() {
+            prices.push(PoolPrice {
+                pool_address: pool_address.value(row).parse()?,
+                token0_price: token0_price.value(row),
+                token1_price: token1_price.value(row),
+                timestamp: timestamp.value(row) as u64,
+                token0_price_usd: optional(token0_price_usd, row),
+                token1_price_usd: optional(token1_price_usd, row),
+                block_number: block_number.is_valid(row).then(|| block_number.value(row)),
+                amount0: optional(amount0, row),
+                amount1: optional(amount1, row),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Buffers `PriceUpdate` and `Swap` events and appends them every `ParquetConfig::flush_interval`,
+/// completing each file once its partition is over. Open files are completed on close.
+#[async_trait]
+impl Sink for ParquetSink {
+    fn name(&self) -> &str {
+        "parquet"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        match event {
+            ScannerEvent::PriceUpdate { price, .. } => self.prices.push(price.clone()),
+            ScannerEvent::Swap(swap) => self.swaps.push(swap.clone()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.write_pending(false).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.write_pending(true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partitions() {
+        // 2024-05-01 13:20:00 UTC
+        let timestamp = 1_714_521_600 + 13 * 3_600 + 20 * 60;
+        assert_eq!(Partition::Hourly.start(timestamp), 1_714_521_600 + 13 * 3_600);
+        assert_eq!(Partition::Daily.start(timestamp), 1_714_521_600);
+        assert_eq!(Partition::Hourly.dir(timestamp), PathBuf::from("date=2024-05-01/hour=13"));
+        assert_eq!(Partition::Daily.dir(timestamp), PathBuf::from("date=2024-05-01"));
+
+        let sink = ParquetSink::new(ParquetConfig {
+            dir: std::env::temp_dir(),
+            partition: Partition::Hourly,
+            ..Default::default()
+        })
+        .unwrap();
+        let rows = [timestamp, timestamp + 60, timestamp + 3_600, timestamp + 3_700];
+        let chunks: Vec<_> = sink.archive.partitions(&rows, |t| *t).collect();
+        assert_eq!(chunks, vec![&rows[..2], &rows[2..]]);
+    }
+
+    #[test]
+    fn test_read_prices() {
+        let dir = std::env::temp_dir().join(format!("parquet-read-{}", std::process::id()));
+        let sink = ParquetSink::new(ParquetConfig {
+            dir: dir.clone(),
+            partition: Partition::Hourly,
+            ..Default::default()
+        })
+        .unwrap();
+        let price = |timestamp, token0_price| PoolPrice {
+            pool_address: alloy::primitives::Address::repeat_byte(1),
+            token0_price,
+            token1_price: 1.0 / token0_price,
+            timestamp,
+            block_number: Some(timestamp / 12),
+            amount0: (token0_price > 3000.0).then_some(-1.5),
+            ..Default::default()
+        };
+        sink.write_prices(&[price(1_714_521_600, 3000.0), price(1_714_525_200, 3100.0)]).unwrap();
+        sink.write_prices(&[price(1_714_521_660, 3050.0)]).unwrap();
+        sink.close().unwrap();
+
+        let prices = read_prices(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let read: Vec<_> = prices.iter().map(|p| (p.timestamp, p.token0_price, p.amount0)).collect();
+        assert_eq!(
+            read,
+            vec![(1_714_521_600, 3000.0, None), (1_714_521_660, 3050.0, Some(-1.5)), (1_714_525_200, 3100.0, Some(-1.5))]
+        );
+        assert_eq!(prices[0].pool_address, alloy::primitives::Address::repeat_byte(1));
+        assert_eq!(prices[0].block_number, Some(1_714_521_600 / 12));
+    }
+}
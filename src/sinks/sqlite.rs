@@ -0,0 +1,228 @@
+//! Price history in a local SQLite database: one row per price update, so a scanner doubles as a
+//! lightweight historical recorder.
+
+use super::{Pending, Sink};
+use crate::events::ScannerEvent;
+use crate::types::PoolPrice;
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::Result;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often buffered price updates are written.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// How often rows older than the retention window are deleted.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS price_history (
+    id            INTEGER PRIMARY KEY,
+    pool_address  TEXT NOT NULL,
+    block_number  INTEGER,
+    timestamp     INTEGER NOT NULL,
+    token0_price  REAL NOT NULL,
+    token1_price  REAL NOT NULL,
+    amount0       REAL,
+    amount1       REAL
+);
+CREATE INDEX IF NOT EXISTS price_history_pool_time ON price_history (pool_address, timestamp);
+CREATE INDEX IF NOT EXISTS price_history_time ON price_history (timestamp);
+"#;
+
+/// Append-only price history. The query methods are blocking; as a `Sink`, writes run on the blocking pool.
+pub struct SqliteSink {
+    conn: Arc<Mutex<Connection>>,
+    retention: Option<Duration>,
+    pending: Pending<PoolPrice>,
+    /// `None` until the first prune.
+    last_prune: Mutex<Option<Instant>>,
+}
+
+impl std::fmt::Debug for SqliteSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteSink")
+            .field("retention", &self.retention)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SqliteSink {
+    /// Open (or create) the database at `path` and apply the schema. History is kept forever unless
+    /// a retention window is set with `with_retention`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// In-memory database, mostly for tests.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            retention: None,
+            pending: Pending::new(usize::MAX, FLUSH_INTERVAL),
+            last_prune: Mutex::new(None),
+        })
+    }
+
+    /// Delete rows older than `retention` (checked hourly while scanning).
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Append price updates in one transaction.
+    pub fn record(&self, prices: &[PoolPrice]) -> Result<()> {
+        record(&self.conn, prices)
+    }
+
+    /// Recorded updates of `pool` with `from <= timestamp < to`, oldest first. Only the fields stored in
+    /// the history (prices, block, amounts) are set.
+    pub fn history(&self, pool: Address, from: u64, to: u64) -> Result<Vec<PoolPrice>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT block_number, timestamp, token0_price, token1_price, amount0, amount1 FROM price_history
+             WHERE pool_address = ?1 AND timestamp >= ?2 AND timestamp < ?3 ORDER BY timestamp, id",
+        )?;
+        let rows = stmt.query_map(params![pool.to_string(), from, to], |row| {
+            Ok(PoolPrice {
+                pool_address: pool,
+                block_number: row.get(0)?,
+                timestamp: row.get(1)?,
+                token0_price: row.get(2)?,
+                token1_price: row.get(3)?,
+                amount0: row.get(4)?,
+                amount1: row.get(5)?,
+                ..Default::default()
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Delete rows older than the retention window as of `now`; returns the number of rows deleted.
+    /// A no-op without a retention window.
+    pub fn prune(&self, now: u64) -> Result<usize> {
+        prune(&self.conn, self.retention, now)
+    }
+
+    /// Write due (or, with `force`, all) buffered updates, and prune once an hour.
+    async fn write_pending(&self, force: bool) -> Result<()> {
+        self.pending
+            .flush(force, async |prices| {
+                let conn = Arc::clone(&self.conn);
+                tokio::task::spawn_blocking(move || record(&conn, &prices)).await?
+            })
+            .await?;
+
+        let prune_due = {
+            let mut last = self.last_prune.lock().unwrap();
+            let due = self.retention.is_some() && last.is_none_or(|last| last.elapsed() >= PRUNE_INTERVAL);
+            if due {
+                *last = Some(Instant::now());
+            }
+            due
+        };
+        if prune_due {
+            let (conn, retention) = (Arc::clone(&self.conn), self.retention);
+            let now = chrono::Utc::now().timestamp() as u64;
+            tokio::task::spawn_blocking(move || prune(&conn, retention, now)).await??;
+        }
+        Ok(())
+    }
+}
+
+fn record(conn: &Mutex<Connection>, prices: &[PoolPrice]) -> Result<()> {
+    let mut conn = conn.lock().unwrap();
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO price_history
+             (pool_address, block_number, timestamp, token0_price, token1_price, amount0, amount1)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for price in prices {
+            stmt.execute(params![
+                price.pool_address.to_string(),
+                price.block_number,
+                price.timestamp,
+                price.token0_price,
+                price.token1_price,
+                price.amount0,
+                price.amount1,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn prune(conn: &Mutex<Connection>, retention: Option<Duration>, now: u64) -> Result<usize> {
+    let Some(retention) = retention else { return Ok(0) };
+    let cutoff = now.saturating_sub(retention.as_secs());
+    let conn = conn.lock().unwrap();
+    Ok(conn.execute("DELETE FROM price_history WHERE timestamp < ?1", params![cutoff])?)
+}
+
+/// Appends every `PriceUpdate`, batched per second, and applies the retention policy hourly.
+#[async_trait]
+impl Sink for SqliteSink {
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+
+    async fn publish(&self, event: &ScannerEvent) -> Result<()> {
+        if let ScannerEvent::PriceUpdate { price, .. } = event {
+            self.pending.push(price.clone());
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.write_pending(false).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.write_pending(true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_roundtrip_and_retention() {
+        let sink = SqliteSink::open_in_memory().unwrap().with_retention(Duration::from_secs(100));
+        let pool = Address::repeat_byte(1);
+        let price = |timestamp: u64, amount0: Option<f64>| PoolPrice {
+            pool_address: pool,
+            token0_price: 2.0,
+            token1_price: 0.5,
+            timestamp,
+            block_number: Some(timestamp),
+            amount0,
+            amount1: amount0.map(|amount| -amount * 2.0),
+            ..Default::default()
+        };
+        sink.record(&[price(10, Some(1.5)), price(200, None), price(300, Some(-3.0))]).unwrap();
+
+        let history = sink.history(pool, 0, 1_000).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].block_number, Some(10));
+        assert_eq!(history[0].amount1, Some(-3.0));
+        assert_eq!(history[1].amount0, None);
+        assert!(sink.history(Address::repeat_byte(2), 0, 1_000).unwrap().is_empty());
+
+        // Retention of 100s as of t=250: only the update at t=10 is older than the cutoff.
+        assert_eq!(sink.prune(250).unwrap(), 1);
+        let history = sink.history(pool, 0, 1_000).unwrap();
+        assert_eq!(history.iter().map(|p| p.timestamp).collect::<Vec<_>>(), vec![200, 300]);
+    }
+}
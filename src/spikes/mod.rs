@@ -0,0 +1,92 @@
+//! Sanity filter for price updates: a large move on a small swap is the signature of a
+//! flash-loan manipulation or a fat-finger trade, not of a new market price.
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+
+/// What to do with an update caught by the filter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpikeAction {
+    /// Deliver the update with `PoolPrice::suspect` set.
+    #[default]
+    Flag,
+    /// Don't deliver the update, and keep it out of metrics, USD pricing and pair prices.
+    Suppress,
+}
+
+/// Enables the filter through `ScannerOptions::spike_filter`.
+#[derive(Debug, Clone)]
+pub struct SpikeFilterConfig {
+    /// Largest move from the last accepted price a single update may make on low volume, in basis points.
+    pub max_move_bps: f64,
+    /// Swaps at least this large (USD) may move the price any distance.
+    pub min_volume_usd: f64,
+    pub action: SpikeAction,
+}
+
+impl Default for SpikeFilterConfig {
+    fn default() -> Self {
+        Self {
+            max_move_bps: 1_000.0,
+            min_volume_usd: 10_000.0,
+            action: SpikeAction::Flag,
+        }
+    }
+}
+
+/// An update that moved the price more than `max_move_bps` on less than `min_volume_usd`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceSpike {
+    pub pool_address: Address,
+    /// token0 price of the last accepted update before the swap's transaction: a V2 `Sync` moves the price
+    /// ahead of its `Swap`, so the move is judged on the `Swap` from the price before the `Sync`.
+    pub previous_price: f64,
+    /// token0 price of this update.
+    pub price: f64,
+    /// `price / previous_price - 1`, in basis points.
+    pub move_bps: f64,
+    /// USD volume of the swap behind the update. Updates without a swap (V2 `Sync`, V3 `Mint` / `Burn`) aren't
+    /// judged.
+    pub volume_usd: f64,
+    /// The update was withheld (`SpikeAction::Suppress`) rather than flagged.
+    pub suppressed: bool,
+    pub timestamp: u64,
+}
+
+impl SpikeFilterConfig {
+    /// Check a move from `previous_price` to `price` (token0 prices) made with `volume_usd`. `None` when
+    /// the update is fine, or when its volume can't be valued.
+    pub fn check(&self, previous_price: f64, price: f64, volume_usd: Option<f64>) -> Option<(f64, f64)> {
+        let volume_usd = volume_usd?;
+        let move_bps = self.large_move(previous_price, price)?;
+        (volume_usd < self.min_volume_usd).then_some((move_bps, volume_usd))
+    }
+
+    /// `price / previous_price - 1` in basis points, when it is more than `max_move_bps` either way.
+    pub(crate) fn large_move(&self, previous_price: f64, price: f64) -> Option<f64> {
+        if !(previous_price.is_finite() && previous_price > 0.0 && price.is_finite()) {
+            return None;
+        }
+        let move_bps = (price / previous_price - 1.0) * 10_000.0;
+        (move_bps.abs() > self.max_move_bps).then_some(move_bps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_move_needs_volume() {
+        let config = SpikeFilterConfig::default();
+        // 20% up on a $500 swap.
+        let (move_bps, _) = config.check(2_000.0, 2_400.0, Some(500.0)).unwrap();
+        assert!((move_bps - 2_000.0).abs() < 1e-6);
+        // Same move backed by a $1M swap, and a small move on a small swap.
+        assert!(config.check(2_000.0, 2_400.0, Some(1_000_000.0)).is_none());
+        assert!(config.check(2_000.0, 2_010.0, Some(500.0)).is_none());
+        // A crash is caught the same way; unvalued volume isn't judged.
+        assert!(config.check(2_000.0, 1_000.0, Some(0.0)).is_some());
+        assert!(config.check(2_000.0, 1_000.0, None).is_none());
+    }
+}
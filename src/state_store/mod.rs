@@ -0,0 +1,151 @@
+//! Decoder state of every tracked pool (reserves, sqrtPrice, tick, known liquidity) in an embedded
+//! RocksDB database, so a restarted scanner resumes where it stopped and only backfills the blocks it
+//! missed instead of re-deriving everything from live logs. Enabled with the `rocksdb` feature.
+
+use crate::liquidity_pools::PoolState;
+use crate::types::PoolPrice;
+use alloy::primitives::Address;
+use eyre::Result;
+use rocksdb::{IteratorMode, Options, WriteBatch, DB};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A pool's state as of its last decoded log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolCheckpoint {
+    /// Block and log index of the last log reflected in `state`.
+    pub block_number: u64,
+    pub log_index: u64,
+    pub state: PoolState,
+    /// Price after that log.
+    pub price: Option<PoolPrice>,
+}
+
+impl PoolCheckpoint {
+    /// Whether the log at `block_number` / `log_index` is already reflected in the state.
+    pub fn covers(&self, block_number: u64, log_index: u64) -> bool {
+        (block_number, log_index) <= (self.block_number, self.log_index)
+    }
+}
+
+/// Pool checkpoints keyed by pool address, stored as JSON. All methods are blocking; the scanner calls
+/// them from the blocking pool.
+pub struct StateStore {
+    db: DB,
+}
+
+impl std::fmt::Debug for StateStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateStore").field("path", &self.db.path()).finish_non_exhaustive()
+    }
+}
+
+impl StateStore {
+    /// Open (or create) the database directory at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        Ok(Self {
+            db: DB::open(&options, path)?,
+        })
+    }
+
+    /// Write checkpoints atomically, replacing earlier ones of the same pools.
+    pub fn save(&self, checkpoints: &[(Address, PoolCheckpoint)]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for (pool, checkpoint) in checkpoints {
+            batch.put(pool, serde_json::to_vec(checkpoint)?);
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    pub fn load(&self, pool: Address) -> Result<Option<PoolCheckpoint>> {
+        self.db
+            .get(pool)?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Every stored checkpoint.
+    pub fn load_all(&self) -> Result<HashMap<Address, PoolCheckpoint>> {
+        let mut checkpoints = HashMap::new();
+        for entry in self.db.iterator(IteratorMode::Start) {
+            let (key, value) = entry?;
+            let Ok(pool) = Address::try_from(&*key) else { continue };
+            checkpoints.insert(pool, serde_json::from_slice(&value)?);
+        }
+        Ok(checkpoints)
+    }
+
+    pub fn remove(&self, pools: &[Address]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for pool in pools {
+            batch.delete(pool);
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liquidity_pools::TickLiquidity;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("state-store-test-{}", std::process::id()));
+        let store = StateStore::open(&dir).unwrap();
+
+        let mut ticks = TickLiquidity::new();
+        ticks.update(-600, 600, 1_000_000_000_000);
+        let v3 = PoolCheckpoint {
+            block_number: 19_000_000,
+            log_index: 12,
+            state: PoolState::V3 {
+                sqrt_price_x96: U256::from(1u128 << 96),
+                tick: -3,
+                liquidity: u128::MAX,
+                ticks,
+            },
+            price: Some(PoolPrice {
+                token0_price: 1.0,
+                token1_price: 1.0,
+                ..Default::default()
+            }),
+        };
+        let v2 = PoolCheckpoint {
+            block_number: 19_000_001,
+            log_index: 0,
+            state: PoolState::V2 {
+                reserve0: U256::from(5u64),
+                reserve1: U256::MAX,
+            },
+            price: None,
+        };
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        store.save(&[(a, v3.clone()), (b, v2.clone())]).unwrap();
+        let loaded = store.load(a).unwrap().unwrap();
+        assert_eq!((loaded.block_number, loaded.log_index), (19_000_000, 12));
+        assert_eq!(loaded.state, v3.state);
+        assert_eq!(loaded.price.map(|price| price.token0_price), Some(1.0));
+        assert_eq!(store.load_all().unwrap().len(), 2);
+
+        store.remove(&[a]).unwrap();
+        assert!(store.load(a).unwrap().is_none());
+        let remaining = store.load_all().unwrap();
+        assert_eq!(remaining.keys().collect::<Vec<_>>(), vec![&b]);
+        assert_eq!(remaining[&b].state, v2.state);
+
+        assert!(v3.covers(19_000_000, 12));
+        assert!(v3.covers(18_999_999, 40));
+        assert!(!v3.covers(19_000_000, 13));
+
+        drop(store);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,372 @@
+//! SQLite-backed store of discovered pools, token metadata, last-seen prices and OHLCV candles.
+//! Lets a scanner restart without network access and makes its view of the world inspectable
+//! with any SQLite client. Enabled with the `sqlite` feature.
+
+use crate::discovery::TokenMetadata;
+use crate::metrics::Candle;
+use crate::types::{CachedPool, PoolPrice, TokenQuirk};
+use alloy::primitives::Address;
+use eyre::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Mutex;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS tokens (
+    address     TEXT PRIMARY KEY,
+    symbol      TEXT NOT NULL,
+    decimals    INTEGER NOT NULL,
+    updated_at  TEXT NOT NULL,
+    quirk       TEXT
+);
+CREATE TABLE IF NOT EXISTS pools (
+    address         TEXT PRIMARY KEY,
+    protocol        TEXT NOT NULL,
+    token0          TEXT NOT NULL REFERENCES tokens(address),
+    token1          TEXT NOT NULL REFERENCES tokens(address),
+    fee             INTEGER NOT NULL,
+    liquidity_usd   REAL NOT NULL,
+    volume_24h_usd  REAL NOT NULL,
+    last_seen       TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS prices (
+    pool_address  TEXT PRIMARY KEY REFERENCES pools(address),
+    token0_price  REAL NOT NULL,
+    token1_price  REAL NOT NULL,
+    timestamp     INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS candles (
+    pool_address   TEXT NOT NULL,
+    interval_secs  INTEGER NOT NULL,
+    open_time      INTEGER NOT NULL,
+    open           REAL NOT NULL,
+    high           REAL NOT NULL,
+    low            REAL NOT NULL,
+    close          REAL NOT NULL,
+    volume0        REAL NOT NULL,
+    volume1        REAL NOT NULL,
+    trades         INTEGER NOT NULL,
+    PRIMARY KEY (pool_address, interval_secs, open_time)
+);
+"#;
+
+/// Persistent pool / token / price store. All methods are blocking; call them from
+/// `tokio::task::spawn_blocking` on hot paths.
+pub struct PoolStore {
+    conn: Mutex<Connection>,
+}
+
+impl std::fmt::Debug for PoolStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolStore").finish_non_exhaustive()
+    }
+}
+
+impl PoolStore {
+    /// Open (or create) the database at `path` and apply the schema.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// In-memory database, mostly for tests.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        // Databases created before quirk tags were stored lack the column.
+        let has_quirk = conn
+            .prepare("SELECT 1 FROM pragma_table_info('tokens') WHERE name = 'quirk'")?
+            .exists([])?;
+        if !has_quirk {
+            conn.execute("ALTER TABLE tokens ADD COLUMN quirk TEXT", [])?;
+        }
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Insert or update pools and their tokens' metadata and quirk tags in one transaction.
+    pub fn upsert_pools(&self, pools: &[CachedPool]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut token_stmt = tx.prepare_cached(
+                "INSERT INTO tokens (address, symbol, decimals, updated_at, quirk) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(address) DO UPDATE SET symbol = ?2, decimals = ?3, updated_at = ?4, quirk = ?5",
+            )?;
+            let mut pool_stmt = tx.prepare_cached(
+                "INSERT INTO pools (address, protocol, token0, token1, fee, liquidity_usd, volume_24h_usd, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(address) DO UPDATE SET protocol = ?2, token0 = ?3, token1 = ?4, fee = ?5,
+                     liquidity_usd = ?6, volume_24h_usd = ?7, last_seen = ?8",
+            )?;
+            for pool in pools {
+                for (token, symbol, decimals, quirk) in [
+                    (pool.token0, &pool.token0_symbol, pool.token0_decimals, pool.token0_quirk),
+                    (pool.token1, &pool.token1_symbol, pool.token1_decimals, pool.token1_quirk),
+                ] {
+                    token_stmt.execute(params![token.to_string(), symbol, decimals, pool.last_seen, quirk.map(quirk_name)])?;
+                }
+                pool_stmt.execute(params![
+                    pool.address.to_string(),
+                    pool.protocol,
+                    pool.token0.to_string(),
+                    pool.token1.to_string(),
+                    pool.fee,
+                    pool.liquidity_usd,
+                    pool.volume_24h_usd,
+                    pool.last_seen,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// All stored pools, joined with their token metadata and quirk tags.
+    pub fn load_pools(&self) -> Result<Vec<CachedPool>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT p.address, p.protocol, p.token0, t0.symbol, t0.decimals, p.token1, t1.symbol, t1.decimals,
+                    p.fee, p.liquidity_usd, p.volume_24h_usd, p.last_seen, t0.quirk, t1.quirk
+             FROM pools p
+             JOIN tokens t0 ON t0.address = p.token0
+             JOIN tokens t1 ON t1.address = p.token1",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(CachedPool {
+                address: parse_address(row.get(0)?),
+                protocol: row.get(1)?,
+                token0: parse_address(row.get(2)?),
+                token0_symbol: row.get(3)?,
+                token0_decimals: row.get(4)?,
+                token1: parse_address(row.get(5)?),
+                token1_symbol: row.get(6)?,
+                token1_decimals: row.get(7)?,
+                fee: row.get(8)?,
+                liquidity_usd: row.get(9)?,
+                volume_24h_usd: row.get(10)?,
+                last_seen: row.get(11)?,
+                token0_quirk: row.get::<_, Option<String>>(12)?.as_deref().and_then(parse_quirk),
+                token1_quirk: row.get::<_, Option<String>>(13)?.as_deref().and_then(parse_quirk),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Remove pools (and their last price) from the store.
+    pub fn remove_pools(&self, addresses: &[Address]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for address in addresses {
+            tx.execute("DELETE FROM prices WHERE pool_address = ?1", [address.to_string()])?;
+            tx.execute("DELETE FROM pools WHERE address = ?1", [address.to_string()])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn upsert_token(&self, token: Address, metadata: &TokenMetadata) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO tokens (address, symbol, decimals, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(address) DO UPDATE SET symbol = ?2, decimals = ?3, updated_at = ?4",
+            params![
+                token.to_string(),
+                metadata.symbol,
+                metadata.decimals,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn token(&self, token: Address) -> Result<Option<TokenMetadata>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT symbol, decimals FROM tokens WHERE address = ?1",
+                [token.to_string()],
+                |row| {
+                    Ok(TokenMetadata {
+                        symbol: row.get(0)?,
+                        decimals: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Record the latest prices, replacing any previous price of the same pool.
+    pub fn record_prices(&self, prices: &[PoolPrice]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO prices (pool_address, token0_price, token1_price, timestamp) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(pool_address) DO UPDATE SET token0_price = ?2, token1_price = ?3, timestamp = ?4",
+            )?;
+            for price in prices {
+                stmt.execute(params![
+                    price.pool_address.to_string(),
+                    price.token0_price,
+                    price.token1_price,
+                    price.timestamp,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Last recorded price of every pool.
+    pub fn last_prices(&self) -> Result<Vec<PoolPrice>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT pool_address, token0_price, token1_price, timestamp FROM prices")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PoolPrice {
+                pool_address: parse_address(row.get(0)?),
+                token0_price: row.get(1)?,
+                token1_price: row.get(2)?,
+                timestamp: row.get(3)?,
+                ..Default::default()
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Insert completed candles, replacing a candle of the same pool, interval and open time.
+    pub fn record_candles(&self, candles: &[Candle]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO candles
+                 (pool_address, interval_secs, open_time, open, high, low, close, volume0, volume1, trades)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            for candle in candles {
+                stmt.execute(params![
+                    candle.pool_address.to_string(),
+                    candle.interval_secs,
+                    candle.open_time,
+                    candle.open,
+                    candle.high,
+                    candle.low,
+                    candle.close,
+                    candle.volume0,
+                    candle.volume1,
+                    candle.trades,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Candles of one pool and interval opened at or after `since`, oldest first.
+    pub fn candles(&self, pool: Address, interval_secs: u64, since: u64) -> Result<Vec<Candle>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT open_time, open, high, low, close, volume0, volume1, trades FROM candles
+             WHERE pool_address = ?1 AND interval_secs = ?2 AND open_time >= ?3 ORDER BY open_time",
+        )?;
+        let rows = stmt.query_map(params![pool.to_string(), interval_secs, since], |row| {
+            Ok(Candle {
+                pool_address: pool,
+                interval_secs,
+                open_time: row.get(0)?,
+                open: row.get(1)?,
+                high: row.get(2)?,
+                low: row.get(3)?,
+                close: row.get(4)?,
+                volume0: row.get(5)?,
+                volume1: row.get(6)?,
+                trades: row.get(7)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+fn parse_address(value: String) -> Address {
+    value.parse().unwrap_or_default()
+}
+
+/// `TokenQuirk` as stored in `tokens.quirk`: its name in config files.
+fn quirk_name(quirk: TokenQuirk) -> &'static str {
+    match quirk {
+        TokenQuirk::FeeOnTransfer => "feeOnTransfer",
+        TokenQuirk::Rebasing => "rebasing",
+    }
+}
+
+fn parse_quirk(name: &str) -> Option<TokenQuirk> {
+    match name {
+        "feeOnTransfer" => Some(TokenQuirk::FeeOnTransfer),
+        "rebasing" => Some(TokenQuirk::Rebasing),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_and_load_pools_with_prices() {
+        let store = PoolStore::open_in_memory().unwrap();
+        let mut pool = CachedPool {
+            address: Address::repeat_byte(1),
+            protocol: "uniswap_v2".to_string(),
+            token0: Address::repeat_byte(2),
+            token0_symbol: "WETH".to_string(),
+            token0_decimals: 18,
+            token1: Address::repeat_byte(3),
+            token1_symbol: "USDC".to_string(),
+            token1_decimals: 6,
+            token1_quirk: Some(TokenQuirk::FeeOnTransfer),
+            ..Default::default()
+        };
+        store.upsert_pools(std::slice::from_ref(&pool)).unwrap();
+        pool.liquidity_usd = 42.0;
+        store.upsert_pools(std::slice::from_ref(&pool)).unwrap();
+
+        let loaded = store.load_pools().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].token1_decimals, 6);
+        assert_eq!(loaded[0].liquidity_usd, 42.0);
+        assert_eq!((loaded[0].token0_quirk, loaded[0].token1_quirk), (None, Some(TokenQuirk::FeeOnTransfer)));
+        assert_eq!(store.token(pool.token0).unwrap().unwrap().symbol, "WETH");
+
+        store
+            .record_prices(&[PoolPrice {
+                pool_address: pool.address,
+                token0_price: 2000.0,
+                token1_price: 0.0005,
+                timestamp: 1,
+                ..Default::default()
+            }])
+            .unwrap();
+        assert_eq!(store.last_prices().unwrap()[0].token0_price, 2000.0);
+
+        let candle = Candle {
+            pool_address: pool.address,
+            interval_secs: 60,
+            open_time: 120,
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume0: 10.0,
+            volume1: 15.0,
+            trades: 3,
+        };
+        store.record_candles(std::slice::from_ref(&candle)).unwrap();
+        assert_eq!(store.candles(pool.address, 60, 0).unwrap(), vec![candle]);
+        assert!(store.candles(pool.address, 60, 180).unwrap().is_empty());
+
+        store.remove_pools(&[pool.address]).unwrap();
+        assert!(store.load_pools().unwrap().is_empty());
+    }
+}
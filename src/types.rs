@@ -1,5 +1,9 @@
+use crate::discovery::PoolRanking;
+use crate::liquidity_pools::DecimalPrice;
+use crate::metrics::{MovingAverageValue, RollingVolume, Twap, Volatility};
 use alloy::primitives::Address;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Protocol {
@@ -9,7 +13,19 @@ pub enum Protocol {
     UniswapV3,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Token behavior that breaks the assumption that transfers conserve amounts. Prices derived
+/// from V2 `Sync` reserves are unreliable for pools containing such tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenQuirk {
+    /// A fee is deducted on transfer, so the receiver gets less than the amount sent.
+    #[serde(rename = "feeOnTransfer")]
+    FeeOnTransfer,
+    /// Balances change without transfers (e.g. stETH, AMPL).
+    #[serde(rename = "rebasing")]
+    Rebasing,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CachedPool {
     pub address: Address,
     pub protocol: String,
@@ -23,17 +39,178 @@ pub struct CachedPool {
     pub liquidity_usd: f64,
     pub volume_24h_usd: f64,
     pub last_seen: String,
+    #[serde(default)]
+    pub token0_quirk: Option<TokenQuirk>,
+    #[serde(default)]
+    pub token1_quirk: Option<TokenQuirk>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl CachedPool {
+    /// Whether either token is fee-on-transfer or rebasing.
+    pub fn has_token_quirks(&self) -> bool {
+        self.token0_quirk.is_some() || self.token1_quirk.is_some()
+    }
+
+    /// The first of `quote_tokens` (in priority order) that this pool trades, if any.
+    pub fn quote_token(&self, quote_tokens: &[Address]) -> Option<Address> {
+        quote_tokens
+            .iter()
+            .copied()
+            .find(|token| *token == self.token0 || *token == self.token1)
+    }
+}
+
+/// A pool price oriented so a configured quote token is the denominator (`ScannerOptions::quote_tokens`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotedPrice {
+    /// The pool's other token.
+    pub base: Address,
+    pub quote: Address,
+    /// `base` in `quote` units.
+    pub price: f64,
+    #[serde(default)]
+    pub price_exact: Option<DecimalPrice>,
+}
+
+impl PoolPrice {
+    /// This price with `quote` (one of `pool`'s tokens) as the denominator.
+    pub fn quoted(&self, pool: &CachedPool, quote: Address) -> QuotedPrice {
+        if quote == pool.token1 {
+            QuotedPrice {
+                base: pool.token0,
+                quote,
+                price: self.token0_price,
+                price_exact: self.token0_price_exact,
+            }
+        } else {
+            QuotedPrice {
+                base: pool.token1,
+                quote,
+                price: self.token1_price,
+                price_exact: self.token1_price_exact,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PoolPrice {
     pub pool_address: Address,
     pub token0_price: f64,
     pub token1_price: f64,
     pub timestamp: u64,
+    /// `token0_price` at full precision; `None` when only the f64 is known (e.g. loaded from the store).
+    #[serde(default)]
+    pub token0_price_exact: Option<DecimalPrice>,
+    /// `token1_price` at full precision.
+    #[serde(default)]
+    pub token1_price_exact: Option<DecimalPrice>,
+    /// Time-weighted averages over the configured `MetricsConfig::twap_windows`, including this update.
+    #[serde(default)]
+    pub twaps: Vec<Twap>,
+    /// Configured `MetricsConfig::moving_averages`, including this update.
+    #[serde(default)]
+    pub moving_averages: Vec<MovingAverageValue>,
+    /// Realized volatility over `MetricsConfig::volatility_window`, including this update.
+    #[serde(default)]
+    pub volatility: Option<Volatility>,
+    /// token0 in USD, routed through `PricingConfig::reference_pools`. `None` when pricing is off or
+    /// neither token is reachable from a USD token.
+    #[serde(default)]
+    pub token0_price_usd: Option<f64>,
+    #[serde(default)]
+    pub token1_price_usd: Option<f64>,
+    /// The price with the pool's highest-priority `ScannerOptions::quote_tokens` entry as the denominator;
+    /// `None` when the pool trades none of them.
+    #[serde(default)]
+    pub quoted: Option<QuotedPrice>,
+    /// Caught by `ScannerOptions::spike_filter` (large move on a small swap) with `SpikeAction::Flag`.
+    #[serde(default)]
+    pub suspect: bool,
+    /// Block of the log behind this update; `None` for prices not read from a log (e.g. loaded from the store).
+    #[serde(default)]
+    pub block_number: Option<u64>,
+    /// Index of that log in its block.
+    #[serde(default)]
+    pub log_index: Option<u64>,
+    /// Swapped token0 amount in token units, from the pool's point of view (positive = paid into the pool).
+    /// `None` for updates that aren't swaps (V2 `Sync`, V3 `Mint` / `Burn`).
+    #[serde(default)]
+    pub amount0: Option<f64>,
+    #[serde(default)]
+    pub amount1: Option<f64>,
 }
 
+/// A tracked pool and its latest state, as returned by `Scanner::snapshot`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    /// `volume_24h_usd` is the live rolling volume once `volume_24h` is complete, the discovery-time
+    /// figure before that.
+    pub pool: CachedPool,
+    /// Swap volume observed over the last 24 hours.
+    pub volume_24h: RollingVolume,
+    /// Last delivered price; `None` before the pool's first update.
+    pub price: Option<PoolPrice>,
+    /// Unix time of the pool's last log, or when tracking started.
+    pub last_activity: u64,
+    /// No log for `ScannerOptions::stale_after`: don't treat `price` as live.
+    pub stale: bool,
+}
+
+/// Where discovery gets its pool list from.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DiscoveryMode {
+    /// Query The Graph subgraphs (requires THE_GRAPH_API_KEY).
+    #[default]
+    #[serde(rename = "subgraph")]
+    Subgraph,
+    /// Scan factory PairCreated/PoolCreated logs over RPC (no API key needed).
+    #[serde(rename = "factoryLogs")]
+    FactoryLogs,
+    /// Enumerate V2 factories with allPairsLength()/allPairs(i) multicalls over RPC.
+    #[serde(rename = "factoryEnumeration")]
+    FactoryEnumeration,
+    /// GeckoTerminal's public API (no API key; needs `network` and per-protocol `dex_id`).
+    #[serde(rename = "geckoTerminal")]
+    GeckoTerminal,
+    /// Dexscreener's public API (no API key; needs `network`, `seed_tokens`, per-protocol `dex_id` and an RPC
+    /// provider, which token decimals are read with).
+    #[serde(rename = "dexscreener")]
+    Dexscreener,
+}
+
+impl DiscoveryMode {
+    /// Whether pools come from factory contracts over RPC rather than an indexer or API.
+    pub fn is_onchain(self) -> bool {
+        matches!(self, DiscoveryMode::FactoryLogs | DiscoveryMode::FactoryEnumeration)
+    }
+}
+
+fn default_log_chunk_size() -> u64 {
+    10_000
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_discovery_concurrency() -> usize {
+    4
+}
+
+fn default_full_refresh_every() -> u32 {
+    60
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DiscoveryConfig {
     pub min_liquidity_usd: f64,
     pub max_pools_per_protocol: u32,
@@ -41,13 +218,183 @@ pub struct DiscoveryConfig {
     pub cache_enabled: bool,
     #[serde(default)]
     pub cache_file: String,
+    #[serde(default)]
+    pub mode: DiscoveryMode,
+    /// Block span per `eth_getLogs` request in `FactoryLogs` mode (halved automatically when the node rejects a range).
+    #[serde(default = "default_log_chunk_size")]
+    pub log_chunk_size: u64,
+    /// Retries per subgraph request after a transient failure (network error, 429/5xx, GraphQL error).
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// First retry delay; doubled per attempt with random jitter.
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+    /// Re-run discovery on a running scanner every this many minutes; 0 disables rediscovery.
+    #[serde(default)]
+    pub cache_refresh_minutes: u32,
+    /// Read `decimals()` / `symbol()` on-chain for subgraph-discovered tokens and prefer those values.
+    #[serde(default = "default_true")]
+    pub verify_token_metadata: bool,
+    /// Exclude pools whose tokens trip the scam/honeypot heuristics (`discovery::screen_pools`).
+    #[serde(default)]
+    pub risk_filter: bool,
+    /// Tag pools containing fee-on-transfer / rebasing tokens (`discovery::tag_token_quirks`).
+    #[serde(default = "default_true")]
+    pub detect_token_quirks: bool,
+    /// Extra fee-on-transfer tokens on top of the built-in list.
+    #[serde(default)]
+    pub fee_on_transfer_tokens: Vec<Address>,
+    /// Extra rebasing tokens on top of the built-in list.
+    #[serde(default)]
+    pub rebasing_tokens: Vec<Address>,
+    /// Keep only the top-ranked pools per token pair across protocols and fee tiers.
+    #[serde(default)]
+    pub ranking: Option<PoolRanking>,
+    /// Aggregator network / chain id (e.g. `eth` on GeckoTerminal, `ethereum` on Dexscreener).
+    #[serde(default)]
+    pub network: String,
+    /// Tokens whose pairs Dexscreener discovery looks up.
+    #[serde(default)]
+    pub seed_tokens: Vec<Address>,
+    /// Compare each subgraph's `_meta` block with the chain head and flag lags above this many blocks.
+    #[serde(default)]
+    pub max_subgraph_lag_blocks: Option<u64>,
+    /// Skip a lagging subgraph's protocol instead of only warning.
+    #[serde(default)]
+    pub fail_on_subgraph_lag: bool,
+    /// Number of protocols discovered concurrently.
+    #[serde(default = "default_discovery_concurrency")]
+    pub discovery_concurrency: usize,
+    /// Rediscover incrementally (new pools since the creation cursor plus liquidity of known pools).
+    #[serde(default)]
+    pub incremental_discovery: bool,
+    /// With `incremental_discovery`, run a full discovery every this many rediscovery runs.
+    #[serde(default = "default_full_refresh_every")]
+    pub full_refresh_every: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The protocols file's defaults for its optional keys, no liquidity threshold and 100 pools per protocol.
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            min_liquidity_usd: 0.0,
+            max_pools_per_protocol: 100,
+            cache_enabled: false,
+            cache_file: "pools_cache.json".to_string(),
+            mode: DiscoveryMode::default(),
+            log_chunk_size: default_log_chunk_size(),
+            max_retries: default_max_retries(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            cache_refresh_minutes: 0,
+            verify_token_metadata: true,
+            risk_filter: false,
+            detect_token_quirks: true,
+            fee_on_transfer_tokens: Vec::new(),
+            rebasing_tokens: Vec::new(),
+            ranking: None,
+            network: String::new(),
+            seed_tokens: Vec::new(),
+            max_subgraph_lag_blocks: None,
+            fail_on_subgraph_lag: false,
+            discovery_concurrency: default_discovery_concurrency(),
+            incremental_discovery: false,
+            full_refresh_every: default_full_refresh_every(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProtocolConfig {
     pub id: String,
     pub name: String,
     pub subgraph_url: String,
     pub pool_type: Protocol,
     pub enabled: bool,
+    #[serde(default)]
+    pub factory: Address,
+    /// Factory deployment block; lower bound for factory log scans.
+    #[serde(default)]
+    pub start_block: Option<u64>,
+    /// V3 fee tiers to keep (e.g. `[500, 3000]`); empty keeps every tier. Ignored for V2.
+    #[serde(default)]
+    pub fee_tiers: Vec<u32>,
+    /// Replaces `DiscoveryConfig::min_liquidity_usd` for this protocol.
+    #[serde(default)]
+    pub min_liquidity_usd: Option<f64>,
+    /// Replaces `DiscoveryConfig::max_pools_per_protocol` for this protocol.
+    #[serde(default)]
+    pub max_pools: Option<u32>,
+    /// DEX id on GeckoTerminal / Dexscreener (e.g. `uniswap_v3`, `uniswap`).
+    #[serde(default)]
+    pub dex_id: Option<String>,
+    /// Ordering and extra where-constraints merged into the generated subgraph pool query.
+    #[serde(default)]
+    pub query_overrides: Option<QueryOverrides>,
+}
+
+/// Per-protocol tuning of the subgraph pool query (`queryOverrides` in protocols.json).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryOverrides {
+    /// Replaces the default `reserveUSD` (V2) / `totalValueLockedUSD` (V3) ordering, e.g. `volumeUSD`.
+    #[serde(rename = "orderBy", default)]
+    pub order_by: Option<String>,
+    /// Extra `where` constraints (e.g. `{ "txCount_gt": "1000" }`); a key also generated by the
+    /// scanner (such as `reserveUSD_gte`) replaces the generated value.
+    #[serde(rename = "where", default)]
+    pub where_clause: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ProtocolConfig {
+    /// Whether a pool with `fee` passes the protocol's fee-tier restriction.
+    pub fn allows_fee(&self, fee: u32) -> bool {
+        self.pool_type != Protocol::UniswapV3 || self.fee_tiers.is_empty() || self.fee_tiers.contains(&fee)
+    }
+
+    /// `discovery` with this protocol's `min_liquidity_usd` / `max_pools` in place of the global values.
+    pub fn discovery_settings<'a>(&self, discovery: &'a DiscoveryConfig) -> Cow<'a, DiscoveryConfig> {
+        if self.min_liquidity_usd.is_none() && self.max_pools.is_none() {
+            return Cow::Borrowed(discovery);
+        }
+        Cow::Owned(DiscoveryConfig {
+            min_liquidity_usd: self.min_liquidity_usd.unwrap_or(discovery.min_liquidity_usd),
+            max_pools_per_protocol: self.max_pools.unwrap_or(discovery.max_pools_per_protocol),
+            ..discovery.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_token_priority_and_orientation() {
+        let (weth, usdc, dai, wbtc) =
+            (Address::repeat_byte(0xee), Address::repeat_byte(0x01), Address::repeat_byte(0xda), Address::repeat_byte(0xbb));
+        let pool = |token0, token1| CachedPool {
+            token0,
+            token1,
+            ..Default::default()
+        };
+        // USDC (token0) / WETH (token1) at 2000 USDC per WETH.
+        let usdc_weth = pool(usdc, weth);
+        let price = PoolPrice {
+            token0_price: 1.0 / 2_000.0,
+            token1_price: 2_000.0,
+            ..Default::default()
+        };
+
+        // The first listed quote token the pool trades wins, whichever side it is on.
+        assert_eq!(usdc_weth.quote_token(&[usdc, weth]), Some(usdc));
+        assert_eq!(usdc_weth.quote_token(&[weth, usdc]), Some(weth));
+        assert_eq!(pool(weth, dai).quote_token(&[usdc, weth]), Some(weth));
+        assert_eq!(pool(wbtc, dai).quote_token(&[usdc, weth]), None);
+
+        // Quote token as token0: WETH priced in USDC.
+        let quoted = price.quoted(&usdc_weth, usdc);
+        assert_eq!((quoted.base, quoted.quote, quoted.price), (weth, usdc, 2_000.0));
+        // Quote token as token1: USDC priced in WETH.
+        let quoted = price.quoted(&usdc_weth, weth);
+        assert_eq!((quoted.base, quoted.quote, quoted.price), (usdc, weth, 1.0 / 2_000.0));
+    }
 }